@@ -0,0 +1,54 @@
+/*
+ * Records the test video for two seconds and prints the average color of its
+ * latest frame.
+ *
+ *   cargo build --release
+ *   cc examples/record.c -Iinclude -Ltarget/release -lsmelter_colors -o record
+ *   LD_LIBRARY_PATH=target/release ./record
+ */
+
+#include <stdio.h>
+#include <unistd.h>
+
+#include "smelter_colors.h"
+
+static const char *SCENE =
+    "{\"type\": \"rescaler\", \"child\": {\"type\": \"input_stream\", \"input_id\": \"mp4\"}}";
+
+static int fail(SmelterHarness *harness) {
+    fprintf(stderr, "error: %s\n", smelter_last_error());
+    smelter_destroy(harness);
+    return 1;
+}
+
+int main(void) {
+    SmelterHarness *harness = smelter_create("../../configs/default.toml");
+    if (!harness) {
+        return fail(NULL);
+    }
+
+    if (smelter_set_scene(harness, SCENE) || smelter_start_recording(harness, "record.mp4")) {
+        return fail(harness);
+    }
+    sleep(2);
+    if (smelter_stop_recording(harness)) {
+        return fail(harness);
+    }
+
+    SmelterFrame frame;
+    if (smelter_latest_frame(harness, &frame)) {
+        return fail(harness);
+    }
+    unsigned long long sums[3] = {0, 0, 0};
+    for (size_t i = 0; i < frame.len; i += 4) {
+        sums[0] += frame.rgba[i];
+        sums[1] += frame.rgba[i + 1];
+        sums[2] += frame.rgba[i + 2];
+    }
+    size_t pixels = frame.len / 4;
+    printf("%ux%u, average RGB %llu %llu %llu\n", frame.width, frame.height,
+           sums[0] / pixels, sums[1] / pixels, sums[2] / pixels);
+
+    smelter_destroy(harness);
+    return 0;
+}
@@ -0,0 +1,65 @@
+/*
+ * C API of the smelter-colors harness.
+ *
+ * Functions returning int return 0 on success and -1 on error, the message of the
+ * last error on the calling thread is then available from smelter_last_error().
+ * A harness must only be used from one thread at a time.
+ */
+
+#ifndef SMELTER_COLORS_H
+#define SMELTER_COLORS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct SmelterHarness SmelterHarness;
+
+/* Latest frame of a harness, the pixels are owned by the harness. */
+typedef struct SmelterFrame {
+    uint32_t width;
+    uint32_t height;
+    /* width * height * 4 bytes of RGBA8, rows top to bottom. */
+    const uint8_t *rgba;
+    size_t len;
+} SmelterFrame;
+
+/* Message of the last error on this thread, NULL if none. Valid until the next call failing. */
+const char *smelter_last_error(void);
+
+/* Creates a pipeline from a TOML or JSON config file, or the default setup when
+ * config_path is NULL. Returns NULL on error. */
+SmelterHarness *smelter_create(const char *config_path);
+
+/* Stops the recording if any and releases the pipeline. Accepts NULL. */
+void smelter_destroy(SmelterHarness *harness);
+
+/* Renders a JSON scene, in the format of the config scene files, from now on. */
+int smelter_set_scene(SmelterHarness *harness, const char *scene_json);
+
+/* Waits for a frame if none is queued, then reads back the latest one into frame.
+ * Its pixels stay valid until the next call on the harness. */
+int smelter_latest_frame(SmelterHarness *harness, SmelterFrame *frame);
+
+/* Starts encoding the scene to an H264 MP4 file, never replacing an existing file: a
+ * suffix is added to its name instead. `%Y%m%d_%H%M%S` in the path are the date and time.
+ * Later scene changes apply to the recording too. */
+int smelter_start_recording(SmelterHarness *harness, const char *path);
+
+/* Stops the recording. The file is finalized asynchronously, shortly after. */
+int smelter_stop_recording(SmelterHarness *harness);
+
+/* Pauses the recording, leaving the frames shown until it resumes out of the file. */
+int smelter_pause_recording(SmelterHarness *harness);
+
+/* Resumes a paused recording, with the current scene. */
+int smelter_resume_recording(SmelterHarness *harness);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,262 @@
+//! C API of the harness, for native applications embedding Smelter in their test rigs.
+//!
+//! See `include/smelter_colors.h` for the documented interface. Functions returning `int`
+//! return 0 on success and -1 on error, with the message in [`smelter_last_error`].
+
+use anyhow::{Context, Result};
+use smelter_colors::compositor::PLACEHOLDER;
+use smelter_colors::compositor_pipeline::pipeline::RawDataReceiver;
+use smelter_colors::compositor_render::scene::Component;
+use smelter_colors::wgpu::to_image;
+use smelter_colors::{scene, Compositor, Config};
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::ptr;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// A pipeline rendering one scene to a raw output, and optionally to an MP4 file.
+pub struct SmelterHarness {
+    compositor: Compositor,
+    receiver: RawDataReceiver,
+    scene: Component,
+    /// RGBA pixels of the latest frame, valid until the next one is read.
+    frame: Vec<u8>,
+    recording: bool,
+}
+
+/// Latest frame of a harness, the pixels are owned by the harness.
+#[repr(C)]
+pub struct SmelterFrame {
+    pub width: u32,
+    pub height: u32,
+    /// `width * height * 4` bytes of RGBA8, rows top to bottom.
+    pub rgba: *const u8,
+    pub len: usize,
+}
+
+/// Message of the last error on this thread, NULL if none. Valid until the next call failing.
+#[no_mangle]
+pub extern "C" fn smelter_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| {
+        error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |error| error.as_ptr())
+    })
+}
+
+/// Creates a pipeline from a config file, or the default setup when `config_path` is NULL.
+///
+/// Returns NULL on error.
+///
+/// # Safety
+///
+/// `config_path` must be NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn smelter_create(config_path: *const c_char) -> *mut SmelterHarness {
+    let harness = catch(|| {
+        let config = match config_path.is_null() {
+            true => Config::default(),
+            false => Config::load(&PathBuf::from(str_arg(config_path)?))?,
+        };
+        config.validate()?;
+
+        let mut compositor = Compositor::new(&config)?;
+        let receiver = compositor.register_raw_output(PLACEHOLDER.clone())?;
+        Ok(Box::new(SmelterHarness {
+            compositor,
+            receiver,
+            scene: PLACEHOLDER.clone(),
+            frame: vec![],
+            recording: false,
+        }))
+    });
+
+    harness.map_or(ptr::null_mut(), Box::into_raw)
+}
+
+/// Stops the recording if any and releases the pipeline.
+///
+/// # Safety
+///
+/// `harness` must be NULL or returned by [`smelter_create`], and not used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn smelter_destroy(harness: *mut SmelterHarness) {
+    if harness.is_null() {
+        return;
+    }
+
+    let mut harness = Box::from_raw(harness);
+    let _ = catch(|| {
+        if harness.recording {
+            harness.compositor.stop_record()?;
+        }
+        harness.compositor.deregister_raw_output()
+    });
+}
+
+/// Renders a JSON scene, in the format of the config scene files, from now on.
+///
+/// # Safety
+///
+/// `harness` must be returned by [`smelter_create`], `scene_json` a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn smelter_set_scene(
+    harness: *mut SmelterHarness,
+    scene_json: *const c_char,
+) -> c_int {
+    status(catch(|| {
+        let harness = harness_arg(harness)?;
+        let component = scene::parse(str_arg(scene_json)?).context("Invalid scene")?;
+
+        harness.compositor.update_raw_output(component.clone())?;
+        if harness.recording {
+            harness.compositor.update_record(component.clone())?;
+        }
+        harness.scene = component;
+        Ok(())
+    }))
+}
+
+/// Waits for a frame if none is queued, then reads back the latest one into `frame`.
+///
+/// # Safety
+///
+/// `harness` must be returned by [`smelter_create`], `frame` point to a writable
+/// [`SmelterFrame`]. Its pixels stay valid until the next call on the harness.
+#[no_mangle]
+pub unsafe extern "C" fn smelter_latest_frame(
+    harness: *mut SmelterHarness,
+    frame: *mut SmelterFrame,
+) -> c_int {
+    status(catch(|| {
+        let harness = harness_arg(harness)?;
+        let frame = frame.as_mut().context("frame is NULL")?;
+
+        let texture = Compositor::get_last_frame(&harness.receiver)?;
+        let image = to_image(
+            harness.compositor.graphics_context(),
+            &texture,
+            harness.compositor.shader_cache().get(),
+        )?;
+        let (width, height) = image.dimensions();
+        harness.frame = image.into_raw();
+
+        *frame = SmelterFrame {
+            width,
+            height,
+            rgba: harness.frame.as_ptr(),
+            len: harness.frame.len(),
+        };
+        Ok(())
+    }))
+}
+
+/// Starts encoding the scene to an H264 MP4 file, never replacing an existing file: a
+/// suffix is added to its name instead. `%Y%m%d_%H%M%S` in the path are the date and time.
+///
+/// Later scene changes apply to the recording too.
+///
+/// # Safety
+///
+/// `harness` must be returned by [`smelter_create`], `path` a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn smelter_start_recording(
+    harness: *mut SmelterHarness,
+    path: *const c_char,
+) -> c_int {
+    status(catch(|| {
+        let harness = harness_arg(harness)?;
+        anyhow::ensure!(!harness.recording, "Already recording");
+
+        harness
+            .compositor
+            .start_record(PathBuf::from(str_arg(path)?))?;
+        harness.recording = true;
+        harness.compositor.update_record(harness.scene.clone())
+    }))
+}
+
+/// Stops the recording. The file is finalized asynchronously, shortly after.
+///
+/// # Safety
+///
+/// `harness` must be returned by [`smelter_create`].
+#[no_mangle]
+pub unsafe extern "C" fn smelter_stop_recording(harness: *mut SmelterHarness) -> c_int {
+    status(catch(|| {
+        let harness = harness_arg(harness)?;
+        anyhow::ensure!(harness.recording, "Not recording");
+
+        harness.recording = false;
+        harness.compositor.stop_record()
+    }))
+}
+
+/// Pauses the recording, leaving the frames shown until it resumes out of the file.
+///
+/// # Safety
+///
+/// `harness` must be returned by [`smelter_create`].
+#[no_mangle]
+pub unsafe extern "C" fn smelter_pause_recording(harness: *mut SmelterHarness) -> c_int {
+    status(catch(|| {
+        let harness = harness_arg(harness)?;
+        anyhow::ensure!(harness.recording, "Not recording");
+
+        harness.compositor.pause_recording()
+    }))
+}
+
+/// Resumes a paused recording, with the current scene.
+///
+/// # Safety
+///
+/// `harness` must be returned by [`smelter_create`].
+#[no_mangle]
+pub unsafe extern "C" fn smelter_resume_recording(harness: *mut SmelterHarness) -> c_int {
+    status(catch(|| {
+        let harness = harness_arg(harness)?;
+        anyhow::ensure!(harness.recording, "Not recording");
+
+        harness.compositor.resume_recording()
+    }))
+}
+
+/// Runs `f`, storing its error or panic as the last error.
+fn catch<T>(f: impl FnOnce() -> Result<T>) -> Option<T> {
+    let result = match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result,
+        Err(_) => Err(anyhow::anyhow!("Panicked, see the logs")),
+    };
+
+    result
+        .map_err(|err| {
+            let message = CString::new(format!("{err:#}").replace('\0', " ")).unwrap_or_default();
+            LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+        })
+        .ok()
+}
+
+fn status(result: Option<()>) -> c_int {
+    match result {
+        Some(()) => 0,
+        None => -1,
+    }
+}
+
+unsafe fn harness_arg<'a>(harness: *mut SmelterHarness) -> Result<&'a mut SmelterHarness> {
+    harness.as_mut().context("harness is NULL")
+}
+
+unsafe fn str_arg<'a>(string: *const c_char) -> Result<&'a str> {
+    anyhow::ensure!(!string.is_null(), "Unexpected NULL string");
+    CStr::from_ptr(string)
+        .to_str()
+        .context("Strings must be UTF-8")
+}
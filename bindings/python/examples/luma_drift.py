@@ -0,0 +1,22 @@
+"""Flags frames of the MP4 input whose luma drifts from the first frame."""
+
+import numpy as np
+import smelter_colors
+
+compositor = smelter_colors.Compositor("../../configs/default.toml")
+reference = None
+
+
+def check(frame):
+    global reference
+    pixels = np.frombuffer(frame.rgba, np.uint8).reshape(frame.height, frame.width, 4)
+    luma = (pixels[..., :3] @ [0.2126, 0.7152, 0.0722]).mean() / 255
+    if reference is None:
+        reference = luma
+    elif abs(luma - reference) > 0.05:
+        return f"luma {luma:.3f} drifted from {reference:.3f}"
+
+
+for finding in compositor.analyze("mp4", 5.0, check):
+    print(finding)
+print(compositor.measure_pacing(5.0))
@@ -0,0 +1,295 @@
+//! Python bindings of the capture and analysis core.
+//!
+//! Frames are handed to Python as RGBA bytes, e.g. for NumPy:
+//! `np.frombuffer(frame.rgba, np.uint8).reshape(frame.height, frame.width, 4)`.
+
+use anyhow::Result;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use smelter_colors::analysis::{self, CapturedFrame, FrameProcessor, FrameStats};
+use smelter_colors::compositor::PLACEHOLDER;
+use smelter_colors::wgpu::to_image;
+use smelter_colors::{Compositor, Config};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[pymodule]
+#[pyo3(name = "smelter_colors")]
+fn module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyCompositor>()?;
+    m.add_class::<Frame>()?;
+    m.add_class::<Finding>()?;
+    Ok(())
+}
+
+/// A compositor pipeline, created from a config file or the default setup.
+#[pyclass(name = "Compositor", unsendable)]
+struct PyCompositor {
+    inner: Compositor,
+}
+
+#[pymethods]
+impl PyCompositor {
+    #[new]
+    #[pyo3(signature = (config = None))]
+    fn new(config: Option<PathBuf>) -> Result<Self> {
+        let config = match config {
+            Some(path) => Config::load(&path)?,
+            None => Config::default(),
+        };
+        Ok(Self {
+            inner: Compositor::new(&config)?,
+        })
+    }
+
+    /// IDs of the components to render: one per input, or the configured scenes.
+    fn components(&self) -> Vec<String> {
+        self.inner
+            .components()
+            .iter()
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    fn load_scene(&mut self, id: &str, path: PathBuf) -> Result<()> {
+        self.inner.load_scene(id, &path)
+    }
+
+    fn restart_input(&mut self, id: &str) -> Result<()> {
+        self.inner.restart_input(id)
+    }
+
+    /// Renders a component and returns its latest frame.
+    fn snapshot(&mut self, component: &str) -> Result<Frame> {
+        let component = self.inner.component(component)?;
+        let receiver = self.inner.register_raw_output(PLACEHOLDER.clone())?;
+        let rendered = self.inner.render_component(&receiver, component);
+        self.inner.deregister_raw_output()?;
+
+        let image = to_image(
+            self.inner.graphics_context(),
+            &rendered?,
+            self.inner.shader_cache().get(),
+        )?;
+        Ok(Frame {
+            index: 0,
+            pts: 0.0,
+            width: image.width(),
+            height: image.height(),
+            rgba: image.into_raw(),
+        })
+    }
+
+    /// Renders a component for `duration_secs`, returns every frame.
+    fn capture(&mut self, component: &str, duration_secs: f64) -> Result<Vec<Frame>> {
+        let component = self.inner.component(component)?;
+        let frames = Arc::new(Mutex::new(vec![]));
+        let mut processors: Vec<Box<dyn FrameProcessor>> = vec![Box::new(Collector {
+            frames: frames.clone(),
+        })];
+        self.inner.analyze(
+            component,
+            Duration::from_secs_f64(duration_secs),
+            &mut processors,
+        )?;
+
+        let frames = std::mem::take(&mut *frames.lock().unwrap());
+        Ok(frames)
+    }
+
+    /// Renders a component for `duration_secs`, calling `callback(frame)` on every frame.
+    ///
+    /// A string returned by the callback is reported as a finding at that frame.
+    fn analyze(
+        &mut self,
+        component: &str,
+        duration_secs: f64,
+        callback: PyObject,
+    ) -> Result<Vec<Finding>> {
+        let component = self.inner.component(component)?;
+        let mut processors: Vec<Box<dyn FrameProcessor>> = vec![Box::new(PyProcessor {
+            callback,
+            findings: vec![],
+        })];
+        let findings = self.inner.analyze(
+            component,
+            Duration::from_secs_f64(duration_secs),
+            &mut processors,
+        )?;
+        Ok(findings.into_iter().map(Finding::from).collect())
+    }
+
+    #[pyo3(signature = (input, loops = 2, tolerance_ms = 150))]
+    fn check_loop(&mut self, input: &str, loops: u32, tolerance_ms: u64) -> Result<Vec<Finding>> {
+        let findings = self
+            .inner
+            .check_loop(input, loops, Duration::from_millis(tolerance_ms))?;
+        Ok(findings.into_iter().map(Finding::from).collect())
+    }
+
+    /// Frame interval statistics in seconds, `None` without enough frames.
+    #[pyo3(signature = (duration_secs, switch_interval_ms = 1000))]
+    fn measure_pacing<'py>(
+        &mut self,
+        py: Python<'py>,
+        duration_secs: f64,
+        switch_interval_ms: u64,
+    ) -> Result<Option<Bound<'py, PyDict>>> {
+        let Some(stats) = self.inner.measure_pacing(
+            Duration::from_secs_f64(duration_secs),
+            Duration::from_millis(switch_interval_ms),
+        )?
+        else {
+            return Ok(None);
+        };
+
+        let dict = PyDict::new_bound(py);
+        dict.set_item("target", stats.target.as_secs_f64())?;
+        dict.set_item("frames", stats.frames)?;
+        dict.set_item("mean", stats.mean.as_secs_f64())?;
+        dict.set_item("p50", stats.p50.as_secs_f64())?;
+        dict.set_item("p95", stats.p95.as_secs_f64())?;
+        dict.set_item("p99", stats.p99.as_secs_f64())?;
+        dict.set_item("max", stats.max.as_secs_f64())?;
+        dict.set_item("jitter", stats.jitter.as_secs_f64())?;
+        dict.set_item("late", stats.late)?;
+        Ok(Some(dict))
+    }
+
+    /// Runs the outputs of the config.
+    fn run(&mut self) -> Result<()> {
+        self.inner.run()
+    }
+}
+
+/// An output frame, pixels as tightly packed RGBA8.
+#[pyclass(frozen)]
+struct Frame {
+    #[pyo3(get)]
+    index: usize,
+    /// Presentation timestamp, in seconds.
+    #[pyo3(get)]
+    pts: f64,
+    #[pyo3(get)]
+    width: u32,
+    #[pyo3(get)]
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+#[pymethods]
+impl Frame {
+    #[getter]
+    fn rgba<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &self.rgba)
+    }
+
+    /// Mean RGB and Rec. 709 luma, normalized to `[0, 1]`.
+    fn stats(&self) -> Result<((f32, f32, f32), f32)> {
+        let image = image::RgbaImage::from_raw(self.width, self.height, self.rgba.clone())
+            .ok_or_else(|| anyhow::anyhow!("Invalid frame size"))?;
+        let stats = FrameStats::of(&image);
+        let [r, g, b] = stats.mean;
+        Ok(((r, g, b), stats.luma))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Frame(index={}, pts={:.3}, {}x{})",
+            self.index, self.pts, self.width, self.height
+        )
+    }
+}
+
+impl From<&CapturedFrame> for Frame {
+    fn from(frame: &CapturedFrame) -> Self {
+        Self {
+            index: frame.index,
+            pts: frame.pts.as_secs_f64(),
+            width: frame.image.width(),
+            height: frame.image.height(),
+            rgba: frame.image.as_raw().clone(),
+        }
+    }
+}
+
+#[pyclass(frozen)]
+struct Finding {
+    #[pyo3(get)]
+    analyzer: String,
+    /// In seconds, `None` for findings about the whole capture.
+    #[pyo3(get)]
+    pts: Option<f64>,
+    #[pyo3(get)]
+    message: String,
+}
+
+#[pymethods]
+impl Finding {
+    fn __repr__(&self) -> String {
+        match self.pts {
+            Some(pts) => format!("[{}] at {pts:.3}s: {}", self.analyzer, self.message),
+            None => format!("[{}] {}", self.analyzer, self.message),
+        }
+    }
+}
+
+impl From<analysis::Finding> for Finding {
+    fn from(finding: analysis::Finding) -> Self {
+        Self {
+            analyzer: finding.analyzer,
+            pts: finding.pts.map(|pts| pts.as_secs_f64()),
+            message: finding.message,
+        }
+    }
+}
+
+struct Collector {
+    frames: Arc<Mutex<Vec<Frame>>>,
+}
+
+impl FrameProcessor for Collector {
+    fn name(&self) -> &str {
+        "capture"
+    }
+
+    fn process(&mut self, frame: &CapturedFrame) -> Result<()> {
+        self.frames.lock().unwrap().push(Frame::from(frame));
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Vec<analysis::Finding> {
+        vec![]
+    }
+}
+
+/// Analyzer calling back into Python.
+struct PyProcessor {
+    callback: PyObject,
+    findings: Vec<analysis::Finding>,
+}
+
+impl FrameProcessor for PyProcessor {
+    fn name(&self) -> &str {
+        "python"
+    }
+
+    fn process(&mut self, frame: &CapturedFrame) -> Result<()> {
+        Python::with_gil(|py| {
+            let result = self.callback.call1(py, (Frame::from(frame),))?;
+            if !result.is_none(py) {
+                self.findings.push(analysis::Finding {
+                    analyzer: self.name().to_string(),
+                    pts: Some(frame.pts),
+                    message: result.extract(py)?,
+                });
+            }
+            Ok(())
+        })
+    }
+
+    fn finish(&mut self) -> Vec<analysis::Finding> {
+        std::mem::take(&mut self.findings)
+    }
+}
@@ -0,0 +1,109 @@
+//! Sample analyzer plugin, reporting frames whose mean luma is out of a range.
+//!
+//! Build it with `cargo build --release`, then list the library in an `analyze` output:
+//!
+//! ```toml
+//! [[outputs.plugins]]
+//! path = "plugins/mean_luma/target/release/libmean_luma.so"
+//! config = { min = 0.05, max = 0.95 }
+//! ```
+//!
+//! The types below mirror `smelter_colors::analysis::plugin`, version 1.
+
+use std::ffi::{c_char, c_void, CStr, CString};
+
+#[repr(C)]
+pub struct PluginFrame {
+    pub index: u64,
+    pub pts_us: u64,
+    pub width: u32,
+    pub height: u32,
+    pub rgba: *const u8,
+    pub len: usize,
+}
+
+pub type EmitFinding = unsafe extern "C" fn(ctx: *mut c_void, pts_us: i64, message: *const c_char);
+
+#[repr(C)]
+pub struct PluginVTable {
+    pub abi_version: u32,
+    pub name: *const c_char,
+    pub create: unsafe extern "C" fn(config_json: *const c_char) -> *mut c_void,
+    pub process: unsafe extern "C" fn(state: *mut c_void, frame: *const PluginFrame) -> i32,
+    pub finish: unsafe extern "C" fn(state: *mut c_void, emit: EmitFinding, ctx: *mut c_void),
+    pub destroy: unsafe extern "C" fn(state: *mut c_void),
+}
+
+// Raw pointers are not `Sync`, the table is immutable and its name static
+struct VTable(PluginVTable);
+unsafe impl Sync for VTable {}
+
+static VTABLE: VTable = VTable(PluginVTable {
+    abi_version: 1,
+    name: c"mean_luma".as_ptr(),
+    create,
+    process,
+    finish,
+    destroy,
+});
+
+#[no_mangle]
+pub extern "C" fn smelter_plugin_v1() -> *const PluginVTable {
+    &VTABLE.0
+}
+
+struct MeanLuma {
+    min: f64,
+    max: f64,
+    /// PTS and mean luma of the out of range frames.
+    outliers: Vec<(u64, f64)>,
+}
+
+unsafe extern "C" fn create(config_json: *const c_char) -> *mut c_void {
+    let config = unsafe { CStr::from_ptr(config_json) };
+    let Ok(config) = serde_json::from_slice::<serde_json::Value>(config.to_bytes()) else {
+        return std::ptr::null_mut();
+    };
+    let bound = |key, default| config.get(key).and_then(|v| v.as_f64()).unwrap_or(default);
+
+    Box::into_raw(Box::new(MeanLuma {
+        min: bound("min", 0.05),
+        max: bound("max", 0.95),
+        outliers: vec![],
+    })) as *mut c_void
+}
+
+unsafe extern "C" fn process(state: *mut c_void, frame: *const PluginFrame) -> i32 {
+    let (state, frame) = unsafe { (&mut *(state as *mut MeanLuma), &*frame) };
+    if frame.rgba.is_null() || frame.len == 0 {
+        return 1;
+    }
+    let pixels = unsafe { std::slice::from_raw_parts(frame.rgba, frame.len) };
+
+    let sum: f64 = pixels
+        .chunks_exact(4)
+        .map(|p| 0.2126 * p[0] as f64 + 0.7152 * p[1] as f64 + 0.0722 * p[2] as f64)
+        .sum();
+    let luma = sum / (pixels.len() / 4) as f64 / 255.0;
+    if luma < state.min || luma > state.max {
+        state.outliers.push((frame.pts_us, luma));
+    }
+
+    0
+}
+
+unsafe extern "C" fn finish(state: *mut c_void, emit: EmitFinding, ctx: *mut c_void) {
+    let state = unsafe { &mut *(state as *mut MeanLuma) };
+    for (pts_us, luma) in state.outliers.drain(..) {
+        let message = CString::new(format!(
+            "Mean luma {luma:.3} out of [{}, {}]",
+            state.min, state.max
+        ))
+        .unwrap();
+        unsafe { emit(ctx, pts_us as i64, message.as_ptr()) };
+    }
+}
+
+unsafe extern "C" fn destroy(state: *mut c_void) {
+    drop(unsafe { Box::from_raw(state as *mut MeanLuma) });
+}
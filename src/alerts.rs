@@ -0,0 +1,47 @@
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+
+use crate::config::AlertsConfig;
+
+/// Title of desktop notifications.
+const SUMMARY: &str = "smelter-colors run failed";
+
+/// Reports a failed run through the configured channels.
+///
+/// Delivery problems are only logged, they must not hide the failure itself.
+pub fn failure(config: &AlertsConfig, error: &anyhow::Error) {
+    let message = format!("{error:#}");
+
+    if config.desktop {
+        match notify_rust::Notification::new()
+            .summary(SUMMARY)
+            .body(&message)
+            .show()
+        {
+            Ok(_) => info!("Desktop notification sent"),
+            Err(err) => warn!("Cannot show desktop notification: {err}"),
+        }
+    }
+
+    if let Some(url) = &config.webhook_url {
+        match post(url, &message) {
+            Ok(()) => info!("Failure posted to webhook"),
+            Err(err) => warn!("Cannot post failure to webhook: {err:#}"),
+        }
+    }
+}
+
+/// Posts `{"text": ...}`, the payload of Slack incoming webhooks.
+fn post(url: &str, message: &str) -> Result<()> {
+    let host = std::env::var("HOSTNAME").unwrap_or_default();
+    let text = match host.as_str() {
+        "" => format!("{SUMMARY}: {message}"),
+        host => format!("{SUMMARY} on {host}: {message}"),
+    };
+
+    ureq::post(url)
+        .send_json(serde_json::json!({ "text": text }))
+        .with_context(|| format!("POST {url} failed"))?;
+
+    Ok(())
+}
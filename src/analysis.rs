@@ -0,0 +1,239 @@
+use anyhow::Result;
+use image::RgbaImage;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hasher};
+use std::time::Duration;
+use tracing::{info, warn};
+
+pub mod bars;
+pub mod cadence;
+pub mod chroma_alignment;
+pub mod classify;
+pub mod loop_junction;
+pub mod markers;
+pub mod metadata;
+pub mod plugin;
+pub mod pts_gaps;
+pub mod sharpness;
+
+/// An output frame read back to the CPU.
+pub struct CapturedFrame {
+    /// Position of the frame in the capture, starting at 0.
+    pub index: usize,
+    pub pts: Duration,
+    pub image: RgbaImage,
+    /// Hash of the pixels of `image`, equal for identical frames, see [`ResultCache`].
+    pub hash: u64,
+}
+
+/// Hash of the pixels of `image`, for [`CapturedFrame::hash`].
+pub fn frame_hash(image: &RgbaImage) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write_u32(image.width());
+    hasher.write_u32(image.height());
+    hasher.write(image.as_raw());
+    hasher.finish()
+}
+
+/// Most results a [`ResultCache`] keeps, it starts over once full.
+const CACHE_CAPACITY: usize = 256;
+
+/// Per-frame results of an analyzer, by [`CapturedFrame::hash`], so static scenes repeating
+/// the same frame are only measured once.
+pub struct ResultCache<T> {
+    results: HashMap<u64, T>,
+    hits: usize,
+    misses: usize,
+}
+
+impl<T: Clone> ResultCache<T> {
+    pub fn new() -> Self {
+        Self {
+            results: HashMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// The result of a frame identical to `frame` if there was one, else the one `compute`
+    /// returns.
+    pub fn get_or_compute(&mut self, frame: &CapturedFrame, compute: impl FnOnce() -> T) -> T {
+        if let Some(result) = self.results.get(&frame.hash) {
+            self.hits += 1;
+            return result.clone();
+        }
+        self.misses += 1;
+        if self.results.len() >= CACHE_CAPACITY {
+            self.results.clear();
+        }
+        let result = compute();
+        self.results.insert(frame.hash, result.clone());
+        result
+    }
+
+    /// Share of the frames whose result came from the cache, in `[0, 1]`.
+    pub fn hit_rate(&self) -> f32 {
+        self.hits as f32 / (self.hits + self.misses).max(1) as f32
+    }
+}
+
+impl<T: Clone> Default for ResultCache<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Something an analyzer noticed, that the run should be told about.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub analyzer: String,
+    pub pts: Option<Duration>,
+    pub message: String,
+}
+
+/// Analyzer fed with the captured frames, at its [`ProcessingRate`].
+pub trait FrameProcessor: Send {
+    fn name(&self) -> &str;
+
+    fn process(&mut self, frame: &CapturedFrame) -> Result<()>;
+
+    /// Called once the capture is over, returns what was found.
+    fn finish(&mut self) -> Vec<Finding>;
+
+    /// How often the processor is fed, expensive processors can skip frames so they don't
+    /// slow down the capture for the others.
+    fn rate(&self) -> ProcessingRate {
+        ProcessingRate::EveryFrame
+    }
+}
+
+/// Which captured frames a [`FrameProcessor`] is fed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProcessingRate {
+    #[default]
+    EveryFrame,
+    /// One frame out of `n`, starting with the first one.
+    EveryNth(usize),
+    /// The first frame of every scene.
+    OncePerScene,
+}
+
+impl ProcessingRate {
+    /// Whether `frame` is fed, `scene_start` telling whether it is the first of its scene.
+    pub fn is_due(&self, frame: &CapturedFrame, scene_start: bool) -> bool {
+        match self {
+            ProcessingRate::EveryFrame => true,
+            ProcessingRate::EveryNth(n) => frame.index % (*n).max(1) == 0,
+            ProcessingRate::OncePerScene => scene_start,
+        }
+    }
+}
+
+/// Feeds `processor` at `rate` instead of its own.
+pub fn with_rate(
+    processor: Box<dyn FrameProcessor>,
+    rate: ProcessingRate,
+) -> Box<dyn FrameProcessor> {
+    Box::new(WithRate { processor, rate })
+}
+
+struct WithRate {
+    processor: Box<dyn FrameProcessor>,
+    rate: ProcessingRate,
+}
+
+impl FrameProcessor for WithRate {
+    fn name(&self) -> &str {
+        self.processor.name()
+    }
+
+    fn process(&mut self, frame: &CapturedFrame) -> Result<()> {
+        self.processor.process(frame)
+    }
+
+    fn finish(&mut self) -> Vec<Finding> {
+        self.processor.finish()
+    }
+
+    fn rate(&self) -> ProcessingRate {
+        self.rate
+    }
+}
+
+impl Finding {
+    pub fn log(&self) {
+        match self.pts {
+            Some(pts) => warn!(
+                "[{}] at {:.3}s: {}",
+                self.analyzer,
+                pts.as_secs_f64(),
+                self.message
+            ),
+            None => warn!("[{}] {}", self.analyzer, self.message),
+        }
+    }
+}
+
+pub fn log_findings(findings: &[Finding]) {
+    if findings.is_empty() {
+        info!("No findings");
+    }
+    for finding in findings {
+        finding.log();
+    }
+}
+
+/// Only every `SAMPLE_STEP`th pixel of every `SAMPLE_STEP`th row is used for statistics,
+/// which is plenty to detect frame-wide changes at a fraction of the cost.
+const SAMPLE_STEP: u32 = 8;
+
+/// Frame-wide color statistics, channels normalized to `[0, 1]`.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameStats {
+    pub mean: [f32; 3],
+    /// Rec. 709 luma of the mean color.
+    pub luma: f32,
+}
+
+impl FrameStats {
+    pub fn of(image: &RgbaImage) -> Self {
+        let mut sum = [0.0f64; 3];
+        let mut count = 0;
+        for y in (0..image.height()).step_by(SAMPLE_STEP as usize) {
+            for x in (0..image.width()).step_by(SAMPLE_STEP as usize) {
+                let pixel = image.get_pixel(x, y);
+                for (channel, sum) in sum.iter_mut().enumerate() {
+                    *sum += pixel[channel] as f64;
+                }
+                count += 1;
+            }
+        }
+
+        let mean = sum.map(|sum| (sum / count.max(1) as f64 / 255.0) as f32);
+        Self {
+            mean,
+            luma: 0.2126 * mean[0] + 0.7152 * mean[1] + 0.0722 * mean[2],
+        }
+    }
+}
+
+/// Mean absolute difference between two frames over all channels, in `[0, 1]`.
+pub fn mean_abs_diff(a: &RgbaImage, b: &RgbaImage) -> f32 {
+    if a.dimensions() != b.dimensions() {
+        return 1.0;
+    }
+
+    let mut sum = 0u64;
+    let mut count = 0u64;
+    for y in (0..a.height()).step_by(SAMPLE_STEP as usize) {
+        for x in (0..a.width()).step_by(SAMPLE_STEP as usize) {
+            let (pa, pb) = (a.get_pixel(x, y), b.get_pixel(x, y));
+            for channel in 0..3 {
+                sum += pa[channel].abs_diff(pb[channel]) as u64;
+            }
+            count += 3;
+        }
+    }
+
+    sum as f32 / count.max(1) as f32 / 255.0
+}
@@ -0,0 +1,103 @@
+use image::RgbaImage;
+use tracing::info;
+
+use super::Finding;
+
+const ANALYZER: &str = "bar color";
+
+/// Smallest difference between both renders on a bar pixel, on at least one channel.
+const MIN_CONTRAST: u8 = 64;
+
+/// Color to clear the bars with in the second render, as far as possible from the expected one.
+pub fn contrast_color(expected: [u8; 3]) -> [u8; 3] {
+    expected.map(|channel| if channel < 128 { 255 } else { 0 })
+}
+
+/// Checks the color of the letterbox or pillarbox bars of a fitted input.
+///
+/// The input is rendered twice, with the bars cleared to `expected` then to
+/// [`contrast_color`]: pixels showing the contrast color in `contrast` and changing between
+/// both renders are bars, and must be `expected` in `render`, within `tolerance`. The content
+/// must not change between both renders, so still inputs work best.
+pub fn check(
+    render: &RgbaImage,
+    contrast: &RgbaImage,
+    expected: [u8; 3],
+    tolerance: u8,
+) -> Vec<Finding> {
+    let contrast_expected = contrast_color(expected);
+    let mut sum = [0u64; 3];
+    let mut count = 0u64;
+    let mut worst = 0;
+    for (pixel, contrast_pixel) in render.pixels().zip(contrast.pixels()) {
+        let is_bar = (0..3).all(|c| contrast_pixel[c].abs_diff(contrast_expected[c]) <= tolerance)
+            && (0..3).any(|c| contrast_pixel[c].abs_diff(pixel[c]) >= MIN_CONTRAST);
+        if !is_bar {
+            continue;
+        }
+        for c in 0..3 {
+            sum[c] += pixel[c] as u64;
+            worst = worst.max(pixel[c].abs_diff(expected[c]));
+        }
+        count += 1;
+    }
+
+    if count == 0 {
+        return vec![finding(
+            "no bars found, the input fills the output".to_string(),
+        )];
+    }
+    let coverage = count as f64 / (render.width() as f64 * render.height() as f64);
+    info!(
+        "Bars cover {:.1}% of the output, {} pixels off by up to {worst}",
+        coverage * 100.0,
+        count
+    );
+    if worst <= tolerance {
+        return vec![];
+    }
+
+    let mean = sum.map(|sum| (sum as f64 / count as f64).round() as u8);
+    let mut message = format!(
+        "bars are {} on average, expected {} (off by up to {worst})",
+        hex(mean),
+        hex(expected)
+    );
+    let close = |color: [u8; 3]| (0..3).all(|c| mean[c].abs_diff(color[c]) <= tolerance.max(1));
+    if close(expected.map(encode_srgb)) {
+        message += ", as if the color was taken as linear and encoded to sRGB";
+    } else if close(expected.map(decode_srgb)) {
+        message += ", as if the color was decoded to linear and never encoded back";
+    }
+    vec![finding(message)]
+}
+
+fn finding(message: String) -> Finding {
+    Finding {
+        analyzer: ANALYZER.to_string(),
+        pts: None,
+        message,
+    }
+}
+
+fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{r:02X}{g:02X}{b:02X}")
+}
+
+fn encode_srgb(channel: u8) -> u8 {
+    let linear = channel as f32 / 255.0;
+    let encoded = match linear <= 0.0031308 {
+        true => linear * 12.92,
+        false => 1.055 * linear.powf(1.0 / 2.4) - 0.055,
+    };
+    (encoded * 255.0).round() as u8
+}
+
+fn decode_srgb(channel: u8) -> u8 {
+    let encoded = channel as f32 / 255.0;
+    let linear = match encoded <= 0.04045 {
+        true => encoded / 12.92,
+        false => ((encoded + 0.055) / 1.055).powf(2.4),
+    };
+    (linear * 255.0).round() as u8
+}
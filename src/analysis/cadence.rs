@@ -0,0 +1,173 @@
+use anyhow::Result;
+use std::time::Duration;
+use tracing::{debug, info};
+
+use super::{CapturedFrame, Finding, FrameProcessor};
+use crate::counter;
+
+/// Slack on the number of source frames expected between two output frames, so PTS
+/// rounding does not count as a cadence error.
+const STEP_TOLERANCE: f64 = 0.05;
+/// Largest drift allowed between the source frames shown and the ones expected, in frames.
+const MAX_DRIFT: f64 = 1.0;
+
+/// Checks the cadence of a frame counter input rendered at another framerate.
+///
+/// Each output frame should show the latest source frame, never a blend of two, and advance
+/// by as many source frames as fit in the interval since the previous output frame: e.g.
+/// steps of 1 and 2 in a 1, 1, 1, 1, 2 pattern from 30fps to 25fps.
+pub struct CadenceAnalyzer {
+    source_interval: Duration,
+    /// PTS and decoded source index of every frame, `None` for blended frames.
+    frames: Vec<(Duration, Option<u32>)>,
+}
+
+impl CadenceAnalyzer {
+    pub fn new(source_interval: Duration) -> Self {
+        Self {
+            source_interval,
+            frames: vec![],
+        }
+    }
+
+    fn finding(&self, pts: Duration, message: String) -> Finding {
+        Finding {
+            analyzer: self.name().to_string(),
+            pts: Some(pts),
+            message,
+        }
+    }
+}
+
+impl FrameProcessor for CadenceAnalyzer {
+    fn name(&self) -> &str {
+        "cadence"
+    }
+
+    fn process(&mut self, frame: &CapturedFrame) -> Result<()> {
+        self.frames.push((frame.pts, counter::decode(&frame.image)));
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Vec<Finding> {
+        let mut findings = vec![];
+        let source_interval = self.source_interval.as_secs_f64();
+
+        // Blended frames are reported, then left out of the cadence
+        let decoded: Vec<(Duration, u32)> = self
+            .frames
+            .iter()
+            .filter_map(|&(pts, index)| match index {
+                Some(index) => Some((pts, index)),
+                None => {
+                    findings.push(self.finding(pts, "blended frame".to_string()));
+                    None
+                }
+            })
+            .collect();
+        let Some(&(first_pts, first_index)) = decoded.first() else {
+            if !self.frames.is_empty() {
+                findings.push(Finding {
+                    analyzer: self.name().to_string(),
+                    pts: None,
+                    message: "no frame counter found".to_string(),
+                });
+            }
+            return findings;
+        };
+
+        let mut steps = vec![];
+        for pair in decoded.windows(2) {
+            let ((previous_pts, previous), (pts, index)) = (pair[0], pair[1]);
+            let step = index as i64 - previous as i64;
+            let expected = (pts - previous_pts).as_secs_f64() / source_interval;
+            let (min, max) = (
+                (expected - STEP_TOLERANCE).floor() as i64,
+                (expected + STEP_TOLERANCE).ceil() as i64,
+            );
+            if step < min || step > max {
+                findings.push(self.finding(
+                    pts,
+                    format!("source frame {index} after {previous}, expected {min} to {max} frames later"),
+                ));
+            }
+
+            let drift = (index as i64 - first_index as i64) as f64
+                - (pts - first_pts).as_secs_f64() / source_interval;
+            if drift.abs() > MAX_DRIFT {
+                findings.push(self.finding(
+                    pts,
+                    format!("source frame {index} is {drift:+.1} frames off the expected cadence"),
+                ));
+            }
+            steps.push(step);
+        }
+
+        let pattern: Vec<String> = steps.iter().take(12).map(i64::to_string).collect();
+        info!(
+            "Cadence of {} frames: {}{}",
+            decoded.len(),
+            pattern.join(", "),
+            if steps.len() > pattern.len() {
+                ", ..."
+            } else {
+                ""
+            }
+        );
+        debug!(
+            "Source frames {first_index} to {}",
+            decoded.last().map_or(0, |(_, index)| *index)
+        );
+
+        findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 30fps source frames shown at 25fps, `indices` being the source frame of each output
+    /// frame.
+    fn analyze(indices: &[Option<u32>]) -> Vec<Finding> {
+        let mut analyzer = CadenceAnalyzer::new(Duration::from_nanos(1_000_000_000 / 30));
+        analyzer.frames = indices
+            .iter()
+            .enumerate()
+            .map(|(frame, &index)| (Duration::from_millis(40) * frame as u32, index))
+            .collect();
+        analyzer.finish()
+    }
+
+    #[test]
+    fn regular_pulldown() {
+        let indices: Vec<_> = (0..50).map(|frame| Some(frame * 6 / 5)).collect();
+        assert!(analyze(&indices).is_empty());
+    }
+
+    #[test]
+    fn skipped_frames() {
+        let mut indices: Vec<_> = (0..10).map(|frame| Some(frame * 6 / 5)).collect();
+        indices[9] = Some(13);
+        let findings = analyze(&indices);
+        assert_eq!(findings.len(), 2);
+        assert!(findings[0].message.starts_with("source frame 13 after 9"));
+        assert!(findings[1].message.contains("off the expected cadence"));
+        assert_eq!(findings[0].pts, Some(Duration::from_millis(360)));
+    }
+
+    #[test]
+    fn blended_frames() {
+        let findings = analyze(&[Some(0), Some(1), None, Some(3)]);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].message, "blended frame");
+        assert_eq!(findings[0].pts, Some(Duration::from_millis(80)));
+    }
+
+    #[test]
+    fn no_counter() {
+        let findings = analyze(&[None, None]);
+        assert_eq!(findings.last().unwrap().message, "no frame counter found");
+        assert!(analyze(&[]).is_empty());
+    }
+}
@@ -0,0 +1,278 @@
+use anyhow::Result;
+use image::RgbaImage;
+use tracing::info;
+
+use super::{CapturedFrame, Finding, FrameProcessor, ResultCache};
+
+/// Only every `LINE_STEP`th row is scanned for vertical edges, and column for horizontal ones.
+const LINE_STEP: u32 = 4;
+/// Smallest luma step, in `[0, 1]`, for a pixel to be on an edge.
+const MIN_LUMA_STEP: f32 = 0.1;
+/// Smallest chroma step across an edge for it to be measured: edges between grays only
+/// move luma.
+const MIN_CHROMA_STEP: f32 = 0.05;
+/// Pixels on each side of an edge its position is computed over. Edges closer than twice
+/// this to each other are skipped, their gradients would overlap.
+const WINDOW: usize = 3;
+
+/// Measures how far the chroma planes are shifted from the luma plane, from the edges of
+/// the content where both change, such as the bars of a `smpte_bars` pattern.
+///
+/// The position of each edge is the centroid of the luma gradient across it, and of the
+/// chroma one, to a fraction of a pixel: chroma siting or scaling mistakes in the
+/// decode/scale chain show up as shifts of around half a pixel, along rows for vertical
+/// edges and along columns for horizontal ones. Offsets are medians over all edges, in
+/// output pixels, positive to the right and down.
+pub struct ChromaAlignmentAnalyzer {
+    tolerance: f32,
+    /// Median horizontal and vertical offsets of every frame, `None` without edges.
+    frames: Vec<(Option<f32>, Option<f32>)>,
+    cache: ResultCache<(Option<f32>, Option<f32>)>,
+}
+
+impl ChromaAlignmentAnalyzer {
+    /// Offsets up to `tolerance` pixels are not reported.
+    pub fn new(tolerance: f32) -> Self {
+        Self {
+            tolerance,
+            frames: vec![],
+            cache: ResultCache::new(),
+        }
+    }
+
+    fn finding(&self, message: String) -> Finding {
+        Finding {
+            analyzer: self.name().to_string(),
+            pts: None,
+            message,
+        }
+    }
+}
+
+impl FrameProcessor for ChromaAlignmentAnalyzer {
+    fn name(&self) -> &str {
+        "chroma alignment"
+    }
+
+    fn process(&mut self, frame: &CapturedFrame) -> Result<()> {
+        let offsets = self
+            .cache
+            .get_or_compute(frame, || frame_offsets(&frame.image));
+        self.frames.push(offsets);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Vec<Finding> {
+        if self.frames.is_empty() {
+            return vec![];
+        }
+        let horizontal = median(self.frames.iter().filter_map(|frame| frame.0).collect());
+        let vertical = median(self.frames.iter().filter_map(|frame| frame.1).collect());
+        if horizontal.is_none() && vertical.is_none() {
+            return vec![self.finding("no colored edges to measure".to_string())];
+        }
+
+        let mut findings = vec![];
+        for (direction, offset) in [("horizontal", horizontal), ("vertical", vertical)] {
+            let Some(offset) = offset else {
+                info!("Chroma alignment: no {direction} offset measured");
+                continue;
+            };
+            info!("Chroma {direction} offset: {offset:+.2} px");
+            if offset.abs() > self.tolerance {
+                findings.push(self.finding(format!(
+                    "chroma shifted {offset:+.2} px {direction}ly from luma, over {:.2} px",
+                    self.tolerance
+                )));
+            }
+        }
+        findings
+    }
+}
+
+/// Median horizontal and vertical offsets of the edges of `image`.
+fn frame_offsets(image: &RgbaImage) -> (Option<f32>, Option<f32>) {
+    let planes = Planes::of(image);
+    let (width, height) = (planes.width, planes.height);
+    let rows = (0..height)
+        .step_by(LINE_STEP as usize)
+        .map(|y| (0..width).map(|x| planes.at(x, y)).collect::<Vec<_>>());
+    let columns = (0..width)
+        .step_by(LINE_STEP as usize)
+        .map(|x| (0..height).map(|y| planes.at(x, y)).collect::<Vec<_>>());
+
+    (
+        median(rows.flat_map(|line| offsets(&line)).collect()),
+        median(columns.flat_map(|line| offsets(&line)).collect()),
+    )
+}
+
+/// Luma and chroma of a frame, BT.709 on the encoded values.
+struct Planes {
+    width: u32,
+    height: u32,
+    /// Y, Cb and Cr of every pixel, row by row.
+    pixels: Vec<[f32; 3]>,
+}
+
+impl Planes {
+    fn of(image: &RgbaImage) -> Self {
+        let pixels = image
+            .pixels()
+            .map(|pixel| {
+                let [r, g, b] = [0, 1, 2].map(|c| pixel[c] as f32 / 255.0);
+                let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+                [y, (b - y) / 1.8556, (r - y) / 1.5748]
+            })
+            .collect();
+        Self {
+            width: image.width(),
+            height: image.height(),
+            pixels,
+        }
+    }
+
+    fn at(&self, x: u32, y: u32) -> [f32; 3] {
+        self.pixels[(y * self.width + x) as usize]
+    }
+}
+
+/// Chroma minus luma position of the isolated edges of a line of YCbCr pixels.
+fn offsets(line: &[[f32; 3]]) -> Vec<f32> {
+    if line.len() < 2 {
+        return vec![];
+    }
+    let luma: Vec<f32> = line
+        .windows(2)
+        .map(|pair| (pair[1][0] - pair[0][0]).abs())
+        .collect();
+    let chroma: Vec<f32> = line
+        .windows(2)
+        .map(|pair| (pair[1][1] - pair[0][1]).abs() + (pair[1][2] - pair[0][2]).abs())
+        .collect();
+
+    // Local maxima of the luma gradient
+    let edges: Vec<usize> = (1..luma.len().saturating_sub(1))
+        .filter(|&i| luma[i] >= MIN_LUMA_STEP && luma[i] > luma[i - 1] && luma[i] >= luma[i + 1])
+        .collect();
+    let mut offsets = vec![];
+    for (index, &edge) in edges.iter().enumerate() {
+        let previous = index.checked_sub(1).map(|i| edges[i]);
+        let next = edges.get(index + 1).copied();
+        if [previous, next]
+            .into_iter()
+            .flatten()
+            .any(|other| other.abs_diff(edge) <= WINDOW * 2)
+        {
+            continue;
+        }
+        if edge < WINDOW || edge + WINDOW >= luma.len() {
+            continue;
+        }
+        let window = edge - WINDOW..edge + WINDOW + 1;
+        if chroma[window.clone()].iter().sum::<f32>() < MIN_CHROMA_STEP {
+            continue;
+        }
+        if let (Some(luma), Some(chroma)) =
+            (centroid(&luma[window.clone()]), centroid(&chroma[window]))
+        {
+            offsets.push(chroma - luma);
+        }
+    }
+    offsets
+}
+
+/// Position of the center of mass of `weights`, from the first one.
+fn centroid(weights: &[f32]) -> Option<f32> {
+    let total: f32 = weights.iter().sum();
+    (total > 0.0).then(|| {
+        weights
+            .iter()
+            .enumerate()
+            .map(|(i, weight)| i as f32 * weight)
+            .sum::<f32>()
+            / total
+    })
+}
+
+fn median(mut values: Vec<f32>) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable_by(f32::total_cmp);
+    Some(values[values.len() / 2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DARK: [f32; 3] = [0.0, 0.0, 0.0];
+    const RED: [f32; 3] = [0.5, 0.2, 0.3];
+
+    /// Line stepping from dark to red after 10 pixels, its chroma after `chroma` more.
+    fn line(chroma: &[[f32; 3]]) -> Vec<[f32; 3]> {
+        let mut line = vec![DARK; 10];
+        line.extend(chroma.iter().map(|&[_, cb, cr]| [RED[0], cb, cr]));
+        line.resize(20, RED);
+        line
+    }
+
+    fn assert_offsets(line: &[[f32; 3]], expected: &[f32]) {
+        let offsets = offsets(line);
+        assert_eq!(offsets.len(), expected.len(), "{offsets:?}");
+        for (offset, expected) in offsets.iter().zip(expected) {
+            assert!((offset - expected).abs() < 1e-4, "{offsets:?}");
+        }
+    }
+
+    #[test]
+    fn aligned_edge() {
+        assert_offsets(&line(&[]), &[0.0]);
+    }
+
+    #[test]
+    fn shifted_chroma() {
+        assert_offsets(&line(&[DARK]), &[1.0]);
+        assert_offsets(&line(&[DARK, DARK]), &[2.0]);
+        // Chroma halfway there on the first pixel
+        assert_offsets(&line(&[[0.0, 0.1, 0.15]]), &[0.5]);
+    }
+
+    #[test]
+    fn skipped_edges() {
+        // Gray edge
+        let mut gray = vec![DARK; 10];
+        gray.resize(20, [0.5, 0.0, 0.0]);
+        assert_offsets(&gray, &[]);
+
+        // Edges overlapping each other
+        let mut close = line(&[]);
+        close[14..].fill(DARK);
+        assert_offsets(&close, &[]);
+
+        // Edge too close to the end of the line
+        assert_offsets(&line(&[])[7..], &[]);
+    }
+
+    #[test]
+    fn median_offsets() {
+        assert_eq!(median(vec![]), None);
+        assert_eq!(median(vec![0.5, -1.0, 0.25]), Some(0.25));
+    }
+
+    #[test]
+    fn findings() {
+        let mut analyzer = ChromaAlignmentAnalyzer::new(0.25);
+        analyzer.frames = vec![(Some(0.5), Some(0.1)), (Some(0.5), None), (Some(0.0), None)];
+        let findings = analyzer.finish();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].message,
+            "chroma shifted +0.50 px horizontally from luma, over 0.25 px"
+        );
+
+        analyzer.frames = vec![(None, None)];
+        assert_eq!(analyzer.finish()[0].message, "no colored edges to measure");
+    }
+}
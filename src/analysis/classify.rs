@@ -0,0 +1,345 @@
+use image::RgbaImage;
+
+use super::Finding;
+use crate::annotate::Rect;
+
+const ANALYZER: &str = "classification";
+/// Cells per side of the grid patches are averaged over.
+const GRID: u32 = 8;
+/// Mean error, out of 255, below which colors are considered right.
+const MIN_ERROR: f32 = 2.0;
+/// Smallest luma difference across a pixel for it to be on an edge, and largest one for it
+/// to be flat, out of 1.
+const EDGE_GRADIENT: f32 = 0.1;
+const FLAT_GRADIENT: f32 = 0.01;
+
+/// Mean color of the same area of a reference frame and of the rendered one, normalized
+/// to `[0, 1]`.
+#[derive(Debug, Clone, Copy)]
+pub struct Patch {
+    pub reference: [f32; 3],
+    pub measured: [f32; 3],
+}
+
+/// Chroma error of the rendered frame on the edges of the reference frame, and away from
+/// them, out of 255.
+#[derive(Debug, Clone, Copy)]
+pub struct EdgeErrors {
+    pub edge: f32,
+    pub flat: f32,
+}
+
+/// Likely root cause of a color error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cause {
+    /// Full range levels squeezed into limited range, or limited range shown as full: washed out.
+    RangeCompressed,
+    /// Limited range levels stretched as if full range was expected: crushed.
+    RangeExpanded,
+    /// BT.601 content decoded with the BT.709 matrix.
+    Matrix601As709,
+    /// BT.709 content decoded with the BT.601 matrix.
+    Matrix709As601,
+    /// The sRGB transfer applied twice: too bright.
+    DoubleEncoded,
+    /// The sRGB transfer undone twice: too dark.
+    DoubleDecoded,
+    /// Right colors on flat areas, wrong ones along edges: chroma siting or alignment.
+    ChromaSiting,
+}
+
+impl Cause {
+    fn describe(self) -> &'static str {
+        match self {
+            Cause::RangeCompressed => "range mismatch, levels compressed (washed out)",
+            Cause::RangeExpanded => "range mismatch, levels expanded (crushed)",
+            Cause::Matrix601As709 => "matrix mismatch, BT.601 content decoded as BT.709",
+            Cause::Matrix709As601 => "matrix mismatch, BT.709 content decoded as BT.601",
+            Cause::DoubleEncoded => "gamma applied twice (sRGB encoded twice)",
+            Cause::DoubleDecoded => "gamma removed twice (sRGB decoded twice)",
+            Cause::ChromaSiting => "chroma siting error, colors bleed along edges",
+        }
+    }
+
+    /// What the error turns the reference into, `None` for spatial causes.
+    fn model(self) -> Option<fn([f32; 3]) -> [f32; 3]> {
+        let model: fn([f32; 3]) -> [f32; 3] = match self {
+            Cause::RangeCompressed => |rgb| rgb.map(|c| (16.0 + c * 219.0) / 255.0),
+            Cause::RangeExpanded => |rgb| rgb.map(|c| ((c * 255.0 - 16.0) / 219.0).clamp(0.0, 1.0)),
+            Cause::Matrix601As709 => |rgb| to_rgb(BT709, to_ycbcr(BT601, rgb)),
+            Cause::Matrix709As601 => |rgb| to_rgb(BT601, to_ycbcr(BT709, rgb)),
+            Cause::DoubleEncoded => |rgb| rgb.map(encode_srgb),
+            Cause::DoubleDecoded => |rgb| rgb.map(decode_srgb),
+            Cause::ChromaSiting => return None,
+        };
+        Some(model)
+    }
+}
+
+/// A cause, with the mean error of the patches before and once explained by it.
+#[derive(Debug, Clone, Copy)]
+pub struct Classification {
+    pub cause: Cause,
+    /// Mean error, out of 255.
+    pub error: f32,
+    /// Mean error left once the cause is accounted for, out of 255.
+    pub residual: f32,
+}
+
+impl Classification {
+    pub fn finding(&self) -> Finding {
+        Finding {
+            analyzer: ANALYZER.to_string(),
+            pts: None,
+            message: format!(
+                "likely {} (mean error {:.1}, {:.1} once accounted for)",
+                self.cause.describe(),
+                self.error,
+                self.residual
+            ),
+        }
+    }
+}
+
+/// Picks the cause whose signature explains the measured colors best.
+///
+/// Global color errors are told apart by how well each cause predicts the measured patches
+/// from the reference ones; the best one must halve the error. Chroma siting errors leave
+/// flat areas right, so they are only considered when the patches match, from `edges`.
+pub fn classify(patches: &[Patch], edges: Option<EdgeErrors>) -> Option<Classification> {
+    let error = mean_error(patches, |rgb| rgb);
+    if error < MIN_ERROR {
+        let edges = edges?;
+        return (edges.edge > MIN_ERROR * 2.0 && edges.edge > edges.flat * 3.0).then_some(
+            Classification {
+                cause: Cause::ChromaSiting,
+                error: edges.edge,
+                residual: edges.flat,
+            },
+        );
+    }
+
+    [
+        Cause::RangeCompressed,
+        Cause::RangeExpanded,
+        Cause::Matrix601As709,
+        Cause::Matrix709As601,
+        Cause::DoubleEncoded,
+        Cause::DoubleDecoded,
+    ]
+    .into_iter()
+    .filter_map(|cause| {
+        Some(Classification {
+            cause,
+            error,
+            residual: mean_error(patches, cause.model()?),
+        })
+    })
+    .filter(|classification| classification.residual < error / 2.0)
+    .min_by(|a, b| a.residual.total_cmp(&b.residual))
+}
+
+/// Mean colors of the cells of a grid over `reference`, and over the same cells of
+/// `measured` within `area`, where the reference is shown scaled.
+///
+/// Only the middle of each cell is averaged, so scaling filters do not blur the patches.
+pub fn patches(reference: &RgbaImage, measured: &RgbaImage, area: Rect) -> Vec<Patch> {
+    let mean = |image: &RgbaImage, x: f32, y: f32, width: f32, height: f32| {
+        let (x0, y0) = ((x + width * 0.2) as u32, (y + height * 0.2) as u32);
+        let (x1, y1) = ((x + width * 0.8) as u32, (y + height * 0.8) as u32);
+        let mut sum = [0.0f64; 3];
+        let mut count = 0;
+        for y in y0..y1.min(image.height()) {
+            for x in x0..x1.min(image.width()) {
+                let pixel = image.get_pixel(x, y);
+                for (c, sum) in sum.iter_mut().enumerate() {
+                    *sum += pixel[c] as f64 / 255.0;
+                }
+                count += 1;
+            }
+        }
+        (count > 0).then(|| sum.map(|sum| (sum / count as f64) as f32))
+    };
+
+    let cell = |width: f32, height: f32| (width / GRID as f32, height / GRID as f32);
+    let (reference_width, reference_height) =
+        cell(reference.width() as f32, reference.height() as f32);
+    let (area_width, area_height) = cell(area.width as f32, area.height as f32);
+    let mut patches = vec![];
+    for row in 0..GRID {
+        for column in 0..GRID {
+            let (column, row) = (column as f32, row as f32);
+            let reference = mean(
+                reference,
+                column * reference_width,
+                row * reference_height,
+                reference_width,
+                reference_height,
+            );
+            let measured = mean(
+                measured,
+                area.x as f32 + column * area_width,
+                area.y as f32 + row * area_height,
+                area_width,
+                area_height,
+            );
+            if let (Some(reference), Some(measured)) = (reference, measured) {
+                patches.push(Patch {
+                    reference,
+                    measured,
+                });
+            }
+        }
+    }
+    patches
+}
+
+/// Chroma errors on and away from the edges of `reference`, for a frame showing it at its
+/// own size. `None` when the sizes differ, or either kind of pixel is missing.
+pub fn edge_errors(reference: &RgbaImage, measured: &RgbaImage) -> Option<EdgeErrors> {
+    if reference.dimensions() != measured.dimensions() {
+        return None;
+    }
+    let rgb = |image: &RgbaImage, x, y| {
+        let pixel = image.get_pixel(x, y);
+        [0, 1, 2].map(|c| pixel[c] as f32 / 255.0)
+    };
+    let luma = |x, y| to_ycbcr(BT709, rgb(reference, x, y))[0];
+
+    let (mut edge, mut flat) = ((0.0, 0), (0.0, 0));
+    for y in 1..reference.height().saturating_sub(1) {
+        for x in 1..reference.width().saturating_sub(1) {
+            let gradient =
+                (luma(x + 1, y) - luma(x - 1, y)).abs() + (luma(x, y + 1) - luma(x, y - 1)).abs();
+            let kind = match gradient {
+                gradient if gradient >= EDGE_GRADIENT => &mut edge,
+                gradient if gradient <= FLAT_GRADIENT => &mut flat,
+                _ => continue,
+            };
+            let [_, cb, cr] = to_ycbcr(BT709, rgb(reference, x, y));
+            let [_, measured_cb, measured_cr] = to_ycbcr(BT709, rgb(measured, x, y));
+            kind.0 += ((cb - measured_cb).abs() + (cr - measured_cr).abs()) / 2.0 * 255.0;
+            kind.1 += 1;
+        }
+    }
+
+    (edge.1 > 0 && flat.1 > 0).then(|| EdgeErrors {
+        edge: edge.0 / edge.1 as f32,
+        flat: flat.0 / flat.1 as f32,
+    })
+}
+
+/// Mean error over the channels of the patches, out of 255, once `model` is applied to
+/// the reference colors.
+fn mean_error(patches: &[Patch], model: fn([f32; 3]) -> [f32; 3]) -> f32 {
+    let total: f32 = patches
+        .iter()
+        .map(|patch| {
+            let predicted = model(patch.reference);
+            (0..3)
+                .map(|c| (predicted[c] - patch.measured[c]).abs())
+                .sum::<f32>()
+        })
+        .sum();
+    total / (patches.len().max(1) * 3) as f32 * 255.0
+}
+
+/// Luma coefficients of red and blue.
+const BT601: (f32, f32) = (0.299, 0.114);
+const BT709: (f32, f32) = (0.2126, 0.0722);
+
+fn to_ycbcr((kr, kb): (f32, f32), [r, g, b]: [f32; 3]) -> [f32; 3] {
+    let y = kr * r + (1.0 - kr - kb) * g + kb * b;
+    [
+        y,
+        (b - y) / (2.0 * (1.0 - kb)),
+        (r - y) / (2.0 * (1.0 - kr)),
+    ]
+}
+
+fn to_rgb((kr, kb): (f32, f32), [y, cb, cr]: [f32; 3]) -> [f32; 3] {
+    let r = y + 2.0 * (1.0 - kr) * cr;
+    let b = y + 2.0 * (1.0 - kb) * cb;
+    let g = (y - kr * r - kb * b) / (1.0 - kr - kb);
+    [r, g, b].map(|c| c.clamp(0.0, 1.0))
+}
+
+fn encode_srgb(linear: f32) -> f32 {
+    match linear <= 0.0031308 {
+        true => linear * 12.92,
+        false => 1.055 * linear.powf(1.0 / 2.4) - 0.055,
+    }
+}
+
+fn decode_srgb(encoded: f32) -> f32 {
+    match encoded <= 0.04045 {
+        true => encoded / 12.92,
+        false => ((encoded + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Patches of saturated and gray colors, measured as `model` turns them.
+    fn patches(model: impl Fn([f32; 3]) -> [f32; 3]) -> Vec<Patch> {
+        let levels = [0.1, 0.5, 0.9];
+        let mut patches = vec![];
+        for r in levels {
+            for g in levels {
+                for b in levels {
+                    let reference = [r, g, b];
+                    patches.push(Patch {
+                        reference,
+                        measured: model(reference),
+                    });
+                }
+            }
+        }
+        patches
+    }
+
+    #[test]
+    fn global_causes() {
+        for cause in [
+            Cause::RangeCompressed,
+            Cause::RangeExpanded,
+            Cause::Matrix601As709,
+            Cause::Matrix709As601,
+            Cause::DoubleEncoded,
+            Cause::DoubleDecoded,
+        ] {
+            let classification = classify(&patches(cause.model().unwrap()), None).unwrap();
+            assert_eq!(classification.cause, cause);
+            assert!(classification.error >= MIN_ERROR, "{cause:?}");
+            assert!(classification.residual < 0.1, "{cause:?}");
+        }
+    }
+
+    #[test]
+    fn matching_colors() {
+        assert!(classify(&patches(|rgb| rgb), None).is_none());
+        let edges = EdgeErrors {
+            edge: 1.0,
+            flat: 0.5,
+        };
+        assert!(classify(&patches(|rgb| rgb), Some(edges)).is_none());
+    }
+
+    #[test]
+    fn chroma_siting() {
+        let edges = EdgeErrors {
+            edge: 12.0,
+            flat: 1.0,
+        };
+        let classification = classify(&patches(|rgb| rgb), Some(edges)).unwrap();
+        assert_eq!(classification.cause, Cause::ChromaSiting);
+        assert_eq!((classification.error, classification.residual), (12.0, 1.0));
+    }
+
+    #[test]
+    fn unexplained_error() {
+        assert!(classify(&patches(|rgb| rgb.map(|c| 1.0 - c)), None).is_none());
+    }
+}
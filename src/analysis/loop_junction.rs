@@ -0,0 +1,136 @@
+use anyhow::Result;
+use image::RgbaImage;
+use std::time::Duration;
+use tracing::debug;
+
+use super::{mean_abs_diff, CapturedFrame, Finding, FrameProcessor, FrameStats};
+
+/// Frames darker than this are considered black.
+const BLACK_LUMA: f32 = 0.03;
+/// Smallest frame difference considered a color pop.
+const MIN_POP_DIFF: f32 = 0.08;
+/// How much larger than the typical frame difference a pop must be.
+const POP_FACTOR: f32 = 4.0;
+/// Typical frame difference above which the content is considered moving,
+/// so that identical consecutive frames are duplicates rather than a still scene.
+const MOVING_DIFF: f32 = 0.01;
+const DUPLICATE_DIFF: f32 = 0.001;
+
+/// Looks for discontinuities where a looping input restarts.
+///
+/// The loop points are predicted from the clip duration, starting from the first
+/// captured frame, so the capture should start together with the clip (e.g. on a
+/// freshly registered input). Glitches outside of `tolerance` of a loop point are
+/// not reported, since they are not caused by looping.
+pub struct LoopJunctionAnalyzer {
+    period: Duration,
+    tolerance: Duration,
+    start_pts: Option<Duration>,
+    previous: Option<RgbaImage>,
+    /// PTS, difference with the previous frame and luma of every frame.
+    frames: Vec<(Duration, f32, f32)>,
+}
+
+impl LoopJunctionAnalyzer {
+    pub fn new(period: Duration, tolerance: Duration) -> Self {
+        Self {
+            period,
+            tolerance,
+            start_pts: None,
+            previous: None,
+            frames: vec![],
+        }
+    }
+
+    fn near_loop_point(&self, pts: Duration) -> bool {
+        let Some(start) = self.start_pts else {
+            return false;
+        };
+        let elapsed = pts.saturating_sub(start).as_secs_f64();
+        let period = self.period.as_secs_f64();
+        if period <= 0.0 || elapsed < period / 2.0 {
+            return false;
+        }
+
+        let nearest = (elapsed / period).round() * period;
+        (elapsed - nearest).abs() <= self.tolerance.as_secs_f64()
+    }
+
+    fn finding(&self, pts: Duration, message: String) -> Finding {
+        Finding {
+            analyzer: self.name().to_string(),
+            pts: Some(pts),
+            message,
+        }
+    }
+}
+
+impl FrameProcessor for LoopJunctionAnalyzer {
+    fn name(&self) -> &str {
+        "loop junction"
+    }
+
+    fn process(&mut self, frame: &CapturedFrame) -> Result<()> {
+        self.start_pts.get_or_insert(frame.pts);
+
+        let diff = match &self.previous {
+            Some(previous) => mean_abs_diff(previous, &frame.image),
+            None => 0.0,
+        };
+        let luma = FrameStats::of(&frame.image).luma;
+        self.frames.push((frame.pts, diff, luma));
+        self.previous = Some(frame.image.clone());
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Vec<Finding> {
+        let mut diffs: Vec<f32> = self
+            .frames
+            .iter()
+            .skip(1)
+            .map(|(_, diff, _)| *diff)
+            .collect();
+        if diffs.is_empty() {
+            return vec![];
+        }
+        diffs.sort_unstable_by(f32::total_cmp);
+        let typical = diffs[diffs.len() / 2];
+        debug!("Typical frame difference: {typical:.4}");
+
+        let mut findings = vec![];
+        let mut ignored = 0;
+        for i in 1..self.frames.len() {
+            let (pts, diff, luma) = self.frames[i];
+            let (_, _, previous_luma) = self.frames[i - 1];
+            let next_luma = self
+                .frames
+                .get(i + 1)
+                .map_or(previous_luma, |(_, _, luma)| *luma);
+
+            let message =
+                if luma < BLACK_LUMA && previous_luma > BLACK_LUMA && next_luma > BLACK_LUMA {
+                    format!(
+                    "black flash (luma {luma:.3} between {previous_luma:.3} and {next_luma:.3})"
+                )
+                } else if typical > MOVING_DIFF && diff < DUPLICATE_DIFF {
+                    format!("duplicated frame (difference {diff:.4}, typical {typical:.4})")
+                } else if diff > MIN_POP_DIFF.max(typical * POP_FACTOR) {
+                    format!("color pop (difference {diff:.4}, typical {typical:.4})")
+                } else {
+                    continue;
+                };
+
+            if self.near_loop_point(pts) {
+                findings.push(self.finding(pts, message));
+            } else {
+                ignored += 1;
+            }
+        }
+        if ignored > 0 {
+            debug!("Ignored {ignored} discontinuities away from loop points");
+        }
+
+        findings
+    }
+}
@@ -0,0 +1,121 @@
+use anyhow::{Context, Result};
+use image::RgbaImage;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::info;
+
+use super::{CapturedFrame, Finding, FrameProcessor};
+use crate::annotate::Rect;
+use crate::markers::Marker;
+
+/// Largest difference of a pixel from the marker color, on each channel, out of 255.
+/// Encoding blurs the edges of the square and shifts its chroma, so only its middle half
+/// is compared.
+const TOLERANCE: u8 = 48;
+
+/// Reads the event markers back out of a recording made with `markers.enabled`, see
+/// [`crate::markers`], and rebuilds the timeline of the events from the video alone.
+///
+/// The recording must be shown at the output resolution, with the marker settings of the
+/// run that recorded it. A marker shown on consecutive frames is a single event, at the PTS
+/// of its first frame.
+pub struct MarkerDecoder {
+    square: Rect,
+    /// CSV file the timeline is written to.
+    timeline: Option<PathBuf>,
+    /// Marker of the previous frame.
+    previous: Option<Marker>,
+    events: Vec<(Duration, Marker)>,
+}
+
+impl MarkerDecoder {
+    /// `square` is where markers are drawn in the output, see [`crate::markers::square`].
+    pub fn new(square: Rect, timeline: Option<PathBuf>) -> Self {
+        Self {
+            square,
+            timeline,
+            previous: None,
+            events: vec![],
+        }
+    }
+
+    /// PTS and marker of every event decoded so far.
+    pub fn events(&self) -> &[(Duration, Marker)] {
+        &self.events
+    }
+
+    fn finding(&self, message: String) -> Finding {
+        Finding {
+            analyzer: self.name().to_string(),
+            pts: None,
+            message,
+        }
+    }
+
+    fn write_timeline(&self) -> Result<()> {
+        let Some(path) = &self.timeline else {
+            return Ok(());
+        };
+        let mut csv = "pts_ms,event\n".to_string();
+        for (pts, marker) in &self.events {
+            writeln!(csv, "{:.1},{}", pts.as_secs_f64() * 1000.0, marker.name())?;
+        }
+        std::fs::write(path, csv).with_context(|| format!("Cannot write {}", path.display()))?;
+        info!("Wrote the marker timeline to {}", path.display());
+        Ok(())
+    }
+}
+
+impl FrameProcessor for MarkerDecoder {
+    fn name(&self) -> &str {
+        "markers"
+    }
+
+    fn process(&mut self, frame: &CapturedFrame) -> Result<()> {
+        let marker = detect(&frame.image, self.square);
+        if let Some(shown) = marker {
+            if self.previous != marker {
+                self.events.push((frame.pts, shown));
+            }
+        }
+        self.previous = marker;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Vec<Finding> {
+        for (pts, marker) in &self.events {
+            info!("Marker at {:.3}s: {}", pts.as_secs_f64(), marker.name());
+        }
+        let mut findings = vec![];
+        if self.events.is_empty() {
+            findings.push(self.finding("no markers found, were markers enabled?".to_string()));
+        }
+        if let Err(err) = self.write_timeline() {
+            findings.push(self.finding(format!("{err:#}")));
+        }
+        findings
+    }
+}
+
+/// Marker whose color covers the middle half of `square`, if any.
+fn detect(image: &RgbaImage, square: Rect) -> Option<Marker> {
+    let (x0, y0) = (square.x + square.width / 4, square.y + square.height / 4);
+    let (x1, y1) = (
+        (square.x + square.width * 3 / 4).min(image.width()),
+        (square.y + square.height * 3 / 4).min(image.height()),
+    );
+    if x1 <= x0 || y1 <= y0 {
+        return None;
+    }
+
+    Marker::ALL.into_iter().find(|marker| {
+        let color = marker.color();
+        (y0..y1).all(|y| {
+            (x0..x1).all(|x| {
+                let pixel = image.get_pixel(x, y);
+                (0..3).all(|c| pixel[c].abs_diff(color[c]) <= TOLERANCE)
+            })
+        })
+    })
+}
@@ -0,0 +1,128 @@
+use compositor_render::{Frame, FrameData, Resolution};
+use image::RgbaImage;
+use std::time::Duration;
+
+use super::Finding;
+
+const ANALYZER: &str = "frame metadata";
+/// Findings reported before the rest are only counted, a broken integration breaks every frame.
+const MAX_FINDINGS: usize = 20;
+
+/// Checks that the metadata of every captured frame matches its pixel data: the PTS
+/// increases, the texture has the format and size the frame claims, and so does its
+/// readback.
+///
+/// Cheap enough to run on every frame, it catches integration bugs before they show up
+/// as wrong colors.
+pub struct MetadataCheck {
+    resolution: Resolution,
+    previous_pts: Option<Duration>,
+    findings: Vec<Finding>,
+    dropped: usize,
+}
+
+impl MetadataCheck {
+    /// `resolution` is the one the output was registered with.
+    pub fn new(resolution: Resolution) -> Self {
+        Self {
+            resolution,
+            previous_pts: None,
+            findings: vec![],
+            dropped: 0,
+        }
+    }
+
+    /// Checks a raw output frame, and the image read back from it.
+    pub fn check(&mut self, frame: &Frame, image: &RgbaImage) {
+        let pts = frame.pts;
+        if let Some(previous) = self.previous_pts.replace(pts) {
+            if pts <= previous {
+                self.report(
+                    pts,
+                    format!("PTS {pts:?} does not increase from {previous:?}"),
+                );
+            }
+        }
+
+        let Resolution { width, height } = frame.resolution;
+        if frame.resolution != self.resolution {
+            self.report(
+                pts,
+                format!(
+                    "frame is {width}x{height}, the output {}x{}",
+                    self.resolution.width, self.resolution.height
+                ),
+            );
+        }
+
+        match &frame.data {
+            FrameData::Rgba8UnormWgpuTexture(texture) => {
+                let size = texture.size();
+                if (size.width as usize, size.height as usize) != (width, height) {
+                    self.report(
+                        pts,
+                        format!(
+                            "texture is {}x{}, the frame claims {width}x{height}",
+                            size.width, size.height
+                        ),
+                    );
+                }
+                if size.depth_or_array_layers != 1 || texture.mip_level_count() != 1 {
+                    self.report(
+                        pts,
+                        format!(
+                            "texture has {} layers and {} mip levels, expected 1 of each",
+                            size.depth_or_array_layers,
+                            texture.mip_level_count()
+                        ),
+                    );
+                }
+                if !matches!(
+                    texture.format(),
+                    wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb
+                ) {
+                    self.report(
+                        pts,
+                        format!("texture is {:?}, expected RGBA8", texture.format()),
+                    );
+                }
+            }
+            _ => self.report(pts, "frame is not an RGBA texture".to_string()),
+        }
+
+        if image.dimensions() != (width as u32, height as u32) {
+            self.report(
+                pts,
+                format!(
+                    "readback is {}x{}, the frame claims {width}x{height}",
+                    image.width(),
+                    image.height()
+                ),
+            );
+        }
+    }
+
+    pub fn finish(&mut self) -> Vec<Finding> {
+        let mut findings = std::mem::take(&mut self.findings);
+        if self.dropped > 0 {
+            findings.push(Finding {
+                analyzer: ANALYZER.to_string(),
+                pts: None,
+                message: format!("{} more metadata mismatches", self.dropped),
+            });
+        }
+        findings
+    }
+
+    fn report(&mut self, pts: Duration, message: String) {
+        if self.findings.len() == MAX_FINDINGS {
+            self.dropped += 1;
+            return;
+        }
+        self.findings.push(Finding {
+            analyzer: ANALYZER.to_string(),
+            pts: Some(pts),
+            message,
+        });
+    }
+}
@@ -0,0 +1,169 @@
+//! Analyzers loaded at runtime from shared libraries.
+//!
+//! Plugins are `cdylib`s exporting [`ENTRY_POINT`], which returns a [`PluginVTable`].
+//! The interface only uses C types, so plugins do not depend on this crate nor on
+//! the compiler version it was built with, see `plugins/mean_luma` for an example.
+
+use anyhow::{bail, Context, Result};
+use libloading::Library;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::Path;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use super::{CapturedFrame, Finding, FrameProcessor};
+
+/// Version of the interface below, plugins built for another one are rejected.
+pub const ABI_VERSION: u32 = 1;
+/// Symbol of the function returning the plugin vtable, `extern "C" fn() -> *const PluginVTable`.
+pub const ENTRY_POINT: &[u8] = b"smelter_plugin_v1\0";
+
+/// A captured frame, the pixels are only valid during the call.
+#[repr(C)]
+pub struct PluginFrame {
+    pub index: u64,
+    pub pts_us: u64,
+    pub width: u32,
+    pub height: u32,
+    /// Tightly packed RGBA8 pixels, `width * height * 4` bytes.
+    pub rgba: *const u8,
+    pub len: usize,
+}
+
+/// Reports a finding, `pts_us` is negative for findings about the whole capture.
+pub type EmitFinding = unsafe extern "C" fn(ctx: *mut c_void, pts_us: i64, message: *const c_char);
+
+#[repr(C)]
+pub struct PluginVTable {
+    pub abi_version: u32,
+    pub name: *const c_char,
+    /// Creates an analyzer from its JSON config, returns null on invalid configs.
+    pub create: unsafe extern "C" fn(config_json: *const c_char) -> *mut c_void,
+    /// Returns 0 on success.
+    pub process: unsafe extern "C" fn(state: *mut c_void, frame: *const PluginFrame) -> i32,
+    pub finish: unsafe extern "C" fn(state: *mut c_void, emit: EmitFinding, ctx: *mut c_void),
+    pub destroy: unsafe extern "C" fn(state: *mut c_void),
+}
+
+/// A [`FrameProcessor`] implemented by a plugin.
+pub struct PluginProcessor {
+    name: String,
+    vtable: *const PluginVTable,
+    state: *mut c_void,
+    // Dropped last, the vtable and state live in it
+    _library: Library,
+}
+
+// Plugins only see their state from one thread at a time
+unsafe impl Send for PluginProcessor {}
+
+impl PluginProcessor {
+    pub fn load(path: &Path, config: &serde_json::Value) -> Result<Self> {
+        // Safety: loading runs the library initializers, plugins are trusted like the config
+        let library = unsafe { Library::new(path) }
+            .with_context(|| format!("Cannot load plugin {}", path.display()))?;
+        let vtable = unsafe {
+            let entry = library
+                .get::<unsafe extern "C" fn() -> *const PluginVTable>(ENTRY_POINT)
+                .with_context(|| format!("{} is not a plugin", path.display()))?;
+            entry()
+        };
+        let Some(table) = (unsafe { vtable.as_ref() }) else {
+            bail!("Plugin {} returned no vtable", path.display());
+        };
+        if table.abi_version != ABI_VERSION {
+            bail!(
+                "Plugin {} uses interface version {}, expected {ABI_VERSION}",
+                path.display(),
+                table.abi_version
+            );
+        }
+
+        if table.name.is_null() {
+            bail!("Plugin {} has no name", path.display());
+        }
+        let name = unsafe { CStr::from_ptr(table.name) }
+            .to_string_lossy()
+            .into_owned();
+        let config = CString::new(config.to_string())?;
+        let state = unsafe { (table.create)(config.as_ptr()) };
+        if state.is_null() {
+            bail!("Plugin {name} rejected its config");
+        }
+        info!("Loaded plugin {name} from {}", path.display());
+
+        Ok(Self {
+            name,
+            vtable,
+            state,
+            _library: library,
+        })
+    }
+
+    fn vtable(&self) -> &PluginVTable {
+        // Safety: checked on load, valid while the library is loaded
+        unsafe { &*self.vtable }
+    }
+}
+
+impl FrameProcessor for PluginProcessor {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn process(&mut self, frame: &CapturedFrame) -> Result<()> {
+        let pixels = frame.image.as_raw();
+        let plugin_frame = PluginFrame {
+            index: frame.index as u64,
+            pts_us: frame.pts.as_micros() as u64,
+            width: frame.image.width(),
+            height: frame.image.height(),
+            rgba: pixels.as_ptr(),
+            len: pixels.len(),
+        };
+
+        let status = unsafe { (self.vtable().process)(self.state, &plugin_frame) };
+        if status != 0 {
+            bail!(
+                "Plugin {} failed on frame {} ({status})",
+                self.name,
+                frame.index
+            );
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Vec<Finding> {
+        unsafe extern "C" fn collect(ctx: *mut c_void, pts_us: i64, message: *const c_char) {
+            let (name, findings) = unsafe { &mut *(ctx as *mut (&str, Vec<Finding>)) };
+            // Cannot fail the capture from here, the finding is left out
+            if message.is_null() {
+                warn!("Plugin {name} reported a finding without a message");
+                return;
+            }
+            findings.push(Finding {
+                analyzer: name.to_string(),
+                pts: u64::try_from(pts_us).ok().map(Duration::from_micros),
+                message: unsafe { CStr::from_ptr(message) }
+                    .to_string_lossy()
+                    .into_owned(),
+            });
+        }
+
+        let mut collected = (self.name.as_str(), Vec::new());
+        unsafe {
+            (self.vtable().finish)(
+                self.state,
+                collect,
+                &mut collected as *mut (&str, Vec<Finding>) as *mut c_void,
+            )
+        };
+        collected.1
+    }
+}
+
+impl Drop for PluginProcessor {
+    fn drop(&mut self) {
+        unsafe { (self.vtable().destroy)(self.state) };
+    }
+}
@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+use super::Finding;
+
+const ANALYZER: &str = "pts gaps";
+/// Gaps reported before the rest are only counted, a starved queue drops frames all along.
+const MAX_FINDINGS: usize = 20;
+
+/// Watches the PTS of the raw output frames for gaps longer than a frame interval, telling
+/// frames the queue never produced from frames the harness fell behind on.
+///
+/// Gaps among the frames that were queued in the output channel when it was seen full are
+/// blamed on the harness: it did not read frames as fast as they were produced. Any other
+/// gap is a queue drop.
+pub struct PtsGapCheck {
+    frame_interval: Duration,
+    previous_pts: Option<Duration>,
+    /// Gaps up to this PTS follow a full output channel.
+    backlog_end: Option<Duration>,
+    queue_drops: usize,
+    harness_drops: usize,
+    findings: Vec<Finding>,
+    dropped: usize,
+}
+
+impl PtsGapCheck {
+    pub fn new(frame_interval: Duration) -> Self {
+        Self {
+            frame_interval,
+            previous_pts: None,
+            backlog_end: None,
+            queue_drops: 0,
+            harness_drops: 0,
+            findings: vec![],
+            dropped: 0,
+        }
+    }
+
+    /// Checks the PTS of a received frame, `queued` frames being left in the output channel,
+    /// which is `full` or not.
+    pub fn check(&mut self, pts: Duration, queued: usize, full: bool) {
+        if full {
+            // The frames after the queued ones could not be sent
+            self.backlog_end = Some(pts + self.frame_interval * (queued as u32 + 2));
+        }
+        let Some(previous) = self.previous_pts.replace(pts) else {
+            return;
+        };
+        // Half a frame of slack for PTS rounding
+        let gap = pts.saturating_sub(previous);
+        if gap <= self.frame_interval * 3 / 2 {
+            return;
+        }
+
+        let missing = (gap.as_secs_f64() / self.frame_interval.as_secs_f64()).round() as usize - 1;
+        let culprit = if self.backlog_end.is_some_and(|end| previous <= end) {
+            self.harness_drops += missing;
+            "the harness fell behind"
+        } else {
+            self.queue_drops += missing;
+            "dropped by the queue"
+        };
+        self.report(
+            pts,
+            format!(
+                "{missing} frames missing after {:.3}s, {culprit}",
+                previous.as_secs_f64()
+            ),
+        );
+    }
+
+    /// Frames the queue did not produce so far.
+    pub fn queue_drops(&self) -> usize {
+        self.queue_drops
+    }
+
+    /// Frames lost because they were not read fast enough so far.
+    pub fn harness_drops(&self) -> usize {
+        self.harness_drops
+    }
+
+    pub fn finish(&mut self) -> Vec<Finding> {
+        let mut findings = std::mem::take(&mut self.findings);
+        if self.dropped > 0 {
+            findings.push(Finding {
+                analyzer: ANALYZER.to_string(),
+                pts: None,
+                message: format!(
+                    "{} more gaps, {} frames dropped by the queue and {} by the harness in total",
+                    self.dropped, self.queue_drops, self.harness_drops
+                ),
+            });
+        }
+        findings
+    }
+
+    fn report(&mut self, pts: Duration, message: String) {
+        if self.findings.len() == MAX_FINDINGS {
+            self.dropped += 1;
+            return;
+        }
+        self.findings.push(Finding {
+            analyzer: ANALYZER.to_string(),
+            pts: Some(pts),
+            message,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(40);
+
+    #[test]
+    fn regular_frames() {
+        let mut gaps = PtsGapCheck::new(INTERVAL);
+        for frame in 0..100 {
+            // Rounded PTS are within half a frame
+            gaps.check(
+                INTERVAL * frame + Duration::from_millis(frame as u64 % 3),
+                0,
+                false,
+            );
+        }
+        assert_eq!((gaps.queue_drops(), gaps.harness_drops()), (0, 0));
+        assert!(gaps.finish().is_empty());
+    }
+
+    #[test]
+    fn queue_and_harness_drops() {
+        let mut gaps = PtsGapCheck::new(INTERVAL);
+        gaps.check(Duration::ZERO, 0, false);
+        gaps.check(INTERVAL, 0, false);
+        gaps.check(INTERVAL * 4, 0, false);
+        assert_eq!((gaps.queue_drops(), gaps.harness_drops()), (2, 0));
+
+        // Frames after the 3 left in the full channel are lost by the harness
+        gaps.check(INTERVAL * 5, 3, true);
+        gaps.check(INTERVAL * 8, 0, false);
+        assert_eq!((gaps.queue_drops(), gaps.harness_drops()), (2, 2));
+
+        let findings = gaps.finish();
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].pts, Some(INTERVAL * 4));
+        assert_eq!(
+            findings[0].message,
+            "2 frames missing after 0.040s, dropped by the queue"
+        );
+        assert!(findings[1].message.ends_with("the harness fell behind"));
+    }
+
+    #[test]
+    fn findings_are_capped() {
+        let mut gaps = PtsGapCheck::new(INTERVAL);
+        for frame in 0..=MAX_FINDINGS as u32 + 5 {
+            gaps.check(INTERVAL * frame * 2, 0, false);
+        }
+        let findings = gaps.finish();
+        assert_eq!(findings.len(), MAX_FINDINGS + 1);
+        assert_eq!(
+            findings[MAX_FINDINGS].message,
+            "5 more gaps, 25 frames dropped by the queue and 0 by the harness in total"
+        );
+    }
+}
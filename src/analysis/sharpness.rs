@@ -0,0 +1,283 @@
+use anyhow::Result;
+use image::RgbaImage;
+use std::f32::consts::PI;
+use tracing::info;
+
+use super::{CapturedFrame, Finding, FrameProcessor, ResultCache};
+use crate::annotate::Rect;
+
+/// Bins per pixel of the edge spread function.
+const OVERSAMPLING: usize = 4;
+/// Pixels on each side of the edge the edge spread function covers.
+const HALF_WIDTH: usize = 16;
+/// Smallest luma change across a row, in `[0, 1]`, for it to be crossed by the edge.
+const MIN_CONTRAST: f32 = 0.2;
+/// Rows crossing the edge needed to fit it.
+const MIN_ROWS: usize = 16;
+/// Smallest slope of the edge, in pixels per row: a vertical edge samples a single phase,
+/// leaving most bins of the edge spread function empty.
+const MIN_SLOPE: f32 = 0.02;
+/// Nyquist frequency of the output, in cycles per pixel.
+const NYQUIST: f32 = 0.5;
+
+/// Measures the MTF50 of the slanted edge at the center of `region`, from a `slanted_edge`
+/// pattern, to quantify how sharp the rescaler keeps it.
+///
+/// This is the slanted-edge method of ISO 12233, simplified: the edge is located on every
+/// row, a line fitted through it, and pixels binned by their distance to the line into an
+/// oversampled edge spread function. Its derivative, the line spread function, gives the
+/// MTF by Fourier transform. MTF50 is the frequency, in cycles per output pixel, where the
+/// contrast falls to half; 0.5 is the Nyquist frequency.
+pub struct SharpnessAnalyzer {
+    label: String,
+    region: Rect,
+    min_mtf50: Option<f32>,
+    values: Vec<f32>,
+    cache: ResultCache<Option<f32>>,
+}
+
+impl SharpnessAnalyzer {
+    /// `label` names the measurement in logs and findings, a value under `min_mtf50` is
+    /// reported.
+    pub fn new(label: String, region: Rect, min_mtf50: Option<f32>) -> Self {
+        Self {
+            label,
+            region,
+            min_mtf50,
+            values: vec![],
+            cache: ResultCache::new(),
+        }
+    }
+
+    fn finding(&self, message: String) -> Finding {
+        Finding {
+            analyzer: self.name().to_string(),
+            pts: None,
+            message,
+        }
+    }
+}
+
+impl FrameProcessor for SharpnessAnalyzer {
+    fn name(&self) -> &str {
+        "sharpness"
+    }
+
+    fn process(&mut self, frame: &CapturedFrame) -> Result<()> {
+        let region = self.region;
+        if let Some(value) = self
+            .cache
+            .get_or_compute(frame, || mtf50(&frame.image, region))
+        {
+            self.values.push(value);
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Vec<Finding> {
+        if self.values.is_empty() {
+            return vec![self.finding(format!("{}: no slanted edge found", self.label))];
+        }
+        self.values.sort_unstable_by(f32::total_cmp);
+        let mtf50 = self.values[self.values.len() / 2];
+        info!(
+            "{}: MTF50 {mtf50:.3} cycles/pixel, {:.0}% of the frames cached",
+            self.label,
+            self.cache.hit_rate() * 100.0
+        );
+
+        match self.min_mtf50 {
+            Some(min) if mtf50 < min => vec![self.finding(format!(
+                "{}: MTF50 {mtf50:.3} cycles/pixel, under {min:.3}",
+                self.label
+            ))],
+            _ => vec![],
+        }
+    }
+}
+
+/// MTF50 of the edge crossing the middle half of the rows of `region` near its center,
+/// `None` when no slanted edge is found there.
+///
+/// Only frequencies up to Nyquist are searched: an edge whose contrast stays above half up
+/// to Nyquist is as sharp as the output can show and gets 0.5.
+pub fn mtf50(image: &RgbaImage, region: Rect) -> Option<f32> {
+    let luma = |x: u32, y: u32| {
+        let pixel = image.get_pixel(x, y);
+        (0.2126 * pixel[0] as f32 + 0.7152 * pixel[1] as f32 + 0.0722 * pixel[2] as f32) / 255.0
+    };
+    let center = region.x + region.width / 2;
+    let margin = (HALF_WIDTH * 2) as u32;
+    let left = center.saturating_sub(margin).max(region.x);
+    let right = (center + margin)
+        .min(region.x + region.width)
+        .min(image.width());
+    let top = region.y + region.height / 4;
+    let bottom = (region.y + region.height * 3 / 4).min(image.height());
+    if right <= left + 2 || bottom <= top {
+        return None;
+    }
+
+    // Edge position on every row, the centroid of the luma derivative
+    let mut points = vec![];
+    for y in top..bottom {
+        let row: Vec<f32> = (left..right).map(|x| luma(x, y)).collect();
+        let derivative: Vec<f32> = row
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).abs())
+            .collect();
+        let total: f32 = derivative.iter().sum();
+        if total < MIN_CONTRAST {
+            continue;
+        }
+        let centroid = derivative
+            .iter()
+            .enumerate()
+            .map(|(i, value)| (i as f32 + 0.5) * value)
+            .sum::<f32>()
+            / total;
+        points.push((y as f32, left as f32 + centroid));
+    }
+    if points.len() < MIN_ROWS {
+        return None;
+    }
+
+    // Least squares fit of x = offset + slope * y
+    let n = points.len() as f32;
+    let (sum_y, sum_x) = points
+        .iter()
+        .fold((0.0f32, 0.0f32), |(sy, sx), (y, x)| (sy + y, sx + x));
+    let (sum_yy, sum_xy) = points.iter().fold((0.0f32, 0.0f32), |(syy, sxy), (y, x)| {
+        (syy + y * y, sxy + x * y)
+    });
+    let slope = (n * sum_xy - sum_y * sum_x) / (n * sum_yy - sum_y * sum_y);
+    let offset = (sum_x - slope * sum_y) / n;
+    if !slope.is_finite() || slope.abs() < MIN_SLOPE {
+        return None;
+    }
+
+    // Edge spread function, binned by the distance to the edge
+    let bins = HALF_WIDTH * 2 * OVERSAMPLING;
+    let mut sums = vec![(0.0, 0); bins];
+    for y in top..bottom {
+        for x in left..right {
+            let distance = x as f32 - (offset + slope * y as f32);
+            let bin = ((distance + HALF_WIDTH as f32) * OVERSAMPLING as f32).floor();
+            if (0.0..bins as f32).contains(&bin) {
+                let (sum, count) = &mut sums[bin as usize];
+                *sum += luma(x, y);
+                *count += 1;
+            }
+        }
+    }
+    let mut esf = Vec::with_capacity(bins);
+    for (sum, count) in sums {
+        // Bins no pixel fell in repeat the previous one
+        match count {
+            0 => esf.push(*esf.last()?),
+            count => esf.push(sum / count as f32),
+        }
+    }
+
+    // Line spread function, windowed to the edge to tame the noise of the tails
+    let lsf: Vec<f32> = esf.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let len = lsf.len();
+    let lsf: Vec<f32> = lsf
+        .iter()
+        .enumerate()
+        .map(|(i, value)| value * (0.54 - 0.46 * (2.0 * PI * i as f32 / (len - 1) as f32).cos()))
+        .collect();
+
+    let magnitude = |k: usize| {
+        let (re, im) = lsf
+            .iter()
+            .enumerate()
+            .fold((0.0f32, 0.0f32), |(re, im), (j, value)| {
+                let angle = -2.0 * PI * (j * k) as f32 / len as f32;
+                (re + value * angle.cos(), im + value * angle.sin())
+            });
+        f32::hypot(re, im)
+    };
+    let dc = magnitude(0);
+    if dc <= 0.0 {
+        return None;
+    }
+    // Frequency of bin k, in cycles per pixel
+    let frequency = |k: usize| (k * OVERSAMPLING) as f32 / len as f32;
+    let mut previous = 1.0;
+    let nyquist = (NYQUIST * len as f32 / OVERSAMPLING as f32).ceil() as usize;
+    for k in 1..=nyquist.min(len / 2) {
+        let mtf = magnitude(k) / dc;
+        if mtf < 0.5 {
+            let fraction = (previous - 0.5) / (previous - mtf);
+            let mtf50 = frequency(k - 1) + fraction * (frequency(k) - frequency(k - 1));
+            return Some(mtf50.min(NYQUIST));
+        }
+        previous = mtf;
+    }
+    Some(NYQUIST)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    const SIZE: u32 = 64;
+    const REGION: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: SIZE,
+        height: SIZE,
+    };
+
+    /// Edge from 20% to 80% gray, `slope` pixels to the right per row, blurred by a box
+    /// filter `blur` pixels wide.
+    fn edge(slope: f32, blur: f32) -> RgbaImage {
+        RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+            let half = SIZE as f32 / 2.0;
+            let edge = half - 4.0 + slope * (y as f32 - half);
+            let coverage = if blur == 0.0 {
+                if x as f32 > edge {
+                    1.0
+                } else {
+                    0.0
+                }
+            } else {
+                ((x as f32 - edge) / blur + 0.5).clamp(0.0, 1.0)
+            };
+            let value = (255.0 * (0.2 + 0.6 * coverage)).round() as u8;
+            Rgba([value, value, value, 255])
+        })
+    }
+
+    #[test]
+    fn hard_edge_reaches_nyquist() {
+        assert_eq!(mtf50(&edge(0.1, 0.0), REGION), Some(NYQUIST));
+    }
+
+    #[test]
+    fn blurred_edges() {
+        // The MTF of a box filter w pixels wide is |sinc(w f)|, half at f = 0.6034 / w
+        for blur in [2.0, 3.0, 4.0] {
+            for slope in [0.1, -0.1] {
+                let mtf50 = mtf50(&edge(slope, blur), REGION).unwrap();
+                let expected = 0.6034 / blur;
+                assert!(
+                    (mtf50 - expected).abs() < 0.01,
+                    "{blur}px blur: {mtf50}, expected {expected}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn no_slanted_edge() {
+        // Vertical or nearly so
+        assert_eq!(mtf50(&edge(0.0, 0.0), REGION), None);
+        assert_eq!(mtf50(&edge(0.01, 3.0), REGION), None);
+        // Flat
+        let flat = RgbaImage::from_pixel(SIZE, SIZE, Rgba([128, 128, 128, 255]));
+        assert_eq!(mtf50(&flat, REGION), None);
+    }
+}
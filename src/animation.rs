@@ -0,0 +1,95 @@
+use anyhow::{bail, Context, Result};
+use compositor_pipeline::pipeline::GraphicsContext;
+use compositor_pipeline::queue::QueueInputOptions;
+use compositor_pipeline::Pipeline;
+use image::codecs::gif::GifDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, Frames, RgbaImage};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::info;
+
+use crate::raw_input;
+
+/// Delay browsers use for frames shorter than [`MIN_DELAY`], which GIFs often set to 0.
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+const MIN_DELAY: Duration = Duration::from_millis(20);
+
+/// Registers a raw data input playing an animated GIF or WebP file at its own frame delays.
+///
+/// Frames are all decoded upfront, so this is meant for short test assets.
+pub fn register(
+    pipeline: &Arc<Mutex<Pipeline>>,
+    graphics_context: &GraphicsContext,
+    id: &str,
+    path: &Path,
+    should_loop: bool,
+    queue_options: QueueInputOptions,
+) -> Result<()> {
+    let frames = decode(path)?;
+    let video = raw_input::register(pipeline, id, queue_options)?;
+    info!(
+        "Registered {} ({} frames) as {id}",
+        path.display(),
+        frames.len()
+    );
+
+    let graphics_context = graphics_context.clone();
+    let input_id = id.to_string();
+    std::thread::Builder::new()
+        .name(format!("animation {id}"))
+        .spawn(move || {
+            let delays: Box<dyn Iterator<Item = &(RgbaImage, Duration)>> = match should_loop {
+                true => Box::new(frames.iter().cycle()),
+                false => Box::new(frames.iter()),
+            };
+            let frames = delays.scan(Duration::ZERO, |pts, (image, delay)| {
+                let frame = (image, *pts);
+                *pts += *delay;
+                Some(Ok(frame))
+            });
+            raw_input::play(video, &graphics_context, &input_id, frames);
+        })?;
+
+    Ok(())
+}
+
+/// Composited frames of an animation, with the delay before the next one.
+fn decode(path: &Path) -> Result<Vec<(RgbaImage, Duration)>> {
+    let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+    let reader = BufReader::new(file);
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase);
+    let frames: Frames = match extension.as_deref() {
+        Some("gif") => GifDecoder::new(reader)?.into_frames(),
+        Some("webp") => WebPDecoder::new(reader)?.into_frames(),
+        _ => bail!(
+            "Unsupported animation {}, expected GIF or WebP",
+            path.display()
+        ),
+    };
+
+    let frames = frames
+        .collect_frames()
+        .with_context(|| format!("Cannot decode {}", path.display()))?;
+    if frames.is_empty() {
+        bail!("No frames in {}", path.display());
+    }
+
+    Ok(frames
+        .into_iter()
+        .map(|frame| {
+            let delay = Duration::from(frame.delay());
+            let delay = match delay < MIN_DELAY {
+                true => DEFAULT_DELAY,
+                false => delay,
+            };
+            (frame.into_buffer(), delay)
+        })
+        .collect())
+}
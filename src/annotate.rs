@@ -0,0 +1,147 @@
+//! Marks drawn over captured frames before they are saved: sampled patches, failed regions
+//! and their labels.
+//!
+//! Everything is drawn on the CPU, clipped to the image. Labels use a built-in 3x5 pixel
+//! font, uppercase only, over a black box so they stay readable on any content.
+
+use image::{Rgba, RgbaImage};
+
+/// Width of the lines of rectangles and crosshairs.
+const LINE_WIDTH: i64 = 2;
+/// Size of a font pixel in image pixels.
+const LABEL_SCALE: i64 = 3;
+/// Half the size of a crosshair.
+const CROSSHAIR_RADIUS: i64 = 12;
+
+pub const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+pub const GREEN: Rgba<u8> = Rgba([0, 255, 0, 255]);
+pub const YELLOW: Rgba<u8> = Rgba([255, 255, 0, 255]);
+
+/// Area of a frame, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Outlines `rect`, the lines being drawn inside it.
+pub fn rect(image: &mut RgbaImage, rect: Rect, color: Rgba<u8>) {
+    let (x, y) = (rect.x as i64, rect.y as i64);
+    let (width, height) = (rect.width as i64, rect.height as i64);
+    let line = LINE_WIDTH.min(width).min(height);
+    fill(image, x, y, width, line, color);
+    fill(image, x, y + height - line, width, line, color);
+    fill(image, x, y, line, height, color);
+    fill(image, x + width - line, y, line, height, color);
+}
+
+/// Crosses the pixel at `x`, `y`, leaving it visible.
+pub fn crosshair(image: &mut RgbaImage, x: u32, y: u32, color: Rgba<u8>) {
+    let (x, y) = (x as i64, y as i64);
+    let half = LINE_WIDTH / 2;
+    for (start, end) in [(-CROSSHAIR_RADIUS, -2), (2, CROSSHAIR_RADIUS)] {
+        fill(image, x + start, y - half, end - start, LINE_WIDTH, color);
+        fill(image, x - half, y + start, LINE_WIDTH, end - start, color);
+    }
+}
+
+/// Writes `text` with its top left corner at `x`, `y`, shifted left or up to stay in the
+/// image. Characters the font lacks are drawn as `?`.
+pub fn label(image: &mut RgbaImage, x: u32, y: u32, text: &str, color: Rgba<u8>) {
+    let advance = 4 * LABEL_SCALE;
+    let width = text.chars().count() as i64 * advance + LABEL_SCALE;
+    let height = 7 * LABEL_SCALE;
+    let x = (x as i64).min(image.width() as i64 - width).max(0);
+    let y = (y as i64).min(image.height() as i64 - height).max(0);
+    fill(image, x, y, width, height, Rgba([0, 0, 0, 255]));
+
+    for (index, character) in text.chars().enumerate() {
+        let left = x + LABEL_SCALE + index as i64 * advance;
+        for (row, bits) in glyph(character).iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) != 0 {
+                    fill(
+                        image,
+                        left + column * LABEL_SCALE,
+                        y + LABEL_SCALE + row as i64 * LABEL_SCALE,
+                        LABEL_SCALE,
+                        LABEL_SCALE,
+                        color,
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn fill(image: &mut RgbaImage, x: i64, y: i64, width: i64, height: i64, color: Rgba<u8>) {
+    let (x_end, y_end) = (
+        (x + width).min(image.width() as i64),
+        (y + height).min(image.height() as i64),
+    );
+    for y in y.max(0)..y_end {
+        for x in x.max(0)..x_end {
+            image.put_pixel(x as u32, y as u32, color);
+        }
+    }
+}
+
+/// Rows of a character, top to bottom, the leftmost pixel being the highest bit.
+fn glyph(character: char) -> [u8; 5] {
+    match character.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '(' => [0b010, 0b100, 0b100, 0b100, 0b010],
+        ')' => [0b010, 0b001, 0b001, 0b001, 0b010],
+        '[' => [0b110, 0b100, 0b100, 0b100, 0b110],
+        ']' => [0b011, 0b001, 0b001, 0b001, 0b011],
+        _ => [0b111, 0b001, 0b010, 0b000, 0b010],
+    }
+}
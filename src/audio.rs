@@ -0,0 +1,145 @@
+//! Mixing of the audio of the inputs into the outputs.
+
+use anyhow::{bail, Result};
+use compositor_pipeline::audio_mixer::{
+    AudioChannels, AudioMixingParams, InputParams, MixingStrategy,
+};
+use compositor_pipeline::pipeline::encoder::{fdk_aac, AudioEncoderOptions};
+use compositor_pipeline::pipeline::{OutputAudioOptions, PipelineOutputEndCondition};
+use compositor_render::scene::Component;
+use compositor_render::InputId;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::compositor::uses_input;
+use crate::config::{AudioChannelsConfig, AudioConfig, InputConfig, MixingStrategyConfig};
+
+/// Sample rate audio is mixed and encoded at.
+pub const SAMPLE_RATE: u32 = 48000;
+/// Largest gain, above which mixes clip too easily to be useful.
+pub const MAX_VOLUME: f32 = 2.0;
+
+/// Gain of the inputs with audio, mixed into the outputs while they are shown.
+#[derive(Debug, Clone)]
+pub struct AudioMixer {
+    inputs: Vec<MixerInput>,
+    channels: AudioChannels,
+    strategy: MixingStrategy,
+}
+
+#[derive(Debug, Clone)]
+struct MixerInput {
+    id: String,
+    volume: f32,
+    muted: bool,
+}
+
+impl AudioMixer {
+    pub fn new(config: &AudioConfig, inputs: &[InputConfig]) -> Self {
+        let inputs = inputs
+            .iter()
+            .filter(|input| has_audio(input))
+            .map(|input| MixerInput {
+                id: input.id().to_string(),
+                volume: config.volumes.get(input.id()).copied().unwrap_or(1.0),
+                muted: config.muted.iter().any(|id| id == input.id()),
+            })
+            .collect();
+
+        Self {
+            inputs,
+            channels: match config.channels {
+                AudioChannelsConfig::Mono => AudioChannels::Mono,
+                AudioChannelsConfig::Stereo => AudioChannels::Stereo,
+            },
+            strategy: match config.strategy {
+                MixingStrategyConfig::SumClip => MixingStrategy::SumClip,
+                MixingStrategyConfig::SumScale => MixingStrategy::SumScale,
+            },
+        }
+    }
+
+    /// Whether no input has audio, outputs are silent then.
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    pub fn channels(&self) -> AudioChannels {
+        self.channels
+    }
+
+    /// Mixes the audio of an input added while running, at full volume.
+    pub fn add_input(&mut self, input: &InputConfig) {
+        if has_audio(input) {
+            self.inputs.push(MixerInput {
+                id: input.id().to_string(),
+                volume: 1.0,
+                muted: false,
+            });
+        }
+    }
+
+    pub fn remove_input(&mut self, id: &str) {
+        self.inputs.retain(|input| input.id != id);
+    }
+
+    /// Sets the gain of an input, 1 keeping its level.
+    pub fn set_volume(&mut self, id: &str, volume: f32) -> Result<()> {
+        if !(0.0..=MAX_VOLUME).contains(&volume) {
+            bail!("Volume must be between 0 and {MAX_VOLUME}, got {volume}");
+        }
+        self.input(id)?.volume = volume;
+        info!("Set the volume of {id} to {volume}");
+        Ok(())
+    }
+
+    pub fn set_muted(&mut self, id: &str, muted: bool) -> Result<()> {
+        self.input(id)?.muted = muted;
+        info!("{} {id}", if muted { "Muted" } else { "Unmuted" });
+        Ok(())
+    }
+
+    /// Audio of the unmuted inputs `component` shows.
+    pub fn mix(&self, component: &Component) -> AudioMixingParams {
+        let inputs = self
+            .inputs
+            .iter()
+            .filter(|input| !input.muted && uses_input(component, &input.id))
+            .map(|input| InputParams {
+                input_id: InputId(Arc::from(input.id.as_str())),
+                volume: input.volume,
+            })
+            .collect();
+        AudioMixingParams { inputs }
+    }
+
+    /// Audio of an output first showing `initial`.
+    pub fn output_options(&self, initial: &Component) -> OutputAudioOptions {
+        OutputAudioOptions {
+            initial: self.mix(initial),
+            mixing_strategy: self.strategy,
+            channels: self.channels,
+            end_condition: PipelineOutputEndCondition::Never,
+        }
+    }
+
+    /// AAC encoding of the mixed audio.
+    pub fn encoder_options(&self) -> AudioEncoderOptions {
+        AudioEncoderOptions::Aac(fdk_aac::AacEncoderOptions {
+            channels: self.channels,
+            sample_rate: SAMPLE_RATE,
+        })
+    }
+
+    fn input(&mut self, id: &str) -> Result<&mut MixerInput> {
+        match self.inputs.iter_mut().find(|input| input.id == id) {
+            Some(input) => Ok(input),
+            None => bail!("Input {id:?} has no audio"),
+        }
+    }
+}
+
+/// Whether the audio of an input is mixed, only MP4 inputs have audio.
+pub fn has_audio(input: &InputConfig) -> bool {
+    matches!(input, InputConfig::Mp4 { audio: true, .. })
+}
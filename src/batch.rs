@@ -0,0 +1,295 @@
+//! Standard capture of every MP4 file and image of a directory, with a combined report.
+
+use anyhow::{Context, Result};
+use image::RgbaImage;
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{info, warn};
+
+use crate::analysis::classify::{self, Classification};
+use crate::analysis::{self, Finding};
+use crate::annotate::Rect;
+use crate::compositor::Compositor;
+use crate::config::{Config, DecoderConfig, InputConfig};
+use crate::media;
+use crate::playlist::is_mp4;
+use crate::watch::DirWatcher;
+use crate::wgpu::to_image;
+
+/// How long each file is captured.
+pub const CAPTURE_DURATION: Duration = Duration::from_secs(5);
+/// How long the size of a dropped file must stay the same before it is captured.
+pub const SETTLE_DELAY: Duration = Duration::from_secs(1);
+/// ID of the input playing the current file.
+const INPUT_ID: &str = "batch";
+
+/// Result of the capture of a file.
+#[derive(Debug)]
+pub struct BatchEntry {
+    pub path: PathBuf,
+    /// Modification time of the file when its capture started.
+    pub modified: Option<SystemTime>,
+    /// Why the file could not be captured.
+    pub error: Option<String>,
+    pub findings: Vec<Finding>,
+}
+
+/// Captures every file of `dir` in turn, fitted to the output as its only input, and writes
+/// the findings of all of them to the CSV file `report`.
+///
+/// Each file gets its own compositor, so a file breaking the pipeline does not affect the
+/// next ones. The outputs of `config` are ignored, its other settings apply to every file.
+pub fn run(config: &Config, dir: &Path, report: &Path) -> Result<Vec<BatchEntry>> {
+    let files = media_files(dir)?;
+    info!("Capturing {} files of {}", files.len(), dir.display());
+
+    let mut entries = vec![];
+    for (index, path) in files.into_iter().enumerate() {
+        info!("[{}] {}", index + 1, path.display());
+        entries.push(process(config, path));
+    }
+
+    if report.exists() {
+        std::fs::remove_file(report)?;
+    }
+    append_report(&entries, report)?;
+    let failed = entries
+        .iter()
+        .filter(|entry| entry.error.is_some() || !entry.findings.is_empty())
+        .count();
+    info!(
+        "{failed} of {} files failed, report saved to {}",
+        entries.len(),
+        report.display()
+    );
+
+    Ok(entries)
+}
+
+/// Runs a batch over `dir`, then captures the files dropped into it as they arrive,
+/// appending their results to `report`. Runs until interrupted.
+///
+/// Files are captured once their size stays the same for [`SETTLE_DELAY`], so copies in
+/// progress are not picked up, and again when they are replaced.
+pub fn watch(config: &Config, dir: &Path, report: &Path) -> Result<()> {
+    // Events name canonical paths
+    let dir = &dir
+        .canonicalize()
+        .with_context(|| format!("Cannot watch {}", dir.display()))?;
+    let watcher = DirWatcher::new(dir)?;
+    // Files dropped while the batch runs were not captured, their events are still queued
+    let mut processed: HashMap<PathBuf, SystemTime> = run(config, dir, report)?
+        .into_iter()
+        .filter_map(|entry| Some((entry.path, entry.modified?)))
+        .collect();
+    info!("Watching {} for new files", dir.display());
+
+    // Size of each changed file when it was last seen
+    let mut pending: HashMap<PathBuf, (u64, Instant)> = HashMap::new();
+    loop {
+        for path in watcher.wait_for_changes(SETTLE_DELAY)? {
+            if is_media(&path) && processed.get(&path) != modified(&path).as_ref() {
+                pending.entry(path).or_insert((u64::MAX, Instant::now()));
+            }
+        }
+
+        let mut settled = vec![];
+        pending.retain(|path, (size, since)| {
+            let Ok(metadata) = path.metadata() else {
+                // Removed before it was captured
+                return false;
+            };
+            if metadata.len() != *size {
+                (*size, *since) = (metadata.len(), Instant::now());
+                return true;
+            }
+            if since.elapsed() < SETTLE_DELAY {
+                return true;
+            }
+            settled.push(path.clone());
+            false
+        });
+
+        settled.sort();
+        for path in settled {
+            info!("New file {}", path.display());
+            let entry = process(config, path);
+            if let Some(time) = entry.modified {
+                processed.insert(entry.path.clone(), time);
+            }
+            append_report(std::slice::from_ref(&entry), report)?;
+        }
+    }
+}
+
+/// MP4 files and images of `dir`, by name.
+pub fn media_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Cannot list {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_file() && is_media(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn is_media(path: &Path) -> bool {
+    is_mp4(path) || image::ImageFormat::from_path(path).is_ok()
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    path.metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Captures a file, logging its findings or why it could not be captured.
+fn process(config: &Config, path: PathBuf) -> BatchEntry {
+    let time = modified(&path);
+    match capture(config, &path) {
+        Ok(findings) => {
+            analysis::log_findings(&findings);
+            BatchEntry {
+                path,
+                modified: time,
+                error: None,
+                findings,
+            }
+        }
+        Err(err) => {
+            warn!("Cannot capture {}: {err:#}", path.display());
+            BatchEntry {
+                path,
+                modified: time,
+                error: Some(format!("{err:#}")),
+                findings: vec![],
+            }
+        }
+    }
+}
+
+fn capture(config: &Config, path: &Path) -> Result<Vec<Finding>> {
+    let input = match is_mp4(path) {
+        true => InputConfig::Mp4 {
+            id: INPUT_ID.to_string(),
+            path: path.to_path_buf(),
+            should_loop: true,
+            decoder: DecoderConfig::default(),
+            trim: None,
+            tracks: None,
+            audio: false,
+            correction: None,
+        },
+        false => InputConfig::Image {
+            id: INPUT_ID.to_string(),
+            path: path.to_path_buf(),
+            correction: None,
+        },
+    };
+    let config = Config {
+        inputs: vec![input],
+        scenes: vec![],
+        outputs: vec![],
+        ..config.clone()
+    };
+
+    let mut compositor = Compositor::new(&config)?;
+    let component = compositor.component(INPUT_ID)?;
+    let mut findings = compositor.analyze(component.clone(), CAPTURE_DURATION, &mut [])?;
+
+    let receiver = compositor.register_raw_output(component.clone())?;
+    let texture = compositor.render_component(&receiver, component)?;
+    let rendered = to_image(
+        compositor.graphics_context(),
+        &texture,
+        compositor.shader_cache().get(),
+    )?;
+    compositor.deregister_raw_output()?;
+    if let Some(classification) = classify_frame(path, &rendered)? {
+        findings.push(classification.finding());
+    }
+
+    Ok(findings)
+}
+
+/// Compares a rendered frame with the file decoded on its own, and classifies the likely
+/// cause of the colors differing.
+///
+/// The file is fitted to the output, so it is compared over the area it fills; edges are
+/// only compared when it is shown at its own size.
+fn classify_frame(path: &Path, rendered: &RgbaImage) -> Result<Option<Classification>> {
+    let reference = match is_mp4(path) {
+        true => media::mp4_first_frame(path)?,
+        false => image::open(path)
+            .with_context(|| format!("Cannot open {}", path.display()))?
+            .to_rgba8(),
+    };
+    let (width, height) = rendered.dimensions();
+    let scale =
+        (width as f32 / reference.width() as f32).min(height as f32 / reference.height() as f32);
+    let (fitted_width, fitted_height) = (
+        (reference.width() as f32 * scale).round() as u32,
+        (reference.height() as f32 * scale).round() as u32,
+    );
+    let area = Rect {
+        x: (width - fitted_width.min(width)) / 2,
+        y: (height - fitted_height.min(height)) / 2,
+        width: fitted_width,
+        height: fitted_height,
+    };
+
+    let patches = classify::patches(&reference, rendered, area);
+    let edges = classify::edge_errors(&reference, rendered);
+    Ok(classify::classify(&patches, edges))
+}
+
+/// Appends one line per finding, and one per file without any, to the CSV file `path`,
+/// which gets a header when created.
+fn append_report(entries: &[BatchEntry], path: &Path) -> Result<()> {
+    let is_new = !path.exists();
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Cannot write {}", path.display()))?;
+    let mut file = BufWriter::new(file);
+    if is_new {
+        writeln!(file, "file,result,analyzer,pts_ms,message")?;
+    }
+    for entry in entries {
+        let name = csv_field(&entry.path.display().to_string());
+        if let Some(error) = &entry.error {
+            writeln!(file, "{name},error,,,{}", csv_field(error))?;
+        } else if entry.findings.is_empty() {
+            writeln!(file, "{name},ok,,,")?;
+        }
+        for finding in &entry.findings {
+            writeln!(
+                file,
+                "{name},findings,{},{},{}",
+                csv_field(&finding.analyzer),
+                finding
+                    .pts
+                    .map(|pts| format!("{:.1}", pts.as_secs_f64() * 1000.0))
+                    .unwrap_or_default(),
+                csv_field(&finding.message)
+            )?;
+        }
+    }
+    file.flush()?;
+
+    Ok(())
+}
+
+/// Quotes a field when it holds a separator or a quote.
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
@@ -0,0 +1,221 @@
+use anyhow::Result;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::compositor::Compositor;
+use crate::config::*;
+
+/// Assembles a [`Compositor`] programmatically, as an alternative to a config file.
+///
+/// Settings default to the ones of [`Config::default`], but there are no inputs
+/// or outputs until they are added.
+#[derive(Debug, Clone)]
+pub struct CompositorBuilder {
+    config: Config,
+}
+
+impl Default for CompositorBuilder {
+    fn default() -> Self {
+        Self {
+            config: Config {
+                inputs: vec![],
+                outputs: vec![],
+                ..Config::default()
+            },
+        }
+    }
+}
+
+impl CompositorBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn resolution(mut self, width: usize, height: usize) -> Self {
+        self.config.resolution = ResolutionConfig { width, height };
+        self
+    }
+
+    pub fn framerate(mut self, num: u32, den: u32) -> Self {
+        self.config.framerate = FramerateConfig { num, den };
+        self
+    }
+
+    pub fn rendering_mode(mut self, mode: RenderingModeConfig) -> Self {
+        self.config.rendering_mode = mode;
+        self
+    }
+
+    /// Extra wgpu features to request, by name, see [`Config::wgpu_features`].
+    pub fn wgpu_features<I, S>(mut self, features: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.wgpu_features = features.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Features not to request, by name, to reproduce constrained devices, see
+    /// [`Config::disabled_wgpu_features`].
+    pub fn disabled_wgpu_features<I, S>(mut self, features: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.disabled_wgpu_features = features.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Buffer duration of the queue and of every input.
+    pub fn buffer_duration(mut self, duration: Duration) -> Self {
+        self.config.queue.default_buffer_duration_ms = duration.as_millis() as u64;
+        self.config.queue.input_buffer_duration_ms = None;
+        self
+    }
+
+    pub fn queue(mut self, queue: QueueConfig) -> Self {
+        self.config.queue = queue;
+        self
+    }
+
+    pub fn startup_budget(mut self, budget: StartupBudgetConfig) -> Self {
+        self.config.startup_budget = budget;
+        self
+    }
+
+    pub fn with_input(mut self, input: InputConfig) -> Self {
+        self.config.inputs.push(input);
+        self
+    }
+
+    pub fn with_image(self, id: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        self.with_input(InputConfig::Image {
+            id: id.into(),
+            path: path.into(),
+            correction: None,
+        })
+    }
+
+    /// Test pattern synthesized on the GPU, see [`InputConfig::Pattern`].
+    pub fn with_pattern(self, id: impl Into<String>, pattern: PatternConfig) -> Self {
+        self.with_input(InputConfig::Pattern {
+            id: id.into(),
+            pattern,
+            square_size: 64,
+            correction: None,
+        })
+    }
+
+    /// Web page rendered by Chromium, enabling the web renderer, see [`InputConfig::Web`].
+    pub fn with_web(mut self, id: impl Into<String>, url: impl Into<String>) -> Self {
+        self.config.web_renderer.enabled = true;
+        self.with_input(InputConfig::Web {
+            id: id.into(),
+            url: url.into(),
+            resolution: None,
+            correction: None,
+        })
+    }
+
+    pub fn with_mp4(
+        self,
+        id: impl Into<String>,
+        path: impl Into<PathBuf>,
+        should_loop: bool,
+    ) -> Self {
+        self.with_input(InputConfig::Mp4 {
+            id: id.into(),
+            path: path.into(),
+            should_loop,
+            decoder: DecoderConfig::default(),
+            trim: None,
+            tracks: None,
+            audio: false,
+            correction: None,
+        })
+    }
+
+    /// Plays an MP4 file with the given decoder, e.g. to compare the output of two decoders.
+    pub fn with_mp4_decoder(
+        self,
+        id: impl Into<String>,
+        path: impl Into<PathBuf>,
+        decoder: DecoderConfig,
+    ) -> Self {
+        self.with_input(InputConfig::Mp4 {
+            id: id.into(),
+            path: path.into(),
+            should_loop: true,
+            decoder,
+            trim: None,
+            tracks: None,
+            audio: false,
+            correction: None,
+        })
+    }
+
+    /// Loops the segment of an MP4 file starting at `start`, see [`TrimConfig`].
+    pub fn with_trimmed_mp4(
+        self,
+        id: impl Into<String>,
+        path: impl Into<PathBuf>,
+        start: Duration,
+        duration: Option<Duration>,
+    ) -> Self {
+        self.with_input(InputConfig::Mp4 {
+            id: id.into(),
+            path: path.into(),
+            should_loop: true,
+            decoder: DecoderConfig::default(),
+            trim: Some(TrimConfig {
+                start_ms: start.as_millis() as u64,
+                duration_ms: duration.map(|duration| duration.as_millis() as u64),
+            }),
+            tracks: None,
+            audio: false,
+            correction: None,
+        })
+    }
+
+    /// Receives a live stream on `port`, see [`InputConfig::Rtp`].
+    pub fn with_rtp(
+        self,
+        id: impl Into<String>,
+        port: u16,
+        transport: RtpTransportConfig,
+        codec: RtpCodecConfig,
+    ) -> Self {
+        self.with_input(InputConfig::Rtp {
+            id: id.into(),
+            port,
+            transport,
+            codec,
+            decoder: None,
+            correction: None,
+        })
+    }
+
+    pub fn with_output(mut self, output: OutputConfig) -> Self {
+        self.config.outputs.push(output);
+        self
+    }
+
+    /// Saves a frame of each input through the raw output, see [`OutputConfig::Snapshots`].
+    pub fn with_raw_output(self, path_template: impl Into<String>) -> Self {
+        self.with_output(OutputConfig::Snapshots {
+            path_template: path_template.into(),
+            format: DumpFormatConfig::default(),
+        })
+    }
+
+    /// Config the compositor will be built from.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn build(self) -> Result<Compositor> {
+        self.config.validate()?;
+        Compositor::new(&self.config)
+    }
+}
@@ -0,0 +1,96 @@
+use anyhow::{Context, Result};
+use compositor_pipeline::pipeline::GraphicsContext;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+use wgpu::*;
+
+/// Environment variable overriding where the pipeline cache is stored.
+pub const CACHE_DIR_ENV: &str = "SMELTER_COLORS_CACHE_DIR";
+
+/// A wgpu `PipelineCache` persisted to disk between runs.
+///
+/// Only backends exposing `Features::PIPELINE_CACHE` (currently Vulkan) support it,
+/// on the others this is a no-op and pipelines are compiled from scratch.
+pub struct ShaderCache {
+    cache: Option<PipelineCache>,
+    path: Option<PathBuf>,
+}
+
+impl ShaderCache {
+    /// Default cache directory, `$SMELTER_COLORS_CACHE_DIR` or `.cache/` in the crate root.
+    pub fn default_dir() -> PathBuf {
+        match std::env::var_os(CACHE_DIR_ENV) {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(".cache"),
+        }
+    }
+
+    /// Creates the cache, seeding it from a previous run if a cache file exists in `dir`.
+    pub fn load(context: &GraphicsContext, dir: &Path) -> Self {
+        let disabled = Self {
+            cache: None,
+            path: None,
+        };
+
+        if !context.device.features().contains(Features::PIPELINE_CACHE) {
+            debug!("Pipeline cache not supported by this backend");
+            return disabled;
+        }
+
+        // The key identifies the adapter and driver, a cache from another one is useless
+        let Some(key) = util::pipeline_cache_key(&context.adapter.get_info()) else {
+            debug!("No pipeline cache key for this adapter");
+            return disabled;
+        };
+        let path = dir.join(key);
+        let data = std::fs::read(&path).ok();
+
+        // SAFETY: the data was written by `save` below, from `PipelineCache::get_data`,
+        // and `fallback` lets wgpu discard it if it is corrupted or stale.
+        let cache = unsafe {
+            context
+                .device
+                .create_pipeline_cache(&PipelineCacheDescriptor {
+                    label: Some("Smelter colors pipeline cache"),
+                    data: data.as_deref(),
+                    fallback: true,
+                })
+        };
+        match data {
+            Some(_) => info!("Loaded pipeline cache from {}", path.display()),
+            None => info!("Created empty pipeline cache at {}", path.display()),
+        }
+
+        Self {
+            cache: Some(cache),
+            path: Some(path),
+        }
+    }
+
+    pub fn get(&self) -> Option<&PipelineCache> {
+        self.cache.as_ref()
+    }
+
+    /// Writes the cache to disk, so the next run can skip shader compilation.
+    pub fn save(&self) -> Result<()> {
+        let (Some(cache), Some(path)) = (&self.cache, &self.path) else {
+            return Ok(());
+        };
+        let Some(data) = cache.get_data() else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Cannot create {}", dir.display()))?;
+        }
+
+        // Write to a temporary file first so that a crash never leaves a truncated cache
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, data)?;
+        std::fs::rename(&tmp_path, path)?;
+        debug!("Saved pipeline cache to {}", path.display());
+
+        Ok(())
+    }
+}
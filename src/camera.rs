@@ -0,0 +1,248 @@
+use anyhow::Result;
+use compositor_pipeline::pipeline::GraphicsContext;
+use compositor_pipeline::queue::QueueInputOptions;
+use compositor_pipeline::Pipeline;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::config::{FramerateConfig, ResolutionConfig};
+
+/// A video capture device.
+#[derive(Debug, Clone)]
+pub struct CameraInfo {
+    pub index: usize,
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Lists the video capture devices, only V4L2 devices are supported for now.
+pub fn list() -> Result<Vec<CameraInfo>> {
+    #[cfg(target_os = "linux")]
+    return v4l2::list();
+
+    #[cfg(not(target_os = "linux"))]
+    Ok(vec![])
+}
+
+/// Registers a raw data input fed with the frames of a camera.
+///
+/// `device` is a device path or name, the first device when unset. The driver picks
+/// the closest mode it supports to the requested resolution and framerate.
+pub fn register(
+    pipeline: &Arc<Mutex<Pipeline>>,
+    graphics_context: &GraphicsContext,
+    id: &str,
+    device: Option<&str>,
+    resolution: Option<ResolutionConfig>,
+    framerate: Option<FramerateConfig>,
+    queue_options: QueueInputOptions,
+) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    return v4l2::register(
+        pipeline,
+        graphics_context,
+        id,
+        device,
+        resolution,
+        framerate,
+        queue_options,
+    );
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (
+            pipeline,
+            graphics_context,
+            device,
+            resolution,
+            framerate,
+            queue_options,
+        );
+        anyhow::bail!("Camera input {id:?} is only supported on Linux");
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod v4l2 {
+    use anyhow::{ensure, Context, Result};
+    use compositor_pipeline::pipeline::GraphicsContext;
+    use compositor_pipeline::queue::{PipelineEvent, QueueInputOptions};
+    use compositor_pipeline::Pipeline;
+    use image::RgbaImage;
+    use std::path::{Path, PathBuf};
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::time::Instant;
+    use tracing::{debug, info, warn};
+    use v4l::buffer::Type;
+    use v4l::format::Format;
+    use v4l::io::traits::CaptureStream;
+    use v4l::prelude::*;
+    use v4l::video::Capture;
+    use v4l::{FourCC, Fraction};
+
+    use super::CameraInfo;
+    use crate::config::{FramerateConfig, ResolutionConfig};
+    use crate::raw_input;
+
+    /// Packed 4:2:2, the format every UVC camera supports uncompressed.
+    const YUYV: &[u8; 4] = b"YUYV";
+    const BUFFER_COUNT: u32 = 4;
+
+    pub fn list() -> Result<Vec<CameraInfo>> {
+        Ok(v4l::context::enum_devices()
+            .into_iter()
+            .map(|node| CameraInfo {
+                index: node.index(),
+                name: node.name().unwrap_or_default(),
+                path: node.path().to_path_buf(),
+            })
+            .collect())
+    }
+
+    pub fn register(
+        pipeline: &Arc<Mutex<Pipeline>>,
+        graphics_context: &GraphicsContext,
+        id: &str,
+        device: Option<&str>,
+        resolution: Option<ResolutionConfig>,
+        framerate: Option<FramerateConfig>,
+        queue_options: QueueInputOptions,
+    ) -> Result<()> {
+        let path = device_path(device)?;
+        let video = raw_input::register(pipeline, id, queue_options)?;
+
+        let (init_sender, init) = mpsc::channel();
+        let graphics_context = graphics_context.clone();
+        let input_id = id.to_string();
+        std::thread::Builder::new()
+            .name(format!("camera {id}"))
+            .spawn(move || {
+                let (device, format) = match open(&path, resolution, framerate) {
+                    Ok(opened) => opened,
+                    Err(err) => {
+                        let _ = init_sender.send(Err(err));
+                        return;
+                    }
+                };
+                let mut stream =
+                    match MmapStream::with_buffers(&device, Type::VideoCapture, BUFFER_COUNT) {
+                        Ok(stream) => stream,
+                        Err(err) => {
+                            let _ = init_sender.send(Err(err.into()));
+                            return;
+                        }
+                    };
+                let _ = init_sender.send(Ok((path.clone(), format.clone())));
+
+                let start = Instant::now();
+                loop {
+                    let image = match stream.next() {
+                        Ok((buffer, _)) => yuyv_to_rgba(buffer, &format),
+                        Err(err) => {
+                            warn!("Camera capture for {input_id} failed: {err}");
+                            return;
+                        }
+                    };
+                    let frame = raw_input::frame(&graphics_context, &image, start.elapsed());
+                    if video.send(PipelineEvent::Data(frame)).is_err() {
+                        debug!("Input {input_id} unregistered, stopping camera capture");
+                        return;
+                    }
+                }
+            })?;
+
+        let (path, format) = init.recv().context("Camera thread stopped")??;
+        info!(
+            "Capturing {} at {}x{} as {id}",
+            path.display(),
+            format.width,
+            format.height
+        );
+
+        Ok(())
+    }
+
+    /// Path of the device given by path or name, or of the first device.
+    fn device_path(device: Option<&str>) -> Result<PathBuf> {
+        if let Some(path) = device.filter(|device| Path::new(device).exists()) {
+            return Ok(PathBuf::from(path));
+        }
+
+        let cameras = list()?;
+        let camera = match device {
+            Some(name) => cameras
+                .into_iter()
+                .find(|camera| camera.name == name)
+                .with_context(|| format!("No camera named {name:?}"))?,
+            None => cameras
+                .into_iter()
+                .min_by_key(|camera| camera.index)
+                .context("No camera found")?,
+        };
+
+        Ok(camera.path)
+    }
+
+    fn open(
+        path: &Path,
+        resolution: Option<ResolutionConfig>,
+        framerate: Option<FramerateConfig>,
+    ) -> Result<(Device, Format)> {
+        let device = Device::with_path(path)
+            .with_context(|| format!("Cannot open camera {}", path.display()))?;
+
+        let mut format = device.format()?;
+        if let Some(resolution) = resolution {
+            format.width = resolution.width as u32;
+            format.height = resolution.height as u32;
+        }
+        format.fourcc = FourCC::new(YUYV);
+        let format = device.set_format(&format)?;
+        ensure!(
+            format.fourcc == FourCC::new(YUYV),
+            "Camera {} does not support YUYV, got {}",
+            path.display(),
+            format.fourcc
+        );
+
+        if let Some(framerate) = framerate {
+            let mut params = device.params()?;
+            params.interval = Fraction::new(framerate.den, framerate.num);
+            device.set_params(&params)?;
+        }
+
+        Ok((device, format))
+    }
+
+    /// Converts a YUYV buffer with BT.601 limited range, the usual for webcams.
+    fn yuyv_to_rgba(buffer: &[u8], format: &Format) -> RgbaImage {
+        let (width, height) = (format.width, format.height);
+        let mut image = RgbaImage::new(width, height);
+        let stride = (format.stride as usize).max(width as usize * 2);
+
+        for (row, pixels) in buffer
+            .chunks(stride)
+            .zip(image.chunks_exact_mut(width as usize * 4))
+        {
+            for (yuyv, rgba) in row.chunks_exact(4).zip(pixels.chunks_exact_mut(8)) {
+                let [y0, u, y1, v] = [yuyv[0], yuyv[1], yuyv[2], yuyv[3]];
+                rgba[..4].copy_from_slice(&yuv_to_rgba(y0, u, v));
+                rgba[4..].copy_from_slice(&yuv_to_rgba(y1, u, v));
+            }
+        }
+
+        image
+    }
+
+    fn yuv_to_rgba(y: u8, u: u8, v: u8) -> [u8; 4] {
+        let c = (y as f32 - 16.0) * 1.164;
+        let d = u as f32 - 128.0;
+        let e = v as f32 - 128.0;
+        [
+            (c + 1.596 * e).clamp(0.0, 255.0) as u8,
+            (c - 0.392 * d - 0.813 * e).clamp(0.0, 255.0) as u8,
+            (c + 2.017 * d).clamp(0.0, 255.0) as u8,
+            255,
+        ]
+    }
+}
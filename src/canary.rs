@@ -0,0 +1,159 @@
+//! Solid border drawn around every scene and checked on every captured frame.
+//!
+//! A cheap invariant that holds whatever the scene shows: a border of the wrong color
+//! points at color space conversions, and a missing or thicker side at cropping or
+//! offset bugs.
+
+use compositor_render::scene::*;
+use compositor_render::Resolution;
+use image::RgbaImage;
+use std::time::Duration;
+
+use crate::analysis::Finding;
+
+const ANALYZER: &str = "canary";
+/// Findings reported before the rest are only counted, a broken border breaks every frame.
+const MAX_FINDINGS: usize = 20;
+
+/// Draws a `width` pixels border of `color` over the edges of `scene`.
+pub fn wrap(scene: Component, color: RGBAColor, width: u32, resolution: Resolution) -> Component {
+    let (output_width, output_height) = (resolution.width as f32, resolution.height as f32);
+    let width = width as f32;
+    let side = |left, top, side_width, side_height| {
+        view(
+            vec![],
+            Position::Absolute(AbsolutePosition {
+                width: Some(side_width),
+                height: Some(side_height),
+                position_horizontal: HorizontalPosition::LeftOffset(left),
+                position_vertical: VerticalPosition::TopOffset(top),
+                rotation_degrees: 0.0,
+            }),
+            color,
+        )
+    };
+
+    view(
+        vec![
+            scene,
+            side(0.0, 0.0, output_width, width),
+            side(0.0, output_height - width, output_width, width),
+            side(0.0, 0.0, width, output_height),
+            side(output_width - width, 0.0, width, output_height),
+        ],
+        Position::Static {
+            width: Some(output_width),
+            height: Some(output_height),
+        },
+        RGBAColor(0, 0, 0, 0),
+    )
+}
+
+fn view(children: Vec<Component>, position: Position, background_color: RGBAColor) -> Component {
+    Component::View(ViewComponent {
+        id: None,
+        children,
+        direction: ViewChildrenDirection::Row,
+        position,
+        transition: None,
+        overflow: Overflow::Hidden,
+        background_color,
+        border_radius: BorderRadius::ZERO,
+        border_width: 0.,
+        border_color: RGBAColor(0, 0, 0, 0),
+        box_shadow: vec![],
+        padding: Padding {
+            top: 0.,
+            right: 0.,
+            bottom: 0.,
+            left: 0.,
+        },
+    })
+}
+
+/// Checks the border drawn by [`wrap`] on captured frames.
+pub struct CanaryCheck {
+    color: [u8; 3],
+    width: u32,
+    tolerance: u8,
+    findings: Vec<Finding>,
+    dropped: usize,
+}
+
+impl CanaryCheck {
+    pub fn new(color: [u8; 3], width: u32, tolerance: u8) -> Self {
+        Self {
+            color,
+            width,
+            tolerance,
+            findings: vec![],
+            dropped: 0,
+        }
+    }
+
+    /// Checks every pixel of the border, side by side, so the message tells which sides
+    /// are off.
+    pub fn check(&mut self, pts: Duration, image: &RgbaImage) {
+        let (width, height) = image.dimensions();
+        let border = self.width.min(width / 2).min(height / 2);
+        if border == 0 {
+            return;
+        }
+
+        let sides = [
+            ("top", (0, 0, width, border)),
+            ("bottom", (0, height - border, width, height)),
+            ("left", (0, 0, border, height)),
+            ("right", (width - border, 0, width, height)),
+        ];
+        let mut wrong = vec![];
+        for (name, (x0, y0, x1, y1)) in sides {
+            let mut sum = [0u64; 3];
+            let mut mismatches = 0;
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let pixel = image.get_pixel(x, y);
+                    for c in 0..3 {
+                        sum[c] += pixel[c] as u64;
+                    }
+                    if (0..3).any(|c| pixel[c].abs_diff(self.color[c]) > self.tolerance) {
+                        mismatches += 1;
+                    }
+                }
+            }
+            if mismatches > 0 {
+                let count = ((x1 - x0) * (y1 - y0)) as u64;
+                let [r, g, b] = sum.map(|sum| (sum / count) as u8);
+                wrong.push(format!(
+                    "{name} {mismatches}/{count} pixels off, #{r:02X}{g:02X}{b:02X} on average"
+                ));
+            }
+        }
+        if wrong.is_empty() {
+            return;
+        }
+
+        if self.findings.len() == MAX_FINDINGS {
+            self.dropped += 1;
+            return;
+        }
+        let [r, g, b] = self.color;
+        self.findings.push(Finding {
+            analyzer: ANALYZER.to_string(),
+            pts: Some(pts),
+            message: format!("border is not #{r:02X}{g:02X}{b:02X}: {}", wrong.join(", ")),
+        });
+    }
+
+    pub fn finish(&mut self) -> Vec<Finding> {
+        let mut findings = std::mem::take(&mut self.findings);
+        if self.dropped > 0 {
+            findings.push(Finding {
+                analyzer: ANALYZER.to_string(),
+                pts: None,
+                message: format!("{} more frames with a wrong border", self.dropped),
+            });
+        }
+        findings
+    }
+}
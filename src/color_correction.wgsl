@@ -0,0 +1,74 @@
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+}
+
+struct BaseShaderParameters {
+    plane_id: i32,
+    time: f32,
+    output_resolution: vec2<u32>,
+    texture_count: u32,
+}
+
+// Rows of the 3x3 matrix, with the offset of each channel in `w`
+struct CorrectionParams {
+    row_r: vec4<f32>,
+    row_g: vec4<f32>,
+    row_b: vec4<f32>,
+    // x: LUT size (0 without LUT), y: LUT strength
+    lut: vec4<f32>,
+}
+
+@group(0) @binding(0) var textures: binding_array<texture_2d<f32>, 16>;
+@group(1) @binding(0) var<uniform> params: CorrectionParams;
+@group(2) @binding(0) var sampler_: sampler;
+
+var<push_constant> base_params: BaseShaderParameters;
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    output.position = vec4<f32>(input.position, 1.0);
+    output.tex_coords = input.tex_coords;
+    return output;
+}
+
+// The LUT is a strip of `size` slices of `size`x`size` pixels, red along x,
+// green along y, and blue selecting the slice.
+fn sample_lut(color: vec3<f32>) -> vec3<f32> {
+    let size = params.lut.x;
+    let scaled = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)) * (size - 1.0);
+
+    let slice_low = floor(scaled.b);
+    let slice_high = min(slice_low + 1.0, size - 1.0);
+    let in_slice = (scaled.rg + 0.5) / size;
+
+    let low = textureSampleLevel(textures[1], sampler_, vec2<f32>((slice_low + in_slice.x) / size, in_slice.y), 0.0);
+    let high = textureSampleLevel(textures[1], sampler_, vec2<f32>((slice_high + in_slice.x) / size, in_slice.y), 0.0);
+    return mix(low.rgb, high.rgb, scaled.b - slice_low);
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    if (base_params.texture_count == 0u) {
+        return vec4<f32>(0.0);
+    }
+
+    let color = textureSample(textures[0], sampler_, input.tex_coords);
+    var rgb = vec3<f32>(
+        dot(params.row_r.xyz, color.rgb) + params.row_r.w,
+        dot(params.row_g.xyz, color.rgb) + params.row_g.w,
+        dot(params.row_b.xyz, color.rgb) + params.row_b.w,
+    );
+
+    if (params.lut.x > 1.0 && base_params.texture_count > 1u) {
+        rgb = mix(rgb, sample_lut(rgb), params.lut.y);
+    }
+
+    return vec4<f32>(rgb, color.a);
+}
@@ -1,32 +1,112 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use compositor_pipeline::event::Event;
 use compositor_pipeline::pipeline::output::*;
 use compositor_pipeline::pipeline::RegisterOutputOptions;
 use compositor_pipeline::queue::PipelineEvent;
+use compositor_render::scene::Size;
 use compositor_render::scene::*;
 use compositor_render::Resolution;
-use std::path::PathBuf;
+use crossbeam_channel::Receiver;
+use image::RgbaImage;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
-use tracing::info;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, info, warn};
 
 use compositor_pipeline::{
     pipeline::{
+        decoder::VideoDecoderOptions,
         input::{
             mp4::{Mp4Options, Source},
-            InputOptions,
+            rtp::{InputVideoStream, RtpReceiverOptions, RtpStream},
+            whip::WhipReceiverOptions,
+            InputInitInfo, InputOptions,
         },
+        rtp::RequestedPort,
         *,
     },
     queue::QueueInputOptions,
     Pipeline,
 };
 use compositor_render::{
-    image::{ImageSource, ImageSpec},
+    image::{ImageSource, ImageSpec, ImageType},
     scene::Component,
+    web_renderer::{WebEmbeddingMethod, WebRendererSpec},
     *,
 };
 
-use crate::wgpu::to_image;
+use crate::analysis::bars;
+use crate::analysis::cadence::CadenceAnalyzer;
+use crate::analysis::chroma_alignment::ChromaAlignmentAnalyzer;
+use crate::analysis::loop_junction::LoopJunctionAnalyzer;
+use crate::analysis::markers::MarkerDecoder;
+use crate::analysis::metadata::MetadataCheck;
+use crate::analysis::plugin::PluginProcessor;
+use crate::analysis::pts_gaps::PtsGapCheck;
+use crate::analysis::sharpness::SharpnessAnalyzer;
+use crate::analysis::{self, CapturedFrame, Finding, FrameProcessor};
+use crate::animation;
+use crate::annotate;
+use crate::audio::{self, AudioMixer};
+use crate::builder::CompositorBuilder;
+use crate::cache::ShaderCache;
+use crate::camera;
+use crate::canary::{self, CanaryCheck};
+use crate::config::{
+    Config, DecoderConfig, DumpFormatConfig, EncoderConfig, EncoderFallbackConfig,
+    GoldenMatchingConfig, InputConfig, NamedOutputConfig, NamedOutputKindConfig, NoiseConfig,
+    OutputConfig, QueueConfig, RecordCodecConfig, RescaleModeConfig, TrackSelectionConfig,
+    WebmCodecConfig, WorkaroundConfig, REQUIRED_WGPU_FEATURES,
+};
+use crate::container::{self, ColorTags, ExpectedParameters};
+use crate::correction;
+use crate::counter;
+use crate::coverage::SceneCoverage;
+use crate::dashboard::{self, Command, Dashboard, DashboardState};
+use crate::decklink::{self, DecklinkSelector};
+use crate::display::{self, DisplayIdentity, DisplayProfiles, PreviewSettings};
+use crate::dual_record::DualRecorder;
+use crate::event_log::{self, EventLog, LogEvent};
+use crate::expected::{self, ExpectedRenderer};
+use crate::fallback::{self, PngSequenceSink};
+use crate::gif::GifWriter;
+use crate::golden::{self, GoldenFrame, SequenceCheck};
+use crate::hevc::HevcEncoder;
+use crate::hls::{HlsOptions, HlsWriter};
+use crate::icc::DisplayCorrection;
+use crate::latency::{self, ArrivalClock, FlashDetector, LatencyStats};
+use crate::lossless::LosslessEncoder;
+use crate::macros::{self, MacroRecorder};
+use crate::markers::{self, Marker, Reconnections};
+use crate::media;
+use crate::monitor::{AudioMonitor, LevelMeter};
+use crate::naming;
+use crate::ndi::{self, NdiSender};
+use crate::noise;
+use crate::outputs::{OutputRegistry, RegisteredOutput, Sink};
+use crate::pacing::{FramePacing, PacingStats};
+use crate::patterns;
+use crate::playlist;
+use crate::raw_input::{self, RawFrameSender};
+use crate::recording::{RecordingController, Rollover};
+use crate::rtp_sender::{RtpSender, RtpSenderOptions};
+use crate::scene;
+use crate::screen;
+use crate::sequence;
+use crate::session::SessionState;
+use crate::srt::{self, SrtOptions};
+use crate::startup::{StageTimer, StartupTimings};
+use crate::stdin_input;
+use crate::svg;
+use crate::sweep::{self, RateDistortion, ReferenceWriter, SweepSettings};
+use crate::terminal;
+use crate::utilization::{self, UtilizationSampler};
+use crate::watch::FileWatcher;
+use crate::webm::WebmRecorder;
+use crate::wgpu::{to_image, to_image_f32, to_image_u16};
+use crate::workarounds;
+use crate::y4m::Y4mWriter;
 
 pub static PLACEHOLDER: Component = Component::View(ViewComponent {
     id: None,
@@ -60,103 +140,322 @@ pub const WIDTH: usize = 1920;
 pub const HEIGHT: usize = 1080;
 pub const IMAGE: &str = "test.png";
 pub const MP4: &str = "test.mp4";
+/// How often recordings check whether to roll over to their next file.
+const RECORDING_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How long each size and mode of a sharpness measurement is rendered.
+const SHARPNESS_DURATION: Duration = Duration::from_secs(1);
 
 pub struct Compositor {
     graphics_context: GraphicsContext,
+    shader_cache: ShaderCache,
     pipeline: Arc<Mutex<Pipeline>>,
+    resolution: Resolution,
+    frame_interval: Duration,
 
-    image_component: Component,
-    mp4_component: Component,
+    /// One component per input, identified by the input ID.
+    components: Vec<(String, Component)>,
+    /// Components of the WHIP inputs, added to `components` while their publisher is connected.
+    publishers: Vec<(String, Component)>,
+    /// Whether `components` only holds a placeholder, while waiting for publishers.
+    waiting_for_publishers: bool,
+    events: Receiver<Event>,
+    /// Senders of the raw inputs, by input ID.
+    raw_inputs: HashMap<String, RawFrameSender>,
+    config: Config,
+    startup_timings: StartupTimings,
+    event_log: EventLog,
+    /// Raw output frames dropped because a newer one was already available.
+    skipped_frames: usize,
+    /// Component last shown by each registered output, so removed inputs can be replaced.
+    shown: Mutex<HashMap<OutputId, Component>>,
+    /// Features of the scenes the outputs switched to.
+    coverage: Mutex<SceneCoverage>,
+    /// Gain of the inputs with audio.
+    mixer: AudioMixer,
+    /// Whether the recording has an audio track, mixing the shown inputs with audio.
+    record_audio: bool,
+    /// Whether the raw output has audio, for monitoring sessions.
+    raw_audio: bool,
+    /// MP4 recording in progress.
+    recording: Option<RecordingController>,
+    /// Inputs that delivered a stream, for the markers of reconnections.
+    reconnections: Reconnections,
+    /// GPU driver workarounds enabled for the adapter.
+    workarounds: Vec<WorkaroundConfig>,
+    /// Encoder of the H265 recording in progress.
+    hevc_encoder: Option<HevcEncoder>,
+    /// Encoder of the ProRes or FFV1 recording in progress.
+    lossless_encoder: Option<LosslessEncoder>,
+    /// Frames of the recording in progress, when its encoder could not start.
+    fallback_sink: Option<PngSequenceSink>,
+    /// Whether the recording fell back to PNG frames, reset when the next one starts.
+    record_fallback: bool,
+    /// Whether the recording was registered showing the first input, so that its first frame
+    /// is the first of the cycle, see [`Self::record_for`]. Taken by the next
+    /// [`Self::alternate_scenes`], which leaves that input shown instead of switching to it.
+    record_on_scene: bool,
+    /// Outputs registered by name, see [`Self::register_named_output`].
+    outputs: OutputRegistry,
 
     mp4_output: OutputId,
     raw_output: OutputId,
+    whip_output: OutputId,
 }
 
 impl Compositor {
-    pub fn new() -> Result<Self> {
-        // Initialize graphics context
-        let graphics_context = GraphicsContext::new(GraphicsContextOptions {
-            force_gpu: false,
-            features: wgpu::Features::PUSH_CONSTANTS | wgpu::Features::TEXTURE_BINDING_ARRAY,
-            limits: wgpu::Limits::default(),
-            compatible_surface: None,
-            libvulkan_path: None,
-        })
-        .context("Cannot initialize WGPU")?;
+    pub fn new(config: &Config) -> Result<Self> {
+        let mut timer = StageTimer::start();
+        let mut startup_timings = StartupTimings::default();
+        let mut config = config.clone();
+        noise::resolve_seed(&mut config.noise);
+        let config = &config;
 
-        // Create and start pipeline
-        let pipeline = Self::create_pipeline(&graphics_context)?;
+        let mut graphics_context = Self::create_graphics_context(config.wgpu_features()?, true)?;
+        let adapter = graphics_context.adapter.get_info();
+        let workarounds = workarounds::select(&adapter, &config.workarounds);
+        if workarounds.contains(&WorkaroundConfig::NoPipelineCache)
+            && graphics_context
+                .device
+                .features()
+                .contains(wgpu::Features::PIPELINE_CACHE)
+        {
+            graphics_context = Self::create_graphics_context(config.wgpu_features()?, false)?;
+        }
+        crate::wgpu::set_cpu_srgb_encoding(
+            workarounds.contains(&WorkaroundConfig::CpuReadbackEncoding),
+        );
+        let shader_cache = ShaderCache::load(&graphics_context, &ShaderCache::default_dir());
+        startup_timings.graphics_init = timer.lap();
 
-        // Register inputs
-        let (image_input_id, mp4_input_id) = Self::register_inputs(&pipeline)?;
+        // Create and start pipeline, then register inputs. Their threads inherit
+        // the priority and affinity of this one.
+        let (pipeline, (components, raw_inputs)) =
+            config.threads.pipeline.scoped("pipeline", || {
+                let pipeline = Self::create_pipeline(&graphics_context, config)?;
+                startup_timings.pipeline_create = timer.lap();
 
-        // Components to alternate between
-        let image_component = Component::Image(ImageComponent {
-            id: None,
-            image_id: RendererId(image_input_id.0.clone()),
-            width: None,
-            height: None,
-        });
-        let mp4_component = Component::Rescaler(RescalerComponent {
-            id: None,
-            child: Box::new(Component::InputStream(InputStreamComponent {
-                id: None,
-                input_id: mp4_input_id.clone(),
-            })),
-            position: Position::Absolute(AbsolutePosition {
-                width: Some(WIDTH as f32),
-                height: Some(HEIGHT as f32),
-                position_horizontal: HorizontalPosition::LeftOffset(0.0),
-                position_vertical: VerticalPosition::TopOffset(0.0),
-                rotation_degrees: 0.0,
-            }),
-            transition: None,
-            mode: RescaleMode::Fill,
-            horizontal_align: HorizontalAlign::Center,
-            vertical_align: VerticalAlign::Center,
-            border_radius: compositor_render::scene::BorderRadius::ZERO,
-            border_width: 0.0,
-            border_color: RGBAColor(0, 0, 0, 0),
-            box_shadow: vec![],
-        });
+                // Register inputs, and the components to alternate between
+                let inputs = Self::register_inputs(&pipeline, &graphics_context, config)?;
+                Ok((pipeline, inputs))
+            })?;
+        let events = pipeline.lock().unwrap().subscribe_pipeline_events();
+
+        // WHIP inputs only join the inputs to cycle through once their publisher connects
+        let (mut publishers, mut components): (Vec<_>, Vec<_>) =
+            components.into_iter().partition(|(id, _)| {
+                config
+                    .inputs
+                    .iter()
+                    .any(|input| matches!(input, InputConfig::Whip { .. }) && input.id() == id)
+            });
+        if !config.scenes.is_empty() {
+            components = config
+                .scenes
+                .iter()
+                .map(|scene| Ok((scene.id.clone(), scene::load(&scene.path)?)))
+                .collect::<Result<_>>()?;
+            publishers.clear();
+        }
+        let waiting_for_publishers = components.is_empty();
+        if waiting_for_publishers {
+            components.push(waiting_component());
+        }
+        startup_timings.input_register = timer.lap();
 
-        Ok(Self {
+        let mut compositor = Self {
             graphics_context,
+            shader_cache,
             pipeline,
+            resolution: config.resolution(),
+            frame_interval: config.frame_interval(),
 
-            image_component,
-            mp4_component,
+            components,
+            publishers,
+            waiting_for_publishers,
+            events,
+            raw_inputs,
+            config: config.clone(),
+            startup_timings,
+            event_log: match config.event_log.enabled {
+                true => EventLog::create(&config.event_log.path)?,
+                false => EventLog::disabled(),
+            },
+            skipped_frames: 0,
+            shown: Mutex::default(),
+            coverage: Mutex::default(),
+            mixer: AudioMixer::new(&config.audio, &config.inputs),
+            record_audio: false,
+            recording: None,
+            reconnections: Reconnections::default(),
+            workarounds: workarounds.clone(),
+            hevc_encoder: None,
+            lossless_encoder: None,
+            fallback_sink: None,
+            record_fallback: false,
+            record_on_scene: false,
+            outputs: OutputRegistry::default(),
+            raw_audio: false,
 
             mp4_output: OutputId(Arc::from("mp4_output")),
             raw_output: OutputId(Arc::from("raw_output")),
-        })
+            whip_output: OutputId(Arc::from("whip_output")),
+        };
+
+        compositor.event_log.write(LogEvent::Started {
+            schema_version: event_log::SCHEMA_VERSION,
+            width: compositor.resolution.width,
+            height: compositor.resolution.height,
+            framerate: [config.framerate.num, config.framerate.den],
+            noise_seed: config.noise.enabled.then_some(config.noise.seed).flatten(),
+            adapter: &adapter.name,
+            driver: format!("{} {}", adapter.driver, adapter.driver_info),
+            workarounds: workarounds
+                .iter()
+                .map(|workaround| workaround.name())
+                .collect(),
+        });
+        compositor.startup_timings.first_frame = compositor.measure_first_frame()?;
+        compositor.startup_timings.log();
+
+        Ok(compositor)
+    }
+
+    pub fn builder() -> CompositorBuilder {
+        CompositorBuilder::new()
+    }
+
+    pub fn startup_timings(&self) -> &StartupTimings {
+        &self.startup_timings
+    }
+
+    /// GPU driver workarounds enabled for the adapter, see [`crate::workarounds`].
+    pub fn workarounds(&self) -> &[WorkaroundConfig] {
+        &self.workarounds
+    }
+
+    pub fn skipped_frames(&self) -> usize {
+        self.skipped_frames
+    }
+
+    pub fn graphics_context(&self) -> &GraphicsContext {
+        &self.graphics_context
     }
 
-    fn create_pipeline(graphics_context: &GraphicsContext) -> Result<Arc<Mutex<Pipeline>>> {
+    pub fn shader_cache(&self) -> &ShaderCache {
+        &self.shader_cache
+    }
+
+    /// Components to alternate between, identified by the input or scene ID.
+    pub fn components(&self) -> &[(String, Component)] {
+        &self.components
+    }
+
+    pub fn component(&self, id: &str) -> Result<Component> {
+        self.components
+            .iter()
+            .find(|(existing, _)| existing == id)
+            .map(|(_, component)| component.clone())
+            .with_context(|| format!("Unknown component {id:?}"))
+    }
+
+    /// Loads a JSON scene file, replacing the component with the same ID or adding it.
+    pub fn load_scene(&mut self, id: &str, path: &Path) -> Result<()> {
+        let component = scene::load(path)?;
+        match self
+            .components
+            .iter_mut()
+            .find(|(existing, _)| existing == id)
+        {
+            Some((_, existing)) => *existing = component,
+            None => self.components.push((id.to_string(), component)),
+        }
+        info!("Loaded scene {id} from {}", path.display());
+
+        Ok(())
+    }
+
+    /// Time for the first input to reach the output, from the output registration.
+    fn measure_first_frame(&mut self) -> Result<Duration> {
+        let mut timer = StageTimer::start();
+        let (_, component) = self.components[0].clone();
+        let receiver = self.register_raw_output(component)?;
+
+        let video = receiver.video.as_ref().context("No video channel")?;
+        loop {
+            if let PipelineEvent::Data(_) = video.recv()? {
+                break;
+            }
+        }
+        let elapsed = timer.lap();
+
+        self.deregister_raw_output()?;
+        Ok(elapsed)
+    }
+
+    /// Initializes the graphics context, with a pipeline cache when `pipeline_cache` is set
+    /// and the backend supports it.
+    fn create_graphics_context(
+        features: wgpu::Features,
+        pipeline_cache: bool,
+    ) -> Result<GraphicsContext> {
+        let create = |features| {
+            GraphicsContext::new(GraphicsContextOptions {
+                force_gpu: false,
+                features,
+                limits: wgpu::Limits::default(),
+                compatible_surface: None,
+                libvulkan_path: None,
+            })
+        };
+
+        let context = match pipeline_cache {
+            // Not every adapter supports pipeline caches, retry without it on failure
+            true => create(features | wgpu::Features::PIPELINE_CACHE).or_else(|_| create(features)),
+            false => create(features),
+        }
+        .context(
+            "Cannot initialize WGPU, the adapter may lack PUSH_CONSTANTS or TEXTURE_BINDING_ARRAY",
+        )?;
+        if !context.device.features().contains(REQUIRED_WGPU_FEATURES) {
+            bail!(
+                "The device lacks {:?}, which every Smelter shader pipeline needs",
+                REQUIRED_WGPU_FEATURES - context.device.features()
+            );
+        }
+        Ok(context)
+    }
+
+    fn create_pipeline(
+        graphics_context: &GraphicsContext,
+        config: &Config,
+    ) -> Result<Arc<Mutex<Pipeline>>> {
         let (pipeline, _event_loop) = Pipeline::new(compositor_pipeline::pipeline::Options {
             queue_options: compositor_pipeline::queue::QueueOptions {
-                default_buffer_duration: Duration::ZERO,
-                ahead_of_time_processing: false,
-                output_framerate: Framerate { num: 30, den: 1 },
-                run_late_scheduled_events: true,
-                never_drop_output_frames: false,
+                default_buffer_duration: config.queue.default_buffer_duration(),
+                ahead_of_time_processing: config.queue.ahead_of_time_processing,
+                output_framerate: Framerate {
+                    num: config.framerate.num,
+                    den: config.framerate.den,
+                },
+                run_late_scheduled_events: config.queue.run_late_scheduled_events,
+                never_drop_output_frames: config.queue.never_drop_output_frames,
             },
-            stream_fallback_timeout: Duration::from_millis(500),
+            stream_fallback_timeout: config.queue.stream_fallback_timeout(),
             web_renderer: compositor_render::web_renderer::WebRendererInitOptions {
-                enable: false,
-                enable_gpu: false,
+                enable: config.web_renderer.enabled,
+                enable_gpu: config.web_renderer.gpu,
             },
             force_gpu: false,
             download_root: std::env::temp_dir(),
-            mixing_sample_rate: 48000,
-            wgpu_features: wgpu::Features::PUSH_CONSTANTS | wgpu::Features::TEXTURE_BINDING_ARRAY,
+            mixing_sample_rate: audio::SAMPLE_RATE,
+            wgpu_features: config.wgpu_features()?,
             load_system_fonts: None,
             wgpu_ctx: Some(graphics_context.clone()),
-            stun_servers: Default::default(),
-            whip_whep_server_port: 9000,
-            start_whip_whep: false,
+            stun_servers: Arc::new(config.stun_servers.clone()),
+            whip_whep_server_port: config.whip_server.port,
+            start_whip_whep: config.whip_server.enabled,
             tokio_rt: None,
-            rendering_mode: RenderingMode::GpuOptimized,
+            rendering_mode: config.rendering_mode.into(),
         })
         .context("Failed to create compositor pipeline")?;
 
@@ -166,217 +465,4331 @@ impl Compositor {
         Ok(pipeline)
     }
 
-    fn register_inputs(pipeline: &Arc<Mutex<Pipeline>>) -> Result<(RendererId, InputId)> {
-        let assets_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets");
-        let image_path = assets_path.join(IMAGE);
-        let mp4_path = assets_path.join(MP4);
+    fn register_inputs(
+        pipeline: &Arc<Mutex<Pipeline>>,
+        graphics_context: &GraphicsContext,
+        config: &Config,
+    ) -> Result<(Vec<(String, Component)>, HashMap<String, RawFrameSender>)> {
+        correction::register(pipeline, &config.inputs)?;
+        patterns::register(pipeline, &config.inputs)?;
+        noise::register(pipeline, &config.noise)?;
 
-        // Register image
-        let image_input_id = RendererId(Arc::from("image_input"));
-        Pipeline::register_renderer(
-            pipeline,
-            image_input_id.clone(),
-            RendererSpec::Image(ImageSpec {
-                src: ImageSource::LocalPath {
-                    path: image_path.to_string_lossy().to_string(),
+        let mut components = Vec::with_capacity(config.inputs.len());
+        let mut raw_inputs = HashMap::new();
+        for input in &config.inputs {
+            if let Some(sender) = Self::register_input(pipeline, graphics_context, input, config)? {
+                raw_inputs.insert(input.id().to_string(), sender);
+            }
+            let component = input_component(input, &config.noise, config.resolution())?;
+            components.push((input.id().to_string(), component));
+        }
+
+        Ok((components, raw_inputs))
+    }
+
+    /// Registers the source of an input, use [`input_component`] to render it.
+    ///
+    /// Returns the sender of raw inputs, which are fed by the embedding code.
+    fn register_input(
+        pipeline: &Arc<Mutex<Pipeline>>,
+        graphics_context: &GraphicsContext,
+        input: &InputConfig,
+        config: &Config,
+    ) -> Result<Option<RawFrameSender>> {
+        match input {
+            InputConfig::Image { id, path, .. } => {
+                Pipeline::register_renderer(
+                    pipeline,
+                    RendererId(Arc::from(id.as_str())),
+                    RendererSpec::Image(ImageSpec {
+                        src: ImageSource::LocalPath {
+                            path: path.to_string_lossy().to_string(),
+                        },
+                        image_type: image_type(path)?,
+                    }),
+                )?;
+                info!("Registered {}", path.display());
+            }
+            InputConfig::Mp4 {
+                id,
+                path,
+                should_loop,
+                decoder,
+                trim,
+                tracks,
+                audio,
+                ..
+            } => {
+                let input_id = InputId(Arc::from(id.as_str()));
+                let video_decoder = decoder.video_decoder(vulkan_decoding(graphics_context))?;
+                let path = match tracks {
+                    Some(tracks) => media::select_mp4_tracks(path, tracks)?,
+                    None => path.clone(),
+                };
+                if *audio && !media::mp4_has_audio(&path)? {
+                    warn!("{} has no audio track, {id} is silent", path.display());
+                }
+                let path = match trim {
+                    Some(trim) => media::trim_mp4(&path, trim)?,
+                    None => path,
+                };
+                let input_options = InputOptions::Mp4(Mp4Options {
+                    source: Source::File(path.clone()),
+                    should_loop: *should_loop,
+                    video_decoder,
+                });
+                let options = RegisterInputOptions {
+                    input_options,
+                    queue_options: queue_options(config),
+                };
+                Pipeline::register_input(pipeline, input_id, options)?;
+                info!(
+                    "Registered {} decoded with {video_decoder:?}",
+                    path.display()
+                );
+            }
+            InputConfig::Rtp {
+                id,
+                port,
+                transport,
+                codec,
+                decoder,
+                ..
+            } => {
+                let input_id = InputId(Arc::from(id.as_str()));
+                let video_decoder =
+                    codec.video_decoder(decoder.as_ref(), vulkan_decoding(graphics_context))?;
+                let input_options = InputOptions::Rtp(RtpReceiverOptions {
+                    port: RequestedPort::Exact(*port),
+                    transport_protocol: (*transport).into(),
+                    stream: RtpStream {
+                        video: Some(InputVideoStream {
+                            options: VideoDecoderOptions {
+                                decoder: video_decoder,
+                            },
+                        }),
+                        audio: None,
+                    },
+                });
+                let options = RegisterInputOptions {
+                    input_options,
+                    queue_options: queue_options(config),
+                };
+                Pipeline::register_input(pipeline, input_id, options)?;
+                info!(
+                    "Listening for {codec:?} RTP stream {id} on {transport:?} port {port}, \
+                    decoded with {video_decoder:?}"
+                );
+            }
+            InputConfig::Srt {
+                id,
+                address,
+                mode,
+                passphrase,
+                latency_ms,
+                ..
+            } => srt::register(
+                pipeline,
+                graphics_context,
+                id,
+                &SrtOptions {
+                    mode: *mode,
+                    address,
+                    passphrase: passphrase.as_deref(),
+                    latency: latency_ms.map(Duration::from_millis),
                 },
-                image_type: compositor_render::image::ImageType::Png,
-            }),
-        )?;
-        info!("Registered {}", image_path.display());
-
-        // Register MP4
-        let mp4_input_id = InputId(Arc::from("mp4_input"));
-        let video_decoder = VideoDecoder::FFmpegH264;
-        let input_options = InputOptions::Mp4(Mp4Options {
-            source: Source::File(mp4_path.clone()),
-            should_loop: true,
-            video_decoder,
-        });
-        let options = RegisterInputOptions {
-            input_options,
-            queue_options: QueueInputOptions {
-                required: false,
-                offset: None,
-                buffer_duration: Some(Duration::ZERO),
-            },
+                config.resolution(),
+                queue_options(config),
+            )?,
+            InputConfig::Stdin {
+                id,
+                resolution,
+                framerate,
+                pixel_format,
+                ..
+            } => stdin_input::register(
+                pipeline,
+                graphics_context,
+                id,
+                *resolution,
+                framerate.interval(),
+                *pixel_format,
+                queue_options(config),
+            )?,
+            InputConfig::Camera {
+                id,
+                device,
+                resolution,
+                framerate,
+                ..
+            } => camera::register(
+                pipeline,
+                graphics_context,
+                id,
+                device.as_deref(),
+                *resolution,
+                *framerate,
+                queue_options(config),
+            )?,
+            InputConfig::Decklink {
+                id,
+                subdevice_index,
+                display_name,
+                persistent_id,
+                ..
+            } => decklink::register(
+                pipeline,
+                id,
+                DecklinkSelector {
+                    subdevice_index: *subdevice_index,
+                    display_name: display_name.clone(),
+                    persistent_id: *persistent_id,
+                },
+                queue_options(config),
+            )?,
+            InputConfig::Ndi { id, source, .. } => ndi::register(
+                pipeline,
+                graphics_context,
+                id,
+                source,
+                queue_options(config),
+            )?,
+            InputConfig::Counter { id, framerate, .. } => counter::register(
+                pipeline,
+                graphics_context,
+                id,
+                config.resolution,
+                framerate.interval(),
+                queue_options(config),
+            )?,
+            InputConfig::Pattern { id, pattern, .. } => {
+                info!("Registered {pattern:?} pattern as {id}");
+            }
+            InputConfig::Web {
+                id,
+                url,
+                resolution,
+                ..
+            } => {
+                let resolution = resolution.unwrap_or(config.resolution);
+                Pipeline::register_renderer(
+                    pipeline,
+                    RendererId(Arc::from(id.as_str())),
+                    RendererSpec::WebRenderer(WebRendererSpec {
+                        url: url.clone(),
+                        resolution: Resolution {
+                            width: resolution.width,
+                            height: resolution.height,
+                        },
+                        embedding_method: WebEmbeddingMethod::ChromiumEmbedding,
+                    }),
+                )?;
+                info!("Registered web page {url} as {id}");
+            }
+            InputConfig::Animation {
+                id,
+                path,
+                should_loop,
+                ..
+            } => animation::register(
+                pipeline,
+                graphics_context,
+                id,
+                path,
+                *should_loop,
+                queue_options(config),
+            )?,
+            InputConfig::Playlist {
+                id,
+                items,
+                should_loop,
+                ..
+            } => playlist::register(
+                pipeline,
+                graphics_context,
+                id,
+                items,
+                config.framerate,
+                *should_loop,
+                queue_options(config),
+            )?,
+            InputConfig::ImageSequence {
+                id,
+                path,
+                framerate,
+                should_loop,
+                ..
+            } => {
+                let interval = match framerate {
+                    Some(framerate) => framerate.interval(),
+                    None => config.frame_interval(),
+                };
+                sequence::register(
+                    pipeline,
+                    graphics_context,
+                    id,
+                    path,
+                    interval,
+                    *should_loop,
+                    queue_options(config),
+                )?
+            }
+            InputConfig::Raw { id, .. } => {
+                let sender = raw_input::register(pipeline, id, queue_options(config))?;
+                info!("Registered raw input {id}");
+                return Ok(Some(sender));
+            }
+            InputConfig::Screen { id, monitor, .. } => screen::register(
+                pipeline,
+                graphics_context,
+                id,
+                monitor.as_deref(),
+                config.frame_interval(),
+                queue_options(config),
+            )?,
+            InputConfig::Whip {
+                id, bearer_token, ..
+            } => {
+                let input_id = InputId(Arc::from(id.as_str()));
+                let input_options = InputOptions::Whip(WhipReceiverOptions {
+                    video_preferences: vec![VideoDecoder::FFmpegH264, VideoDecoder::FFmpegVp8],
+                    bearer_token: bearer_token.as_deref().map(Arc::from),
+                });
+                let options = RegisterInputOptions {
+                    input_options,
+                    queue_options: queue_options(config),
+                };
+                let port = config.whip_server.port;
+                match Pipeline::register_input(pipeline, input_id, options)? {
+                    InputInitInfo::Whip { bearer_token } => info!(
+                        "Waiting for a publisher on http://localhost:{port}/whip/{id}, \
+                         bearer token {bearer_token}"
+                    ),
+                    _ => info!("Waiting for a publisher on http://localhost:{port}/whip/{id}"),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Registers an MP4 input while running, it gets a component like configured inputs.
+    pub fn add_mp4_input(
+        &mut self,
+        path: impl Into<PathBuf>,
+        should_loop: bool,
+        decoder: DecoderConfig,
+    ) -> Result<InputId> {
+        let id = (self.config.inputs.len()..)
+            .map(|i| format!("mp4_{i}"))
+            .find(|id| self.config.inputs.iter().all(|input| input.id() != id))
+            .unwrap();
+        let input = InputConfig::Mp4 {
+            id: id.clone(),
+            path: path.into(),
+            should_loop,
+            decoder,
+            trim: None,
+            tracks: None,
+            audio: false,
+            correction: None,
         };
-        Pipeline::register_input(pipeline, mp4_input_id.clone(), options)?;
-        info!("Registered {}", mp4_path.display());
 
-        Ok((image_input_id, mp4_input_id))
+        Self::register_input(&self.pipeline, &self.graphics_context, &input, &self.config)?;
+        let component = input_component(&input, &self.config.noise, self.resolution)?;
+        self.config.inputs.push(input);
+        self.components.push((id.clone(), component));
+
+        Ok(InputId(Arc::from(id)))
     }
 
-    fn start_record(&mut self, path: PathBuf) -> Result<()> {
-        use compositor_pipeline::pipeline::encoder::*;
-        use compositor_pipeline::pipeline::output::*;
+    /// Registers any input while running, it joins the inputs to cycle through like
+    /// configured ones, see [`Self::remove_input`].
+    pub fn add_input(&mut self, input: InputConfig) -> Result<()> {
+        let mut config = self.config.clone();
+        config.inputs.push(input.clone());
+        config.validate()?;
+        let id = input.id().to_string();
 
-        if path.exists() {
-            std::fs::remove_file(path.clone())?;
+        // Shaders shared between inputs are registered along with the first one using them
+        let existing = &self.config.inputs;
+        if let Some(correction) = input.correction() {
+            match existing.iter().any(|input| input.correction().is_some()) {
+                true => correction::register_lut(&self.pipeline, &id, correction)?,
+                false => correction::register(&self.pipeline, std::slice::from_ref(&input))?,
+            }
+        }
+        if !existing
+            .iter()
+            .any(|input| matches!(input, InputConfig::Pattern { .. }))
+        {
+            patterns::register(&self.pipeline, std::slice::from_ref(&input))?;
         }
 
-        compositor_pipeline::Pipeline::register_output(
-            &self.pipeline,
-            self.mp4_output.clone(),
-            RegisterOutputOptions {
-                output_options: OutputOptions::Mp4(mp4::Mp4OutputOptions {
-                    output_path: path.clone(),
-                    video: Some(VideoEncoderOptions::H264(ffmpeg_h264::Options {
-                        preset: ffmpeg_h264::EncoderPreset::Medium,
-                        resolution: Resolution {
-                            width: WIDTH,
-                            height: HEIGHT,
-                        },
-                        raw_options: [].to_vec(),
-                        pixel_format: OutputPixelFormat::YUV420P,
-                    })),
-                    audio: None,
-                }),
-                video: Some(OutputVideoOptions {
-                    initial: PLACEHOLDER.clone(),
-                    end_condition: PipelineOutputEndCondition::Never,
-                }),
-                audio: None,
-            },
-        )?;
-        info!("Started recording to {}", path.display());
+        let sender = Self::register_input(&self.pipeline, &self.graphics_context, &input, &config)?;
+        let component = input_component(&input, &self.config.noise, self.resolution)?;
+        self.config = config;
+        self.mixer.add_input(&input);
+        if let Some(sender) = sender {
+            self.raw_inputs.insert(id.clone(), sender);
+        }
+        match input {
+            InputConfig::Whip { .. } => self.publishers.push((id.clone(), component)),
+            _ => {
+                if self.waiting_for_publishers {
+                    self.components.clear();
+                    self.waiting_for_publishers = false;
+                }
+                self.components.push((id.clone(), component));
+            }
+        }
+        info!("Added input {id}");
 
         Ok(())
     }
 
-    fn stop_record(&mut self) -> Result<()> {
+    /// Unregisters an input while running.
+    ///
+    /// Outputs showing a scene that uses the input switch to the placeholder, and so do
+    /// the inputs to cycle through once none is left.
+    pub fn remove_input(&mut self, id: &str) -> Result<()> {
+        let index = self
+            .config
+            .inputs
+            .iter()
+            .position(|input| input.id() == id)
+            .with_context(|| format!("Unknown input {id:?}"))?;
+
+        // Renderers cannot be unregistered while a scene still uses them
+        let shown: Vec<OutputId> = self
+            .shown
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, component)| uses_input(component, id))
+            .map(|(output, _)| output.clone())
+            .collect();
+        for output in shown {
+            match self.outputs.name_of(&output).map(str::to_string) {
+                Some(name) => self.update_named_output(&name, PLACEHOLDER.clone())?,
+                None if output == self.raw_output => self.update_raw_output(PLACEHOLDER.clone())?,
+                None => self.update_record(PLACEHOLDER.clone())?,
+            }
+            info!("Output {} falls back to the placeholder", output.0);
+        }
+
+        // Unregistered first, so that an input the pipeline keeps stays in the config
+        let input = &self.config.inputs[index];
         let mut pipeline = self.pipeline.lock().unwrap();
-        Pipeline::unregister_output(&mut *pipeline, &self.mp4_output)?;
-        info!("Stopped recording");
+        match input {
+            InputConfig::Image { .. } => {
+                pipeline.unregister_renderer(&RendererId(Arc::from(id)), RegistryType::Image)?
+            }
+            InputConfig::Web { .. } => pipeline
+                .unregister_renderer(&RendererId(Arc::from(id)), RegistryType::WebRenderer)?,
+            // The pattern shader stays registered for the other patterns
+            InputConfig::Pattern { .. } => {}
+            _ => Pipeline::unregister_input(&mut *pipeline, &InputId(Arc::from(id)))?,
+        }
+        if input
+            .correction()
+            .is_some_and(|correction| correction.lut.is_some())
+        {
+            pipeline.unregister_renderer(&correction::lut_id(id), RegistryType::Image)?;
+        }
+        drop(pipeline);
+
+        self.config.inputs.remove(index);
+        self.mixer.remove_input(id);
+        self.raw_inputs.remove(id);
+        self.publishers.retain(|(existing, _)| existing != id);
+        self.components.retain(|(existing, _)| existing != id);
+        if self.components.is_empty() {
+            self.components.push(waiting_component());
+            self.waiting_for_publishers = true;
+        }
+        self.outputs.remove_input(id);
+        info!("Removed input {id}");
 
         Ok(())
     }
 
-    /// Alternates between the image and the MP4, changing every second.
-    fn alternate_scenes(&mut self, duration: Duration) -> Result<()> {
-        for i in 0..duration.as_secs() {
-            let component = match i % 2 {
-                0 => self.image_component.clone(),
-                _ => self.mp4_component.clone(),
-            };
+    /// Registers a raw input at runtime, fed with [`Self::push_raw_frame`].
+    ///
+    /// Like [`Self::add_mp4_input`], it joins the inputs to cycle through.
+    pub fn add_raw_input(&mut self, id: &str) -> Result<InputId> {
+        if self.config.inputs.iter().any(|input| input.id() == id) {
+            bail!("Duplicate input ID {id:?}");
+        }
+        let input = InputConfig::Raw {
+            id: id.to_string(),
+            correction: None,
+        };
 
-            let mut pipeline_lock = self.pipeline.lock().unwrap();
-            Pipeline::update_output(
-                &mut *pipeline_lock,
-                self.mp4_output.clone(),
-                Some(component.clone()),
-                None,
-            )?;
-            drop(pipeline_lock);
+        let sender =
+            Self::register_input(&self.pipeline, &self.graphics_context, &input, &self.config)?
+                .context("Raw input without sender")?;
+        let component = input_component(&input, &self.config.noise, self.resolution)?;
+        self.config.inputs.push(input);
+        self.components.push((id.to_string(), component));
+        self.raw_inputs.insert(id.to_string(), sender);
 
-            std::thread::sleep(Duration::from_secs(1));
-        }
-        Ok(())
+        Ok(InputId(Arc::from(id)))
     }
 
-    fn register_raw_output(&mut self) -> Result<RawDataReceiver> {
-        let raw_receiver = Pipeline::register_raw_data_output(
-            &self.pipeline,
-            self.raw_output.clone(),
-            RegisterOutputOptions {
-                output_options: RawDataOutputOptions {
-                    video: Some(RawVideoOptions {
-                        resolution: Resolution {
-                            width: WIDTH,
-                            height: HEIGHT,
-                        },
-                    }),
-                    audio: None,
-                },
-                video: Some(OutputVideoOptions {
-                    initial: PLACEHOLDER.clone(),
-                    end_condition: PipelineOutputEndCondition::Never,
-                }),
-                audio: None,
+    /// Pushes a frame to a raw input, its PTS is relative to the start of the input.
+    ///
+    /// The frame data must be a texture created with the graphics context of the
+    /// compositor, see [`Self::push_raw_image`] for CPU images.
+    pub fn push_raw_frame(&self, input_id: &InputId, frame: Frame) -> Result<()> {
+        let sender = self
+            .raw_inputs
+            .get(&*input_id.0)
+            .with_context(|| format!("{} is not a raw input", input_id.0))?;
+        sender
+            .send(PipelineEvent::Data(frame))
+            .map_err(|_| anyhow!("Raw input {} was unregistered", input_id.0))
+    }
+
+    /// Uploads an image and pushes it to a raw input, see [`Self::push_raw_frame`].
+    pub fn push_raw_image(
+        &self,
+        input_id: &InputId,
+        image: &RgbaImage,
+        pts: Duration,
+    ) -> Result<()> {
+        let frame = raw_input::frame(&self.graphics_context, image, pts);
+        self.push_raw_frame(input_id, frame)
+    }
+
+    /// Inputs registered so far, from the config, [`Self::add_input`], [`Self::add_mp4_input`]
+    /// and [`Self::add_raw_input`].
+    pub fn inputs(&self) -> &[InputConfig] {
+        &self.config.inputs
+    }
+
+    /// Shows the given inputs next to each other, each one fitted in an equal share of the width.
+    pub fn tiled(&self, ids: &[&str]) -> Result<Component> {
+        let width = self.resolution.width as f32 / ids.len().max(1) as f32;
+        let children = ids
+            .iter()
+            .map(|id| {
+                let input = self
+                    .config
+                    .inputs
+                    .iter()
+                    .find(|input| input.id() == *id)
+                    .with_context(|| format!("Unknown input {id:?}"))?;
+                Ok(fit(
+                    input_content(input, &self.config.noise, self.resolution)?,
+                    width,
+                    self.resolution.height as f32,
+                ))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Component::View(ViewComponent {
+            id: None,
+            children,
+            direction: ViewChildrenDirection::Row,
+            position: Position::Static {
+                width: Some(self.resolution.width as f32),
+                height: Some(self.resolution.height as f32),
             },
-        )?;
+            transition: None,
+            overflow: Overflow::Hidden,
+            background_color: RGBAColor(0, 0, 0, 255),
+            border_radius: compositor_render::scene::BorderRadius::ZERO,
+            border_width: 0.,
+            border_color: RGBAColor(0, 0, 0, 0),
+            box_shadow: vec![],
+            padding: Padding {
+                top: 0.,
+                right: 0.,
+                bottom: 0.,
+                left: 0.,
+            },
+        }))
+    }
 
-        Ok(raw_receiver)
+    /// Registers every track of a multi-track MP4 file as its own input while running: one
+    /// per video track, `{id}_video{n}`, and one per audio track playing it over the first
+    /// video track, `{id}_audio{n}`. Returns their IDs, e.g. for [`Self::tiled`].
+    pub fn add_mp4_tracks(&mut self, id: &str, path: &Path) -> Result<Vec<String>> {
+        let tracks = media::mp4_tracks(path)?;
+        if tracks.video == 0 {
+            bail!("{} has no video track", path.display());
+        }
+        let mut selections: Vec<(String, TrackSelectionConfig)> = (0..tracks.video)
+            .map(|video| {
+                let selection = TrackSelectionConfig { video, audio: None };
+                (format!("{id}_video{video}"), selection)
+            })
+            .collect();
+        for (audio, language) in tracks.audio.iter().enumerate() {
+            let selection = TrackSelectionConfig {
+                video: 0,
+                audio: Some(audio),
+            };
+            selections.push((format!("{id}_audio{audio}"), selection));
+            debug!("Audio track {audio} of {} is in {language}", path.display());
+        }
+
+        let mut ids = vec![];
+        for (track_id, selection) in selections {
+            self.add_input(InputConfig::Mp4 {
+                id: track_id.clone(),
+                path: path.to_path_buf(),
+                should_loop: true,
+                decoder: DecoderConfig::default(),
+                trim: None,
+                tracks: Some(selection),
+                audio: selection.audio.is_some(),
+                correction: None,
+            })?;
+            ids.push(track_id);
+        }
+        info!("Registered {} tracks of {}", ids.len(), path.display());
+
+        Ok(ids)
     }
 
-    fn deregister_raw_output(&mut self) -> Result<()> {
+    /// Unregisters and registers an input again, restarting it from the beginning.
+    pub fn restart_input(&mut self, id: &str) -> Result<()> {
+        let input = self
+            .config
+            .inputs
+            .iter()
+            .find(|input| input.id() == id)
+            .with_context(|| format!("Unknown input {id:?}"))?
+            .clone();
+
+        // Images, patterns and web pages are renderers, and WHIP, raw and stdin inputs are
+        // driven by their producer
+        if let InputConfig::Image { .. }
+        | InputConfig::Pattern { .. }
+        | InputConfig::Web { .. }
+        | InputConfig::Whip { .. }
+        | InputConfig::Raw { .. }
+        | InputConfig::Stdin { .. } = input
+        {
+            return Ok(());
+        }
+
         let mut pipeline = self.pipeline.lock().unwrap();
-        Pipeline::unregister_output(&mut *pipeline, &self.raw_output)?;
+        Pipeline::unregister_input(&mut *pipeline, &InputId(Arc::from(id)))?;
+        drop(pipeline);
 
+        Self::register_input(&self.pipeline, &self.graphics_context, &input, &self.config)?;
         Ok(())
     }
 
-    pub fn get_last_frame(raw_receiver: &RawDataReceiver) -> Result<Arc<wgpu::Texture>> {
-        let receiver = raw_receiver.video.as_ref().context("No video channel")?;
+    /// Starts encoding to an MP4 file, showing the placeholder until [`Self::update_record`].
+    ///
+    /// `path` is a template with the time the recording starts, see [`naming::expand`], and
+    /// a suffix is added when a file already has its name. Returns the path recorded to.
+    pub fn start_record(&mut self, path: PathBuf) -> Result<PathBuf> {
+        self.start_recording(&path, None, PLACEHOLDER.clone())
+    }
 
-        // Wait to have at least one frame
-        let mut latest_frame = loop {
-            match receiver.recv()? {
-                PipelineEvent::Data(frame) => break frame,
-                _ => continue,
-            }
+    /// Like [`Self::start_record`], moving on to numbered files `{stem}_0001.mp4`,
+    /// `{stem}_0002.mp4`, ... as each one reaches the `rollover` duration or size, see
+    /// [`Self::poll_recording`].
+    pub fn start_segmented_record(&mut self, path: PathBuf, rollover: Rollover) -> Result<PathBuf> {
+        self.start_recording(&path, Some(rollover), PLACEHOLDER.clone())
+    }
+
+    fn start_recording(
+        &mut self,
+        template: &Path,
+        rollover: Option<Rollover>,
+        initial: Component,
+    ) -> Result<PathBuf> {
+        if self.recording.is_some() {
+            bail!("Already recording");
+        }
+        // Taken too by the frames of an earlier recording that fell back to PNG files
+        let path = naming::resolve(template, |candidate| {
+            let file = RecordingController::new(candidate.to_path_buf(), rollover).first_file();
+            file.exists() || fallback::frames_dir(&file).exists()
+        })?;
+        let mut recording = RecordingController::new(path.clone(), rollover);
+        self.record_fallback = false;
+        self.record_on_scene = false;
+        // Only recordings of inputs with audio get a track, silent ones are kept as they were
+        self.record_audio =
+            !self.mixer.is_empty() && self.config.encoder.codec == RecordCodecConfig::H264;
+        let file = recording.next_file();
+        self.register_record(&file, initial)?;
+        info!(
+            "Started recording to {}{}",
+            file.display(),
+            if self.record_audio { " with audio" } else { "" }
+        );
+        self.recording = Some(recording);
+
+        Ok(path)
+    }
+
+    /// Closes the file being recorded to and starts the next one, when a segmented
+    /// recording reached its rollover. To call regularly while recording.
+    pub fn poll_recording(&mut self) -> Result<()> {
+        let Some(recording) = &mut self.recording else {
+            return Ok(());
         };
+        if !recording.should_roll_over() {
+            return Ok(());
+        }
+        let file = recording.next_file();
+        let component = self.shown_by_record();
+        self.unregister_record()?;
+        self.register_record(&file, component)?;
+        info!("Recording rolled over to {}", file.display());
 
-        // Drain any additional available frames
-        while let Ok(event) = receiver.try_recv() {
-            if let PipelineEvent::Data(frame) = event {
-                latest_frame = frame;
-            }
+        Ok(())
+    }
+
+    /// Stops encoding until [`Self::resume_recording`], scene changes still apply.
+    ///
+    /// The frames shown while paused are left out of the recording: the file recorded so
+    /// far is finalized, and joined with the next ones when the recording stops, unless it
+    /// is segmented.
+    pub fn pause_recording(&mut self) -> Result<()> {
+        let recording = self.recording.as_mut().context("Not recording")?;
+        if recording.is_paused() {
+            bail!("Recording already paused");
         }
+        recording.set_paused(true);
+        self.unregister_record()?;
+        info!("Paused recording");
 
-        // Extract the texture
-        match latest_frame.data {
-            FrameData::Rgba8UnormWgpuTexture(texture) => Ok(texture.clone()),
-            _ => Err(anyhow!("Expected Rgba8UnormWgpuTexture")),
+        Ok(())
+    }
+
+    /// Starts encoding again, to a new file, the component last shown.
+    pub fn resume_recording(&mut self) -> Result<()> {
+        let recording = self.recording.as_mut().context("Not recording")?;
+        if !recording.is_paused() {
+            bail!("Recording not paused");
         }
+        let file = recording.next_file();
+        recording.set_paused(false);
+        let component = self.shown_by_record();
+        self.register_record(&file, component)?;
+        info!("Resumed recording to {}", file.display());
+
+        Ok(())
     }
 
-    /// Switch to a given component and extract pipeline output.
-    pub fn render_component(
-        &mut self,
-        receiver: &RawDataReceiver,
-        component: Component,
-    ) -> Result<Arc<wgpu::Texture>> {
-        let mut pipeline_lock = self.pipeline.lock().unwrap();
+    /// Stops the recording, the file is finalized shortly after.
+    ///
+    /// A paused recording is joined once all its segments are finalized, before returning.
+    pub fn stop_record(&mut self) -> Result<()> {
+        let mut recording = self.recording.take().context("Not recording")?;
+        if self.record_fallback {
+            recording.keep_files_apart();
+        }
+        if !recording.is_paused() {
+            self.unregister_record()?;
+        }
+        self.shown.lock().unwrap().remove(&self.mp4_output);
+        let files = recording.finish()?;
+        info!("Stopped recording, {} files", files.len());
+
+        Ok(())
+    }
+
+    /// Switches the recording to another component.
+    pub fn update_record(&self, component: Component) -> Result<()> {
+        let paused = self
+            .recording
+            .as_ref()
+            .is_some_and(|recording| recording.is_paused());
+        if !paused {
+            self.flash_marker(Marker::SceneSwitch, &component)?;
+            let mut pipeline_lock = self.pipeline.lock().unwrap();
+            Pipeline::update_output(
+                &mut *pipeline_lock,
+                self.mp4_output.clone(),
+                Some(self.output_scene(component.clone())),
+                self.record_audio.then(|| self.mixer.mix(&component)),
+            )?;
+        }
+        self.coverage.lock().unwrap().record(&component);
+        // Shown again on resume
+        self.shown
+            .lock()
+            .unwrap()
+            .insert(self.mp4_output.clone(), component);
+
+        Ok(())
+    }
+
+    /// Shows `marker` over `component` in the recording, when markers are enabled, for a
+    /// frame interval: the frames of the recording are not observed, so the marker can span
+    /// one or more frames, or none when the pipeline falls behind. The caller shows
+    /// `component` alone again afterwards.
+    fn flash_marker(&self, marker: Marker, component: &Component) -> Result<()> {
+        let config = &self.config.markers;
+        if !config.enabled {
+            return Ok(());
+        }
+        let marked = markers::wrap(
+            self.output_scene(component.clone()),
+            marker,
+            config,
+            self.marker_inset(),
+            self.resolution,
+        );
         Pipeline::update_output(
-            &mut *pipeline_lock,
-            self.raw_output.clone(),
-            Some(component),
+            &mut *self.pipeline.lock().unwrap(),
+            self.mp4_output.clone(),
+            Some(marked),
             None,
         )?;
-        drop(pipeline_lock);
+        std::thread::sleep(self.frame_interval);
 
-        std::thread::sleep(Duration::from_millis(100)); // Make sure this is the new component
+        Ok(())
+    }
 
-        let frame = Self::get_last_frame(&receiver)?;
+    /// Distance of the markers from the edges, keeping them inside the canary border so
+    /// both can be checked.
+    fn marker_inset(&self) -> u32 {
+        match self.config.canary.enabled {
+            true => self.config.canary.width,
+            false => 0,
+        }
+    }
 
-        Ok(frame)
+    /// Changes the H264 settings of the next recordings.
+    pub fn set_encoder(&mut self, encoder: EncoderConfig) -> Result<()> {
+        if self.recording.is_some() {
+            bail!("Cannot change the encoder settings while recording");
+        }
+        encoder.validate()?;
+        self.config.encoder = encoder;
+        Ok(())
     }
 
-    pub fn save_images(&mut self) -> Result<()> {
-        let receiver = self.register_raw_output()?;
-        info!("Saving output to output_*.png");
+    /// Registers the MP4 output, encoding `initial` to `path`, or saving its frames with
+    /// [`PngSequenceSink`] when the encoder cannot start and `encoder.fallback` allows it.
+    fn register_record(&mut self, path: &Path, initial: Component) -> Result<()> {
+        if path.exists() {
+            bail!("{} already exists, not overwriting it", path.display());
+        }
+        let registered = match self.config.encoder.codec {
+            RecordCodecConfig::H264 => self.register_pipeline_record(path, &initial),
+            _ => self.register_ffmpeg_record(path, &initial),
+        };
+        match (registered, self.config.encoder.fallback) {
+            (Ok(()), _) => {}
+            (Err(err), EncoderFallbackConfig::PngSequence) => {
+                self.register_fallback_record(path, &initial, err)?
+            }
+            (Err(err), EncoderFallbackConfig::None) => return Err(err),
+        }
+        self.shown
+            .lock()
+            .unwrap()
+            .insert(self.mp4_output.clone(), initial);
 
-        let frame = self.render_component(&receiver, self.image_component.clone())?;
-        let image = to_image(&self.graphics_context, &frame)?;
-        image.save("output_png.png")?;
+        Ok(())
+    }
 
-        let frame = self.render_component(&receiver, self.mp4_component.clone())?;
-        let image = to_image(&self.graphics_context, &frame)?;
-        image.save("output_mp4.png")?;
+    /// Registers the MP4 output of the pipeline, which encodes H264.
+    fn register_pipeline_record(&self, path: &Path, initial: &Component) -> Result<()> {
+        use compositor_pipeline::pipeline::encoder::*;
+        use compositor_pipeline::pipeline::output::*;
 
-        self.deregister_raw_output()?;
-        info!("Images saved");
+        let record_audio = self.record_audio;
+        let register = || {
+            compositor_pipeline::Pipeline::register_output(
+                &self.pipeline,
+                self.mp4_output.clone(),
+                RegisterOutputOptions {
+                    output_options: OutputOptions::Mp4(mp4::Mp4OutputOptions {
+                        output_path: path.to_path_buf(),
+                        video: Some(VideoEncoderOptions::H264(ffmpeg_h264::Options {
+                            preset: self.config.encoder.preset.into(),
+                            resolution: self.resolution,
+                            raw_options: self.config.encoder.raw_options(),
+                            pixel_format: OutputPixelFormat::YUV420P,
+                        })),
+                        audio: record_audio.then(|| self.mixer.encoder_options()),
+                    }),
+                    video: Some(OutputVideoOptions {
+                        initial: self.output_scene(initial.clone()),
+                        end_condition: PipelineOutputEndCondition::Never,
+                    }),
+                    audio: record_audio.then(|| self.mixer.output_options(initial)),
+                },
+            )?;
+            Ok(())
+        };
+        // Encoder threads inherit the priority and affinity of this one
+        self.config.threads.encoding.scoped("encoding", register)
+    }
 
-        Ok(())
+    /// Registers a raw output in place of the MP4 one, whose frames [`HevcEncoder`] or
+    /// [`LosslessEncoder`] encodes to `path`.
+    fn register_ffmpeg_record(&mut self, path: &Path, initial: &Component) -> Result<()> {
+        let receiver = self.register_raw_record(initial)?;
+        let context = self.graphics_context.clone();
+        let (resolution, framerate) = (self.resolution, self.config.framerate);
+        let started = match self.config.encoder.codec {
+            RecordCodecConfig::Prores | RecordCodecConfig::Ffv1 => LosslessEncoder::start(
+                path,
+                receiver,
+                context,
+                resolution,
+                framerate,
+                &self.config.encoder,
+            )
+            .map(|encoder| self.lossless_encoder = Some(encoder)),
+            _ => HevcEncoder::start(
+                path,
+                receiver,
+                context,
+                resolution,
+                framerate,
+                &self.config.encoder,
+            )
+            .map(|encoder| self.hevc_encoder = Some(encoder)),
+        };
+        if started.is_err() {
+            self.unregister_record()?;
+        }
+        started
     }
 
-    pub fn record_for(&mut self, duration: Duration) -> Result<()> {
-        self.start_record(PathBuf::from("output.mp4"))?;
-        self.alternate_scenes(duration)?;
+    /// Registers a raw output in place of the MP4 one, whose frames [`PngSequenceSink`]
+    /// saves next to `path`, after its encoder failed to start with `err`.
+    fn register_fallback_record(
+        &mut self,
+        path: &Path,
+        initial: &Component,
+        err: anyhow::Error,
+    ) -> Result<()> {
+        let dir = fallback::frames_dir(path);
+        warn!(
+            "Cannot start the {:?} encoder of {}, recording PNG frames to {} instead: {err:#}",
+            self.config.encoder.codec,
+            path.display(),
+            dir.display()
+        );
+        let receiver = self.register_raw_record(initial)?;
+        match PngSequenceSink::start(&dir, receiver, self.graphics_context.clone()) {
+            Ok(sink) => self.fallback_sink = Some(sink),
+            Err(sink_err) => {
+                self.unregister_record()?;
+                return Err(sink_err);
+            }
+        }
+        self.record_fallback = true;
+        self.event_log.write(LogEvent::EncoderFallback {
+            path: path.display().to_string(),
+            frames_dir: dir.display().to_string(),
+            reason: format!("{err:#}"),
+        });
+        Ok(())
+    }
+
+    /// Registers a raw output as the MP4 one, showing `initial`.
+    fn register_raw_record(&self, initial: &Component) -> Result<RawDataReceiver> {
+        let receiver = Pipeline::register_raw_data_output(
+            &self.pipeline,
+            self.mp4_output.clone(),
+            RegisterOutputOptions {
+                output_options: RawDataOutputOptions {
+                    video: Some(RawVideoOptions {
+                        resolution: self.resolution,
+                    }),
+                    audio: None,
+                },
+                video: Some(OutputVideoOptions {
+                    initial: self.output_scene(initial.clone()),
+                    end_condition: PipelineOutputEndCondition::Never,
+                }),
+                audio: None,
+            },
+        )?;
+        Ok(receiver)
+    }
+
+    fn unregister_record(&mut self) -> Result<()> {
+        let mut pipeline = self.pipeline.lock().unwrap();
+        Pipeline::unregister_output(&mut *pipeline, &self.mp4_output)?;
+        drop(pipeline);
+        if let Some(encoder) = self.hevc_encoder.take() {
+            encoder.finish()?;
+        }
+        if let Some(encoder) = self.lossless_encoder.take() {
+            encoder.finish()?;
+        }
+        if let Some(sink) = self.fallback_sink.take() {
+            sink.finish()?;
+        }
+        Ok(())
+    }
+
+    /// Component the recording shows, or showed before it was paused.
+    fn shown_by_record(&self) -> Component {
+        self.shown
+            .lock()
+            .unwrap()
+            .get(&self.mp4_output)
+            .cloned()
+            .unwrap_or_else(|| PLACEHOLDER.clone())
+    }
+
+    /// Sets the gain of an input with audio, from 0 to [`audio::MAX_VOLUME`], applied to
+    /// the recording right away.
+    pub fn set_volume(&mut self, id: &str, volume: f32) -> Result<()> {
+        self.mixer.set_volume(id, volume)?;
+        self.update_audio()
+    }
+
+    /// Leaves an input with audio out of the mix, or brings it back.
+    pub fn set_muted(&mut self, id: &str, muted: bool) -> Result<()> {
+        self.mixer.set_muted(id, muted)?;
+        self.update_audio()
+    }
+
+    /// Mixes the audio of the outputs with audio again, after the mixer changed.
+    fn update_audio(&self) -> Result<()> {
+        let paused = self
+            .recording
+            .as_ref()
+            .is_some_and(|recording| recording.is_paused());
+        for (output, has_audio) in [
+            (&self.mp4_output, self.record_audio && !paused),
+            (&self.raw_output, self.raw_audio),
+        ] {
+            if !has_audio {
+                continue;
+            }
+            let Some(component) = self.shown.lock().unwrap().get(output).cloned() else {
+                continue;
+            };
+            let mut pipeline_lock = self.pipeline.lock().unwrap();
+            Pipeline::update_output(
+                &mut *pipeline_lock,
+                output.clone(),
+                None,
+                Some(self.mixer.mix(&component)),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Cycles through the input components, changing every `interval`, on the recording
+    /// when one is in progress and on the registered outputs named in `targets`, each
+    /// through its own inputs.
+    fn alternate_scenes(
+        &mut self,
+        duration: Duration,
+        interval: Duration,
+        targets: &[String],
+    ) -> Result<()> {
+        let switches = (duration.as_millis() / interval.as_millis().max(1)) as usize;
+        for i in 0..switches {
+            self.poll_events();
+            if self.recording.is_some() {
+                let (id, component) = self.components[i % self.components.len()].clone();
+                // Already shown from the first frame of the recording, a switch would only
+                // flash the marker over its first frames
+                match i == 0 && std::mem::take(&mut self.record_on_scene) {
+                    true => self.coverage.lock().unwrap().record(&component),
+                    false => self.update_record(component)?,
+                }
+                self.event_log.write(LogEvent::SceneApplied {
+                    output: "mp4",
+                    scene: &id,
+                });
+            }
+            for name in targets {
+                // The first input is shown from registration
+                if i == 0 {
+                    continue;
+                }
+                let Some((id, component)) = self.outputs.get(name)?.component(i).cloned() else {
+                    continue;
+                };
+                self.update_named_output(name, component)?;
+                self.event_log.write(LogEvent::SceneApplied {
+                    output: name,
+                    scene: &id,
+                });
+            }
+
+            let switched = Instant::now();
+            while switched.elapsed() < interval {
+                self.poll_recording()?;
+                self.outputs.drain();
+                std::thread::sleep(RECORDING_POLL_INTERVAL.min(interval));
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the `outputs` at once for `duration`, those that alternate switching input
+    /// every `switch_interval`.
+    pub fn run_simultaneous(
+        &mut self,
+        outputs: &[NamedOutputConfig],
+        duration: Duration,
+        switch_interval: Duration,
+    ) -> Result<()> {
+        let mut result = outputs
+            .iter()
+            .try_for_each(|output| self.register_named_output(output))
+            .and_then(|()| {
+                let targets = self.outputs.alternating();
+                self.alternate_scenes(duration, switch_interval, &targets)
+            });
+        // Those registered before a failure too, the first error is the one returned
+        for name in self.outputs.names() {
+            if let Err(err) = self.unregister_named_output(&name) {
+                match result {
+                    Ok(()) => result = Err(err),
+                    Err(_) => warn!("Cannot unregister output {name}: {err:#}"),
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Registers an output next to the recording and the raw output, under the name in
+    /// `config`, showing the first of its inputs.
+    ///
+    /// Inputs are rendered at the resolution of the config and scaled to that of the
+    /// output, which has no audio.
+    pub fn register_named_output(&mut self, config: &NamedOutputConfig) -> Result<()> {
+        use compositor_pipeline::pipeline::encoder::*;
+
+        let resolution = config
+            .resolution
+            .map_or(self.resolution, |resolution| Resolution {
+                width: resolution.width,
+                height: resolution.height,
+            });
+        let components = match config.inputs.is_empty() {
+            true => self.components.clone(),
+            false => config
+                .inputs
+                .iter()
+                .map(|id| {
+                    self.components
+                        .iter()
+                        .find(|(existing, _)| existing == id)
+                        .cloned()
+                        .with_context(|| {
+                            format!("Output {:?} shows unknown input {id:?}", config.name)
+                        })
+                })
+                .collect::<Result<Vec<_>>>()?,
+        };
+        let (_, initial) = components
+            .first()
+            .cloned()
+            .unwrap_or_else(waiting_component);
+        let scaling = RescaleMode::from(config.scaling);
+        let id = OutputId(Arc::from(format!("output_{}", config.name)));
+        let video = Some(OutputVideoOptions {
+            initial: self.output_scene_at(initial.clone(), resolution, scaling),
+            end_condition: PipelineOutputEndCondition::Never,
+        });
+        let h264 = || {
+            VideoEncoderOptions::H264(ffmpeg_h264::Options {
+                preset: self.config.encoder.preset.into(),
+                resolution,
+                raw_options: self.config.encoder.raw_options(),
+                pixel_format: OutputPixelFormat::YUV420P,
+            })
+        };
+
+        let sink = match &config.output {
+            NamedOutputKindConfig::Preview { path } => {
+                let receiver = Pipeline::register_raw_data_output(
+                    &self.pipeline,
+                    id.clone(),
+                    RegisterOutputOptions {
+                        output_options: RawDataOutputOptions {
+                            video: Some(RawVideoOptions { resolution }),
+                            audio: None,
+                        },
+                        video,
+                        audio: None,
+                    },
+                )?;
+                Sink::Preview {
+                    receiver,
+                    path: path.clone(),
+                    latest: None,
+                    frames: 0,
+                }
+            }
+            NamedOutputKindConfig::Mp4 { path } => {
+                let path = naming::resolve_file(path)?;
+                info!("Output {} records to {}", config.name, path.display());
+                let options = OutputOptions::Mp4(mp4::Mp4OutputOptions {
+                    output_path: path,
+                    video: Some(h264()),
+                    audio: None,
+                });
+                self.register_encoded_output(id.clone(), options, video)?;
+                Sink::Encoded
+            }
+            NamedOutputKindConfig::Whip {
+                endpoint_url,
+                bearer_token,
+            } => {
+                let options = OutputOptions::Whip(whip::WhipSenderOptions {
+                    endpoint_url: endpoint_url.clone(),
+                    bearer_token: bearer_token.as_deref().map(Arc::from),
+                    video: Some(whip::VideoWhipOptions {
+                        encoder_preferences: vec![h264()],
+                    }),
+                    audio: None,
+                });
+                self.register_encoded_output(id.clone(), options, video)?;
+                Sink::Encoded
+            }
+        };
+        self.coverage.lock().unwrap().record(&initial);
+        self.shown.lock().unwrap().insert(id.clone(), initial);
+        self.outputs.insert(
+            &config.name,
+            RegisteredOutput {
+                id,
+                resolution,
+                scaling,
+                components,
+                alternate: config.alternate,
+                sink,
+            },
+        )?;
+        info!(
+            "Registered output {} at {}x{}",
+            config.name, resolution.width, resolution.height
+        );
+
+        Ok(())
+    }
+
+    /// Registers an output the pipeline encodes, its encoder threads inheriting the
+    /// priority and affinity of the encoding group.
+    fn register_encoded_output(
+        &self,
+        id: OutputId,
+        output_options: OutputOptions,
+        video: Option<OutputVideoOptions>,
+    ) -> Result<()> {
+        self.config.threads.encoding.scoped("encoding", || {
+            Pipeline::register_output(
+                &self.pipeline,
+                id,
+                RegisterOutputOptions {
+                    output_options,
+                    video,
+                    audio: None,
+                },
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Switches the output registered as `name` to another component.
+    pub fn update_named_output(&self, name: &str, component: Component) -> Result<()> {
+        let output = self.outputs.get(name)?;
+        Pipeline::update_output(
+            &mut *self.pipeline.lock().unwrap(),
+            output.id.clone(),
+            Some(self.output_scene_at(component.clone(), output.resolution, output.scaling)),
+            None,
+        )?;
+        self.coverage.lock().unwrap().record(&component);
+        self.shown
+            .lock()
+            .unwrap()
+            .insert(output.id.clone(), component);
+
+        Ok(())
+    }
+
+    /// Unregisters the output registered as `name`, saving the last frame of a preview.
+    pub fn unregister_named_output(&mut self, name: &str) -> Result<()> {
+        let mut output = self.outputs.remove(name)?;
+        Pipeline::unregister_output(&mut *self.pipeline.lock().unwrap(), &output.id)?;
+        self.shown.lock().unwrap().remove(&output.id);
+
+        output.drain();
+        if let Sink::Preview {
+            path,
+            latest,
+            frames,
+            ..
+        } = output.sink
+        {
+            info!("Output {name} received {frames} frames");
+            if let (Some(path), Some(frame)) = (path, latest) {
+                let FrameData::Rgba8UnormWgpuTexture(texture) = &frame.data else {
+                    bail!("Expected Rgba8UnormWgpuTexture");
+                };
+                to_image(&self.graphics_context, texture, self.shader_cache.get())?.save(&path)?;
+                info!("Saved the last frame of {name} to {}", path.display());
+            }
+        }
+        info!("Unregistered output {name}");
+
+        Ok(())
+    }
+
+    /// Logs findings, and writes them to the event log.
+    fn log_findings(&mut self, findings: &[Finding]) {
+        analysis::log_findings(findings);
+        for finding in findings {
+            self.event_log.write(LogEvent::finding(finding));
+        }
+    }
+
+    /// Receives the pipeline events emitted since the last call, and updates the inputs
+    /// to cycle through with the WHIP publishers that connected or disconnected.
+    fn poll_events(&mut self) -> Vec<Event> {
+        let events: Vec<Event> = self.events.try_iter().collect();
+        for event in &events {
+            self.update_publishers(event);
+            self.event_log.write(LogEvent::pipeline(event));
+            if self.reconnections.is_reconnection(event) {
+                self.flash_reconnection();
+            }
+        }
+        events
+    }
+
+    /// Flashes the reconnection marker over the component the recording shows.
+    fn flash_reconnection(&self) {
+        let recording = self
+            .recording
+            .as_ref()
+            .is_some_and(|recording| !recording.is_paused());
+        if !recording {
+            return;
+        }
+        let component = self.shown_by_record();
+        let flash = || -> Result<()> {
+            self.flash_marker(Marker::InputReconnected, &component)?;
+            Pipeline::update_output(
+                &mut *self.pipeline.lock().unwrap(),
+                self.mp4_output.clone(),
+                Some(self.output_scene(component.clone())),
+                None,
+            )?;
+            Ok(())
+        };
+        if let Err(err) = flash() {
+            warn!("Cannot show the reconnection marker: {err:#}");
+        }
+    }
+
+    fn update_publishers(&mut self, event: &Event) {
+        match event {
+            Event::VideoInputStreamDelivered(input_id) => {
+                let Some(publisher) = self
+                    .publishers
+                    .iter()
+                    .find(|(id, _)| id.as_str() == &*input_id.0)
+                    .cloned()
+                else {
+                    return;
+                };
+                info!("Publisher connected to {}", publisher.0);
+                if self.waiting_for_publishers {
+                    self.components.clear();
+                    self.waiting_for_publishers = false;
+                }
+                // A publisher reconnecting, without its stream ending first, keeps its place
+                match self
+                    .components
+                    .iter_mut()
+                    .find(|(id, _)| *id == publisher.0)
+                {
+                    Some(existing) => *existing = publisher,
+                    None => self.components.push(publisher),
+                }
+            }
+            Event::VideoInputStreamEos(input_id) => {
+                if !self
+                    .publishers
+                    .iter()
+                    .any(|(id, _)| id.as_str() == &*input_id.0)
+                {
+                    return;
+                }
+                info!("Publisher disconnected from {}", input_id.0);
+                self.components
+                    .retain(|(id, _)| id.as_str() != &*input_id.0);
+                if self.components.is_empty() {
+                    self.components.push(waiting_component());
+                    self.waiting_for_publishers = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Wraps a scene in the canary border when it is enabled, before it goes to an output.
+    fn output_scene(&self, scene: Component) -> Component {
+        self.output_scene_at(scene, self.resolution, RescaleMode::Fit)
+    }
+
+    /// Like [`Self::output_scene`], for an output at `resolution`, the scene being scaled
+    /// to it in `scaling` when it differs from that of the config.
+    fn output_scene_at(
+        &self,
+        scene: Component,
+        resolution: Resolution,
+        scaling: RescaleMode,
+    ) -> Component {
+        let scene = match resolution == self.resolution {
+            true => scene,
+            false => rescaled(
+                scene,
+                scaling,
+                resolution.width as f32,
+                resolution.height as f32,
+            ),
+        };
+        let canary = &self.config.canary;
+        match scene::parse_color(Some(&canary.color)) {
+            Ok(color) if canary.enabled => canary::wrap(scene, color, canary.width, resolution),
+            _ => scene,
+        }
+    }
+
+    /// Checker of the canary border on captured frames, when it is enabled.
+    fn canary_check(&self) -> Option<CanaryCheck> {
+        let canary = &self.config.canary;
+        let color = scene::parse_color(Some(&canary.color)).ok()?;
+        canary
+            .enabled
+            .then(|| CanaryCheck::new([color.0, color.1, color.2], canary.width, canary.tolerance))
+    }
+
+    /// Registers the raw output, to receive rendered frames with [`Self::render_component`].
+    pub fn register_raw_output(&mut self, initial: Component) -> Result<RawDataReceiver> {
+        let raw_receiver = Pipeline::register_raw_data_output(
+            &self.pipeline,
+            self.raw_output.clone(),
+            RegisterOutputOptions {
+                output_options: RawDataOutputOptions {
+                    video: Some(RawVideoOptions {
+                        resolution: self.resolution,
+                    }),
+                    audio: self.raw_audio.then_some(RawAudioOptions),
+                },
+                video: Some(OutputVideoOptions {
+                    initial: self.output_scene(initial.clone()),
+                    end_condition: PipelineOutputEndCondition::Never,
+                }),
+                audio: self.raw_audio.then(|| self.mixer.output_options(&initial)),
+            },
+        )?;
+        self.coverage.lock().unwrap().record(&initial);
+        self.shown
+            .lock()
+            .unwrap()
+            .insert(self.raw_output.clone(), initial);
+
+        Ok(raw_receiver)
+    }
+
+    pub fn deregister_raw_output(&mut self) -> Result<()> {
+        let mut pipeline = self.pipeline.lock().unwrap();
+        Pipeline::unregister_output(&mut *pipeline, &self.raw_output)?;
+        self.shown.lock().unwrap().remove(&self.raw_output);
+
+        Ok(())
+    }
+
+    /// Waits for a frame, then keeps only the latest one already queued.
+    ///
+    /// Returns the frame with the number of older frames skipped. The drain is bounded
+    /// by what was queued when it started, so a fast producer cannot keep it spinning.
+    pub fn latest_frame(raw_receiver: &RawDataReceiver) -> Result<(Frame, usize)> {
+        let receiver = raw_receiver.video.as_ref().context("No video channel")?;
+
+        // Wait to have at least one frame
+        let mut latest_frame = loop {
+            match receiver.recv()? {
+                PipelineEvent::Data(frame) => break frame,
+                _ => continue,
+            }
+        };
+
+        // Drain the frames already available, the latest wins
+        let mut skipped = 0;
+        for _ in 0..receiver.len() {
+            match receiver.try_recv() {
+                Ok(PipelineEvent::Data(frame)) => {
+                    latest_frame = frame;
+                    skipped += 1;
+                }
+                Ok(_) => continue,
+                Err(_) => break,
+            }
+        }
+
+        Ok((latest_frame, skipped))
+    }
+
+    pub fn get_last_frame(raw_receiver: &RawDataReceiver) -> Result<Arc<wgpu::Texture>> {
+        let (latest_frame, _) = Self::latest_frame(raw_receiver)?;
+
+        // Extract the texture
+        match latest_frame.data {
+            FrameData::Rgba8UnormWgpuTexture(texture) => Ok(texture.clone()),
+            _ => Err(anyhow!("Expected Rgba8UnormWgpuTexture")),
+        }
+    }
+
+    /// Switches the raw output to another component.
+    pub fn update_raw_output(&self, component: Component) -> Result<()> {
+        let mut pipeline_lock = self.pipeline.lock().unwrap();
+        Pipeline::update_output(
+            &mut *pipeline_lock,
+            self.raw_output.clone(),
+            Some(self.output_scene(component.clone())),
+            self.raw_audio.then(|| self.mixer.mix(&component)),
+        )?;
+        self.coverage.lock().unwrap().record(&component);
+        self.shown
+            .lock()
+            .unwrap()
+            .insert(self.raw_output.clone(), component);
+
+        Ok(())
+    }
+
+    /// Switch to a given component and extract pipeline output.
+    pub fn render_component(
+        &mut self,
+        receiver: &RawDataReceiver,
+        component: Component,
+    ) -> Result<Arc<wgpu::Texture>> {
+        self.update_raw_output(component)?;
+
+        std::thread::sleep(Duration::from_millis(100)); // Make sure this is the new component
+
+        let (frame, skipped) = Self::latest_frame(receiver)?;
+        self.skipped_frames += skipped;
+        debug!("Skipped {skipped} frames to reach the latest one");
+
+        match frame.data {
+            FrameData::Rgba8UnormWgpuTexture(texture) => Ok(texture),
+            _ => Err(anyhow!("Expected Rgba8UnormWgpuTexture")),
+        }
+    }
+
+    /// Saves one frame of each input rendered alone, `{id}` in the template is the input ID.
+    pub fn save_images(&mut self, path_template: &str) -> Result<()> {
+        self.save_images_as(path_template, DumpFormatConfig::Png)
+    }
+
+    /// Like [`Self::save_images`], in another format, see [`Self::save_frame`].
+    pub fn save_images_as(&mut self, path_template: &str, format: DumpFormatConfig) -> Result<()> {
+        let receiver = self.register_raw_output(PLACEHOLDER.clone())?;
+        info!("Saving output to {path_template}");
+
+        for (id, component) in self.components.clone() {
+            let frame = self.render_component(&receiver, component)?;
+            self.event_log.write(LogEvent::SceneApplied {
+                output: "raw",
+                scene: &id,
+            });
+            self.save_frame(
+                &frame,
+                Path::new(&path_template.replace("{id}", &id)),
+                format,
+            )?;
+        }
+
+        self.deregister_raw_output()?;
+        self.shader_cache.save()?;
+        info!("Images saved");
+
+        Ok(())
+    }
+
+    /// Renders an input or scene for `duration`, saving every `stride`th output frame to
+    /// `dir`, named after `template`, see [`OutputConfig::FrameSequence`].
+    ///
+    /// Only the saved frames are read back. Returns the number of saved frames.
+    pub fn save_frame_sequence(
+        &mut self,
+        id: &str,
+        dir: &Path,
+        duration: Duration,
+        stride: usize,
+        template: &str,
+        format: DumpFormatConfig,
+    ) -> Result<usize> {
+        std::fs::create_dir_all(dir).with_context(|| format!("Cannot create {}", dir.display()))?;
+        let component = self.component(id)?;
+        let receiver = self.register_raw_output(component)?;
+        let video = receiver.video.as_ref().context("No video channel")?;
+        info!(
+            "Saving every {stride} frames of {id} to {}",
+            dir.join(template).display()
+        );
+
+        let start = Instant::now();
+        let (mut index, mut saved) = (0, 0);
+        while start.elapsed() < duration {
+            let frame = match video.recv_timeout(self.frame_interval * 4) {
+                Ok(PipelineEvent::Data(frame)) => frame,
+                Err(err) if err.is_disconnected() => bail!("Raw output disconnected"),
+                Ok(_) | Err(_) => continue,
+            };
+            index += 1;
+            if (index - 1) % stride != 0 {
+                continue;
+            }
+            let FrameData::Rgba8UnormWgpuTexture(texture) = &frame.data else {
+                bail!("Expected Rgba8UnormWgpuTexture");
+            };
+
+            let name = template
+                .replace("{index}", &format!("{:06}", index - 1))
+                .replace("{pts_ms}", &frame.pts.as_millis().to_string());
+            self.save_frame(texture, &dir.join(&name), format)?;
+            self.event_log.write(LogEvent::FrameCaptured {
+                index: index - 1,
+                pts_ms: frame.pts.as_secs_f64() * 1000.0,
+            });
+            saved += 1;
+        }
+        self.deregister_raw_output()?;
+        info!("Saved {saved} of {index} frames to {}", dir.display());
+
+        Ok(saved)
+    }
+
+    /// Renders an input or scene for `duration`, writing every frame to the Y4M file `path`,
+    /// and returns the number of frames written.
+    pub fn record_y4m(&mut self, id: &str, path: &Path, duration: Duration) -> Result<usize> {
+        let mut writer = Y4mWriter::create(path, self.resolution, self.config.framerate)?;
+        let component = self.component(id)?;
+        let receiver = self.register_raw_output(component)?;
+        let video = receiver.video.as_ref().context("No video channel")?;
+        info!("Writing {id} to {}", path.display());
+
+        let start = Instant::now();
+        let mut index = 0;
+        while start.elapsed() < duration {
+            let frame = match video.recv_timeout(self.frame_interval * 4) {
+                Ok(PipelineEvent::Data(frame)) => frame,
+                Err(err) if err.is_disconnected() => bail!("Raw output disconnected"),
+                Ok(_) | Err(_) => continue,
+            };
+            let FrameData::Rgba8UnormWgpuTexture(texture) = &frame.data else {
+                bail!("Expected Rgba8UnormWgpuTexture");
+            };
+
+            writer.write(&to_image(
+                &self.graphics_context,
+                texture,
+                self.shader_cache.get(),
+            )?)?;
+            self.event_log.write(LogEvent::FrameCaptured {
+                index,
+                pts_ms: frame.pts.as_secs_f64() * 1000.0,
+            });
+            index += 1;
+        }
+        self.deregister_raw_output()?;
+        let frames = writer.finish()?;
+        info!("Wrote {frames} frames to {}", path.display());
+
+        Ok(frames)
+    }
+
+    /// Reads back a frame and saves it to `path`, at the precision of `format`.
+    ///
+    /// 16-bit PNG and OpenEXR files are made from the linear values of the texture rather
+    /// than from 8-bit sRGB, so subtle color shifts are not lost to rounding.
+    pub fn save_frame(
+        &self,
+        texture: &wgpu::Texture,
+        path: &Path,
+        format: DumpFormatConfig,
+    ) -> Result<()> {
+        let (context, cache) = (&self.graphics_context, self.shader_cache.get());
+        let result = match format {
+            DumpFormatConfig::Png => to_image(context, texture, cache)?.save(path),
+            DumpFormatConfig::Png16 => to_image_u16(context, texture, cache)?.save(path),
+            DumpFormatConfig::Exr => to_image_f32(context, texture, cache)?
+                .save_with_format(path, image::ImageFormat::OpenExr),
+        };
+        result.with_context(|| format!("Cannot save {}", path.display()))
+    }
+
+    /// Saves the next frame of the raw output to `path` as a PNG file, showing what the
+    /// recording shows, or the first input when not recording.
+    ///
+    /// The raw output must not be registered already: while it is, save its frames with
+    /// [`Self::save_frame`].
+    pub fn snapshot(&mut self, path: &Path) -> Result<()> {
+        if self.shown.lock().unwrap().contains_key(&self.raw_output) {
+            bail!("Cannot take a snapshot while the raw output is registered");
+        }
+        let component = match self.recording {
+            Some(_) => self.shown_by_record(),
+            None => self.components[0].1.clone(),
+        };
+        let receiver = self.register_raw_output(component)?;
+        let texture = Self::get_last_frame(&receiver);
+        self.deregister_raw_output()?;
+        self.save_frame(&texture?, path, DumpFormatConfig::Png)?;
+        info!("Saved a snapshot to {}", path.display());
+
+        Ok(())
+    }
+
+    /// Renders a scene file, then again on every change to it, saving each render.
+    ///
+    /// Invalid edits are logged and the previous scene is kept.
+    pub fn watch_scene(
+        &mut self,
+        scene_path: &Path,
+        snapshot_path: &Path,
+        duration: Option<Duration>,
+    ) -> Result<()> {
+        let watcher = FileWatcher::new(scene_path)?;
+        let receiver = self.register_raw_output(scene::load(scene_path)?)?;
+        info!("Watching {}", scene_path.display());
+
+        let start = Instant::now();
+        let mut reload = true;
+        while duration.map_or(true, |duration| start.elapsed() < duration) {
+            if reload {
+                match scene::load(scene_path) {
+                    Ok(component) => {
+                        let frame = self.render_component(&receiver, component)?;
+                        let image =
+                            to_image(&self.graphics_context, &frame, self.shader_cache.get())?;
+                        image.save(snapshot_path)?;
+                        info!("Scene reloaded, saved to {}", snapshot_path.display());
+                    }
+                    Err(err) => warn!("Keeping the previous scene: {err:#}"),
+                }
+            }
+            reload = watcher.wait_for_change(Duration::from_millis(200))?;
+        }
+
+        self.deregister_raw_output()?;
+        Ok(())
+    }
+
+    /// Records for `duration`, switching input every `switch_interval`, to numbered files
+    /// with a `rollover`.
+    ///
+    /// The recording starts on the first input rather than the placeholder, so that its first
+    /// frame is on a scene boundary and each input is shown for whole `switch_interval`s of
+    /// the file, timed from the same start as the switches. The registered outputs that
+    /// alternate switch input along with the recording. Returns the path recorded to, see
+    /// [`Self::start_record`].
+    pub fn record_for(
+        &mut self,
+        path: PathBuf,
+        duration: Duration,
+        switch_interval: Duration,
+        rollover: Option<Rollover>,
+    ) -> Result<PathBuf> {
+        let initial = self.components[0].1.clone();
+        let path = self.start_recording(&path, rollover, initial)?;
+        self.record_on_scene = true;
+        let targets = self.outputs.alternating();
+        self.alternate_scenes(duration, switch_interval, &targets)?;
         self.stop_record()?;
         std::thread::sleep(Duration::from_secs(1));
 
-        Ok(())
+        Ok(path)
+    }
+
+    /// Like [`Self::record_for`], for `cycles` complete cycles through the inputs, so that
+    /// the recording ends after the last input was shown for a whole `switch_interval`
+    /// rather than mid-cycle.
+    pub fn record_cycles(
+        &mut self,
+        path: PathBuf,
+        cycles: u32,
+        switch_interval: Duration,
+        rollover: Option<Rollover>,
+    ) -> Result<PathBuf> {
+        let duration = switch_interval * cycles * self.components.len().max(1) as u32;
+        info!(
+            "Recording {cycles} cycles through {} inputs, {:.1}s",
+            self.components.len(),
+            duration.as_secs_f64()
+        );
+        self.record_for(path, duration, switch_interval, rollover)
+    }
+
+    /// Reads back the codec parameters of an H264 recording, failing if they differ from
+    /// the encoder settings, the resolution and framerate of the output, or the BT.709
+    /// limited range tags of the conversion.
+    pub fn verify_recording(&self, path: &Path) -> Result<()> {
+        let actual = container::read(path)?;
+        let expected = ExpectedParameters {
+            width: self.resolution.width as u32,
+            height: self.resolution.height as u32,
+            framerate: self.config.framerate.num as f64 / self.config.framerate.den as f64,
+            profile: self.config.encoder.profile.map(|profile| profile.idc()),
+            level: self.config.encoder.level_idc(),
+            chroma_format: 1,
+            bit_depth: 8,
+            color: ColorTags::BT709_LIMITED,
+        };
+        let mismatches = container::mismatches(&actual, &expected);
+        if !mismatches.is_empty() {
+            bail!(
+                "{} was written with {}",
+                path.display(),
+                mismatches.join(", ")
+            );
+        }
+        info!(
+            "Verified the metadata of {}: profile {}, level {}, {:.3} fps",
+            path.display(),
+            actual.profile,
+            actual.level,
+            actual.framerate
+        );
+        Ok(())
+    }
+
+    /// Records the MP4 file and the PNG sequence of [`DualRecorder`], switching input
+    /// every `switch_interval`. Returns the number of frames recorded.
+    pub fn record_dual(
+        &mut self,
+        mp4_path: &Path,
+        frames_dir: &Path,
+        duration: Duration,
+        switch_interval: Duration,
+    ) -> Result<usize> {
+        let mp4_path = naming::resolve_file(mp4_path)?;
+        let frames_dir = naming::resolve_file(frames_dir)?;
+        let mut recorder = DualRecorder::start(
+            &mp4_path,
+            &frames_dir,
+            self.resolution,
+            self.config.framerate,
+            &self.config.encoder,
+        )?;
+        self.record_raw(duration, switch_interval, |image, pts| {
+            recorder.push(image, pts)
+        })?;
+        recorder.finish()
+    }
+
+    /// Records a WebM file encoded with `codec`, see [`WebmRecorder`], switching input
+    /// every `switch_interval`. Returns the number of frames recorded.
+    pub fn record_webm(
+        &mut self,
+        path: &Path,
+        codec: WebmCodecConfig,
+        duration: Duration,
+        switch_interval: Duration,
+    ) -> Result<usize> {
+        let path = naming::resolve_file(path)?;
+        let mut recorder =
+            WebmRecorder::start(&path, codec, self.resolution, self.config.framerate)?;
+        self.record_raw(duration, switch_interval, |image, _| recorder.push(image))?;
+        recorder.finish()
+    }
+
+    /// Captures the output for `duration` to a GIF file at `fps`, with a palette of up to
+    /// `max_colors`, see [`GifWriter`], switching input every `switch_interval`. Returns the
+    /// number of frames captured.
+    pub fn record_gif(
+        &mut self,
+        duration: Duration,
+        path: &Path,
+        fps: u32,
+        max_colors: u16,
+        switch_interval: Duration,
+    ) -> Result<usize> {
+        let path = naming::resolve_file(path)?;
+        let mut writer = GifWriter::start(
+            &path,
+            self.resolution,
+            self.config.framerate,
+            fps,
+            max_colors,
+        )?;
+        self.record_raw(duration, switch_interval, |image, _| writer.push(image))?;
+        writer.finish()
+    }
+
+    /// Captures the output for `duration` to a lossless reference in `dir`, switching input
+    /// every `switch_interval`, then encodes it with each preset and CRF of `settings`, see
+    /// [`sweep::run`]. The measurements are written to `dir/rd_report.csv` and returned.
+    pub fn encoder_sweep(
+        &mut self,
+        dir: &Path,
+        duration: Duration,
+        switch_interval: Duration,
+        settings: &SweepSettings,
+    ) -> Result<Vec<RateDistortion>> {
+        std::fs::create_dir_all(dir).with_context(|| format!("Cannot create {}", dir.display()))?;
+        let reference = dir.join("reference.mkv");
+        let mut writer =
+            ReferenceWriter::start(&reference, self.resolution, self.config.framerate)?;
+        // Inputs switch on the clock of the capture, which the PTS follow
+        let ids: Vec<String> = self.components.iter().map(|(id, _)| id.clone()).collect();
+        let (mut scenes, mut first_pts) = (vec![], None);
+        self.record_raw(duration, switch_interval, |image, pts| {
+            let elapsed = pts.saturating_sub(*first_pts.get_or_insert(pts));
+            let switches = elapsed.as_millis() / switch_interval.as_millis().max(1);
+            if writer.push(image)? {
+                scenes.push(ids[switches as usize % ids.len()].clone());
+            }
+            Ok(())
+        })?;
+        writer.finish()?;
+
+        let points = sweep::run(
+            &reference,
+            dir,
+            settings,
+            &self.config.encoder,
+            self.resolution,
+            self.config.framerate,
+            &scenes,
+        )?;
+        let report = dir.join("rd_report.csv");
+        sweep::write_report(&points, &report)?;
+        info!(
+            "Swept {} encodes of {} frames, report in {}",
+            points.len(),
+            scenes.len(),
+            report.display()
+        );
+        Ok(points)
+    }
+
+    /// Writes an HLS stream of the output to `dir`, see [`HlsWriter`], switching input every
+    /// `switch_interval`. Returns the number of frames written.
+    pub fn write_hls(
+        &mut self,
+        dir: &Path,
+        options: HlsOptions,
+        duration: Duration,
+        switch_interval: Duration,
+    ) -> Result<usize> {
+        // A directory holding another stream is left to it
+        let dir = naming::resolve(dir, |dir| dir.join("index.m3u8").exists())?;
+        let mut writer = HlsWriter::start(
+            &dir,
+            options,
+            self.resolution,
+            self.config.framerate,
+            &self.config.encoder,
+        )?;
+        self.record_raw(duration, switch_interval, |image, _| writer.push(image))?;
+        writer.finish()
+    }
+
+    /// Sends the output over RTP, see [`RtpSender`], switching input every
+    /// `switch_interval`. Returns the number of frames sent.
+    pub fn send_rtp(
+        &mut self,
+        options: &RtpSenderOptions,
+        duration: Duration,
+        switch_interval: Duration,
+    ) -> Result<usize> {
+        let mut sender = RtpSender::start(
+            options,
+            self.resolution,
+            self.config.framerate,
+            &self.config.encoder,
+        )?;
+        self.record_raw(duration, switch_interval, |image, _| sender.push(image))?;
+        sender.finish()
+    }
+
+    /// Publishes the output as the NDI source `name`, see [`NdiSender`], switching input
+    /// every `switch_interval`. Returns the number of frames sent.
+    pub fn publish_ndi(
+        &mut self,
+        name: &str,
+        duration: Duration,
+        switch_interval: Duration,
+    ) -> Result<usize> {
+        let mut sender = NdiSender::create(name, self.config.framerate)?;
+        self.record_raw(duration, switch_interval, |image, _| sender.send(&image))?;
+        Ok(sender.finish())
+    }
+
+    /// Publishes the output to the WHIP server at `endpoint_url` for `duration`, switching
+    /// input every `switch_interval`, H264 encoded with the encoder settings.
+    pub fn publish_whip(
+        &mut self,
+        endpoint_url: &str,
+        bearer_token: Option<&str>,
+        duration: Duration,
+        switch_interval: Duration,
+    ) -> Result<()> {
+        use compositor_pipeline::pipeline::encoder::*;
+
+        let (_, initial) = self.components[0].clone();
+        let register = || {
+            Pipeline::register_output(
+                &self.pipeline,
+                self.whip_output.clone(),
+                RegisterOutputOptions {
+                    output_options: OutputOptions::Whip(whip::WhipSenderOptions {
+                        endpoint_url: endpoint_url.to_string(),
+                        bearer_token: bearer_token.map(Arc::from),
+                        video: Some(whip::VideoWhipOptions {
+                            encoder_preferences: vec![VideoEncoderOptions::H264(
+                                ffmpeg_h264::Options {
+                                    preset: self.config.encoder.preset.into(),
+                                    resolution: self.resolution,
+                                    raw_options: self.config.encoder.raw_options(),
+                                    pixel_format: OutputPixelFormat::YUV420P,
+                                },
+                            )],
+                        }),
+                        audio: None,
+                    }),
+                    video: Some(OutputVideoOptions {
+                        initial: self.output_scene(initial),
+                        end_condition: PipelineOutputEndCondition::Never,
+                    }),
+                    audio: None,
+                },
+            )?;
+            Ok(())
+        };
+        self.config.threads.encoding.scoped("encoding", register)?;
+        match self.config.stun_servers.is_empty() {
+            true => info!("Publishing to {endpoint_url}, without STUN servers"),
+            false => info!(
+                "Publishing to {endpoint_url}, STUN servers {}",
+                self.config.stun_servers.join(", ")
+            ),
+        }
+
+        // Unregistered on errors too, the first error is the one returned
+        let shown = self.cycle_whip_output(duration, switch_interval);
+        let unregistered =
+            Pipeline::unregister_output(&mut *self.pipeline.lock().unwrap(), &self.whip_output);
+        match (&shown, unregistered) {
+            (Ok(()), unregistered) => unregistered?,
+            (Err(_), Err(err)) => warn!("Cannot unregister the WHIP output: {err:#}"),
+            (Err(_), Ok(())) => {}
+        }
+        info!("Stopped publishing to {endpoint_url}");
+
+        shown
+    }
+
+    /// Cycles through the inputs on the WHIP output for `duration`, changing every
+    /// `switch_interval`, the last scene shown for what is left of `duration`.
+    fn cycle_whip_output(&mut self, duration: Duration, switch_interval: Duration) -> Result<()> {
+        let start = Instant::now();
+        let mut switches = 0;
+        while start.elapsed() < duration {
+            self.poll_events();
+            let (id, component) = self.components[switches % self.components.len()].clone();
+            if switches > 0 {
+                Pipeline::update_output(
+                    &mut *self.pipeline.lock().unwrap(),
+                    self.whip_output.clone(),
+                    Some(self.output_scene(component.clone())),
+                    None,
+                )?;
+            }
+            self.coverage.lock().unwrap().record(&component);
+            self.event_log.write(LogEvent::SceneApplied {
+                output: "whip",
+                scene: &id,
+            });
+            std::thread::sleep(switch_interval.min(duration.saturating_sub(start.elapsed())));
+            switches += 1;
+        }
+        Ok(())
+    }
+
+    /// Cycles through the inputs on the raw output for `duration`, changing every
+    /// `switch_interval`, and hands every frame to `record` with its PTS.
+    fn record_raw(
+        &mut self,
+        duration: Duration,
+        switch_interval: Duration,
+        mut record: impl FnMut(RgbaImage, Duration) -> Result<()>,
+    ) -> Result<()> {
+        let (id, initial) = self.components[0].clone();
+        let receiver = self.register_raw_output(initial)?;
+        let video = receiver.video.as_ref().context("No video channel")?;
+        self.event_log.write(LogEvent::SceneApplied {
+            output: "raw",
+            scene: &id,
+        });
+        let mut canary = self.canary_check();
+        let mut gaps = PtsGapCheck::new(self.frame_interval);
+
+        let start = Instant::now();
+        let mut switches = 1;
+        while start.elapsed() < duration {
+            if start.elapsed() >= switch_interval * switches {
+                self.poll_events();
+                let (id, component) = &self.components[switches as usize % self.components.len()];
+                self.update_raw_output(component.clone())?;
+                self.event_log.write(LogEvent::SceneApplied {
+                    output: "raw",
+                    scene: id,
+                });
+                switches += 1;
+            }
+
+            let frame = match video.recv_timeout(self.frame_interval * 4) {
+                Ok(PipelineEvent::Data(frame)) => frame,
+                Err(err) if err.is_disconnected() => bail!("Raw output disconnected"),
+                Ok(_) | Err(_) => continue,
+            };
+            let FrameData::Rgba8UnormWgpuTexture(texture) = &frame.data else {
+                bail!("Expected Rgba8UnormWgpuTexture");
+            };
+            gaps.check(frame.pts, video.len(), video.is_full());
+            let image = to_image(&self.graphics_context, texture, self.shader_cache.get())?;
+            if let Some(canary) = &mut canary {
+                canary.check(frame.pts, &image);
+            }
+            record(image, frame.pts)?;
+        }
+
+        self.deregister_raw_output()?;
+        if let Some(mut canary) = canary {
+            let findings = canary.finish();
+            self.log_findings(&findings);
+        }
+        let findings = gaps.finish();
+        self.log_findings(&findings);
+        self.check_gaps(&gaps)
+    }
+
+    /// Renders `component` for `duration`, feeding the output frames to the processors at
+    /// their rates, the first frame starting the scene.
+    ///
+    /// The metadata of every frame is checked against its pixels too, see [`MetadataCheck`].
+    pub fn analyze(
+        &mut self,
+        component: Component,
+        duration: Duration,
+        processors: &mut [Box<dyn FrameProcessor>],
+    ) -> Result<Vec<Finding>> {
+        self.analyze_scenes(&[component], duration, duration, processors)
+    }
+
+    /// Like [`Self::analyze`], cycling through `scenes` every `switch_interval`. The first
+    /// frame received after each switch starts a scene.
+    pub fn analyze_scenes(
+        &mut self,
+        scenes: &[Component],
+        duration: Duration,
+        switch_interval: Duration,
+        processors: &mut [Box<dyn FrameProcessor>],
+    ) -> Result<Vec<Finding>> {
+        let first = scenes.first().context("No scene to analyze")?;
+        let receiver = self.register_raw_output(first.clone())?;
+        let video = receiver.video.as_ref().context("No video channel")?;
+
+        let mut metadata = MetadataCheck::new(self.resolution);
+        let mut canary = self.canary_check();
+        let mut gaps = PtsGapCheck::new(self.frame_interval);
+        let rates: Vec<_> = processors
+            .iter()
+            .map(|processor| processor.rate())
+            .collect();
+        let mut processed = vec![0; processors.len()];
+        let start = Instant::now();
+        let (mut index, mut switches, mut scene_start) = (0, 1, true);
+        while start.elapsed() < duration {
+            if scenes.len() > 1 && start.elapsed() >= switch_interval * switches {
+                self.poll_events();
+                self.update_raw_output(scenes[switches as usize % scenes.len()].clone())?;
+                switches += 1;
+                scene_start = true;
+            }
+
+            let frame = match video.recv_timeout(self.frame_interval * 4) {
+                Ok(PipelineEvent::Data(frame)) => frame,
+                Err(err) if err.is_disconnected() => bail!("Raw output disconnected"),
+                Ok(_) | Err(_) => continue,
+            };
+            let FrameData::Rgba8UnormWgpuTexture(texture) = &frame.data else {
+                bail!("Expected Rgba8UnormWgpuTexture");
+            };
+
+            gaps.check(frame.pts, video.len(), video.is_full());
+            // Read back for every frame: the checks below and the result caches compare pixels
+            let image = to_image(&self.graphics_context, texture, self.shader_cache.get())?;
+            let captured = CapturedFrame {
+                index,
+                pts: frame.pts,
+                hash: analysis::frame_hash(&image),
+                image,
+            };
+            metadata.check(&frame, &captured.image);
+            if let Some(canary) = &mut canary {
+                canary.check(frame.pts, &captured.image);
+            }
+            self.event_log.write(LogEvent::FrameCaptured {
+                index,
+                pts_ms: frame.pts.as_secs_f64() * 1000.0,
+            });
+            let starts_scene = std::mem::take(&mut scene_start);
+            for ((processor, rate), processed) in
+                processors.iter_mut().zip(&rates).zip(&mut processed)
+            {
+                if rate.is_due(&captured, starts_scene) {
+                    processor.process(&captured)?;
+                    *processed += 1;
+                }
+            }
+            index += 1;
+        }
+
+        self.deregister_raw_output()?;
+        info!("Analyzed {index} frames");
+        for (processor, processed) in processors.iter().zip(processed) {
+            debug!("{} processed {processed} frames", processor.name());
+        }
+
+        let findings: Vec<_> = metadata
+            .finish()
+            .into_iter()
+            .chain(canary.iter_mut().flat_map(CanaryCheck::finish))
+            .chain(gaps.finish())
+            .chain(
+                processors
+                    .iter_mut()
+                    .flat_map(|processor| processor.finish()),
+            )
+            .collect();
+        if let Err(err) = self.check_gaps(&gaps) {
+            self.log_findings(&findings);
+            return Err(err);
+        }
+        Ok(findings)
+    }
+
+    /// Logs the frames missing from a capture, failing if `pts_gaps.fail` is set.
+    fn check_gaps(&self, gaps: &PtsGapCheck) -> Result<()> {
+        let (queue, harness) = (gaps.queue_drops(), gaps.harness_drops());
+        if queue + harness == 0 {
+            return Ok(());
+        }
+        warn!("{queue} frames dropped by the queue, {harness} lost by the harness");
+        if self.config.pts_gaps.fail {
+            bail!("{} frames missing from the output", queue + harness);
+        }
+        Ok(())
+    }
+
+    /// Plays an MP4 input from its start for `loops` loops, checking for glitches at loop points.
+    pub fn check_loop(
+        &mut self,
+        id: &str,
+        loops: u32,
+        tolerance: Duration,
+    ) -> Result<Vec<Finding>> {
+        let Some(InputConfig::Mp4 { path, trim, .. }) =
+            self.config.inputs.iter().find(|input| input.id() == id)
+        else {
+            bail!("Loop check requires an MP4 input, {id:?} is not one");
+        };
+        let period = match trim {
+            Some(trim) => media::mp4_duration(&media::trim_mp4(path, trim)?)?,
+            None => media::mp4_duration(path)?,
+        };
+        let component = self.component(id)?;
+        info!(
+            "Checking loop junctions of {id} ({:.3}s clip)",
+            period.as_secs_f64()
+        );
+
+        self.restart_input(id)?;
+        let mut processors: Vec<Box<dyn FrameProcessor>> =
+            vec![Box::new(LoopJunctionAnalyzer::new(period, tolerance))];
+        self.analyze(component, period * loops + period / 2, &mut processors)
+    }
+
+    /// Fits an input in the output over a `color` background, and checks the color of the
+    /// letterbox or pillarbox bars, see [`bars::check`].
+    pub fn check_bars(&mut self, id: &str, color: &str, tolerance: u8) -> Result<Vec<Finding>> {
+        let expected = scene::parse_color(Some(color))?;
+        let expected = [expected.0, expected.1, expected.2];
+        let input = self
+            .config
+            .inputs
+            .iter()
+            .find(|input| input.id() == id)
+            .with_context(|| format!("Unknown input {id:?}"))?;
+        let content = input_content(input, &self.config.noise, self.resolution)?;
+        let (width, height) = (self.resolution.width as f32, self.resolution.height as f32);
+        let boxed = |[r, g, b]: [u8; 3]| {
+            Component::View(ViewComponent {
+                id: None,
+                children: vec![fit(content.clone(), width, height)],
+                direction: ViewChildrenDirection::Row,
+                position: Position::Static {
+                    width: Some(width),
+                    height: Some(height),
+                },
+                transition: None,
+                overflow: Overflow::Hidden,
+                background_color: RGBAColor(r, g, b, 255),
+                border_radius: compositor_render::scene::BorderRadius::ZERO,
+                border_width: 0.,
+                border_color: RGBAColor(0, 0, 0, 0),
+                box_shadow: vec![],
+                padding: Padding {
+                    top: 0.,
+                    right: 0.,
+                    bottom: 0.,
+                    left: 0.,
+                },
+            })
+        };
+        let (render, contrast) = (boxed(expected), boxed(bars::contrast_color(expected)));
+        info!("Checking the bar color of {id}, expecting {color}");
+
+        let receiver = self.register_raw_output(PLACEHOLDER.clone())?;
+        let mut images = vec![];
+        for component in [render, contrast] {
+            let frame = self.render_component(&receiver, component)?;
+            images.push(to_image(
+                &self.graphics_context,
+                &frame,
+                self.shader_cache.get(),
+            )?);
+        }
+        self.deregister_raw_output()?;
+
+        let findings = bars::check(&images[0], &images[1], expected, tolerance);
+        if let Some(finding) = findings.first() {
+            let mut image = images.swap_remove(0);
+            annotate::label(&mut image, 0, 0, &finding.message, annotate::RED);
+            self.save_failure(&format!("bar_color_{id}"), &image)?;
+        }
+        Ok(findings)
+    }
+
+    /// Renders an input or scene, and compares the frame with the one drawn by an
+    /// [`ExpectedRenderer`], which knows the images of the inputs.
+    pub fn check_expected(&mut self, id: &str, tolerance: u8) -> Result<Vec<Finding>> {
+        let component = self.component(id)?;
+        let renderer = self
+            .config
+            .inputs
+            .iter()
+            .filter_map(|input| match input {
+                InputConfig::Image { id, path, .. } if !svg::is_svg(path) => Some((id, path)),
+                _ => None,
+            })
+            .fold(ExpectedRenderer::new(), |renderer, (id, path)| {
+                renderer.with_image(RendererId(Arc::from(id.as_str())), path)
+            });
+        let expected = renderer
+            .render(&self.output_scene(component.clone()), self.resolution)
+            .with_context(|| format!("Cannot draw the expected frame of {id}"))?;
+
+        let receiver = self.register_raw_output(PLACEHOLDER.clone())?;
+        let frame = self.render_component(&receiver, component)?;
+        let mut actual = to_image(&self.graphics_context, &frame, self.shader_cache.get())?;
+        self.deregister_raw_output()?;
+
+        let findings = expected::compare(&expected, &actual, tolerance)
+            .map(|message| Finding {
+                analyzer: "expected scene".to_string(),
+                pts: None,
+                message: format!("{id}: {message}"),
+            })
+            .into_iter()
+            .collect();
+        let mismatch = (expected.dimensions() == actual.dimensions())
+            .then(|| expected::mismatch(&expected, &actual, tolerance))
+            .flatten();
+        if let Some(mismatch) = mismatch {
+            let (x, y) = mismatch.first;
+            let bounds = mismatch.bounds;
+            annotate::rect(&mut actual, bounds, annotate::RED);
+            annotate::crosshair(&mut actual, x, y, annotate::YELLOW);
+            annotate::label(
+                &mut actual,
+                bounds.x,
+                bounds.y + bounds.height,
+                &format!("{} pixels differ", mismatch.count),
+                annotate::RED,
+            );
+            self.save_failure(&format!("expected_{id}"), &actual)?;
+        }
+        Ok(findings)
+    }
+
+    /// Switches from `from` to `to` and compares the frames from the first one showing `to`
+    /// with the golden sequence of `dir`, as set by `check`. The sequence is saved instead
+    /// when `dir` holds none, or with `check.update`.
+    ///
+    /// The switch toggles a latency marker, see [`Self::measure_update_latency`], so the
+    /// sequence starts on the first frame rendered with `to` whatever the timing of the
+    /// update: runs compare the same frames of a transition.
+    pub fn check_golden_sequence(
+        &mut self,
+        from: &str,
+        to: &str,
+        dir: &Path,
+        check: &SequenceCheck,
+    ) -> Result<Vec<Finding>> {
+        /// Longest wait for a scene to show up on the output, then for each next frame of it.
+        const SWITCH_TIMEOUT: Duration = Duration::from_secs(5);
+        let (before, after) = ([0, 0, 0], [255, 255, 255]);
+
+        let goldens = match check.update || !golden::exists(dir) {
+            true => None,
+            false => Some(golden::load(dir)?),
+        };
+        // Matched by PTS, the sequence is rendered for as long as the golden one lasts
+        let end = match (&goldens, check.matching) {
+            (Some(goldens), GoldenMatchingConfig::NearestPts) => {
+                goldens.last().map(|golden| golden.pts)
+            }
+            _ => None,
+        };
+
+        let from_component = self.component(from)?;
+        let to_component = self.component(to)?;
+        let receiver =
+            self.register_raw_output(marker_scene(from_component, before, self.resolution))?;
+        let video = receiver.video.as_ref().context("No video channel")?;
+        let receive = |color: [u8; 3], since: Instant| -> Result<(Duration, RgbaImage)> {
+            loop {
+                if since.elapsed() > SWITCH_TIMEOUT {
+                    bail!("Scene not shown within {SWITCH_TIMEOUT:?}");
+                }
+                let frame = match video.recv_timeout(self.frame_interval * 4) {
+                    Ok(PipelineEvent::Data(frame)) => frame,
+                    Err(err) if err.is_disconnected() => bail!("Raw output disconnected"),
+                    Ok(_) | Err(_) => continue,
+                };
+                let FrameData::Rgba8UnormWgpuTexture(texture) = &frame.data else {
+                    bail!("Expected Rgba8UnormWgpuTexture");
+                };
+                let image = to_image(&self.graphics_context, texture, self.shader_cache.get())?;
+                if latency::shows_marker(&image, color) {
+                    return Ok((frame.pts, image));
+                }
+            }
+        };
+        receive(before, Instant::now()).with_context(|| format!("Cannot show {from}"))?;
+
+        self.update_raw_output(marker_scene(to_component, after, self.resolution))?;
+        self.event_log.write(LogEvent::SceneApplied {
+            output: "raw",
+            scene: to,
+        });
+        let mut sequence = vec![];
+        let (start, image) =
+            receive(after, Instant::now()).with_context(|| format!("Cannot switch to {to}"))?;
+        sequence.push(GoldenFrame {
+            pts: Duration::ZERO,
+            image,
+        });
+        loop {
+            let rendered = sequence.last().map_or(Duration::ZERO, |frame| frame.pts);
+            let done = match end {
+                Some(end) => rendered >= end,
+                None => sequence.len() >= check.frames,
+            };
+            if done {
+                break;
+            }
+            // Sequences last longer than the switch timeout, each frame gets its own
+            let (pts, image) = receive(after, Instant::now())
+                .with_context(|| format!("{to} lost after {} frames", sequence.len()))?;
+            sequence.push(GoldenFrame {
+                pts: pts.saturating_sub(start),
+                image,
+            });
+        }
+        self.deregister_raw_output()?;
+
+        let Some(goldens) = goldens else {
+            golden::save(dir, &sequence)?;
+            return Ok(vec![]);
+        };
+        let finding = |pts: Option<Duration>, message: String| Finding {
+            analyzer: "golden sequence".to_string(),
+            pts,
+            message: format!("{from} to {to}: {message}"),
+        };
+        let mut findings = vec![];
+        let pairs: Vec<(usize, &GoldenFrame, &GoldenFrame)> = match check.matching {
+            GoldenMatchingConfig::Index => {
+                if goldens.len() != sequence.len() {
+                    findings.push(finding(
+                        None,
+                        format!(
+                            "{} golden frames, {} rendered",
+                            goldens.len(),
+                            sequence.len()
+                        ),
+                    ));
+                }
+                goldens
+                    .iter()
+                    .zip(&sequence)
+                    .enumerate()
+                    .map(|(index, (golden, actual))| (index, golden, actual))
+                    .collect()
+            }
+            GoldenMatchingConfig::NearestPts => sequence
+                .iter()
+                .filter_map(|actual| {
+                    let (index, golden) = golden::nearest(&goldens, actual.pts)?;
+                    Some((index, golden, actual))
+                })
+                .collect(),
+        };
+        for (position, (index, golden, actual)) in pairs.into_iter().enumerate() {
+            if position < check.skip_after_switch {
+                continue;
+            }
+            let tolerance = check.tolerance(index);
+            let Some(message) = expected::compare(&golden.image, &actual.image, tolerance) else {
+                continue;
+            };
+            findings.push(finding(
+                Some(actual.pts),
+                format!("frame {position}, golden frame {index}: {message}"),
+            ));
+            self.save_failure(&format!("golden_{to}_{position:04}"), &actual.image)?;
+        }
+        Ok(findings)
+    }
+
+    /// Saves the frame of a failed check to `{failure_snapshots.dir}/{name}.png`, when
+    /// failure snapshots are enabled.
+    fn save_failure(&self, name: &str, image: &RgbaImage) -> Result<()> {
+        let snapshots = &self.config.failure_snapshots;
+        if !snapshots.enabled {
+            return Ok(());
+        }
+        std::fs::create_dir_all(&snapshots.dir)
+            .with_context(|| format!("Cannot create {}", snapshots.dir.display()))?;
+        let path = snapshots.dir.join(format!("{name}.png"));
+        image
+            .save(&path)
+            .with_context(|| format!("Cannot save {}", path.display()))?;
+        info!("Failed frame saved to {}", path.display());
+        Ok(())
+    }
+
+    /// Simulates a switcher cutting to a freshly registered copy of an MP4 input `switches`
+    /// times, one cut every `switch_interval`, measuring the registration time and the time
+    /// from each cut to the first frame of the new copy, and looking for glitches.
+    ///
+    /// With a `pool_size`, cuts go to copies registered in advance, and a replacement is
+    /// registered before each cut is timed. Each cut toggles a latency marker, see
+    /// [`Self::measure_update_latency`], so frames of the new scene are told apart. Black
+    /// frames shown before the first frame of a copy are glitches, and so are first frames
+    /// differing from the one of the first cut, which only applies without pool since
+    /// pooled copies are already playing.
+    pub fn check_input_switching(
+        &mut self,
+        id: &str,
+        switches: u32,
+        switch_interval: Duration,
+        pool_size: usize,
+    ) -> Result<Vec<Finding>> {
+        /// Frames darker than this are considered black.
+        const BLACK_LUMA: f32 = 0.03;
+        /// Largest difference between first frames of the same clip.
+        const MAX_FIRST_FRAME_DIFF: f32 = 0.05;
+
+        let Some(source @ InputConfig::Mp4 { .. }) = self
+            .config
+            .inputs
+            .iter()
+            .find(|input| input.id() == id)
+            .cloned()
+        else {
+            bail!("Input switching requires an MP4 input, {id:?} is not one");
+        };
+        info!(
+            "Cutting {switches} times to new copies of {id}, {}",
+            match pool_size {
+                0 => "registered on the cut".to_string(),
+                size => format!("from a pool of {size} prewarmed copies"),
+            }
+        );
+
+        let mut copies = 0;
+        // Copies still registered, unregistered on the error paths too
+        let mut registered = RegisteredInputs::new(&self.pipeline);
+        let mut registrations = vec![];
+        let mut pool = VecDeque::new();
+        for _ in 0..pool_size {
+            let (copy, component, registration) = self.register_copy(&source, &mut copies)?;
+            registered.push(&copy);
+            registrations.push(registration);
+            pool.push_back((copy, component));
+        }
+
+        let colors = [[255, 255, 255], [0, 0, 0]];
+        let receiver = self.register_raw_output(PLACEHOLDER.clone())?;
+        let video = receiver.video.as_ref().context("No video channel")?;
+        let mut findings = vec![];
+        let mut first_frame_latencies = vec![];
+        let mut reference: Option<RgbaImage> = None;
+        let mut previous: Option<String> = None;
+        for switch in 0..switches {
+            let color = colors[switch as usize % 2];
+            let pooled = pool.pop_front();
+            // The pool is refilled before the cut is timed, only registrations on the cut count
+            if pool_size > 0 {
+                let (copy, component, registration) = self.register_copy(&source, &mut copies)?;
+                registered.push(&copy);
+                registrations.push(registration);
+                pool.push_back((copy, component));
+            }
+            let cut = Instant::now();
+            let (copy, component) = match pooled {
+                Some(pooled) => pooled,
+                None => {
+                    let (copy, component, registration) =
+                        self.register_copy(&source, &mut copies)?;
+                    registered.push(&copy);
+                    registrations.push(registration);
+                    (copy, component)
+                }
+            };
+            self.update_raw_output(marker_scene(component, color, self.resolution))?;
+            self.event_log.write(LogEvent::SceneApplied {
+                output: "raw",
+                scene: &copy,
+            });
+            if let Some(previous) = previous.replace(copy.clone()) {
+                registered.unregister(&previous)?;
+            }
+
+            let mut black_frames = 0;
+            let mut shown = false;
+            // Keep receiving until the next cut, so the raw output never backs up
+            while cut.elapsed() < switch_interval {
+                let frame = match video.recv_timeout(self.frame_interval * 4) {
+                    Ok(PipelineEvent::Data(frame)) => frame,
+                    Err(err) if err.is_disconnected() => bail!("Raw output disconnected"),
+                    Ok(_) | Err(_) => continue,
+                };
+                let received = Instant::now();
+                if shown {
+                    continue;
+                }
+                let FrameData::Rgba8UnormWgpuTexture(texture) = &frame.data else {
+                    bail!("Expected Rgba8UnormWgpuTexture");
+                };
+                let image = to_image(&self.graphics_context, texture, self.shader_cache.get())?;
+                // Frames of the previous scene still in flight
+                if !latency::shows_marker(&image, color) {
+                    continue;
+                }
+                if analysis::FrameStats::of(&image).luma < BLACK_LUMA {
+                    black_frames += 1;
+                    continue;
+                }
+
+                shown = true;
+                first_frame_latencies.push(received - cut);
+                if pool_size > 0 {
+                    continue;
+                }
+                match &reference {
+                    None => reference = Some(image),
+                    Some(reference) => {
+                        let diff = analysis::mean_abs_diff(reference, &image);
+                        if diff > MAX_FIRST_FRAME_DIFF {
+                            findings.push(switch_finding(
+                                Some(frame.pts),
+                                format!("first frame of {copy} is off the clip start by {diff:.3}"),
+                            ));
+                        }
+                    }
+                }
+            }
+
+            if black_frames > 0 {
+                findings.push(switch_finding(
+                    None,
+                    format!("{black_frames} black frames before the first frame of {copy}"),
+                ));
+            }
+            if !shown {
+                findings.push(switch_finding(
+                    None,
+                    format!("{copy} not shown within {switch_interval:?}"),
+                ));
+            }
+        }
+
+        self.deregister_raw_output()?;
+        registered.unregister_all()?;
+
+        if let Some(stats) = LatencyStats::new(&registrations) {
+            stats.log("Input registration");
+        }
+        if let Some(stats) = LatencyStats::new(&first_frame_latencies) {
+            stats.log("Cut to first frame");
+        }
+        Ok(findings)
+    }
+
+    /// Registers a copy of an MP4 input under a new ID, without its color correction.
+    ///
+    /// Returns the ID and component of the copy, with the time the registration took.
+    fn register_copy(
+        &mut self,
+        source: &InputConfig,
+        copies: &mut usize,
+    ) -> Result<(String, Component, Duration)> {
+        let InputConfig::Mp4 {
+            id,
+            path,
+            should_loop,
+            decoder,
+            trim,
+            tracks,
+            ..
+        } = source
+        else {
+            bail!("Only MP4 inputs can be copied");
+        };
+        *copies += 1;
+        let copy = InputConfig::Mp4 {
+            id: format!("{id}_copy_{copies}"),
+            path: path.clone(),
+            should_loop: *should_loop,
+            decoder: *decoder,
+            trim: *trim,
+            tracks: *tracks,
+            // The source already plays its audio
+            audio: false,
+            correction: None,
+        };
+
+        let start = Instant::now();
+        Self::register_input(&self.pipeline, &self.graphics_context, &copy, &self.config)?;
+        let registration = start.elapsed();
+        debug!("Registered {} in {registration:?}", copy.id());
+
+        let component = input_component(&copy, &self.config.noise, self.resolution)?;
+        Ok((copy.id().to_string(), component, registration))
+    }
+
+    /// Renders a `counter` input from its first frame for `duration`, checking the cadence
+    /// of its source frames at the output framerate.
+    pub fn check_cadence(&mut self, id: &str, duration: Duration) -> Result<Vec<Finding>> {
+        let Some(InputConfig::Counter { framerate, .. }) =
+            self.config.inputs.iter().find(|input| input.id() == id)
+        else {
+            bail!("Cadence check requires a counter input, {id:?} is not one");
+        };
+        let source_interval = framerate.interval();
+        info!(
+            "Checking the cadence of {id}, {}/{} to {}/{} fps",
+            framerate.num, framerate.den, self.config.framerate.num, self.config.framerate.den
+        );
+
+        let component = self.component(id)?;
+        self.restart_input(id)?;
+        let mut processors: Vec<Box<dyn FrameProcessor>> =
+            vec![Box::new(CadenceAnalyzer::new(source_interval))];
+        self.analyze(component, duration, &mut processors)
+    }
+
+    /// Renders an input for `duration`, measuring the offset of its chroma planes, see
+    /// [`ChromaAlignmentAnalyzer`].
+    pub fn check_chroma_alignment(
+        &mut self,
+        id: &str,
+        duration: Duration,
+        tolerance: f32,
+    ) -> Result<Vec<Finding>> {
+        info!("Measuring the chroma alignment of {id}");
+        let component = self.component(id)?;
+        let mut processors: Vec<Box<dyn FrameProcessor>> =
+            vec![Box::new(ChromaAlignmentAnalyzer::new(tolerance))];
+        self.analyze(component, duration, &mut processors)
+    }
+
+    /// Plays a recording of an input for `duration`, decoding its event markers, see
+    /// [`MarkerDecoder`].
+    pub fn decode_markers(
+        &mut self,
+        id: &str,
+        duration: Duration,
+        timeline: Option<PathBuf>,
+    ) -> Result<Vec<Finding>> {
+        info!("Decoding the event markers of {id}");
+        let component = self.component(id)?;
+        let square = markers::square(&self.config.markers, self.marker_inset(), self.resolution);
+        let mut processors: Vec<Box<dyn FrameProcessor>> =
+            vec![Box::new(MarkerDecoder::new(square, timeline))];
+        self.analyze(component, duration, &mut processors)
+    }
+
+    /// Renders an input rescaled to each of `sizes`, the output resolution when empty, in
+    /// each of `modes`, measuring its sharpness, see [`SharpnessAnalyzer`].
+    pub fn measure_sharpness(
+        &mut self,
+        id: &str,
+        sizes: &[Resolution],
+        modes: &[RescaleModeConfig],
+        min_mtf50: Option<f32>,
+    ) -> Result<Vec<Finding>> {
+        let input = self
+            .config
+            .inputs
+            .iter()
+            .find(|input| input.id() == id)
+            .with_context(|| format!("Unknown input {id:?}"))?;
+        let content = input_content(input, &self.config.noise, self.resolution)?;
+        let sizes = match sizes.is_empty() {
+            true => vec![self.resolution],
+            false => sizes.to_vec(),
+        };
+
+        let mut findings = vec![];
+        for &mode in modes {
+            for size in &sizes {
+                let label = format!("{id} {mode:?} {}x{}", size.width, size.height);
+                // The rescaled input is at the top left corner of the output
+                let region = annotate::Rect {
+                    x: 0,
+                    y: 0,
+                    width: size.width.min(self.resolution.width) as u32,
+                    height: size.height.min(self.resolution.height) as u32,
+                };
+                let component = rescaled(
+                    content.clone(),
+                    mode.into(),
+                    size.width as f32,
+                    size.height as f32,
+                );
+                let mut processors: Vec<Box<dyn FrameProcessor>> =
+                    vec![Box::new(SharpnessAnalyzer::new(label, region, min_mtf50))];
+                findings.extend(self.analyze(component, SHARPNESS_DURATION, &mut processors)?);
+            }
+        }
+
+        Ok(findings)
+    }
+
+    /// Receives raw output frames for `duration`, switching input every `switch_interval`,
+    /// and computes the distribution of the intervals between them.
+    pub fn measure_pacing(
+        &mut self,
+        duration: Duration,
+        switch_interval: Duration,
+    ) -> Result<Option<PacingStats>> {
+        let (_, initial) = self.components[0].clone();
+        let receiver = self.register_raw_output(initial)?;
+        let video = receiver.video.as_ref().context("No video channel")?;
+
+        let mut pacing = FramePacing::new(self.frame_interval);
+        let start = Instant::now();
+        let mut switches = 1;
+        while start.elapsed() < duration {
+            if start.elapsed() >= switch_interval * switches {
+                self.poll_events();
+                let (id, component) = &self.components[switches as usize % self.components.len()];
+                self.update_raw_output(component.clone())?;
+                self.event_log.write(LogEvent::SceneApplied {
+                    output: "raw",
+                    scene: id,
+                });
+                switches += 1;
+            }
+
+            match video.recv_timeout(self.frame_interval * 4) {
+                Ok(PipelineEvent::Data(_)) => pacing.record(Instant::now()),
+                Ok(_) | Err(_) => continue,
+            }
+        }
+
+        self.deregister_raw_output()?;
+
+        let stats = pacing.stats();
+        if let Some(stats) = &stats {
+            stats.log();
+        }
+        Ok(stats)
+    }
+
+    /// Toggles a black and white marker over the first input `switches` times, one switch
+    /// every `switch_interval`, and measures the time from each `update_output` call to the
+    /// first raw output frame showing the new marker color.
+    ///
+    /// Latencies are taken when frames are received, before their readback, and corrected for
+    /// the frames waiting behind the readback of the previous one, see [`ArrivalClock`], so
+    /// the readback time does not count. Switches not shown within `switch_interval` are
+    /// counted as missed.
+    pub fn measure_update_latency(
+        &mut self,
+        switches: u32,
+        switch_interval: Duration,
+    ) -> Result<Option<LatencyStats>> {
+        let (id, background) = self.components[0].clone();
+        let resolution = self.resolution;
+        let with_marker = |color: [u8; 3]| marker_scene(background.clone(), color, resolution);
+        let colors = [[0, 0, 0], [255, 255, 255]];
+        let receiver = self.register_raw_output(with_marker(colors[0]))?;
+        let video = receiver.video.as_ref().context("No video channel")?;
+        info!("Measuring scene update latency over {switches} switches, marker over {id}");
+
+        // Let the initial scene settle
+        std::thread::sleep(switch_interval);
+        let mut clock = ArrivalClock::default();
+        let mut latencies = vec![];
+        let mut frames_late = vec![];
+        let mut missed = 0;
+        for switch in 1..=switches {
+            let color = colors[switch as usize % 2];
+            let updated = Instant::now();
+            self.update_raw_output(with_marker(color))?;
+            self.event_log.write(LogEvent::SceneApplied {
+                output: "raw",
+                scene: &id,
+            });
+
+            let mut frames = 0;
+            let mut shown = false;
+            // Keep receiving until the next switch, so the raw output never backs up
+            while updated.elapsed() < switch_interval {
+                let frame = match video.recv_timeout(self.frame_interval * 4) {
+                    Ok(PipelineEvent::Data(frame)) => frame,
+                    Err(err) if err.is_disconnected() => bail!("Raw output disconnected"),
+                    Ok(_) | Err(_) => continue,
+                };
+                let received = clock.arrival(Instant::now(), frame.pts);
+                if shown {
+                    continue;
+                }
+                frames += 1;
+                let FrameData::Rgba8UnormWgpuTexture(texture) = &frame.data else {
+                    bail!("Expected Rgba8UnormWgpuTexture");
+                };
+                let image = to_image(&self.graphics_context, texture, self.shader_cache.get())?;
+                if latency::shows_marker(&image, color) {
+                    latencies.push(received.saturating_duration_since(updated));
+                    frames_late.push(frames);
+                    shown = true;
+                }
+            }
+            if !shown {
+                missed += 1;
+                warn!("Switch {switch} not shown within {switch_interval:?}");
+            }
+        }
+
+        self.deregister_raw_output()?;
+
+        let stats = LatencyStats::new(&latencies);
+        if let Some(stats) = &stats {
+            stats.log("Scene update latency");
+            let mean_frames = frames_late.iter().sum::<u32>() as f64 / frames_late.len() as f64;
+            info!(
+                "Switches shown on frame {:.1} on average, up to frame {}",
+                mean_frames,
+                frames_late.iter().max().unwrap()
+            );
+        }
+        if missed > 0 {
+            warn!("{missed} of {switches} switches never shown");
+        }
+        Ok(stats)
+    }
+
+    /// Renders a live input flashing every `flash_interval` for `duration`, and measures
+    /// how long after each flash the raw output shows it, see [`FlashDetector`].
+    pub fn measure_glass_to_glass(
+        &mut self,
+        id: &str,
+        duration: Duration,
+        flash_interval: Duration,
+        threshold: f32,
+    ) -> Result<Option<LatencyStats>> {
+        let component = self.component(id)?;
+        let receiver = self.register_raw_output(component)?;
+        let video = receiver.video.as_ref().context("No video channel")?;
+        info!(
+            "Measuring glass-to-glass latency of {id}, flashing every {}ms",
+            flash_interval.as_millis()
+        );
+
+        let mut detector = FlashDetector::new(flash_interval, threshold);
+        let mut clock = ArrivalClock::default();
+        let mut latencies = vec![];
+        let start = Instant::now();
+        while start.elapsed() < duration {
+            let frame = match video.recv_timeout(self.frame_interval * 4) {
+                Ok(PipelineEvent::Data(frame)) => frame,
+                Err(err) if err.is_disconnected() => bail!("Raw output disconnected"),
+                Ok(_) | Err(_) => continue,
+            };
+            // Taken before the readback, without the time spent queued behind the previous one
+            let now = (Instant::now(), SystemTime::now());
+            let arrival = now.1 - now.0.duration_since(clock.arrival(now.0, frame.pts));
+            let FrameData::Rgba8UnormWgpuTexture(texture) = &frame.data else {
+                bail!("Expected Rgba8UnormWgpuTexture");
+            };
+            let image = to_image(&self.graphics_context, texture, self.shader_cache.get())?;
+            if let Some(latency) = detector.process(&image, arrival) {
+                latencies.push(latency);
+            }
+        }
+
+        self.deregister_raw_output()?;
+
+        let stats = LatencyStats::new(&latencies);
+        match &stats {
+            Some(stats) => stats.log("Glass-to-glass latency"),
+            None => warn!("No flash seen on {id} in {duration:?}"),
+        }
+        Ok(stats)
+    }
+
+    /// Replaces the pipeline with one using other queue settings, registering the inputs
+    /// again.
+    ///
+    /// The inputs are unregistered first, so the new pipeline can take their ports and
+    /// devices.
+    fn rebuild_pipeline(&mut self, queue: QueueConfig) -> Result<()> {
+        self.config.queue = queue;
+        {
+            let mut pipeline = self.pipeline.lock().unwrap();
+            // Renderer inputs are not pipeline inputs, and go with the pipeline
+            for input in &self.config.inputs {
+                let input_id = InputId(Arc::from(input.id()));
+                let _ = Pipeline::unregister_input(&mut *pipeline, &input_id);
+            }
+        }
+
+        let config = &self.config;
+        let (pipeline, raw_inputs) = config.threads.pipeline.scoped("pipeline", || {
+            let pipeline = Self::create_pipeline(&self.graphics_context, config)?;
+            let (_, raw_inputs) = Self::register_inputs(&pipeline, &self.graphics_context, config)?;
+            Ok((pipeline, raw_inputs))
+        })?;
+        self.events = pipeline.lock().unwrap().subscribe_pipeline_events();
+        self.pipeline = pipeline;
+        self.raw_inputs = raw_inputs;
+
+        Ok(())
+    }
+
+    /// Prints the latest frame as ANSI text every `interval`, switching input every
+    /// `switch_interval`, so remote sessions can tell what is playing.
+    pub fn preview_in_terminal(
+        &mut self,
+        duration: Option<Duration>,
+        interval: Duration,
+        columns: u32,
+        switch_interval: Duration,
+    ) -> Result<()> {
+        self.restore_preview_settings();
+        let correction = self.display_correction();
+        let (_, initial) = self.components[0].clone();
+        let receiver = self.register_raw_output(initial)?;
+        let video = receiver.video.as_ref().context("No video channel")?;
+
+        let start = Instant::now();
+        let mut switches = 1;
+        let mut previews = 0;
+        let mut latest = None;
+        while duration.map_or(true, |duration| start.elapsed() < duration) {
+            if start.elapsed() >= switch_interval * switches {
+                self.poll_events();
+                let (id, component) = &self.components[switches as usize % self.components.len()];
+                self.update_raw_output(component.clone())?;
+                self.event_log.write(LogEvent::SceneApplied {
+                    output: "raw",
+                    scene: id,
+                });
+                switches += 1;
+            }
+
+            // Keep receiving, so the raw output never backs up between previews
+            match video.recv_timeout(self.frame_interval * 4) {
+                Ok(PipelineEvent::Data(frame)) => latest = Some(frame),
+                Err(err) if err.is_disconnected() => bail!("Raw output disconnected"),
+                Ok(_) | Err(_) => {}
+            }
+
+            if start.elapsed() < interval * previews {
+                continue;
+            }
+            let Some(frame) = latest.take() else {
+                continue;
+            };
+            let FrameData::Rgba8UnormWgpuTexture(texture) = &frame.data else {
+                bail!("Expected Rgba8UnormWgpuTexture");
+            };
+            let mut image = self.preview_image(texture)?;
+            if let Some(correction) = &correction {
+                correction.apply(&mut image);
+            }
+            let (id, _) = &self.components[(switches as usize - 1) % self.components.len()];
+            println!(
+                "{id} at {:.1}s{}\n{}",
+                frame.pts.as_secs_f64(),
+                correction
+                    .as_ref()
+                    .map(|correction| format!(" [ICC: {}]", correction.description()))
+                    .unwrap_or_default(),
+                terminal::render_ansi(&image, columns)
+            );
+            previews += 1;
+        }
+
+        self.deregister_raw_output()?;
+        Ok(())
+    }
+
+    /// Reads a frame back for a preview, magnified with the preview zoom.
+    fn preview_image(&self, texture: &wgpu::Texture) -> Result<RgbaImage> {
+        let image = to_image(&self.graphics_context, texture, self.shader_cache.get())?;
+        match self.config.preview.zoom > 1.0 {
+            true => Ok(terminal::zoom(&image, self.config.preview.zoom)),
+            false => Ok(image),
+        }
+    }
+
+    /// Replaces the preview settings with those saved for the attached display, and
+    /// returns the display. None is identified when headless.
+    fn restore_preview_settings(&mut self) -> Option<DisplayIdentity> {
+        if self.config.headless {
+            return None;
+        }
+        let preview = &self.config.preview;
+        let display = match display::attached_display(preview.monitor.as_deref()) {
+            Ok(display) => display,
+            Err(err) => {
+                debug!("Preview settings not restored, no display identified: {err:#}");
+                return None;
+            }
+        };
+        match DisplayProfiles::load(&preview.profiles) {
+            Ok(profiles) => match profiles.get(&display) {
+                Some(settings) => {
+                    settings.apply(&mut self.config.preview);
+                    info!("Restored the preview settings of {display}");
+                }
+                None => debug!("No preview settings saved for {display}"),
+            },
+            Err(err) => warn!("Preview settings not restored: {err:#}"),
+        }
+        Some(display)
+    }
+
+    /// Saves the preview settings for `display`, restored when previewing on it again.
+    fn save_preview_settings(&self, display: &DisplayIdentity) -> Result<()> {
+        let preview = &self.config.preview;
+        let mut profiles = DisplayProfiles::load(&preview.profiles)?;
+        profiles.save(display, PreviewSettings::of(preview))?;
+        info!(
+            "Saved the preview settings of {display} to {}",
+            preview.profiles.display()
+        );
+        Ok(())
+    }
+
+    /// Correction of the previews for the display, when enabled and its profile can be read.
+    fn display_correction(&self) -> Option<DisplayCorrection> {
+        let preview = &self.config.preview;
+        if !preview.correct_display {
+            return None;
+        }
+        match DisplayCorrection::load(preview.icc_profile.as_deref()) {
+            Ok(correction) => {
+                info!("Correcting previews for {}", correction.description());
+                Some(correction)
+            }
+            Err(err) => {
+                warn!("Previews left uncorrected: {err:#}");
+                None
+            }
+        }
+    }
+
+    /// Shows the terminal dashboard until `duration` elapses or it is quit.
+    pub fn run_dashboard(
+        &mut self,
+        duration: Option<Duration>,
+        switch_interval: Duration,
+    ) -> Result<()> {
+        let session = self.restore_session();
+        // Settings saved for the display, restored below, take precedence
+        session.apply_preview(&mut self.config.preview);
+        let current_scene = session
+            .scene
+            .as_ref()
+            .and_then(|scene| self.components.iter().position(|(id, _)| id == scene))
+            .unwrap_or(0);
+        let (_, initial) = self.components[current_scene].clone();
+        // The mixed audio is metered, and played along unless muted
+        self.raw_audio = !self.mixer.is_empty();
+        let monitor = match self.raw_audio && !self.config.audio.mute {
+            true => AudioMonitor::start(self.mixer.channels())
+                .inspect_err(|err| warn!("Monitoring without audio: {err:#}"))
+                .ok(),
+            false => None,
+        };
+        let mut meter = self
+            .raw_audio
+            .then(|| LevelMeter::new(self.mixer.channels()));
+        let receiver = self.register_raw_output(initial)?;
+        let video = receiver.video.as_ref().context("No video channel")?;
+        let mut dashboard = Dashboard::start()?;
+        let mut gaps = PtsGapCheck::new(self.frame_interval);
+
+        let start = Instant::now();
+        let display = self.restore_preview_settings();
+        let mut correction = self.display_correction();
+        let mut state = DashboardState {
+            current_scene,
+            cycling: session.cycling,
+            color_management: correction
+                .as_ref()
+                .map(|correction| correction.description().to_string()),
+            display: display.as_ref().map(ToString::to_string),
+            zoom: self.config.preview.zoom,
+            preview_width: self.config.preview.preview_width,
+            ..Default::default()
+        };
+        let mut preview = None;
+        let mut latest = None;
+        let mut snapshot = false;
+        let mut recorder = self.record_commands(&state);
+        let mut last_switch = Instant::now();
+        let mut last_draw = Instant::now();
+        let mut frames_at_draw = 0;
+        while duration.map_or(true, |duration| start.elapsed() < duration) {
+            let timestamp = start.elapsed().as_secs_f64();
+            for event in self.poll_events() {
+                dashboard.log(format!("{timestamp:>8.1}s {event:?}"));
+            }
+
+            let mut target = None;
+            if state.cycling && last_switch.elapsed() >= switch_interval {
+                target = Some(state.current_scene + 1);
+            }
+            let command = dashboard.poll_command(Duration::ZERO)?;
+            if let (Some(recorder), Some(command)) = (&mut recorder, command) {
+                if command != Command::Quit {
+                    if let Err(err) = recorder.record(command) {
+                        warn!("Commands no longer recorded: {err:#}");
+                    }
+                }
+            }
+            match command {
+                Some(Command::Quit) => break,
+                Some(Command::NextScene) => target = Some(state.current_scene + 1),
+                Some(Command::PreviousScene) => {
+                    target = Some(state.current_scene + self.components.len() - 1)
+                }
+                Some(Command::SelectScene(index)) if index < self.components.len() => {
+                    target = Some(index)
+                }
+                Some(Command::ToggleCycling) => state.cycling = !state.cycling,
+                Some(Command::ZoomIn) => {
+                    self.config.preview.zoom = (self.config.preview.zoom * 2.0).min(16.0)
+                }
+                Some(Command::ZoomOut) => {
+                    self.config.preview.zoom = (self.config.preview.zoom / 2.0).max(1.0)
+                }
+                Some(Command::WiderPreview) => {
+                    self.config.preview.preview_width =
+                        (self.config.preview.preview_width + 5).min(90)
+                }
+                Some(Command::NarrowerPreview) => {
+                    self.config.preview.preview_width =
+                        self.config.preview.preview_width.saturating_sub(5).max(20)
+                }
+                Some(Command::ToggleDisplayCorrection) => {
+                    self.config.preview.correct_display = !self.config.preview.correct_display;
+                    correction = self.display_correction();
+                    state.color_management = correction
+                        .as_ref()
+                        .map(|correction| correction.description().to_string());
+                }
+                Some(Command::Snapshot) => snapshot = true,
+                Some(Command::SaveSettings) => match &display {
+                    Some(display) => match self.save_preview_settings(display) {
+                        Ok(()) => dashboard.log(format!("{timestamp:>8.1}s Saved for {display}")),
+                        Err(err) => dashboard.log(format!("{timestamp:>8.1}s {err:#}")),
+                    },
+                    None => dashboard.log(format!("{timestamp:>8.1}s No display identified")),
+                },
+                _ => {}
+            }
+            state.zoom = self.config.preview.zoom;
+            state.preview_width = self.config.preview.preview_width;
+            if let Some(target) = target {
+                state.current_scene = target % self.components.len();
+                let (id, component) = self.components[state.current_scene].clone();
+                self.update_raw_output(component)?;
+                self.event_log.write(LogEvent::SceneApplied {
+                    output: "raw",
+                    scene: &id,
+                });
+                dashboard.log(format!("{timestamp:>8.1}s Switched to {id}"));
+                last_switch = Instant::now();
+            }
+
+            if let (Some(meter), Some(audio)) = (&mut meter, &receiver.audio) {
+                for event in audio.try_iter() {
+                    if let PipelineEvent::Data(samples) = event {
+                        meter.push(&samples);
+                        if let Some(monitor) = &monitor {
+                            monitor.push(&samples);
+                        }
+                    }
+                }
+            }
+
+            match video.recv_timeout(self.frame_interval) {
+                Ok(PipelineEvent::Data(frame)) => {
+                    gaps.check(frame.pts, video.len(), video.is_full());
+                    if let (true, FrameData::Rgba8UnormWgpuTexture(texture)) =
+                        (snapshot, &frame.data)
+                    {
+                        let path = self.snapshot_path(state.current_scene, frame.pts);
+                        match self.save_frame(texture, &path, DumpFormatConfig::Png) {
+                            Ok(()) => {
+                                dashboard.log(format!("{timestamp:>8.1}s Saved {}", path.display()))
+                            }
+                            Err(err) => dashboard.log(format!("{timestamp:>8.1}s {err:#}")),
+                        }
+                        snapshot = false;
+                    }
+                    latest = Some(frame);
+                    state.frames += 1;
+                }
+                Err(err) if err.is_disconnected() => bail!("Raw output disconnected"),
+                Ok(_) | Err(_) => {}
+            }
+
+            if last_draw.elapsed() < dashboard::REFRESH_INTERVAL {
+                continue;
+            }
+            if let Some(frame) = latest.take() {
+                if let FrameData::Rgba8UnormWgpuTexture(texture) = &frame.data {
+                    let mut image = self.preview_image(texture)?;
+                    if let Some(correction) = &correction {
+                        correction.apply(&mut image);
+                    }
+                    preview = Some(image);
+                }
+            }
+            state.elapsed = start.elapsed();
+            state.fps = (state.frames - frames_at_draw) as f64 / last_draw.elapsed().as_secs_f64();
+            state.skipped_frames = self.skipped_frames;
+            state.queue_drops = gaps.queue_drops();
+            state.harness_drops = gaps.harness_drops();
+            state.audio_levels = meter.as_mut().map(LevelMeter::read).unwrap_or_default();
+            state.scenes = self.components.iter().map(|(id, _)| id.clone()).collect();
+            // WHIP publishers leaving can shrink the list
+            state.current_scene = state.current_scene.min(state.scenes.len() - 1);
+            dashboard.draw(&state, preview.as_ref())?;
+            frames_at_draw = state.frames;
+            last_draw = Instant::now();
+        }
+
+        drop(dashboard);
+        self.deregister_raw_output()?;
+        self.raw_audio = false;
+        if let Some(mut recorder) = recorder {
+            // Replays last as long as the session
+            recorder.record(Command::Quit)?;
+            info!("Recorded {} commands", recorder.steps());
+        }
+        let session = SessionState {
+            scene: self
+                .components
+                .get(state.current_scene)
+                .map(|(id, _)| id.clone()),
+            cycling: state.cycling,
+            zoom: Some(self.config.preview.zoom),
+            preview_width: Some(self.config.preview.preview_width),
+        };
+        if let Err(err) = session.save(&self.config.session.path) {
+            warn!("Session not saved: {err:#}");
+        }
+        Ok(())
+    }
+
+    /// Recorder of the commands issued in the dashboard, when enabled, starting with the
+    /// commands that lead to its initial `state`.
+    fn record_commands(&self, state: &DashboardState) -> Option<MacroRecorder> {
+        let path = self.config.session.record_commands.as_ref()?;
+        let start = || -> Result<MacroRecorder> {
+            let mut recorder = MacroRecorder::create(path)?;
+            recorder.record(Command::SelectScene(state.current_scene))?;
+            if !state.cycling {
+                recorder.record(Command::ToggleCycling)?;
+            }
+            Ok(recorder)
+        };
+        match start() {
+            Ok(recorder) => {
+                info!("Recording the commands to {}", path.display());
+                Some(recorder)
+            }
+            Err(err) => {
+                warn!("Commands not recorded: {err:#}");
+                None
+            }
+        }
+    }
+
+    /// File a snapshot of the frame at `pts` showing the `scene`th component is saved to, in
+    /// the working directory.
+    fn snapshot_path(&self, scene: usize, pts: Duration) -> PathBuf {
+        let id = self
+            .components
+            .get(scene)
+            .map_or("output", |(id, _)| id.as_str());
+        PathBuf::from(format!("snapshot_{id}_{}.png", pts.as_millis()))
+    }
+
+    /// Replays the commands of a script recorded in the dashboard, see [`crate::macros`],
+    /// on the raw output, cycling every `switch_interval` like the dashboard while cycling
+    /// is on. Snapshots are saved like in the dashboard.
+    ///
+    /// Commands changing the preview only are skipped. Returns the number of commands
+    /// replayed.
+    pub fn replay_commands(&mut self, path: &Path, switch_interval: Duration) -> Result<usize> {
+        let steps = macros::load(path)?;
+        info!("Replaying {} commands of {}", steps.len(), path.display());
+        let mut steps = steps.into_iter().peekable();
+        let (_, initial) = self.components[0].clone();
+        let receiver = self.register_raw_output(initial)?;
+        let video = receiver.video.as_ref().context("No video channel")?;
+
+        let start = Instant::now();
+        let (mut current_scene, mut cycling, mut snapshot) = (0, true, false);
+        let mut replayed = 0;
+        let mut last_switch = Instant::now();
+        'replay: while steps.peek().is_some() || snapshot {
+            self.poll_events();
+            let mut target = None;
+            if cycling && last_switch.elapsed() >= switch_interval {
+                target = Some(current_scene + 1);
+            }
+            let elapsed = start.elapsed().as_millis() as u64;
+            while let Some(step) = steps.next_if(|step| step.at_ms <= elapsed) {
+                replayed += 1;
+                match step.command {
+                    Command::Quit => break 'replay,
+                    Command::NextScene => target = Some(current_scene + 1),
+                    Command::PreviousScene => {
+                        target = Some(current_scene + self.components.len() - 1)
+                    }
+                    Command::SelectScene(index) if index < self.components.len() => {
+                        target = Some(index)
+                    }
+                    Command::ToggleCycling => cycling = !cycling,
+                    Command::Snapshot => snapshot = true,
+                    _ => {}
+                }
+            }
+            if let Some(target) = target {
+                current_scene = target % self.components.len();
+                let (id, component) = self.components[current_scene].clone();
+                self.update_raw_output(component)?;
+                self.event_log.write(LogEvent::SceneApplied {
+                    output: "raw",
+                    scene: &id,
+                });
+                last_switch = Instant::now();
+            }
+
+            match video.recv_timeout(self.frame_interval) {
+                Ok(PipelineEvent::Data(frame)) if snapshot => {
+                    let FrameData::Rgba8UnormWgpuTexture(texture) = &frame.data else {
+                        bail!("Expected Rgba8UnormWgpuTexture");
+                    };
+                    let path = self.snapshot_path(current_scene, frame.pts);
+                    self.save_frame(texture, &path, DumpFormatConfig::Png)?;
+                    info!("Saved {}", path.display());
+                    snapshot = false;
+                }
+                Err(err) if err.is_disconnected() => bail!("Raw output disconnected"),
+                Ok(_) | Err(_) => {}
+            }
+        }
+
+        self.deregister_raw_output()?;
+        info!("Replayed {replayed} commands");
+        Ok(replayed)
+    }
+
+    /// State the dashboard was left in, when restoring sessions.
+    fn restore_session(&self) -> SessionState {
+        let config = &self.config.session;
+        if !config.restore {
+            return SessionState::default();
+        }
+        match SessionState::load(&config.path) {
+            Ok(Some(session)) => {
+                info!("Restored the session of {}", config.path.display());
+                session
+            }
+            Ok(None) => SessionState::default(),
+            Err(err) => {
+                warn!("Session not restored: {err:#}");
+                SessionState::default()
+            }
+        }
+    }
+
+    /// Runs every configured output, one after the other.
+    pub fn run(&mut self) -> Result<()> {
+        self.config.threads.capture.apply("capture")?;
+
+        let sampler = self.config.utilization.enabled.then(|| {
+            UtilizationSampler::start(Duration::from_millis(self.config.utilization.interval_ms))
+        });
+
+        let result = self.run_outputs();
+        let coverage = self.coverage.lock().unwrap();
+        coverage.log_summary();
+        self.event_log.write(LogEvent::Coverage {
+            scenes: coverage.scenes(),
+            exercised: coverage
+                .exercised()
+                .into_iter()
+                .map(|(feature, _)| feature)
+                .collect(),
+            missing: coverage.missing(),
+        });
+        drop(coverage);
+        self.event_log.write(LogEvent::Finished {
+            error: result.as_ref().err().map(|err| format!("{err:#}")),
+        });
+
+        if let Some(sampler) = sampler {
+            let samples = sampler.stop();
+            utilization::log_summary(&samples);
+            let path = &self.config.utilization.path;
+            utilization::write_csv(&samples, path)?;
+            let plot = path.with_extension("svg");
+            utilization::write_plot(&samples, &plot)?;
+            info!(
+                "Utilization samples saved to {}, plotted to {}",
+                path.display(),
+                plot.display()
+            );
+        }
+
+        result
+    }
+
+    fn run_outputs(&mut self) -> Result<()> {
+        for output in self.config.outputs.clone() {
+            self.event_log.write(LogEvent::OutputStarted {
+                output: output.name(),
+            });
+            match output {
+                OutputConfig::Snapshots {
+                    path_template,
+                    format,
+                } => self.save_images_as(&path_template, format)?,
+                OutputConfig::Mp4 {
+                    path,
+                    duration_secs,
+                    cycles,
+                    switch_interval_ms,
+                    segment_secs,
+                    segment_mb,
+                    verify_metadata,
+                } => {
+                    let rollover =
+                        (segment_secs.is_some() || segment_mb.is_some()).then(|| Rollover {
+                            duration: segment_secs.map(Duration::from_secs),
+                            size: segment_mb.map(|mb| mb * 1_000_000),
+                        });
+                    let switch_interval = Duration::from_millis(switch_interval_ms);
+                    let path = match cycles {
+                        Some(cycles) => {
+                            self.record_cycles(path, cycles, switch_interval, rollover)?
+                        }
+                        None => self.record_for(
+                            path,
+                            Duration::from_secs(duration_secs.unwrap_or_default()),
+                            switch_interval,
+                            rollover,
+                        )?,
+                    };
+                    if verify_metadata && self.record_fallback {
+                        warn!("Not verifying {}, recorded as PNG frames", path.display());
+                    } else if verify_metadata {
+                        self.verify_recording(&path)?;
+                    }
+                }
+                OutputConfig::Simultaneous {
+                    outputs,
+                    duration_secs,
+                    switch_interval_ms,
+                } => {
+                    self.run_simultaneous(
+                        &outputs,
+                        Duration::from_secs(duration_secs),
+                        Duration::from_millis(switch_interval_ms),
+                    )?;
+                }
+                OutputConfig::Webm {
+                    path,
+                    codec,
+                    duration_secs,
+                    switch_interval_ms,
+                } => {
+                    self.record_webm(
+                        &path,
+                        codec,
+                        Duration::from_secs(duration_secs),
+                        Duration::from_millis(switch_interval_ms),
+                    )?;
+                }
+                OutputConfig::Gif {
+                    path,
+                    duration_secs,
+                    fps,
+                    max_colors,
+                    switch_interval_ms,
+                } => {
+                    self.record_gif(
+                        Duration::from_secs(duration_secs),
+                        &path,
+                        fps,
+                        max_colors,
+                        Duration::from_millis(switch_interval_ms),
+                    )?;
+                }
+                OutputConfig::EncoderSweep {
+                    dir,
+                    duration_secs,
+                    presets,
+                    crfs,
+                    vmaf,
+                    switch_interval_ms,
+                } => {
+                    self.encoder_sweep(
+                        &dir,
+                        Duration::from_secs(duration_secs),
+                        Duration::from_millis(switch_interval_ms),
+                        &SweepSettings {
+                            presets,
+                            crfs,
+                            vmaf,
+                        },
+                    )?;
+                }
+                OutputConfig::Hls {
+                    dir,
+                    segment_type,
+                    segment_secs,
+                    playlist_size,
+                    duration_secs,
+                    switch_interval_ms,
+                } => {
+                    let options = HlsOptions {
+                        segment_type,
+                        segment_secs,
+                        playlist_size,
+                    };
+                    self.write_hls(
+                        &dir,
+                        options,
+                        Duration::from_secs(duration_secs),
+                        Duration::from_millis(switch_interval_ms),
+                    )?;
+                }
+                OutputConfig::Rtp {
+                    address,
+                    port,
+                    payload_type,
+                    codec,
+                    sdp,
+                    duration_secs,
+                    switch_interval_ms,
+                } => {
+                    let options = RtpSenderOptions {
+                        address: &address,
+                        port,
+                        payload_type,
+                        codec,
+                        sdp: sdp.as_deref(),
+                    };
+                    self.send_rtp(
+                        &options,
+                        Duration::from_secs(duration_secs),
+                        Duration::from_millis(switch_interval_ms),
+                    )?;
+                }
+                OutputConfig::Ndi {
+                    name,
+                    duration_secs,
+                    switch_interval_ms,
+                } => {
+                    self.publish_ndi(
+                        &name,
+                        Duration::from_secs(duration_secs),
+                        Duration::from_millis(switch_interval_ms),
+                    )?;
+                }
+                OutputConfig::Whip {
+                    endpoint_url,
+                    bearer_token,
+                    duration_secs,
+                    switch_interval_ms,
+                } => {
+                    self.publish_whip(
+                        &endpoint_url,
+                        bearer_token.as_deref(),
+                        Duration::from_secs(duration_secs),
+                        Duration::from_millis(switch_interval_ms),
+                    )?;
+                }
+                OutputConfig::DualRecording {
+                    path,
+                    frames_dir,
+                    duration_secs,
+                    switch_interval_ms,
+                } => {
+                    self.record_dual(
+                        &path,
+                        &frames_dir,
+                        Duration::from_secs(duration_secs),
+                        Duration::from_millis(switch_interval_ms),
+                    )?;
+                }
+                OutputConfig::FrameSequence {
+                    scene,
+                    dir,
+                    duration_secs,
+                    stride,
+                    template,
+                    format,
+                } => {
+                    self.save_frame_sequence(
+                        &scene,
+                        &dir,
+                        Duration::from_secs(duration_secs),
+                        stride,
+                        &template,
+                        format,
+                    )?;
+                }
+                OutputConfig::Y4m {
+                    scene,
+                    path,
+                    duration_secs,
+                } => {
+                    self.record_y4m(&scene, &path, Duration::from_secs(duration_secs))?;
+                }
+                OutputConfig::WatchScene {
+                    scene,
+                    snapshot_path,
+                    duration_secs,
+                } => self.watch_scene(
+                    &scene,
+                    &snapshot_path,
+                    duration_secs.map(Duration::from_secs),
+                )?,
+                OutputConfig::LoopCheck {
+                    input,
+                    loops,
+                    tolerance_ms,
+                } => {
+                    let findings =
+                        self.check_loop(&input, loops, Duration::from_millis(tolerance_ms))?;
+                    self.log_findings(&findings);
+                    if !findings.is_empty() {
+                        bail!("{} glitches at loop points of {input}", findings.len());
+                    }
+                }
+                OutputConfig::BarColor {
+                    input,
+                    color,
+                    tolerance,
+                } => {
+                    let findings = self.check_bars(&input, &color, tolerance)?;
+                    self.log_findings(&findings);
+                    if !findings.is_empty() {
+                        bail!("Wrong bar color around {input}");
+                    }
+                }
+                OutputConfig::ExpectedScene { scene, tolerance } => {
+                    let findings = self.check_expected(&scene, tolerance)?;
+                    self.log_findings(&findings);
+                    if !findings.is_empty() {
+                        bail!("{scene} differs from its expected frame");
+                    }
+                }
+                OutputConfig::GoldenSequence {
+                    from,
+                    to,
+                    dir,
+                    frames,
+                    tolerance,
+                    frame_tolerances,
+                    matching,
+                    skip_after_switch,
+                    update,
+                } => {
+                    let check = SequenceCheck {
+                        frames,
+                        tolerance,
+                        frame_tolerances: &frame_tolerances,
+                        matching,
+                        skip_after_switch,
+                        update,
+                    };
+                    let findings = self.check_golden_sequence(&from, &to, &dir, &check)?;
+                    self.log_findings(&findings);
+                    if !findings.is_empty() {
+                        bail!("{from} to {to} differs from its golden sequence");
+                    }
+                }
+                OutputConfig::InputSwitching {
+                    input,
+                    switches,
+                    switch_interval_ms,
+                    pool_size,
+                } => {
+                    let findings = self.check_input_switching(
+                        &input,
+                        switches,
+                        Duration::from_millis(switch_interval_ms),
+                        pool_size,
+                    )?;
+                    self.log_findings(&findings);
+                    if !findings.is_empty() {
+                        bail!(
+                            "{} glitches when cutting to copies of {input}",
+                            findings.len()
+                        );
+                    }
+                }
+                OutputConfig::Cadence {
+                    input,
+                    duration_secs,
+                } => {
+                    let findings =
+                        self.check_cadence(&input, Duration::from_secs(duration_secs))?;
+                    self.log_findings(&findings);
+                    if !findings.is_empty() {
+                        bail!("{} cadence errors on {input}", findings.len());
+                    }
+                }
+                OutputConfig::ChromaAlignment {
+                    input,
+                    duration_secs,
+                    tolerance_px,
+                } => {
+                    let findings = self.check_chroma_alignment(
+                        &input,
+                        Duration::from_secs(duration_secs),
+                        tolerance_px,
+                    )?;
+                    self.log_findings(&findings);
+                    if !findings.is_empty() {
+                        bail!("Chroma planes of {input} misaligned");
+                    }
+                }
+                OutputConfig::Markers {
+                    input,
+                    duration_secs,
+                    timeline,
+                } => {
+                    let findings =
+                        self.decode_markers(&input, Duration::from_secs(duration_secs), timeline)?;
+                    self.log_findings(&findings);
+                    if !findings.is_empty() {
+                        bail!("Cannot decode the markers of {input}");
+                    }
+                }
+                OutputConfig::Sharpness {
+                    input,
+                    sizes,
+                    modes,
+                    min_mtf50,
+                } => {
+                    let sizes: Vec<Resolution> = sizes
+                        .iter()
+                        .map(|size| Resolution {
+                            width: size.width,
+                            height: size.height,
+                        })
+                        .collect();
+                    let findings = self.measure_sharpness(&input, &sizes, &modes, min_mtf50)?;
+                    self.log_findings(&findings);
+                    if !findings.is_empty() {
+                        bail!("{} sharpness findings on {input}", findings.len());
+                    }
+                }
+                OutputConfig::Analyze {
+                    input,
+                    duration_secs,
+                    switch_interval_ms,
+                    plugins,
+                    fail_on_findings,
+                } => {
+                    let mut processors = plugins
+                        .iter()
+                        .map(|plugin| {
+                            let processor = PluginProcessor::load(&plugin.path, &plugin.config)?;
+                            Ok(analysis::with_rate(Box::new(processor), plugin.rate.into()))
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    let scenes = match &input {
+                        Some(input) => vec![self.component(input)?],
+                        None => self
+                            .components
+                            .iter()
+                            .map(|(_, component)| component.clone())
+                            .collect(),
+                    };
+                    let findings = self.analyze_scenes(
+                        &scenes,
+                        Duration::from_secs(duration_secs),
+                        Duration::from_millis(switch_interval_ms),
+                        &mut processors,
+                    )?;
+                    self.log_findings(&findings);
+                    if fail_on_findings && !findings.is_empty() {
+                        let analyzed = input.as_deref().unwrap_or("the inputs");
+                        bail!("{} findings on {analyzed}", findings.len());
+                    }
+                }
+                OutputConfig::FramePacing {
+                    duration_secs,
+                    switch_interval_ms,
+                } => {
+                    self.measure_pacing(
+                        Duration::from_secs(duration_secs),
+                        Duration::from_millis(switch_interval_ms),
+                    )?;
+                }
+                OutputConfig::UpdateLatency {
+                    switches,
+                    switch_interval_ms,
+                } => {
+                    self.measure_update_latency(
+                        switches,
+                        Duration::from_millis(switch_interval_ms),
+                    )?;
+                }
+                OutputConfig::GlassToGlass {
+                    input,
+                    duration_secs,
+                    flash_interval_ms,
+                    threshold,
+                    presets,
+                } => {
+                    let (duration, flash_interval) = (
+                        Duration::from_secs(duration_secs),
+                        Duration::from_millis(flash_interval_ms),
+                    );
+                    if presets.is_empty() {
+                        self.measure_glass_to_glass(&input, duration, flash_interval, threshold)?;
+                        continue;
+                    }
+
+                    let configured = self.config.queue.clone();
+                    let mut results = vec![];
+                    for preset in presets {
+                        info!("Switching to the {preset:?} queue preset");
+                        self.rebuild_pipeline(preset.apply(&configured))?;
+                        let stats = self.measure_glass_to_glass(
+                            &input,
+                            duration,
+                            flash_interval,
+                            threshold,
+                        )?;
+                        results.push((preset, stats));
+                    }
+                    self.rebuild_pipeline(configured)?;
+
+                    for (preset, stats) in results {
+                        match stats {
+                            Some(stats) => stats.log(&format!("{preset:?}")),
+                            None => warn!("{preset:?}: no flash seen"),
+                        }
+                    }
+                }
+                OutputConfig::Dashboard {
+                    duration_secs,
+                    switch_interval_ms,
+                } => self.run_dashboard(
+                    duration_secs.map(Duration::from_secs),
+                    Duration::from_millis(switch_interval_ms),
+                )?,
+                OutputConfig::Replay {
+                    path,
+                    switch_interval_ms,
+                } => {
+                    self.replay_commands(&path, Duration::from_millis(switch_interval_ms))?;
+                }
+                OutputConfig::TerminalPreview {
+                    duration_secs,
+                    interval_secs,
+                    columns,
+                    switch_interval_ms,
+                } => self.preview_in_terminal(
+                    duration_secs.map(Duration::from_secs),
+                    Duration::from_secs(interval_secs),
+                    columns,
+                    Duration::from_millis(switch_interval_ms),
+                )?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether `component` or one of its children renders the input `id`.
+pub(crate) fn uses_input(component: &Component, id: &str) -> bool {
+    let children: &[Component] = match component {
+        Component::InputStream(input) => return &*input.input_id.0 == id,
+        Component::Image(image) => return &*image.image_id.0 == id,
+        Component::Web(web) => return &*web.instance_id.0 == id || uses_any(&web.children, id),
+        Component::Rescaler(rescaler) => return uses_input(&rescaler.child, id),
+        Component::View(view) => &view.children,
+        Component::Tiles(tiles) => &tiles.children,
+        Component::Shader(shader) => &shader.children,
+        _ => &[],
+    };
+    uses_any(children, id)
+}
+
+/// Inputs registered for the duration of a check, unregistered when dropped if the check
+/// ends early.
+struct RegisteredInputs {
+    pipeline: Arc<Mutex<Pipeline>>,
+    ids: Vec<String>,
+}
+
+impl RegisteredInputs {
+    fn new(pipeline: &Arc<Mutex<Pipeline>>) -> Self {
+        Self {
+            pipeline: pipeline.clone(),
+            ids: vec![],
+        }
+    }
+
+    fn push(&mut self, id: &str) {
+        self.ids.push(id.to_string());
+    }
+
+    fn unregister(&mut self, id: &str) -> Result<()> {
+        self.ids.retain(|registered| registered != id);
+        let mut pipeline = self.pipeline.lock().unwrap();
+        Pipeline::unregister_input(&mut *pipeline, &InputId(Arc::from(id)))?;
+        Ok(())
+    }
+
+    fn unregister_all(&mut self) -> Result<()> {
+        while let Some(id) = self.ids.first().cloned() {
+            self.unregister(&id)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RegisteredInputs {
+    fn drop(&mut self) {
+        let mut pipeline = self.pipeline.lock().unwrap();
+        for id in self.ids.drain(..) {
+            if let Err(err) = Pipeline::unregister_input(&mut *pipeline, &InputId(Arc::from(id))) {
+                warn!("Cannot unregister input: {err:#}");
+            }
+        }
+    }
+}
+
+fn uses_any(components: &[Component], id: &str) -> bool {
+    components.iter().any(|component| uses_input(component, id))
+}
+
+fn switch_finding(pts: Option<Duration>, message: String) -> Finding {
+    Finding {
+        analyzer: "input switching".to_string(),
+        pts,
+        message,
+    }
+}
+
+/// Draws the latency marker over `background`, in the region of [`latency::marker_region`].
+fn marker_scene(background: Component, [r, g, b]: [u8; 3], resolution: Resolution) -> Component {
+    let (x, y, size) = latency::marker_region(resolution.width as u32, resolution.height as u32);
+    let view = |children, position, background_color| {
+        Component::View(ViewComponent {
+            id: None,
+            children,
+            direction: ViewChildrenDirection::Row,
+            position,
+            transition: None,
+            overflow: Overflow::Hidden,
+            background_color,
+            border_radius: compositor_render::scene::BorderRadius::ZERO,
+            border_width: 0.,
+            border_color: RGBAColor(0, 0, 0, 0),
+            box_shadow: vec![],
+            padding: Padding {
+                top: 0.,
+                right: 0.,
+                bottom: 0.,
+                left: 0.,
+            },
+        })
+    };
+    let marker = view(
+        vec![],
+        Position::Absolute(AbsolutePosition {
+            width: Some(size as f32),
+            height: Some(size as f32),
+            position_horizontal: HorizontalPosition::LeftOffset(x as f32),
+            position_vertical: VerticalPosition::TopOffset(y as f32),
+            rotation_degrees: 0.0,
+        }),
+        RGBAColor(r, g, b, 255),
+    );
+    view(
+        vec![fullscreen(background, resolution), marker],
+        Position::Static {
+            width: Some(resolution.width as f32),
+            height: Some(resolution.height as f32),
+        },
+        RGBAColor(0, 0, 0, 0),
+    )
+}
+
+/// Stretches a component over the whole output.
+fn fullscreen(child: Component, resolution: Resolution) -> Component {
+    Component::Rescaler(RescalerComponent {
+        id: None,
+        child: Box::new(child),
+        position: Position::Absolute(AbsolutePosition {
+            width: Some(resolution.width as f32),
+            height: Some(resolution.height as f32),
+            position_horizontal: HorizontalPosition::LeftOffset(0.0),
+            position_vertical: VerticalPosition::TopOffset(0.0),
+            rotation_degrees: 0.0,
+        }),
+        transition: None,
+        mode: RescaleMode::Fill,
+        horizontal_align: HorizontalAlign::Center,
+        vertical_align: VerticalAlign::Center,
+        border_radius: compositor_render::scene::BorderRadius::ZERO,
+        border_width: 0.0,
+        border_color: RGBAColor(0, 0, 0, 0),
+        box_shadow: vec![],
+    })
+}
+
+/// Fits a component in a statically positioned box, keeping its aspect ratio.
+fn fit(child: Component, width: f32, height: f32) -> Component {
+    rescaled(child, RescaleMode::Fit, width, height)
+}
+
+/// Rescales a component to a statically positioned box, in `mode`.
+fn rescaled(child: Component, mode: RescaleMode, width: f32, height: f32) -> Component {
+    Component::Rescaler(RescalerComponent {
+        id: None,
+        child: Box::new(child),
+        position: Position::Static {
+            width: Some(width),
+            height: Some(height),
+        },
+        transition: None,
+        mode,
+        horizontal_align: HorizontalAlign::Center,
+        vertical_align: VerticalAlign::Center,
+        border_radius: compositor_render::scene::BorderRadius::ZERO,
+        border_width: 0.0,
+        border_color: RGBAColor(0, 0, 0, 0),
+        box_shadow: vec![],
+    })
+}
+
+/// Rendered while no input is available, until a WHIP publisher connects.
+fn waiting_component() -> (String, Component) {
+    ("waiting".to_string(), PLACEHOLDER.clone())
+}
+
+/// Whether the GPU of the pipeline can decode with Vulkan Video.
+fn vulkan_decoding(graphics_context: &GraphicsContext) -> bool {
+    #[cfg(feature = "vk-video")]
+    return graphics_context.vulkan_ctx.is_some();
+    #[cfg(not(feature = "vk-video"))]
+    {
+        let _ = graphics_context;
+        false
+    }
+}
+
+fn queue_options(config: &Config) -> QueueInputOptions {
+    QueueInputOptions {
+        required: false,
+        offset: None,
+        buffer_duration: Some(config.queue.input_buffer_duration()),
+    }
+}
+
+/// The component rendering an input alone: images as they are, videos over the whole output.
+fn input_component(
+    input: &InputConfig,
+    noise: &NoiseConfig,
+    resolution: Resolution,
+) -> Result<Component> {
+    let content = input_content(input, noise, resolution)?;
+    match input {
+        InputConfig::Image {
+            correction: None, ..
+        } => Ok(content),
+        _ => Ok(fullscreen(content, resolution)),
+    }
+}
+
+/// The bare component of an input, without any positioning, with its color correction
+/// and noise.
+fn input_content(
+    input: &InputConfig,
+    noise: &NoiseConfig,
+    resolution: Resolution,
+) -> Result<Component> {
+    let content = match input {
+        // SVGs are rasterized at the size they fit the output at, rather than scaled
+        // afterwards, given the dimension they fill so that the renderer keeps their aspect
+        InputConfig::Image { id, path, .. } => {
+            let (width, height) = match svg::is_svg(path) {
+                true => {
+                    let size = svg::size(path, resolution)?;
+                    let filled_width = size.width / resolution.width as f32;
+                    match filled_width >= size.height / resolution.height as f32 {
+                        true => (Some(size.width), None),
+                        false => (None, Some(size.height)),
+                    }
+                }
+                false => (None, None),
+            };
+            Component::Image(ImageComponent {
+                id: None,
+                image_id: RendererId(Arc::from(id.as_str())),
+                width,
+                height,
+            })
+        }
+        InputConfig::Pattern {
+            pattern,
+            square_size,
+            ..
+        } => patterns::component(*pattern, *square_size, resolution),
+        InputConfig::Web { id, .. } => Component::Web(WebViewComponent {
+            id: None,
+            children: vec![],
+            instance_id: RendererId(Arc::from(id.as_str())),
+        }),
+        InputConfig::Mp4 { id, .. }
+        | InputConfig::Rtp { id, .. }
+        | InputConfig::Camera { id, .. }
+        | InputConfig::Decklink { id, .. }
+        | InputConfig::Ndi { id, .. }
+        | InputConfig::Animation { id, .. }
+        | InputConfig::Srt { id, .. }
+        | InputConfig::Counter { id, .. }
+        | InputConfig::Stdin { id, .. }
+        | InputConfig::ImageSequence { id, .. }
+        | InputConfig::Playlist { id, .. }
+        | InputConfig::Raw { id, .. }
+        | InputConfig::Screen { id, .. }
+        | InputConfig::Whip { id, .. } => Component::InputStream(InputStreamComponent {
+            id: None,
+            input_id: InputId(Arc::from(id.as_str())),
+        }),
+    };
+
+    let content = match input.correction() {
+        Some(correction) => correction::wrap(input.id(), correction, content, resolution)?,
+        None => content,
+    };
+    match noise::applies(noise, input.id()) {
+        true => Ok(noise::wrap(
+            noise,
+            content,
+            content_size(input, resolution)?,
+        )),
+        false => Ok(content),
+    }
+}
+
+/// Size the bare content of an input renders at: images without color correction keep
+/// their own, SVGs fit the output and everything else fills it.
+fn content_size(input: &InputConfig, resolution: Resolution) -> Result<Size> {
+    match input {
+        InputConfig::Image {
+            path,
+            correction: None,
+            ..
+        } if svg::is_svg(path) => svg::size(path, resolution),
+        InputConfig::Image {
+            path,
+            correction: None,
+            ..
+        } if !svg::is_svg(path) => {
+            let (width, height) = image::image_dimensions(path)
+                .with_context(|| format!("Cannot read the size of {}", path.display()))?;
+            Ok(Size {
+                width: width as f32,
+                height: height as f32,
+            })
+        }
+        _ => Ok(Size {
+            width: resolution.width as f32,
+            height: resolution.height as f32,
+        }),
+    }
+}
+
+fn image_type(path: &Path) -> Result<ImageType> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("png") => Ok(ImageType::Png),
+        Some("jpg" | "jpeg") => Ok(ImageType::Jpeg),
+        Some("svg") => Ok(ImageType::Svg),
+        _ => Err(anyhow!("Unsupported image {}", path.display())),
     }
 }
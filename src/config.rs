@@ -0,0 +1,2610 @@
+use anyhow::{bail, Context, Result};
+use compositor_pipeline::pipeline::encoder::ffmpeg_h264::EncoderPreset;
+use compositor_pipeline::pipeline::rtp::TransportProtocol;
+use compositor_pipeline::pipeline::VideoDecoder;
+use compositor_render::scene::RescaleMode;
+use compositor_render::{RenderingMode, Resolution};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::analysis::ProcessingRate;
+use crate::cache::ShaderCache;
+use crate::compositor::{HEIGHT, IMAGE, MP4, WIDTH};
+
+/// wgpu features Smelter renders with, requested on every device.
+pub const REQUIRED_WGPU_FEATURES: wgpu::Features =
+    wgpu::Features::PUSH_CONSTANTS.union(wgpu::Features::TEXTURE_BINDING_ARRAY);
+
+/// Declarative description of a run: pipeline settings, inputs and outputs.
+///
+/// Loaded from a TOML or JSON file, every field is optional and defaults
+/// to the original hardcoded setup (the test image and the test MP4).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub resolution: ResolutionConfig,
+    pub framerate: FramerateConfig,
+    pub queue: QueueConfig,
+    pub rendering_mode: RenderingModeConfig,
+    /// Extra wgpu features to request, by name (e.g. `"PIPELINE_CACHE"`).
+    pub wgpu_features: Vec<String>,
+    /// Optional features not to request, by name, to reproduce devices without them. Those in
+    /// [`REQUIRED_WGPU_FEATURES`] cannot be disabled.
+    pub disabled_wgpu_features: Vec<String>,
+    pub inputs: Vec<InputConfig>,
+    /// JSON scene files to alternate between, instead of one scene per input.
+    pub scenes: Vec<SceneConfig>,
+    pub outputs: Vec<OutputConfig>,
+    pub startup_budget: StartupBudgetConfig,
+    pub utilization: UtilizationConfig,
+    pub threads: ThreadsConfig,
+    pub whip_server: WhipServerConfig,
+    /// STUN servers WebRTC connections gather their ICE candidates with, of `whip` inputs
+    /// and outputs, e.g. `"stun:stun.l.google.com:19302"`. None by default, host candidates
+    /// are enough on a LAN.
+    pub stun_servers: Vec<String>,
+    pub web_renderer: WebRendererConfig,
+    pub alerts: AlertsConfig,
+    pub event_log: EventLogConfig,
+    pub canary: CanaryConfig,
+    pub pts_gaps: PtsGapsConfig,
+    pub audio: AudioConfig,
+    pub failure_snapshots: FailureSnapshotsConfig,
+    pub noise: NoiseConfig,
+    pub markers: MarkersConfig,
+    pub encoder: EncoderConfig,
+    pub workarounds: WorkaroundsConfig,
+    pub preview: PreviewConfig,
+    pub session: SessionConfig,
+    /// Runs without a display server, e.g. in CI containers: inputs and outputs needing one
+    /// or an interactive terminal are rejected, and no display is identified.
+    pub headless: bool,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResolutionConfig {
+    pub width: usize,
+    pub height: usize,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FramerateConfig {
+    pub num: u32,
+    pub den: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QueueConfig {
+    pub default_buffer_duration_ms: u64,
+    /// Buffer duration of each input, `None` uses `default_buffer_duration_ms`.
+    pub input_buffer_duration_ms: Option<u64>,
+    pub ahead_of_time_processing: bool,
+    pub run_late_scheduled_events: bool,
+    pub never_drop_output_frames: bool,
+    pub stream_fallback_timeout_ms: u64,
+}
+
+/// Typical queue settings for live inputs, from the lowest latency to the smoothest.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueuePresetConfig {
+    /// No input buffering, frames are shown as soon as they are decoded.
+    LowLatency,
+    /// 80ms of input buffering, the Smelter default.
+    Balanced,
+    /// 500ms of input buffering, absorbing large network jitter.
+    Buffered,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderingModeConfig {
+    #[default]
+    GpuOptimized,
+    CpuOptimized,
+    WebGl,
+}
+
+impl From<RenderingModeConfig> for RenderingMode {
+    fn from(mode: RenderingModeConfig) -> Self {
+        match mode {
+            RenderingModeConfig::GpuOptimized => RenderingMode::GpuOptimized,
+            RenderingModeConfig::CpuOptimized => RenderingMode::CpuOptimized,
+            RenderingModeConfig::WebGl => RenderingMode::WebGl,
+        }
+    }
+}
+
+/// Maximum duration of each startup stage, the run fails if one is exceeded.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StartupBudgetConfig {
+    pub graphics_init_ms: Option<u64>,
+    pub pipeline_create_ms: Option<u64>,
+    pub input_register_ms: Option<u64>,
+    pub first_frame_ms: Option<u64>,
+    pub total_ms: Option<u64>,
+}
+
+/// Server receiving WebRTC streams published with WHIP, required by `whip` inputs.
+///
+/// Publishers send their offer to `http://<host>:<port>/whip/<input ID>`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WhipServerConfig {
+    pub enabled: bool,
+    pub port: u16,
+}
+
+impl Default for WhipServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 9000,
+        }
+    }
+}
+
+/// Chromium-based renderer of web pages, required by `web` inputs.
+///
+/// Needs a build with the `web-renderer` feature.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebRendererConfig {
+    pub enabled: bool,
+    /// Renders the pages on the GPU, rather than with Chromium's software rasterizer.
+    pub gpu: bool,
+}
+
+impl Default for WebRendererConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            gpu: true,
+        }
+    }
+}
+
+/// Where to report a failed run, so unattended runs surface problems promptly.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertsConfig {
+    /// Shows a desktop notification.
+    pub desktop: bool,
+    /// Posts `{"text": ...}` to the URL, e.g. a Slack incoming webhook.
+    pub webhook_url: Option<String>,
+}
+
+/// Border drawn around every scene and checked on every captured frame, see
+/// [`crate::canary`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CanaryConfig {
+    pub enabled: bool,
+    /// `#RRGGBB` of the border.
+    pub color: String,
+    /// Thickness of the border, in pixels.
+    pub width: u32,
+    /// Largest difference allowed on each channel, out of 255.
+    pub tolerance: u8,
+}
+
+impl Default for CanaryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            color: "#FF00FF".to_string(),
+            width: 2,
+            tolerance: 2,
+        }
+    }
+}
+
+/// Settings of the terminal and dashboard previews.
+///
+/// Those saved for the attached display, see [`crate::display`], replace the ones of the
+/// config.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PreviewConfig {
+    /// Whether previews are corrected for the display, from its ICC profile, see
+    /// [`crate::icc`].
+    pub correct_display: bool,
+    /// ICC profile of the display, read from the OS when unset.
+    pub icc_profile: Option<PathBuf>,
+    /// Magnification of the previews, around the center of the frame.
+    pub zoom: f32,
+    /// Share of the dashboard width the preview takes, in percent.
+    pub preview_width: u16,
+    /// File the settings are saved to for each display.
+    pub profiles: PathBuf,
+    /// Display whose settings are restored, by ID or EDID name. The first connected one
+    /// when unset.
+    pub monitor: Option<String>,
+}
+
+impl Default for PreviewConfig {
+    fn default() -> Self {
+        Self {
+            correct_display: false,
+            icc_profile: None,
+            zoom: 1.0,
+            preview_width: 65,
+            profiles: ShaderCache::default_dir().join("display_profiles.toml"),
+            monitor: None,
+        }
+    }
+}
+
+impl PreviewConfig {
+    /// Brings settings restored from a file into the ranges the dashboard steps through.
+    pub fn clamp(&mut self) {
+        self.zoom = match self.zoom.is_nan() {
+            true => 1.0,
+            false => self.zoom.clamp(1.0, 16.0),
+        };
+        self.preview_width = self.preview_width.clamp(20, 90);
+    }
+}
+
+/// Interactive state of the dashboard kept between runs, see [`crate::session`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionConfig {
+    /// Whether the dashboard starts in the state it was left in.
+    pub restore: bool,
+    /// File the state is saved to when the dashboard stops.
+    pub path: PathBuf,
+    /// Script the commands issued in the dashboard are recorded to, see [`crate::macros`].
+    pub record_commands: Option<PathBuf>,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            restore: true,
+            path: ShaderCache::default_dir().join("session.toml"),
+            record_commands: None,
+        }
+    }
+}
+
+/// Squares flashed over recordings on pipeline events, see [`crate::markers`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarkersConfig {
+    pub enabled: bool,
+    /// Side of the square, in pixels.
+    pub size: u32,
+    pub corner: MarkerCornerConfig,
+}
+
+impl Default for MarkersConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            size: 16,
+            corner: MarkerCornerConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkerCornerConfig {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+/// Encoder settings of the MP4 recordings, also used by `dual_recording` outputs, which
+/// always encode H264.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EncoderConfig {
+    /// H265 recordings are encoded by `ffmpeg`, see [`crate::hevc::HevcEncoder`], as are
+    /// the ProRes and FFV1 ones, see [`crate::lossless::LosslessEncoder`].
+    pub codec: RecordCodecConfig,
+    /// 8, or 10 for the Main10 profile of H265, or 16-bit frames for the lossless codecs.
+    pub bit_depth: u8,
+    pub preset: EncoderPresetConfig,
+    /// Constant rate factor, 0 to 51, lower is better. Exclusive with `bitrate_kbps`.
+    pub crf: Option<u8>,
+    /// Average bitrate, in kilobits per second.
+    pub bitrate_kbps: Option<u32>,
+    pub profile: Option<H264ProfileConfig>,
+    /// Frames between keyframes.
+    pub keyframe_interval: Option<u32>,
+    /// FFmpeg options of the libx264 or libx265 encoder, e.g. `tune = "film"` or
+    /// `x264-params = "aq-mode=3"`, applied after the settings above.
+    pub raw_options: BTreeMap<String, String>,
+    /// What is recorded when the encoder cannot start, e.g. without `ffmpeg` installed.
+    pub fallback: EncoderFallbackConfig,
+}
+
+impl Default for EncoderConfig {
+    fn default() -> Self {
+        Self {
+            codec: RecordCodecConfig::default(),
+            bit_depth: 8,
+            preset: EncoderPresetConfig::default(),
+            crf: None,
+            bitrate_kbps: None,
+            profile: None,
+            keyframe_interval: None,
+            raw_options: BTreeMap::new(),
+            fallback: EncoderFallbackConfig::default(),
+        }
+    }
+}
+
+impl EncoderConfig {
+    /// The settings as FFmpeg options of the encoder, besides the preset.
+    pub fn raw_options(&self) -> Vec<(String, String)> {
+        let mut options = vec![];
+        if let Some(crf) = self.crf {
+            options.push(("crf".to_string(), crf.to_string()));
+        }
+        if let Some(bitrate) = self.bitrate_kbps {
+            options.push(("b".to_string(), format!("{bitrate}k")));
+        }
+        if let Some(profile) = self.profile {
+            options.push(("profile".to_string(), profile.name().to_string()));
+        }
+        if let Some(interval) = self.keyframe_interval {
+            options.push(("g".to_string(), interval.to_string()));
+        }
+        options.extend(
+            self.raw_options
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+        options
+    }
+
+    /// `level_idc` set with the `level` raw option, `4.1` or `41`.
+    pub fn level_idc(&self) -> Option<u8> {
+        let level = self.raw_options.get("level")?;
+        match level.split_once('.') {
+            Some((major, minor)) => {
+                Some(major.parse::<u8>().ok()? * 10 + minor.parse::<u8>().ok()?)
+            }
+            None => level.parse().ok(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.crf.is_some_and(|crf| crf > 51) {
+            bail!("Encoder crf must be between 0 and 51");
+        }
+        if self.crf.is_some() && self.bitrate_kbps.is_some() {
+            bail!("Encoder crf and bitrate_kbps cannot both be set");
+        }
+        if self.bitrate_kbps == Some(0) || self.keyframe_interval == Some(0) {
+            bail!("Encoder bitrate_kbps and keyframe_interval must be at least 1");
+        }
+        match (self.codec, self.bit_depth) {
+            (_, 8) => {}
+            (RecordCodecConfig::H264, 10) => bail!("10-bit recordings need the h265 codec"),
+            (_, 10) => {}
+            (_, depth) => bail!("Unsupported encoder bit_depth {depth}, expected 8 or 10"),
+        }
+        if self.codec == RecordCodecConfig::H265 && self.profile.is_some() {
+            bail!("Encoder profile only applies to h264, h265 picks main or main10");
+        }
+        if self.codec.is_lossless()
+            && (self.crf.is_some()
+                || self.bitrate_kbps.is_some()
+                || self.profile.is_some()
+                || self.keyframe_interval.is_some()
+                || !self.raw_options.is_empty())
+        {
+            bail!(
+                "Encoder crf, bitrate_kbps, profile, keyframe_interval and raw_options do not \
+                 apply to lossless codecs, whose settings are those of archives"
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordCodecConfig {
+    #[default]
+    H264,
+    H265,
+    /// ProRes 4444 in a MOV file, see [`crate::lossless::LosslessEncoder`].
+    Prores,
+    /// FFV1 in a Matroska file, see [`crate::lossless::LosslessEncoder`].
+    Ffv1,
+}
+
+impl RecordCodecConfig {
+    /// Whether the codec keeps the chroma of every pixel, for archival.
+    pub fn is_lossless(self) -> bool {
+        matches!(self, RecordCodecConfig::Prores | RecordCodecConfig::Ffv1)
+    }
+
+    /// Extension of the files the codec is recorded to.
+    pub fn extension(self) -> &'static str {
+        match self {
+            RecordCodecConfig::H264 | RecordCodecConfig::H265 => "mp4",
+            RecordCodecConfig::Prores => "mov",
+            RecordCodecConfig::Ffv1 => "mkv",
+        }
+    }
+}
+
+/// Recording made when the encoder of `encoder.codec` cannot start.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EncoderFallbackConfig {
+    /// The frames are saved to `{stem}_frames/frame_{index:06}.png` next to the recording,
+    /// see [`crate::fallback::PngSequenceSink`], and the run goes on.
+    #[default]
+    PngSequence,
+    /// The run fails.
+    None,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EncoderPresetConfig {
+    Ultrafast,
+    Superfast,
+    Veryfast,
+    Faster,
+    Fast,
+    #[default]
+    Medium,
+    Slow,
+    Slower,
+    Veryslow,
+    Placebo,
+}
+
+impl EncoderPresetConfig {
+    /// Name of the preset for libx264.
+    pub fn name(self) -> &'static str {
+        match self {
+            EncoderPresetConfig::Ultrafast => "ultrafast",
+            EncoderPresetConfig::Superfast => "superfast",
+            EncoderPresetConfig::Veryfast => "veryfast",
+            EncoderPresetConfig::Faster => "faster",
+            EncoderPresetConfig::Fast => "fast",
+            EncoderPresetConfig::Medium => "medium",
+            EncoderPresetConfig::Slow => "slow",
+            EncoderPresetConfig::Slower => "slower",
+            EncoderPresetConfig::Veryslow => "veryslow",
+            EncoderPresetConfig::Placebo => "placebo",
+        }
+    }
+}
+
+impl From<EncoderPresetConfig> for EncoderPreset {
+    fn from(preset: EncoderPresetConfig) -> Self {
+        match preset {
+            EncoderPresetConfig::Ultrafast => EncoderPreset::Ultrafast,
+            EncoderPresetConfig::Superfast => EncoderPreset::Superfast,
+            EncoderPresetConfig::Veryfast => EncoderPreset::Veryfast,
+            EncoderPresetConfig::Faster => EncoderPreset::Faster,
+            EncoderPresetConfig::Fast => EncoderPreset::Fast,
+            EncoderPresetConfig::Medium => EncoderPreset::Medium,
+            EncoderPresetConfig::Slow => EncoderPreset::Slow,
+            EncoderPresetConfig::Slower => EncoderPreset::Slower,
+            EncoderPresetConfig::Veryslow => EncoderPreset::Veryslow,
+            EncoderPresetConfig::Placebo => EncoderPreset::Placebo,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum H264ProfileConfig {
+    Baseline,
+    Main,
+    High,
+}
+
+impl H264ProfileConfig {
+    /// `profile_idc` of the streams encoded with the profile.
+    pub fn idc(self) -> u8 {
+        match self {
+            H264ProfileConfig::Baseline => 66,
+            H264ProfileConfig::Main => 77,
+            H264ProfileConfig::High => 100,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            H264ProfileConfig::Baseline => "baseline",
+            H264ProfileConfig::Main => "main",
+            H264ProfileConfig::High => "high",
+        }
+    }
+}
+
+/// Encoder of a `webm` output.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebmCodecConfig {
+    /// libvpx-vp9, constant quality.
+    #[default]
+    Vp9,
+    /// libaom-av1, constant quality.
+    Av1,
+}
+
+impl WebmCodecConfig {
+    /// FFmpeg encoder and settings.
+    pub fn ffmpeg_args(self) -> &'static [&'static str] {
+        match self {
+            WebmCodecConfig::Vp9 => &[
+                "-c:v",
+                "libvpx-vp9",
+                "-crf",
+                "31",
+                "-b:v",
+                "0",
+                "-row-mt",
+                "1",
+                "-deadline",
+                "good",
+                "-cpu-used",
+                "4",
+            ],
+            WebmCodecConfig::Av1 => &[
+                "-c:v",
+                "libaom-av1",
+                "-crf",
+                "30",
+                "-b:v",
+                "0",
+                "-row-mt",
+                "1",
+                "-cpu-used",
+                "6",
+            ],
+        }
+    }
+}
+
+/// Container of the segments of an HLS stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HlsSegmentConfig {
+    /// MPEG-TS segments, played by every HLS player.
+    #[default]
+    Ts,
+    /// Fragmented MP4 segments after an `init.mp4`, as for LL-HLS and DASH players.
+    Fmp4,
+}
+
+/// GPU driver workarounds, see [`crate::workarounds`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkaroundsConfig {
+    /// Whether the workarounds known for the adapter are enabled.
+    pub auto: bool,
+    /// Workarounds enabled on every adapter.
+    pub enable: Vec<WorkaroundConfig>,
+    /// Workarounds never enabled, even when known for the adapter.
+    pub disable: Vec<WorkaroundConfig>,
+    /// Adapters needing workarounds, besides the known ones.
+    pub rules: Vec<WorkaroundRuleConfig>,
+}
+
+impl Default for WorkaroundsConfig {
+    fn default() -> Self {
+        Self {
+            auto: true,
+            enable: vec![],
+            disable: vec![],
+            rules: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkaroundConfig {
+    /// Frames are encoded to sRGB on the CPU when read back, instead of by a render pass.
+    CpuReadbackEncoding,
+    /// No pipeline cache, neither in the pipeline nor on disk.
+    NoPipelineCache,
+}
+
+impl WorkaroundConfig {
+    pub fn name(self) -> &'static str {
+        match self {
+            WorkaroundConfig::CpuReadbackEncoding => "cpu_readback_encoding",
+            WorkaroundConfig::NoPipelineCache => "no_pipeline_cache",
+        }
+    }
+}
+
+/// Adapters a workaround applies to, matching every set field.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkaroundRuleConfig {
+    pub workaround: WorkaroundConfig,
+    /// PCI vendor ID, e.g. `0x10DE` for NVIDIA.
+    #[serde(default)]
+    pub vendor: Option<u32>,
+    /// Part of the adapter name, case-insensitive.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Part of the driver name or info, case-insensitive.
+    #[serde(default)]
+    pub driver: Option<String>,
+    /// Driver versions before this one, e.g. `"24.1"`, compared with the first version
+    /// number of the driver info.
+    #[serde(default)]
+    pub driver_below: Option<String>,
+    /// Why the workaround is needed, logged when it is enabled.
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Mixing of the inputs with `audio = true`, see [`crate::audio::AudioMixer`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioConfig {
+    pub channels: AudioChannelsConfig,
+    pub strategy: MixingStrategyConfig,
+    /// Gain of each input by ID, 1 when unset.
+    pub volumes: HashMap<String, f32>,
+    /// IDs of the inputs left out of the mix.
+    pub muted: Vec<String>,
+    /// Whether dashboards stay silent, instead of playing the mix on the default device.
+    pub mute: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioChannelsConfig {
+    Mono,
+    #[default]
+    Stereo,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MixingStrategyConfig {
+    /// Sums the inputs, clipping the peaks.
+    #[default]
+    SumClip,
+    /// Sums the inputs, scaled down when they would clip.
+    SumScale,
+}
+
+/// File format frames are saved in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DumpFormatConfig {
+    /// 8-bit sRGB PNG.
+    #[default]
+    Png,
+    /// 16-bit sRGB PNG, encoded from the linear values without 8-bit rounding.
+    Png16,
+    /// 32-bit float linear OpenEXR.
+    Exr,
+}
+
+impl DumpFormatConfig {
+    pub fn extension(self) -> &'static str {
+        match self {
+            DumpFormatConfig::Png | DumpFormatConfig::Png16 => "png",
+            DumpFormatConfig::Exr => "exr",
+        }
+    }
+}
+
+/// Gaps in the PTS of captured frames, see [`crate::analysis::pts_gaps`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PtsGapsConfig {
+    /// Whether the run fails when frames are missing, instead of only reporting them.
+    pub fail: bool,
+}
+
+/// Frames of failed checks saved with the failing regions marked, see [`crate::annotate`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FailureSnapshotsConfig {
+    pub enabled: bool,
+    /// Directory the PNG files are written to, created if needed.
+    pub dir: PathBuf,
+}
+
+/// Gaussian noise added over inputs, see [`crate::noise`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NoiseConfig {
+    pub enabled: bool,
+    /// Standard deviation of the noise of each channel, out of 255.
+    pub sigma: [f32; 3],
+    /// Drawn at startup and logged when unset.
+    pub seed: Option<u32>,
+    /// Inputs the noise is added over, all of them when empty.
+    pub inputs: Vec<String>,
+    /// Whether the noise changes every frame, rather than staying the same.
+    pub temporal: bool,
+}
+
+/// Machine-readable log of the run, see [`crate::event_log`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventLogConfig {
+    pub enabled: bool,
+    /// JSON lines file the events are written to.
+    pub path: PathBuf,
+}
+
+/// CPU/GPU utilization sampling while the outputs run.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UtilizationConfig {
+    pub enabled: bool,
+    pub interval_ms: u64,
+    /// CSV file the samples are written to, plotted next to it with the `svg` extension.
+    pub path: PathBuf,
+}
+
+/// Priority and CPU affinity of a group of threads.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThreadOptions {
+    /// Cross-platform priority from 0 (lowest) to 99 (highest), may require privileges.
+    pub priority: Option<u8>,
+    /// CPU cores the threads are pinned to, all cores when empty.
+    pub cores: Vec<usize>,
+}
+
+/// Thread groups of a run.
+///
+/// The pipeline spawns its own threads (queue, renderer, decoders, encoders), which
+/// inherit the priority and affinity of the thread creating them. Options are thus
+/// applied to the current thread while the pipeline or an encoder is created.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThreadsConfig {
+    /// Queue, renderer and decoder threads, spawned when the pipeline and inputs are created.
+    pub pipeline: ThreadOptions,
+    /// Encoder threads, spawned when a recording starts.
+    pub encoding: ThreadOptions,
+    /// The thread driving the run: switching scenes and receiving raw frames.
+    pub capture: ThreadOptions,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum InputConfig {
+    /// PNG, JPEG or SVG image, SVGs are rasterized at the output resolution.
+    Image {
+        id: String,
+        path: PathBuf,
+        correction: Option<ColorCorrectionConfig>,
+    },
+    Mp4 {
+        id: String,
+        path: PathBuf,
+        #[serde(default = "default_true")]
+        should_loop: bool,
+        #[serde(default)]
+        decoder: DecoderConfig,
+        /// Segment of the file played instead of the whole file.
+        trim: Option<TrimConfig>,
+        /// Tracks played instead of the first video and audio tracks.
+        #[serde(default)]
+        tracks: Option<TrackSelectionConfig>,
+        /// Whether the audio track is mixed into recordings while the input is shown.
+        #[serde(default)]
+        audio: bool,
+        correction: Option<ColorCorrectionConfig>,
+    },
+    /// Live stream received over RTP, video only.
+    Rtp {
+        id: String,
+        port: u16,
+        #[serde(default)]
+        transport: RtpTransportConfig,
+        #[serde(default)]
+        codec: RtpCodecConfig,
+        /// H264 decoder, FFmpeg when not set.
+        decoder: Option<DecoderConfig>,
+        correction: Option<ColorCorrectionConfig>,
+    },
+    /// SRT stream, received and scaled to the output resolution by `ffmpeg`.
+    Srt {
+        id: String,
+        /// `host:port` to connect to as a caller, or to bind as a listener.
+        address: String,
+        #[serde(default)]
+        mode: SrtModeConfig,
+        /// Encryption passphrase, 10 to 79 characters.
+        passphrase: Option<String>,
+        /// Receiver latency, the SRT default (120ms) when unset.
+        latency_ms: Option<u64>,
+        correction: Option<ColorCorrectionConfig>,
+    },
+    /// Web page rendered by Chromium, e.g. an HTML color test page.
+    Web {
+        id: String,
+        url: String,
+        /// Size of the page, the output resolution when unset.
+        resolution: Option<ResolutionConfig>,
+        correction: Option<ColorCorrectionConfig>,
+    },
+    /// Video capture device, see [`crate::camera::list`].
+    Camera {
+        id: String,
+        /// Device path or name, the first device when unset.
+        device: Option<String>,
+        /// Requested mode, the driver picks the closest one it supports.
+        resolution: Option<ResolutionConfig>,
+        framerate: Option<FramerateConfig>,
+        correction: Option<ColorCorrectionConfig>,
+    },
+    /// Blackmagic DeckLink capture card, in builds with the `decklink` feature.
+    ///
+    /// Which card input to open can be selected by any combination of the options below,
+    /// the first one when none is set.
+    Decklink {
+        id: String,
+        /// Index of the input on a card with several of them.
+        subdevice_index: Option<u32>,
+        /// Name shown by Blackmagic Desktop Video Setup.
+        display_name: Option<String>,
+        /// Persistent ID of the card input, usually written in hexadecimal (`0x...`).
+        persistent_id: Option<u32>,
+        correction: Option<ColorCorrectionConfig>,
+    },
+    /// NDI source on the local network, see [`crate::ndi::discover`].
+    Ndi {
+        id: String,
+        /// Name of the source, e.g. `"STUDIO (Preview Monitor)"`.
+        source: String,
+        correction: Option<ColorCorrectionConfig>,
+    },
+    /// Test pattern synthesized on the GPU, see [`crate::patterns`].
+    Pattern {
+        id: String,
+        pattern: PatternConfig,
+        /// Size of the checkerboard squares, in pixels.
+        #[serde(default = "default_square_size")]
+        square_size: u32,
+        correction: Option<ColorCorrectionConfig>,
+    },
+    /// Frames carrying their index, at a framerate that can differ from the output one,
+    /// for [`OutputConfig::Cadence`].
+    Counter {
+        id: String,
+        framerate: FramerateConfig,
+        correction: Option<ColorCorrectionConfig>,
+    },
+    /// Animated GIF or WebP file, played at its own frame delays.
+    Animation {
+        id: String,
+        path: PathBuf,
+        #[serde(default = "default_true")]
+        should_loop: bool,
+        correction: Option<ColorCorrectionConfig>,
+    },
+    /// MP4 files and images played one after the other, see [`crate::playlist`].
+    Playlist {
+        id: String,
+        items: Vec<PlaylistItemConfig>,
+        #[serde(default = "default_true")]
+        should_loop: bool,
+        correction: Option<ColorCorrectionConfig>,
+    },
+    /// Numbered images of a directory (PNG, JPEG or scene-linear EXR), played as a video.
+    ImageSequence {
+        id: String,
+        path: PathBuf,
+        /// Output framerate when unset.
+        framerate: Option<FramerateConfig>,
+        #[serde(default = "default_true")]
+        should_loop: bool,
+        correction: Option<ColorCorrectionConfig>,
+    },
+    /// Raw frames read from stdin, e.g. piped from `ffmpeg -f rawvideo`.
+    Stdin {
+        id: String,
+        resolution: ResolutionConfig,
+        framerate: FramerateConfig,
+        #[serde(default)]
+        pixel_format: PixelFormatConfig,
+        correction: Option<ColorCorrectionConfig>,
+    },
+    /// Frames pushed by the embedding code with [`crate::Compositor::push_raw_frame`].
+    Raw {
+        id: String,
+        correction: Option<ColorCorrectionConfig>,
+    },
+    /// Captures of a monitor, at the output framerate.
+    Screen {
+        id: String,
+        /// Monitor name, the primary monitor when unset.
+        monitor: Option<String>,
+        correction: Option<ColorCorrectionConfig>,
+    },
+    /// WebRTC stream published through the WHIP server, video only.
+    ///
+    /// Unless scenes are configured, the input joins the inputs to cycle through once
+    /// its publisher connects, and leaves them when it disconnects.
+    Whip {
+        id: String,
+        /// Token publishers must present, generated and logged when unset.
+        bearer_token: Option<String>,
+        correction: Option<ColorCorrectionConfig>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RtpTransportConfig {
+    #[default]
+    Udp,
+    /// Listens for a single TCP connection, packets framed as in RFC 4571.
+    TcpServer,
+}
+
+impl From<RtpTransportConfig> for TransportProtocol {
+    fn from(transport: RtpTransportConfig) -> Self {
+        match transport {
+            RtpTransportConfig::Udp => TransportProtocol::Udp,
+            RtpTransportConfig::TcpServer => TransportProtocol::TcpServer,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternConfig {
+    /// SMPTE EG 1 color bars, 75% amplitude.
+    SmpteBars,
+    /// Horizontal gray ramp, from black to white.
+    Ramp,
+    /// Circular zone plate, reaching the Nyquist frequency at the edges.
+    ZonePlate,
+    Checkerboard,
+    /// Dark to light edge through the center, 5° from vertical, for sharpness measurements.
+    SlantedEdge,
+}
+
+/// How a rescaled component fills its box.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RescaleModeConfig {
+    /// Scaled to fit inside, letterboxed or pillarboxed.
+    #[default]
+    Fit,
+    /// Scaled to cover it, cropped.
+    Fill,
+}
+
+impl From<RescaleModeConfig> for RescaleMode {
+    fn from(mode: RescaleModeConfig) -> Self {
+        match mode {
+            RescaleModeConfig::Fit => RescaleMode::Fit,
+            RescaleModeConfig::Fill => RescaleMode::Fill,
+        }
+    }
+}
+
+/// Layout of raw frames.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PixelFormatConfig {
+    #[default]
+    Rgba,
+    /// Planar 4:2:0 YUV, BT.709 limited range.
+    Yuv420p,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SrtModeConfig {
+    /// Connects to a remote listener.
+    Caller,
+    /// Waits for a remote caller.
+    #[default]
+    Listener,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RtpCodecConfig {
+    #[default]
+    H264,
+    Vp8,
+}
+
+/// Correction applied to an input before composition, to normalize a known-bad source.
+///
+/// The matrix and offset are applied first, then the LUT.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorCorrectionConfig {
+    /// Row-major RGB matrix.
+    pub matrix: [[f32; 3]; 3],
+    pub offset: [f32; 3],
+    /// 3D LUT as a PNG strip of N slices of NxN pixels: red along x, green along y,
+    /// blue selecting the slice.
+    pub lut: Option<PathBuf>,
+    /// Blend between the matrix output (0) and the LUT output (1).
+    pub lut_strength: f32,
+}
+
+impl Default for ColorCorrectionConfig {
+    fn default() -> Self {
+        Self {
+            matrix: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            offset: [0.0; 3],
+            lut: None,
+            lut_strength: 1.0,
+        }
+    }
+}
+
+/// Entry of a playlist input.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlaylistItemConfig {
+    /// MP4 file, or image decoded by the `image` crate.
+    pub path: PathBuf,
+    /// How long the item plays: MP4 files play to their end and images for 5s when unset.
+    pub duration_ms: Option<u64>,
+}
+
+/// Tolerance of some frames of a golden sequence, e.g. looser for those in the middle of a
+/// transition.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FrameToleranceConfig {
+    /// First frame it applies to, from 0.
+    pub from: usize,
+    /// Last frame it applies to, included.
+    pub to: usize,
+    /// Largest difference allowed on each channel, out of 255.
+    pub tolerance: u8,
+}
+
+/// Which golden frame each rendered frame of a sequence is compared with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoldenMatchingConfig {
+    /// The one at the same position, for sequences saved at the framerate of the run.
+    #[default]
+    Index,
+    /// The one with the nearest PTS, so a sequence saved at one framerate covers others.
+    /// Frames are rendered until the end of the sequence.
+    NearestPts,
+}
+
+/// Tracks of a multi-track MP4 file an input plays, see [`crate::media::select_mp4_tracks`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrackSelectionConfig {
+    /// Index of the video track among the video tracks, from 0.
+    pub video: usize,
+    /// Index of the audio track among the audio tracks, from 0. None when unset.
+    pub audio: Option<usize>,
+}
+
+/// Segment of an MP4 file, see [`crate::media::trim_mp4`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrimConfig {
+    /// Start of the segment, moved back to the keyframe at or before it.
+    pub start_ms: u64,
+    /// Length of the segment, up to the end of the file if unset.
+    pub duration_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecoderConfig {
+    #[default]
+    FfmpegH264,
+    /// Hardware decoding, requires the `vk-video` feature and a Vulkan Video capable GPU.
+    VulkanH264,
+    /// Hardware decoding when available, ffmpeg otherwise.
+    Auto,
+}
+
+/// Analyzer plugin, see [`crate::analysis::plugin`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    /// Shared library implementing the plugin.
+    pub path: PathBuf,
+    /// Passed to the plugin as JSON.
+    #[serde(default)]
+    pub config: serde_json::Value,
+    /// Frames the plugin is fed, e.g. `{ every_nth = 10 }` for expensive metrics.
+    #[serde(default)]
+    pub rate: RateConfig,
+}
+
+/// See [`crate::analysis::ProcessingRate`].
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateConfig {
+    #[default]
+    EveryFrame,
+    EveryNth(usize),
+    OncePerScene,
+}
+
+impl From<RateConfig> for ProcessingRate {
+    fn from(rate: RateConfig) -> Self {
+        match rate {
+            RateConfig::EveryFrame => ProcessingRate::EveryFrame,
+            RateConfig::EveryNth(n) => ProcessingRate::EveryNth(n),
+            RateConfig::OncePerScene => ProcessingRate::OncePerScene,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneConfig {
+    pub id: String,
+    /// Component tree, see [`crate::scene::SceneComponent`].
+    pub path: PathBuf,
+}
+
+/// One of the outputs of a `simultaneous` output, see [`crate::outputs`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NamedOutputConfig {
+    /// Name of the output in logs and the event log.
+    pub name: String,
+    pub output: NamedOutputKindConfig,
+    /// Resolution of the output, independent of that of the config, used when unset.
+    #[serde(default)]
+    pub resolution: Option<ResolutionConfig>,
+    /// How the inputs, rendered at the resolution of the config, are scaled to that of the
+    /// output.
+    #[serde(default)]
+    pub scaling: RescaleModeConfig,
+    /// Inputs the output cycles through, by ID. All of them when empty.
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    /// Whether the output switches input along with the others, or keeps showing its
+    /// first one.
+    #[serde(default = "default_alternate")]
+    pub alternate: bool,
+}
+
+/// Where the frames of a [`NamedOutputConfig`] go.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum NamedOutputKindConfig {
+    /// Read back by the harness, the last frame saved to `path` as a PNG file.
+    Preview {
+        #[serde(default)]
+        path: Option<PathBuf>,
+    },
+    /// Recorded to an MP4 file, H264 encoded with the `encoder` settings.
+    Mp4 { path: PathBuf },
+    /// Published with WHIP, H264 encoded with the `encoder` settings.
+    Whip {
+        endpoint_url: String,
+        #[serde(default)]
+        bearer_token: Option<String>,
+    },
+}
+
+impl NamedOutputKindConfig {
+    /// File the output writes, resolved against the directory of the config.
+    fn path_mut(&mut self) -> Option<&mut PathBuf> {
+        match self {
+            NamedOutputKindConfig::Preview { path } => path.as_mut(),
+            NamedOutputKindConfig::Mp4 { path } => Some(path),
+            NamedOutputKindConfig::Whip { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum OutputConfig {
+    /// Renders each input alone and saves a single frame of it,
+    /// `{id}` in the template is replaced by the input ID.
+    Snapshots {
+        #[serde(default = "default_snapshot_template")]
+        path_template: String,
+        #[serde(default)]
+        format: DumpFormatConfig,
+    },
+    /// Records an MP4 file cycling through the inputs.
+    ///
+    /// With `segment_secs` or `segment_mb`, the recording goes to numbered files next to
+    /// `path` instead, `{stem}_0001.mp4`, `{stem}_0002.mp4`, ... each closed once it is that
+    /// long or large.
+    ///
+    /// With `verify_metadata`, the codec parameters of the file are read back once it is
+    /// recorded and the run fails if they differ from the configuration, see
+    /// [`crate::container`].
+    ///
+    /// The recording lasts `duration_secs`, or `cycles` complete cycles through the inputs,
+    /// so that it ends on a whole iteration of the scenes rather than mid-cycle.
+    Mp4 {
+        path: PathBuf,
+        #[serde(default)]
+        duration_secs: Option<u64>,
+        #[serde(default)]
+        cycles: Option<u32>,
+        #[serde(default = "default_switch_interval_ms")]
+        switch_interval_ms: u64,
+        #[serde(default)]
+        segment_secs: Option<u64>,
+        #[serde(default)]
+        segment_mb: Option<u64>,
+        #[serde(default)]
+        verify_metadata: bool,
+    },
+    /// Records an MP4 file and a PNG sequence of the same frames, cycling through the inputs.
+    ///
+    /// See [`crate::dual_record::DualRecorder`].
+    DualRecording {
+        path: PathBuf,
+        frames_dir: PathBuf,
+        duration_secs: u64,
+        #[serde(default = "default_switch_interval_ms")]
+        switch_interval_ms: u64,
+    },
+    /// Records a WebM file in VP9 or AV1, cycling through the inputs.
+    ///
+    /// See [`crate::webm::WebmRecorder`].
+    Webm {
+        path: PathBuf,
+        #[serde(default)]
+        codec: WebmCodecConfig,
+        duration_secs: u64,
+        #[serde(default = "default_switch_interval_ms")]
+        switch_interval_ms: u64,
+    },
+    /// Captures an animated GIF at `fps`, with a palette of up to `max_colors`, cycling
+    /// through the inputs.
+    ///
+    /// See [`crate::gif::GifWriter`].
+    Gif {
+        path: PathBuf,
+        duration_secs: u64,
+        #[serde(default = "default_gif_fps")]
+        fps: u32,
+        #[serde(default = "default_gif_max_colors")]
+        max_colors: u16,
+        #[serde(default = "default_switch_interval_ms")]
+        switch_interval_ms: u64,
+    },
+    /// Records `duration_secs` of the inputs once, then encodes it with every preset and
+    /// CRF of the matrix to `dir`, writing the size, PSNR, CIEDE2000 and VMAF of each encode
+    /// to `dir/rd_report.csv`.
+    ///
+    /// See [`crate::sweep`].
+    EncoderSweep {
+        dir: PathBuf,
+        duration_secs: u64,
+        #[serde(default = "default_sweep_presets")]
+        presets: Vec<EncoderPresetConfig>,
+        #[serde(default = "default_sweep_crfs")]
+        crfs: Vec<u8>,
+        #[serde(default)]
+        vmaf: bool,
+        #[serde(default = "default_switch_interval_ms")]
+        switch_interval_ms: u64,
+    },
+    /// Writes an HLS stream to `dir`, its playlist `index.m3u8` listing the segments,
+    /// cycling through the inputs.
+    ///
+    /// See [`crate::hls::HlsWriter`].
+    Hls {
+        dir: PathBuf,
+        #[serde(default)]
+        segment_type: HlsSegmentConfig,
+        #[serde(default = "default_hls_segment_secs")]
+        segment_secs: u64,
+        /// Segments kept in the playlist, older ones are deleted. All of them when unset.
+        #[serde(default)]
+        playlist_size: Option<usize>,
+        duration_secs: u64,
+        #[serde(default = "default_switch_interval_ms")]
+        switch_interval_ms: u64,
+    },
+    /// Sends the output over RTP to `address`, cycling through the inputs, e.g. to the
+    /// `rtp` input of another instance for a network loopback test.
+    ///
+    /// See [`crate::rtp_sender::RtpSender`].
+    Rtp {
+        address: String,
+        port: u16,
+        /// Dynamic payload type of the stream, 96 is what `rtp` inputs expect.
+        #[serde(default = "default_payload_type")]
+        payload_type: u8,
+        #[serde(default)]
+        codec: RtpCodecConfig,
+        /// Where to write the SDP description of the stream, for `ffplay`.
+        #[serde(default)]
+        sdp: Option<PathBuf>,
+        duration_secs: u64,
+        #[serde(default = "default_switch_interval_ms")]
+        switch_interval_ms: u64,
+    },
+    /// Publishes the output as an NDI source named `name`, cycling through the inputs, for
+    /// hardware monitors and NDI tools on the network.
+    ///
+    /// See [`crate::ndi::NdiSender`], only available in builds with the `ndi` feature.
+    Ndi {
+        #[serde(default = "default_ndi_name")]
+        name: String,
+        duration_secs: u64,
+        #[serde(default = "default_switch_interval_ms")]
+        switch_interval_ms: u64,
+    },
+    /// Runs several outputs at once for `duration_secs`, each cycling through its own
+    /// inputs at its own resolution, switching every `switch_interval_ms`.
+    ///
+    /// See [`crate::outputs::OutputRegistry`].
+    Simultaneous {
+        outputs: Vec<NamedOutputConfig>,
+        duration_secs: u64,
+        #[serde(default = "default_switch_interval_ms")]
+        switch_interval_ms: u64,
+    },
+    /// Publishes the output to a WebRTC server with WHIP, H264 encoded with the `encoder`
+    /// settings, cycling through the inputs.
+    ///
+    /// ICE candidates are gathered with the `stun_servers`.
+    Whip {
+        endpoint_url: String,
+        #[serde(default)]
+        bearer_token: Option<String>,
+        duration_secs: u64,
+        #[serde(default = "default_switch_interval_ms")]
+        switch_interval_ms: u64,
+    },
+    /// Renders an input or scene for `duration_secs`, saving every `stride`th frame to a
+    /// PNG file of `dir`.
+    ///
+    /// In the template, `{index}` is replaced by the index of the frame among all the
+    /// output frames, on 6 digits, and `{pts_ms}` by its PTS in milliseconds.
+    FrameSequence {
+        scene: String,
+        dir: PathBuf,
+        duration_secs: u64,
+        #[serde(default = "default_stride")]
+        stride: usize,
+        #[serde(default = "default_frame_template")]
+        template: String,
+        #[serde(default)]
+        format: DumpFormatConfig,
+    },
+    /// Renders an input or scene for `duration_secs`, writing every frame to the
+    /// uncompressed YUV4MPEG2 file `path`.
+    ///
+    /// See [`crate::y4m::Y4mWriter`].
+    Y4m {
+        scene: String,
+        path: PathBuf,
+        duration_secs: u64,
+    },
+    /// Renders a JSON scene file, and renders it again each time the file changes.
+    ///
+    /// Every render is saved to `snapshot_path`, an image viewer reloading it
+    /// on changes makes a preview of the scene being edited.
+    WatchScene {
+        scene: PathBuf,
+        #[serde(default = "default_watch_snapshot_path")]
+        snapshot_path: PathBuf,
+        /// Watches forever when unset.
+        duration_secs: Option<u64>,
+    },
+    /// Plays an MP4 input from its start, checking for black flashes, duplicated
+    /// frames or color pops where it loops. The run fails if any is found.
+    LoopCheck {
+        input: String,
+        #[serde(default = "default_loops")]
+        loops: u32,
+        /// Distance to the predicted loop point within which glitches are reported.
+        #[serde(default = "default_loop_tolerance_ms")]
+        tolerance_ms: u64,
+    },
+    /// Renders an input, or a scene when scenes are configured, feeding every frame
+    /// to analyzer plugins. Cycles through all of them every `switch_interval_ms` when
+    /// `input` is unset, plugins sampling once per scene being fed after each switch.
+    Analyze {
+        #[serde(default)]
+        input: Option<String>,
+        duration_secs: u64,
+        #[serde(default = "default_switch_interval_ms")]
+        switch_interval_ms: u64,
+        plugins: Vec<PluginConfig>,
+        /// Whether the run fails when a plugin reports a finding.
+        #[serde(default = "default_true")]
+        fail_on_findings: bool,
+    },
+    /// Fits an input in the output and checks the color of the bars around it.
+    ///
+    /// See [`crate::analysis::bars::check`].
+    BarColor {
+        input: String,
+        /// `#RRGGBB` the bars are cleared with.
+        #[serde(default = "default_bar_color")]
+        color: String,
+        /// Largest difference allowed on each channel, out of 255.
+        #[serde(default = "default_bar_tolerance")]
+        tolerance: u8,
+    },
+    /// Renders an input or scene, and compares the frame with the one drawn on the CPU.
+    ///
+    /// Only simple scenes can be drawn, see [`crate::expected`].
+    ExpectedScene {
+        scene: String,
+        /// Largest difference allowed on each channel, out of 255.
+        #[serde(default = "default_bar_tolerance")]
+        tolerance: u8,
+    },
+    /// Switches from a scene to another, and compares the `frames` frames from the first
+    /// one showing `to` with the golden sequence stored in `dir`, e.g. to verify a
+    /// transition animation.
+    ///
+    /// The sequence is saved instead when `dir` holds none or with `update`, see
+    /// [`crate::golden`].
+    GoldenSequence {
+        from: String,
+        to: String,
+        dir: PathBuf,
+        frames: usize,
+        /// Largest difference allowed on each channel, out of 255.
+        #[serde(default = "default_bar_tolerance")]
+        tolerance: u8,
+        /// Tolerances of some frames instead of `tolerance`, the last one matching applies.
+        #[serde(default)]
+        frame_tolerances: Vec<FrameToleranceConfig>,
+        #[serde(default)]
+        matching: GoldenMatchingConfig,
+        /// Rendered frames right after the switch left out of the comparison.
+        #[serde(default)]
+        skip_after_switch: usize,
+        #[serde(default)]
+        update: bool,
+    },
+    /// Renders a `counter` input for `duration_secs`, checking which of its frames are shown.
+    ///
+    /// See [`crate::analysis::cadence::CadenceAnalyzer`].
+    Cadence {
+        input: String,
+        #[serde(default = "default_cadence_secs")]
+        duration_secs: u64,
+    },
+    /// Renders an input for `duration_secs`, measuring the offset of its chroma planes from
+    /// its luma plane on colored edges.
+    ///
+    /// See [`crate::analysis::chroma_alignment::ChromaAlignmentAnalyzer`].
+    ChromaAlignment {
+        input: String,
+        #[serde(default = "default_chroma_alignment_secs")]
+        duration_secs: u64,
+        /// Largest offset allowed, in output pixels.
+        #[serde(default = "default_chroma_alignment_tolerance")]
+        tolerance_px: f32,
+    },
+    /// Plays a recording made with `markers.enabled`, rebuilding the timeline of its events
+    /// from the markers, written to the `timeline` CSV file if set.
+    ///
+    /// See [`crate::analysis::markers::MarkerDecoder`].
+    Markers {
+        input: String,
+        duration_secs: u64,
+        #[serde(default)]
+        timeline: Option<PathBuf>,
+    },
+    /// Renders an input rescaled to each of `sizes` in each of `modes`, measuring the MTF50
+    /// of the edge of a `slanted_edge` pattern.
+    ///
+    /// See [`crate::analysis::sharpness::SharpnessAnalyzer`].
+    Sharpness {
+        input: String,
+        /// Sizes of the rescaled input, the output resolution when empty.
+        #[serde(default)]
+        sizes: Vec<ResolutionConfig>,
+        #[serde(default = "default_rescale_modes")]
+        modes: Vec<RescaleModeConfig>,
+        /// Lowest MTF50 allowed, in cycles per pixel. Only measured when unset.
+        min_mtf50: Option<f32>,
+    },
+    /// Cuts `switches` times to new copies of an MP4 input, like a switcher, measuring
+    /// registration and first frame times and looking for glitches.
+    ///
+    /// Copies are registered on each cut, or ahead of time with a `pool_size`.
+    InputSwitching {
+        input: String,
+        #[serde(default = "default_input_switches")]
+        switches: u32,
+        #[serde(default = "default_switch_interval_ms")]
+        switch_interval_ms: u64,
+        #[serde(default)]
+        pool_size: usize,
+    },
+    /// Measures the intervals between raw output frames while cycling through the inputs.
+    FramePacing {
+        duration_secs: u64,
+        #[serde(default = "default_switch_interval_ms")]
+        switch_interval_ms: u64,
+    },
+    /// Toggles a marker over the first input `switches` times, measuring the time until
+    /// the raw output shows each switch.
+    UpdateLatency {
+        #[serde(default = "default_latency_switches")]
+        switches: u32,
+        #[serde(default = "default_switch_interval_ms")]
+        switch_interval_ms: u64,
+    },
+    /// Measures the latency from a live input flashing on the system clock to the output,
+    /// once per queue preset, see [`crate::latency::FlashDetector`].
+    GlassToGlass {
+        input: String,
+        #[serde(default = "default_glass_to_glass_secs")]
+        duration_secs: u64,
+        /// Interval of the flashes, they start on its multiples since the UNIX epoch.
+        #[serde(default = "default_flash_interval_ms")]
+        flash_interval_ms: u64,
+        /// Frame luma above which the flash is shown, in `[0, 1]`.
+        #[serde(default = "default_flash_threshold")]
+        threshold: f32,
+        /// Queue settings to compare, the configured ones when empty.
+        #[serde(default)]
+        presets: Vec<QueuePresetConfig>,
+    },
+    /// Interactive terminal dashboard with metrics, pipeline events, scene controls and
+    /// a preview of the latest frame, cycling through the inputs until switched manually.
+    Dashboard {
+        /// Runs until quit when unset.
+        duration_secs: Option<u64>,
+        #[serde(default = "default_switch_interval_ms")]
+        switch_interval_ms: u64,
+    },
+    /// Replays the commands of a script recorded in the dashboard, headless, see
+    /// [`crate::macros`]. `switch_interval_ms` should be that of the recorded dashboard.
+    Replay {
+        path: PathBuf,
+        #[serde(default = "default_switch_interval_ms")]
+        switch_interval_ms: u64,
+    },
+    /// Prints the latest frame to the terminal as ANSI truecolor text every `interval_secs`,
+    /// while cycling through the inputs. Needs a truecolor terminal.
+    TerminalPreview {
+        /// Runs forever when unset.
+        duration_secs: Option<u64>,
+        #[serde(default = "default_terminal_interval_secs")]
+        interval_secs: u64,
+        #[serde(default = "default_terminal_columns")]
+        columns: u32,
+        #[serde(default = "default_switch_interval_ms")]
+        switch_interval_ms: u64,
+    },
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_bar_color() -> String {
+    "#000000".to_string()
+}
+
+fn default_bar_tolerance() -> u8 {
+    1
+}
+
+fn default_cadence_secs() -> u64 {
+    10
+}
+
+fn default_rescale_modes() -> Vec<RescaleModeConfig> {
+    vec![RescaleModeConfig::Fit, RescaleModeConfig::Fill]
+}
+
+fn default_chroma_alignment_secs() -> u64 {
+    2
+}
+
+fn default_chroma_alignment_tolerance() -> f32 {
+    0.25
+}
+
+fn default_glass_to_glass_secs() -> u64 {
+    30
+}
+
+fn default_flash_interval_ms() -> u64 {
+    1000
+}
+
+fn default_flash_threshold() -> f32 {
+    0.5
+}
+
+fn default_input_switches() -> u32 {
+    20
+}
+
+fn default_latency_switches() -> u32 {
+    100
+}
+
+fn default_square_size() -> u32 {
+    64
+}
+
+fn default_snapshot_template() -> String {
+    "output_{id}.png".to_string()
+}
+
+fn default_stride() -> usize {
+    1
+}
+
+fn default_frame_template() -> String {
+    "frame_{index}.png".to_string()
+}
+
+fn default_watch_snapshot_path() -> PathBuf {
+    PathBuf::from("output_watch.png")
+}
+
+fn default_loops() -> u32 {
+    2
+}
+
+fn default_loop_tolerance_ms() -> u64 {
+    150
+}
+
+fn default_switch_interval_ms() -> u64 {
+    1000
+}
+
+fn default_alternate() -> bool {
+    true
+}
+
+fn default_gif_fps() -> u32 {
+    15
+}
+
+fn default_gif_max_colors() -> u16 {
+    256
+}
+
+fn default_sweep_presets() -> Vec<EncoderPresetConfig> {
+    vec![EncoderPresetConfig::Medium]
+}
+
+fn default_sweep_crfs() -> Vec<u8> {
+    vec![18, 23, 28]
+}
+
+fn default_hls_segment_secs() -> u64 {
+    2
+}
+
+fn default_payload_type() -> u8 {
+    96
+}
+
+fn default_ndi_name() -> String {
+    "smelter-colors".to_string()
+}
+
+fn default_terminal_interval_secs() -> u64 {
+    5
+}
+
+fn default_terminal_columns() -> u32 {
+    80
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let assets_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets");
+
+        Self {
+            resolution: ResolutionConfig::default(),
+            framerate: FramerateConfig::default(),
+            queue: QueueConfig::default(),
+            rendering_mode: RenderingModeConfig::default(),
+            wgpu_features: vec![],
+            disabled_wgpu_features: vec![],
+            inputs: vec![
+                InputConfig::Image {
+                    id: "png".to_string(),
+                    path: assets_path.join(IMAGE),
+                    correction: None,
+                },
+                InputConfig::Mp4 {
+                    id: "mp4".to_string(),
+                    path: assets_path.join(MP4),
+                    should_loop: true,
+                    decoder: DecoderConfig::default(),
+                    trim: None,
+                    tracks: None,
+                    audio: false,
+                    correction: None,
+                },
+            ],
+            scenes: vec![],
+            outputs: vec![
+                OutputConfig::Snapshots {
+                    path_template: default_snapshot_template(),
+                    format: DumpFormatConfig::default(),
+                },
+                OutputConfig::Mp4 {
+                    path: PathBuf::from("output.mp4"),
+                    duration_secs: Some(5),
+                    cycles: None,
+                    switch_interval_ms: default_switch_interval_ms(),
+                    segment_secs: None,
+                    segment_mb: None,
+                    verify_metadata: false,
+                },
+            ],
+            startup_budget: StartupBudgetConfig::default(),
+            utilization: UtilizationConfig::default(),
+            threads: ThreadsConfig::default(),
+            whip_server: WhipServerConfig::default(),
+            stun_servers: vec![],
+            web_renderer: WebRendererConfig::default(),
+            alerts: AlertsConfig::default(),
+            event_log: EventLogConfig::default(),
+            canary: CanaryConfig::default(),
+            pts_gaps: PtsGapsConfig::default(),
+            audio: AudioConfig::default(),
+            failure_snapshots: FailureSnapshotsConfig::default(),
+            noise: NoiseConfig::default(),
+            markers: MarkersConfig::default(),
+            encoder: EncoderConfig::default(),
+            workarounds: WorkaroundsConfig::default(),
+            preview: PreviewConfig::default(),
+            session: SessionConfig::default(),
+            headless: false,
+        }
+    }
+}
+
+impl Default for ResolutionConfig {
+    fn default() -> Self {
+        Self {
+            width: WIDTH,
+            height: HEIGHT,
+        }
+    }
+}
+
+impl Default for FramerateConfig {
+    fn default() -> Self {
+        Self { num: 30, den: 1 }
+    }
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            default_buffer_duration_ms: 0,
+            input_buffer_duration_ms: None,
+            ahead_of_time_processing: false,
+            run_late_scheduled_events: true,
+            never_drop_output_frames: false,
+            stream_fallback_timeout_ms: 500,
+        }
+    }
+}
+
+impl Default for FailureSnapshotsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: PathBuf::from("failures"),
+        }
+    }
+}
+
+impl Default for NoiseConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sigma: [2.0; 3],
+            seed: None,
+            inputs: vec![],
+            temporal: true,
+        }
+    }
+}
+
+impl Default for EventLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: PathBuf::from("events.jsonl"),
+        }
+    }
+}
+
+impl Default for UtilizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_ms: 500,
+            path: PathBuf::from("utilization.csv"),
+        }
+    }
+}
+
+impl Config {
+    /// Loads a config file, the format is chosen from the extension (`.toml` or `.json`).
+    ///
+    /// Relative input and scene paths are resolved from the directory of the file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read config {}", path.display()))?;
+
+        let mut config: Config = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&content)
+                .with_context(|| format!("Invalid TOML config {}", path.display()))?,
+            Some("json") => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid JSON config {}", path.display()))?,
+            _ => bail!(
+                "Unknown config format {}, use .toml or .json",
+                path.display()
+            ),
+        };
+
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+        for input in &mut config.inputs {
+            let correction = match input {
+                InputConfig::Image {
+                    path, correction, ..
+                }
+                | InputConfig::Mp4 {
+                    path, correction, ..
+                }
+                | InputConfig::Animation {
+                    path, correction, ..
+                }
+                | InputConfig::ImageSequence {
+                    path, correction, ..
+                } => {
+                    if path.is_relative() {
+                        *path = base_dir.join(&*path);
+                    }
+                    correction
+                }
+                InputConfig::Playlist {
+                    items, correction, ..
+                } => {
+                    for item in items.iter_mut().filter(|item| item.path.is_relative()) {
+                        item.path = base_dir.join(&item.path);
+                    }
+                    correction
+                }
+                InputConfig::Rtp { correction, .. }
+                | InputConfig::Srt { correction, .. }
+                | InputConfig::Counter { correction, .. }
+                | InputConfig::Pattern { correction, .. }
+                | InputConfig::Stdin { correction, .. }
+                | InputConfig::Camera { correction, .. }
+                | InputConfig::Decklink { correction, .. }
+                | InputConfig::Ndi { correction, .. }
+                | InputConfig::Raw { correction, .. }
+                | InputConfig::Screen { correction, .. }
+                | InputConfig::Web { correction, .. }
+                | InputConfig::Whip { correction, .. } => correction,
+            };
+            if let Some(lut) = correction.as_mut().and_then(|c| c.lut.as_mut()) {
+                if lut.is_relative() {
+                    *lut = base_dir.join(&*lut);
+                }
+            }
+        }
+        for scene in &mut config.scenes {
+            if scene.path.is_relative() {
+                scene.path = base_dir.join(&scene.path);
+            }
+        }
+        let session = &mut config.session;
+        if session.path.is_relative() {
+            session.path = base_dir.join(&session.path);
+        }
+        if let Some(path) = session
+            .record_commands
+            .as_mut()
+            .filter(|path| path.is_relative())
+        {
+            *path = base_dir.join(&*path);
+        }
+        let preview = &mut config.preview;
+        if preview.profiles.is_relative() {
+            preview.profiles = base_dir.join(&preview.profiles);
+        }
+        if let Some(icc_profile) = preview
+            .icc_profile
+            .as_mut()
+            .filter(|path| path.is_relative())
+        {
+            *icc_profile = base_dir.join(&*icc_profile);
+        }
+        for output in &mut config.outputs {
+            match output {
+                OutputConfig::WatchScene { scene, .. } if scene.is_relative() => {
+                    *scene = base_dir.join(&*scene);
+                }
+                OutputConfig::Replay { path, .. } if path.is_relative() => {
+                    *path = base_dir.join(&*path);
+                }
+                OutputConfig::GoldenSequence { dir, .. } if dir.is_relative() => {
+                    *dir = base_dir.join(&*dir);
+                }
+                OutputConfig::Simultaneous { outputs, .. } => {
+                    for path in outputs
+                        .iter_mut()
+                        .filter_map(|output| output.output.path_mut())
+                        .filter(|path| path.is_relative())
+                    {
+                        *path = base_dir.join(&*path);
+                    }
+                }
+                OutputConfig::Analyze { plugins, .. } => {
+                    for plugin in plugins
+                        .iter_mut()
+                        .filter(|plugin| plugin.path.is_relative())
+                    {
+                        plugin.path = base_dir.join(&plugin.path);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Rejects what needs a display server or an interactive terminal.
+    fn validate_headless(&self) -> Result<()> {
+        if let Some(input) = self
+            .inputs
+            .iter()
+            .find(|input| matches!(input, InputConfig::Screen { .. }))
+        {
+            bail!(
+                "Screen input {:?} needs a display, not headless",
+                input.id()
+            );
+        }
+        if self
+            .outputs
+            .iter()
+            .any(|output| matches!(output, OutputConfig::Dashboard { .. }))
+        {
+            bail!("The dashboard needs an interactive terminal, not headless");
+        }
+        if self.preview.correct_display && self.preview.icc_profile.is_none() {
+            bail!("Headless previews are only corrected with preview.icc_profile set");
+        }
+        Ok(())
+    }
+
+    /// Checks the settings, inputs and outputs are consistent. Loaded files are checked by
+    /// [`Config::load`], configs changed afterwards or built in code are to be checked again.
+    pub fn validate(&self) -> Result<()> {
+        if self.inputs.is_empty() {
+            bail!("Config must declare at least one input");
+        }
+        if self.framerate.num == 0 || self.framerate.den == 0 {
+            bail!(
+                "Invalid framerate {}/{}",
+                self.framerate.num,
+                self.framerate.den
+            );
+        }
+
+        let mut ids: Vec<&str> = self.inputs.iter().map(InputConfig::id).collect();
+        ids.sort_unstable();
+        if let Some(duplicate) = ids.windows(2).find(|pair| pair[0] == pair[1]) {
+            bail!("Duplicate input ID {:?}", duplicate[0]);
+        }
+
+        let mut scene_ids: Vec<&str> = self.scenes.iter().map(|scene| scene.id.as_str()).collect();
+        scene_ids.sort_unstable();
+        if let Some(duplicate) = scene_ids.windows(2).find(|pair| pair[0] == pair[1]) {
+            bail!("Duplicate scene ID {:?}", duplicate[0]);
+        }
+
+        // Fail early on typos rather than once the pipeline is running
+        self.wgpu_features()?;
+        if self.canary.enabled {
+            crate::scene::parse_color(Some(&self.canary.color)).context("Invalid canary color")?;
+        }
+        if self.markers.enabled && self.markers.size == 0 {
+            bail!("Marker size must be at least 1");
+        }
+        self.encoder.validate()?;
+        if let Some(server) = self
+            .stun_servers
+            .iter()
+            .find(|server| !server.starts_with("stun:") && !server.starts_with("stuns:"))
+        {
+            bail!("STUN server {server:?} must start with stun: or stuns:");
+        }
+        let zero_rate = self.outputs.iter().any(|output| match output {
+            OutputConfig::Analyze { plugins, .. } => plugins
+                .iter()
+                .any(|plugin| matches!(plugin.rate, RateConfig::EveryNth(0))),
+            _ => false,
+        });
+        if zero_rate {
+            bail!("Plugin rate every_nth must be at least 1");
+        }
+        for output in &self.outputs {
+            if let OutputConfig::FrameSequence {
+                stride, template, ..
+            } = output
+            {
+                if *stride == 0 {
+                    bail!("Frame sequence stride must be at least 1");
+                }
+                if !template.contains("{index}") && !template.contains("{pts_ms}") {
+                    bail!("Frame sequence template {template:?} has no {{index}} or {{pts_ms}}");
+                }
+            }
+            if let OutputConfig::Snapshots {
+                path_template: template,
+                format,
+            }
+            | OutputConfig::FrameSequence {
+                template, format, ..
+            } = output
+            {
+                let extension = format.extension();
+                if !template
+                    .to_ascii_lowercase()
+                    .ends_with(&format!(".{extension}"))
+                {
+                    bail!("{template:?} must end with .{extension} to be saved as {format:?}");
+                }
+            }
+            if let OutputConfig::Mp4 {
+                path,
+                duration_secs,
+                cycles,
+                segment_secs,
+                segment_mb,
+                verify_metadata,
+                ..
+            } = output
+            {
+                if *segment_secs == Some(0) || *segment_mb == Some(0) {
+                    bail!("MP4 segment_secs and segment_mb must be at least 1");
+                }
+                match (duration_secs, cycles) {
+                    (None, Some(0)) => bail!("MP4 cycles must be at least 1"),
+                    (Some(_), None) | (None, Some(_)) => {}
+                    _ => bail!("MP4 outputs need either duration_secs or cycles"),
+                }
+                if *verify_metadata
+                    && (segment_secs.is_some()
+                        || segment_mb.is_some()
+                        || self.encoder.codec != RecordCodecConfig::H264)
+                {
+                    bail!("MP4 verify_metadata only checks unsegmented H264 recordings");
+                }
+                let extension = self.encoder.codec.extension();
+                if self.encoder.codec.is_lossless()
+                    && !path.extension().is_some_and(|found| found == extension)
+                {
+                    bail!(
+                        "{:?} recordings are written to .{extension} files, not {}",
+                        self.encoder.codec,
+                        path.display()
+                    );
+                }
+            }
+            if let OutputConfig::Whip { endpoint_url, .. } = output {
+                if !endpoint_url.starts_with("http://") && !endpoint_url.starts_with("https://") {
+                    bail!("WHIP endpoint {endpoint_url:?} is not an HTTP URL");
+                }
+                if self.encoder.codec != RecordCodecConfig::H264 {
+                    bail!("WHIP outputs are encoded in H264");
+                }
+            }
+            if let OutputConfig::Simultaneous { outputs, .. } = output {
+                if outputs.is_empty() {
+                    bail!("Simultaneous outputs must declare at least one output");
+                }
+                for (i, named) in outputs.iter().enumerate() {
+                    if outputs[..i].iter().any(|other| other.name == named.name) {
+                        bail!("Duplicate output name {:?}", named.name);
+                    }
+                    if let Some(id) = named
+                        .inputs
+                        .iter()
+                        .find(|id| !self.inputs.iter().any(|input| input.id() == *id))
+                    {
+                        bail!(
+                            "Output {:?} shows {id:?}, which is not an input",
+                            named.name
+                        );
+                    }
+                    if named
+                        .resolution
+                        .is_some_and(|resolution| resolution.width == 0 || resolution.height == 0)
+                    {
+                        bail!("Output {:?} has an empty resolution", named.name);
+                    }
+                    match &named.output {
+                        NamedOutputKindConfig::Whip { endpoint_url, .. }
+                            if !endpoint_url.starts_with("http://")
+                                && !endpoint_url.starts_with("https://") =>
+                        {
+                            bail!("WHIP endpoint {endpoint_url:?} is not an HTTP URL");
+                        }
+                        NamedOutputKindConfig::Mp4 { .. } | NamedOutputKindConfig::Whip { .. }
+                            if self.encoder.codec != RecordCodecConfig::H264 =>
+                        {
+                            bail!("Simultaneous MP4 and WHIP outputs are encoded in H264");
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            if let OutputConfig::EncoderSweep { presets, crfs, .. } = output {
+                if presets.is_empty() || crfs.is_empty() {
+                    bail!("Encoder sweeps need at least one preset and one CRF");
+                }
+                if let Some(crf) = crfs.iter().find(|crf| **crf > 51) {
+                    bail!("Encoder sweep CRF {crf} is not between 0 and 51");
+                }
+            }
+            if let OutputConfig::Gif {
+                fps, max_colors, ..
+            } = output
+            {
+                if !(1..=50).contains(fps) {
+                    bail!("GIF fps must be between 1 and 50");
+                }
+                if !(4..=256).contains(max_colors) {
+                    bail!("GIF max_colors must be between 4 and 256");
+                }
+            }
+            if let OutputConfig::Hls {
+                segment_secs,
+                playlist_size,
+                ..
+            } = output
+            {
+                if *segment_secs == 0 || *playlist_size == Some(0) {
+                    bail!("HLS segment_secs and playlist_size must be at least 1");
+                }
+                if self.encoder.codec != RecordCodecConfig::H264 {
+                    bail!("HLS outputs are encoded in H264");
+                }
+            }
+            if let OutputConfig::Rtp {
+                payload_type,
+                codec,
+                ..
+            } = output
+            {
+                if !(96..=127).contains(payload_type) {
+                    bail!("RTP payload type {payload_type} is not a dynamic one, from 96 to 127");
+                }
+                if matches!(codec, RtpCodecConfig::H264)
+                    && self.encoder.codec != RecordCodecConfig::H264
+                {
+                    bail!("H264 RTP outputs need the H264 encoder settings");
+                }
+            }
+            if let OutputConfig::GoldenSequence {
+                frames,
+                frame_tolerances,
+                ..
+            } = output
+            {
+                if *frames == 0 {
+                    bail!("Golden sequences must have at least 1 frame");
+                }
+                if let Some(range) = frame_tolerances.iter().find(|range| range.from > range.to) {
+                    bail!(
+                        "Frame tolerance from {} to {} is an empty range",
+                        range.from,
+                        range.to
+                    );
+                }
+            }
+            if let OutputConfig::Sharpness { sizes, .. } = output {
+                let resolution = self.resolution;
+                if let Some(size) = sizes
+                    .iter()
+                    .find(|size| size.width > resolution.width || size.height > resolution.height)
+                {
+                    bail!(
+                        "Sharpness size {}x{} is larger than the output",
+                        size.width,
+                        size.height
+                    );
+                }
+            }
+        }
+        if self
+            .noise
+            .sigma
+            .iter()
+            .any(|sigma| !(0.0..=255.0).contains(sigma))
+        {
+            bail!("Noise sigma must be between 0 and 255");
+        }
+        if self.preview.zoom.is_nan() || self.preview.zoom < 1.0 {
+            bail!("Preview zoom must be at least 1");
+        }
+        if !(20..=90).contains(&self.preview.preview_width) {
+            bail!("Preview width must be between 20 and 90%");
+        }
+        if let Some(id) = self
+            .noise
+            .inputs
+            .iter()
+            .find(|id| !self.inputs.iter().any(|input| input.id() == *id))
+        {
+            bail!("Noise added over {id:?}, which is not an input");
+        }
+        for id in self.audio.volumes.keys().chain(&self.audio.muted) {
+            if !self
+                .inputs
+                .iter()
+                .any(|input| input.id() == id && crate::audio::has_audio(input))
+            {
+                bail!("Audio settings given for {id:?}, which is not an input with audio");
+            }
+        }
+        if let Some((id, volume)) = self
+            .audio
+            .volumes
+            .iter()
+            .find(|(_, volume)| !(0.0..=crate::audio::MAX_VOLUME).contains(*volume))
+        {
+            bail!(
+                "Volume of {id:?} must be between 0 and {}, got {volume}",
+                crate::audio::MAX_VOLUME
+            );
+        }
+        let stdin_inputs = self
+            .inputs
+            .iter()
+            .filter(|input| matches!(input, InputConfig::Stdin { .. }))
+            .count();
+        if stdin_inputs > 1 {
+            bail!("Only one input can read from stdin");
+        }
+        if self.headless {
+            self.validate_headless()?;
+        }
+        for input in &self.inputs {
+            match input {
+                InputConfig::Image { .. }
+                | InputConfig::Pattern { .. }
+                | InputConfig::Camera { .. }
+                | InputConfig::Ndi { .. }
+                | InputConfig::Raw { .. }
+                | InputConfig::Screen { .. } => {}
+                InputConfig::ImageSequence {
+                    id,
+                    framerate: Some(framerate),
+                    ..
+                } if framerate.num == 0 || framerate.den == 0 => {
+                    bail!("Invalid framerate of image sequence {id:?}");
+                }
+                InputConfig::ImageSequence { .. } | InputConfig::Animation { .. } => {}
+                InputConfig::Playlist { id, items, .. } => {
+                    if items.is_empty() {
+                        bail!("Playlist {id:?} has no items");
+                    }
+                    if items.iter().any(|item| item.duration_ms == Some(0)) {
+                        bail!("Items of playlist {id:?} must last longer than 0ms");
+                    }
+                }
+                InputConfig::Stdin { id, framerate, .. }
+                | InputConfig::Counter { id, framerate, .. }
+                    if framerate.num == 0 || framerate.den == 0 =>
+                {
+                    bail!("Invalid framerate of input {id:?}");
+                }
+                InputConfig::Counter { .. } => {}
+                InputConfig::Stdin { .. } => {}
+                InputConfig::Decklink { id, .. } => {
+                    if !cfg!(feature = "decklink") {
+                        bail!("DeckLink input {id:?} requires a build with the decklink feature");
+                    }
+                }
+                InputConfig::Web { id, .. } => {
+                    if !self.web_renderer.enabled {
+                        bail!(
+                            "Web input {id:?} requires the web renderer, set web_renderer.enabled"
+                        );
+                    }
+                }
+                InputConfig::Whip { id, .. } => {
+                    if !self.whip_server.enabled {
+                        bail!(
+                            "WHIP input {id:?} requires the WHIP server, set whip_server.enabled"
+                        );
+                    }
+                }
+                InputConfig::Mp4 {
+                    id,
+                    decoder,
+                    trim,
+                    tracks,
+                    audio,
+                    ..
+                } => {
+                    decoder.video_decoder(cfg!(feature = "vk-video"))?;
+                    if tracks.is_some_and(|tracks| tracks.audio.is_some()) && !*audio {
+                        bail!(
+                            "MP4 input {id:?} selects an audio track, set audio = true to mix it"
+                        );
+                    }
+                    if trim.is_some_and(|trim| trim.duration_ms == Some(0)) {
+                        bail!("Trimmed MP4 input {id:?} must last longer than 0ms");
+                    }
+                    if trim.is_some() && *audio {
+                        bail!("Trimmed MP4 input {id:?} has no audio, trimming drops it");
+                    }
+                }
+                InputConfig::Rtp { codec, decoder, .. } => {
+                    codec.video_decoder(decoder.as_ref(), cfg!(feature = "vk-video"))?;
+                }
+                InputConfig::Srt { id, passphrase, .. } => {
+                    if passphrase
+                        .as_ref()
+                        .is_some_and(|passphrase| !(10..=79).contains(&passphrase.len()))
+                    {
+                        bail!("Passphrase of SRT input {id:?} must be 10 to 79 characters");
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn frame_interval(&self) -> Duration {
+        self.framerate.interval()
+    }
+
+    pub fn resolution(&self) -> Resolution {
+        Resolution {
+            width: self.resolution.width,
+            height: self.resolution.height,
+        }
+    }
+
+    /// Features required by the compositor, plus the ones listed in the config, less the
+    /// disabled ones.
+    pub fn wgpu_features(&self) -> Result<wgpu::Features> {
+        let mut features = REQUIRED_WGPU_FEATURES;
+        for name in &self.wgpu_features {
+            features |= wgpu_feature(name)?;
+        }
+        for name in &self.disabled_wgpu_features {
+            let feature = wgpu_feature(name)?;
+            if REQUIRED_WGPU_FEATURES.intersects(feature) {
+                bail!(
+                    "{name} cannot be disabled, Smelter builds every shader pipeline with a \
+                     binding array of textures and push constants"
+                );
+            }
+            features -= feature;
+        }
+        Ok(features)
+    }
+}
+
+impl QueueConfig {
+    pub fn default_buffer_duration(&self) -> Duration {
+        Duration::from_millis(self.default_buffer_duration_ms)
+    }
+
+    pub fn input_buffer_duration(&self) -> Duration {
+        Duration::from_millis(
+            self.input_buffer_duration_ms
+                .unwrap_or(self.default_buffer_duration_ms),
+        )
+    }
+
+    pub fn stream_fallback_timeout(&self) -> Duration {
+        Duration::from_millis(self.stream_fallback_timeout_ms)
+    }
+}
+
+impl QueuePresetConfig {
+    /// The configured queue settings, with the buffering of the preset.
+    pub fn apply(self, queue: &QueueConfig) -> QueueConfig {
+        let buffer_duration_ms = match self {
+            QueuePresetConfig::LowLatency => 0,
+            QueuePresetConfig::Balanced => 80,
+            QueuePresetConfig::Buffered => 500,
+        };
+        QueueConfig {
+            default_buffer_duration_ms: buffer_duration_ms,
+            input_buffer_duration_ms: None,
+            ..queue.clone()
+        }
+    }
+}
+
+impl DecoderConfig {
+    /// Decoder to use, `vulkan` telling whether the GPU can decode, for [`DecoderConfig::Auto`].
+    pub fn video_decoder(&self, vulkan: bool) -> Result<VideoDecoder> {
+        match self {
+            DecoderConfig::FfmpegH264 => Ok(VideoDecoder::FFmpegH264),
+            DecoderConfig::Auto if !vulkan => Ok(VideoDecoder::FFmpegH264),
+            #[cfg(feature = "vk-video")]
+            DecoderConfig::Auto => Ok(VideoDecoder::VulkanVideoH264),
+            #[cfg(not(feature = "vk-video"))]
+            DecoderConfig::Auto => Ok(VideoDecoder::FFmpegH264),
+            #[cfg(feature = "vk-video")]
+            DecoderConfig::VulkanH264 => Ok(VideoDecoder::VulkanVideoH264),
+            #[cfg(not(feature = "vk-video"))]
+            DecoderConfig::VulkanH264 => {
+                bail!("Built without Vulkan decoding, enable the vk-video feature")
+            }
+        }
+    }
+}
+
+impl FramerateConfig {
+    /// Duration of a frame.
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs_f64(self.den as f64 / self.num as f64)
+    }
+}
+
+impl PixelFormatConfig {
+    /// Size in bytes of a frame.
+    pub fn frame_size(&self, width: u32, height: u32) -> usize {
+        let (width, height) = (width as usize, height as usize);
+        match self {
+            PixelFormatConfig::Rgba => width * height * 4,
+            PixelFormatConfig::Yuv420p => {
+                width * height + 2 * width.div_ceil(2) * height.div_ceil(2)
+            }
+        }
+    }
+}
+
+impl RtpCodecConfig {
+    pub fn video_decoder(
+        &self,
+        decoder: Option<&DecoderConfig>,
+        vulkan: bool,
+    ) -> Result<VideoDecoder> {
+        match (self, decoder) {
+            (RtpCodecConfig::H264, decoder) => {
+                decoder.copied().unwrap_or_default().video_decoder(vulkan)
+            }
+            (RtpCodecConfig::Vp8, None) => Ok(VideoDecoder::FFmpegVp8),
+            (RtpCodecConfig::Vp8, Some(_)) => bail!("RTP decoder can only be set for H264"),
+        }
+    }
+}
+
+impl OutputConfig {
+    /// Type of the output, as written in config files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutputConfig::Snapshots { .. } => "snapshots",
+            OutputConfig::Mp4 { .. } => "mp4",
+            OutputConfig::DualRecording { .. } => "dual_recording",
+            OutputConfig::Webm { .. } => "webm",
+            OutputConfig::Gif { .. } => "gif",
+            OutputConfig::EncoderSweep { .. } => "encoder_sweep",
+            OutputConfig::Whip { .. } => "whip",
+            OutputConfig::Hls { .. } => "hls",
+            OutputConfig::Rtp { .. } => "rtp",
+            OutputConfig::Ndi { .. } => "ndi",
+            OutputConfig::Simultaneous { .. } => "simultaneous",
+            OutputConfig::FrameSequence { .. } => "frame_sequence",
+            OutputConfig::Y4m { .. } => "y4m",
+            OutputConfig::WatchScene { .. } => "watch_scene",
+            OutputConfig::LoopCheck { .. } => "loop_check",
+            OutputConfig::Analyze { .. } => "analyze",
+            OutputConfig::Cadence { .. } => "cadence",
+            OutputConfig::ChromaAlignment { .. } => "chroma_alignment",
+            OutputConfig::Markers { .. } => "markers",
+            OutputConfig::Sharpness { .. } => "sharpness",
+            OutputConfig::InputSwitching { .. } => "input_switching",
+            OutputConfig::BarColor { .. } => "bar_color",
+            OutputConfig::ExpectedScene { .. } => "expected_scene",
+            OutputConfig::GoldenSequence { .. } => "golden_sequence",
+            OutputConfig::FramePacing { .. } => "frame_pacing",
+            OutputConfig::UpdateLatency { .. } => "update_latency",
+            OutputConfig::GlassToGlass { .. } => "glass_to_glass",
+            OutputConfig::Dashboard { .. } => "dashboard",
+            OutputConfig::Replay { .. } => "replay",
+            OutputConfig::TerminalPreview { .. } => "terminal_preview",
+        }
+    }
+}
+
+impl InputConfig {
+    pub fn id(&self) -> &str {
+        match self {
+            InputConfig::Image { id, .. }
+            | InputConfig::Mp4 { id, .. }
+            | InputConfig::Rtp { id, .. }
+            | InputConfig::Srt { id, .. }
+            | InputConfig::Counter { id, .. }
+            | InputConfig::Pattern { id, .. }
+            | InputConfig::Stdin { id, .. }
+            | InputConfig::Camera { id, .. }
+            | InputConfig::Decklink { id, .. }
+            | InputConfig::Ndi { id, .. }
+            | InputConfig::Animation { id, .. }
+            | InputConfig::ImageSequence { id, .. }
+            | InputConfig::Playlist { id, .. }
+            | InputConfig::Raw { id, .. }
+            | InputConfig::Screen { id, .. }
+            | InputConfig::Web { id, .. }
+            | InputConfig::Whip { id, .. } => id,
+        }
+    }
+
+    pub fn correction(&self) -> Option<&ColorCorrectionConfig> {
+        match self {
+            InputConfig::Image { correction, .. }
+            | InputConfig::Mp4 { correction, .. }
+            | InputConfig::Rtp { correction, .. }
+            | InputConfig::Srt { correction, .. }
+            | InputConfig::Counter { correction, .. }
+            | InputConfig::Pattern { correction, .. }
+            | InputConfig::Stdin { correction, .. }
+            | InputConfig::Camera { correction, .. }
+            | InputConfig::Decklink { correction, .. }
+            | InputConfig::Ndi { correction, .. }
+            | InputConfig::Animation { correction, .. }
+            | InputConfig::ImageSequence { correction, .. }
+            | InputConfig::Playlist { correction, .. }
+            | InputConfig::Raw { correction, .. }
+            | InputConfig::Screen { correction, .. }
+            | InputConfig::Web { correction, .. }
+            | InputConfig::Whip { correction, .. } => correction.as_ref(),
+        }
+    }
+}
+
+fn wgpu_feature(name: &str) -> Result<wgpu::Features> {
+    Ok(match name {
+        "PUSH_CONSTANTS" => wgpu::Features::PUSH_CONSTANTS,
+        "TEXTURE_BINDING_ARRAY" => wgpu::Features::TEXTURE_BINDING_ARRAY,
+        "PIPELINE_CACHE" => wgpu::Features::PIPELINE_CACHE,
+        "TIMESTAMP_QUERY" => wgpu::Features::TIMESTAMP_QUERY,
+        "SHADER_F16" => wgpu::Features::SHADER_F16,
+        "TEXTURE_FORMAT_16BIT_NORM" => wgpu::Features::TEXTURE_FORMAT_16BIT_NORM,
+        _ => bail!("Unknown or unsupported wgpu feature {name:?}"),
+    })
+}
@@ -0,0 +1,565 @@
+//! Codec parameters written to the container of an H264 recording, read back after it is
+//! recorded to check them against the configuration: a muxer regression in the profile,
+//! level, framerate or color tags goes unnoticed by pixel comparisons, which decode the file
+//! with whatever tags it has.
+
+use anyhow::{bail, Context, Result};
+use mp4::TrackType;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Profiles whose SPS holds the chroma format, bit depths and scaling matrices.
+const HIGH_PROFILES: [u8; 13] = [100, 110, 122, 244, 44, 83, 86, 118, 128, 138, 139, 134, 135];
+
+/// Color description of the VUI of the SPS, as ITU-T H.273 code points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorTags {
+    pub primaries: u8,
+    pub transfer: u8,
+    pub matrix: u8,
+    pub full_range: bool,
+}
+
+impl ColorTags {
+    /// BT.709 in limited range, which the recordings are converted to.
+    pub const BT709_LIMITED: Self = Self {
+        primaries: 1,
+        transfer: 1,
+        matrix: 1,
+        full_range: false,
+    };
+}
+
+impl fmt::Display for ColorTags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "primaries {}, transfer {}, matrix {}, {} range",
+            self.primaries,
+            self.transfer,
+            self.matrix,
+            if self.full_range { "full" } else { "limited" }
+        )
+    }
+}
+
+/// Parameters of the video track, from the `avcC` box and the SPS it holds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoParameters {
+    pub width: u32,
+    pub height: u32,
+    /// Average framerate, from the samples and duration of the track.
+    pub framerate: f64,
+    /// `profile_idc`, e.g. 100 for High.
+    pub profile: u8,
+    /// `level_idc`, ten times the level, e.g. 41 for 4.1.
+    pub level: u8,
+    /// `chroma_format_idc`, 1 for 4:2:0.
+    pub chroma_format: u8,
+    pub bit_depth: u8,
+    /// `None` when the SPS has no color description.
+    pub color: Option<ColorTags>,
+}
+
+/// What a recording should have been written with, unset fields are not checked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpectedParameters {
+    pub width: u32,
+    pub height: u32,
+    pub framerate: f64,
+    pub profile: Option<u8>,
+    pub level: Option<u8>,
+    pub chroma_format: u8,
+    pub bit_depth: u8,
+    pub color: ColorTags,
+}
+
+/// Reads the parameters of the H264 video track of an MP4 file.
+pub fn read(path: &Path) -> Result<VideoParameters> {
+    let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+    let size = file.metadata()?.len();
+    let reader = mp4::Mp4Reader::read_header(BufReader::new(file), size)
+        .with_context(|| format!("Invalid MP4 {}", path.display()))?;
+    let track = reader
+        .tracks()
+        .values()
+        .find(|track| track.track_type().ok() == Some(TrackType::Video))
+        .with_context(|| format!("{} has no video track", path.display()))?;
+    let avcc = &track
+        .trak
+        .mdia
+        .minf
+        .stbl
+        .stsd
+        .avc1
+        .as_ref()
+        .with_context(|| format!("The video track of {} is not H264", path.display()))?
+        .avcc;
+    let sps = avcc
+        .sequence_parameter_sets
+        .first()
+        .with_context(|| format!("{} has no SPS", path.display()))?;
+    let sps =
+        Sps::parse(&sps.bytes).with_context(|| format!("Invalid SPS in {}", path.display()))?;
+
+    Ok(VideoParameters {
+        width: track.width() as u32,
+        height: track.height() as u32,
+        framerate: track.frame_rate(),
+        profile: avcc.avc_profile_indication,
+        level: avcc.avc_level_indication,
+        chroma_format: sps.chroma_format,
+        bit_depth: sps.bit_depth,
+        color: sps.color,
+    })
+}
+
+/// Differences of `actual` from `expected`, as messages.
+pub fn mismatches(actual: &VideoParameters, expected: &ExpectedParameters) -> Vec<String> {
+    let mut mismatches = vec![];
+    if (actual.width, actual.height) != (expected.width, expected.height) {
+        mismatches.push(format!(
+            "resolution {}x{}, expected {}x{}",
+            actual.width, actual.height, expected.width, expected.height
+        ));
+    }
+    // Averaged over the track, so off by a frame or so over short recordings
+    if (actual.framerate - expected.framerate).abs() > expected.framerate * 0.01 {
+        mismatches.push(format!(
+            "framerate {:.3}, expected {:.3}",
+            actual.framerate, expected.framerate
+        ));
+    }
+    if let Some(profile) = expected
+        .profile
+        .filter(|profile| *profile != actual.profile)
+    {
+        mismatches.push(format!("profile {}, expected {profile}", actual.profile));
+    }
+    if let Some(level) = expected.level.filter(|level| *level != actual.level) {
+        mismatches.push(format!("level {}, expected {level}", actual.level));
+    }
+    if actual.chroma_format != expected.chroma_format {
+        mismatches.push(format!(
+            "chroma format {}, expected {}",
+            actual.chroma_format, expected.chroma_format
+        ));
+    }
+    if actual.bit_depth != expected.bit_depth {
+        mismatches.push(format!(
+            "bit depth {}, expected {}",
+            actual.bit_depth, expected.bit_depth
+        ));
+    }
+    match actual.color {
+        Some(color) if color == expected.color => {}
+        Some(color) => mismatches.push(format!("colors {color}, expected {}", expected.color)),
+        None => mismatches.push(format!("no color tags, expected {}", expected.color)),
+    }
+    mismatches
+}
+
+/// Fields of an SPS the checks need.
+struct Sps {
+    chroma_format: u8,
+    bit_depth: u8,
+    color: Option<ColorTags>,
+}
+
+impl Sps {
+    /// Parses an SPS NAL unit, header included, up to the color description of its VUI.
+    fn parse(nal: &[u8]) -> Result<Self> {
+        if nal.first().map(|header| header & 0x1F) != Some(7) {
+            bail!("Not an SPS");
+        }
+        let mut bits = BitReader::new(unescape(&nal[1..]));
+        let profile = bits.bits(8)? as u8;
+        bits.skip(16)?; // Constraint flags and level
+        bits.ue()?; // seq_parameter_set_id
+
+        let (mut chroma_format, mut bit_depth) = (1, 8);
+        if HIGH_PROFILES.contains(&profile) {
+            chroma_format = bits.ue()? as u8;
+            if chroma_format == 3 {
+                bits.skip(1)?; // separate_colour_plane_flag
+            }
+            bit_depth = 8 + bits.ue()? as u8;
+            bits.ue()?; // bit_depth_chroma_minus8
+            bits.skip(1)?; // qpprime_y_zero_transform_bypass_flag
+            if bits.flag()? {
+                let lists = if chroma_format == 3 { 12 } else { 8 };
+                for i in 0..lists {
+                    if bits.flag()? {
+                        bits.skip_scaling_list(if i < 6 { 16 } else { 64 })?;
+                    }
+                }
+            }
+        }
+
+        bits.ue()?; // log2_max_frame_num_minus4
+        match bits.ue()? {
+            0 => {
+                bits.ue()?; // log2_max_pic_order_cnt_lsb_minus4
+            }
+            1 => {
+                bits.skip(1)?; // delta_pic_order_always_zero_flag
+                bits.se()?; // offset_for_non_ref_pic
+                bits.se()?; // offset_for_top_to_bottom_field
+                for _ in 0..bits.ue()? {
+                    bits.se()?;
+                }
+            }
+            _ => {}
+        }
+        bits.ue()?; // max_num_ref_frames
+        bits.skip(1)?; // gaps_in_frame_num_value_allowed_flag
+        bits.ue()?; // pic_width_in_mbs_minus1
+        bits.ue()?; // pic_height_in_map_units_minus1
+        if !bits.flag()? {
+            bits.skip(1)?; // mb_adaptive_frame_field_flag
+        }
+        bits.skip(1)?; // direct_8x8_inference_flag
+        if bits.flag()? {
+            for _ in 0..4 {
+                bits.ue()?; // Frame cropping offsets
+            }
+        }
+
+        let mut color = None;
+        if bits.flag()? {
+            if bits.flag()? && bits.bits(8)? == 255 {
+                bits.skip(32)?; // Extended sample aspect ratio
+            }
+            if bits.flag()? {
+                bits.skip(1)?; // overscan_appropriate_flag
+            }
+            if bits.flag()? {
+                bits.skip(3)?; // video_format
+                let full_range = bits.flag()?;
+                // Unspecified, 2, when there is no color description
+                color = Some(match bits.flag()? {
+                    true => ColorTags {
+                        primaries: bits.bits(8)? as u8,
+                        transfer: bits.bits(8)? as u8,
+                        matrix: bits.bits(8)? as u8,
+                        full_range,
+                    },
+                    false => ColorTags {
+                        primaries: 2,
+                        transfer: 2,
+                        matrix: 2,
+                        full_range,
+                    },
+                });
+            }
+        }
+
+        Ok(Self {
+            chroma_format,
+            bit_depth,
+            color,
+        })
+    }
+}
+
+/// Removes the emulation prevention bytes, the 3 of each `00 00 03`.
+fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(data.len());
+    let mut zeros = 0;
+    for &byte in data {
+        if zeros >= 2 && byte == 3 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        bytes.push(byte);
+    }
+    bytes
+}
+
+/// Reads the bits of an RBSP, most significant first.
+struct BitReader {
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl BitReader {
+    fn new(bytes: Vec<u8>) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn flag(&mut self) -> Result<bool> {
+        let byte = self
+            .bytes
+            .get(self.position / 8)
+            .context("SPS ends early")?;
+        let bit = (byte >> (7 - self.position % 8)) & 1;
+        self.position += 1;
+        Ok(bit == 1)
+    }
+
+    fn bits(&mut self, count: u32) -> Result<u32> {
+        let mut value = 0;
+        for _ in 0..count {
+            value = (value << 1) | self.flag()? as u32;
+        }
+        Ok(value)
+    }
+
+    fn skip(&mut self, count: u32) -> Result<()> {
+        self.bits(count).map(|_| ())
+    }
+
+    /// Unsigned Exp-Golomb code.
+    fn ue(&mut self) -> Result<u32> {
+        let mut zeros = 0;
+        while !self.flag()? {
+            zeros += 1;
+            if zeros > 31 {
+                bail!("Invalid Exp-Golomb code");
+            }
+        }
+        Ok((1 << zeros) - 1 + self.bits(zeros)?)
+    }
+
+    /// Signed Exp-Golomb code.
+    fn se(&mut self) -> Result<i32> {
+        let code = self.ue()? as i64;
+        Ok(match code % 2 {
+            1 => (code + 1) / 2,
+            _ => -code / 2,
+        } as i32)
+    }
+
+    fn skip_scaling_list(&mut self, size: usize) -> Result<()> {
+        let (mut last, mut next) = (8, 8);
+        for _ in 0..size {
+            if next != 0 {
+                next = (last + self.se()? + 256) % 256;
+            }
+            if next != 0 {
+                last = next;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes the bits of an RBSP, most significant first.
+    #[derive(Default)]
+    struct BitWriter {
+        bytes: Vec<u8>,
+        position: usize,
+    }
+
+    impl BitWriter {
+        fn flag(&mut self, bit: bool) {
+            if self.position / 8 == self.bytes.len() {
+                self.bytes.push(0);
+            }
+            if bit {
+                *self.bytes.last_mut().unwrap() |= 1 << (7 - self.position % 8);
+            }
+            self.position += 1;
+        }
+
+        fn bits(&mut self, value: u32, count: u32) {
+            for i in (0..count).rev() {
+                self.flag((value >> i) & 1 == 1);
+            }
+        }
+
+        fn ue(&mut self, value: u32) {
+            let code = value + 1;
+            let length = 32 - code.leading_zeros();
+            self.bits(0, length - 1);
+            self.bits(code, length);
+        }
+
+        /// NAL unit of `header` with the RBSP, trailing bits and emulation prevention added.
+        fn nal(mut self, header: u8) -> Vec<u8> {
+            self.flag(true);
+            let mut nal = vec![header];
+            let mut zeros = 0;
+            for byte in self.bytes {
+                if zeros >= 2 && byte <= 3 {
+                    nal.push(3);
+                    zeros = 0;
+                }
+                zeros = if byte == 0 { zeros + 1 } else { 0 };
+                nal.push(byte);
+            }
+            nal
+        }
+    }
+
+    struct Fields {
+        profile: u8,
+        chroma_format: u32,
+        bit_depth: u32,
+        scaling_lists: bool,
+        pic_order_cnt_type: u32,
+        /// Full range flag and color description of the VUI.
+        vui: Option<(bool, Option<[u8; 3]>)>,
+    }
+
+    /// SPS of a 1920x1080 stream.
+    fn encode(fields: &Fields) -> Vec<u8> {
+        let mut bits = BitWriter::default();
+        bits.bits(fields.profile as u32, 8);
+        bits.bits(0, 8); // Constraint flags
+        bits.bits(40, 8); // level_idc
+        bits.ue(0); // seq_parameter_set_id
+        if HIGH_PROFILES.contains(&fields.profile) {
+            bits.ue(fields.chroma_format);
+            if fields.chroma_format == 3 {
+                bits.flag(false);
+            }
+            bits.ue(fields.bit_depth - 8);
+            bits.ue(fields.bit_depth - 8);
+            bits.flag(false);
+            bits.flag(fields.scaling_lists);
+            if fields.scaling_lists {
+                let lists = if fields.chroma_format == 3 { 12 } else { 8 };
+                for i in 0..lists {
+                    // Flat first list, the other ones left to their defaults
+                    bits.flag(i == 0);
+                    if i == 0 {
+                        for _ in 0..16 {
+                            bits.ue(0);
+                        }
+                    }
+                }
+            }
+        }
+        bits.ue(0); // log2_max_frame_num_minus4
+        bits.ue(fields.pic_order_cnt_type);
+        match fields.pic_order_cnt_type {
+            0 => bits.ue(2),
+            1 => {
+                bits.flag(false);
+                bits.ue(1); // offset_for_non_ref_pic, 1
+                bits.ue(2); // offset_for_top_to_bottom_field, -1
+                bits.ue(2);
+                bits.ue(3);
+                bits.ue(4);
+            }
+            _ => {}
+        }
+        bits.ue(4); // max_num_ref_frames
+        bits.flag(false);
+        bits.ue(119);
+        bits.ue(67);
+        bits.flag(true); // frame_mbs_only_flag
+        bits.flag(true);
+        bits.flag(true); // Cropped to 1080 lines
+        for offset in [0, 0, 0, 4] {
+            bits.ue(offset);
+        }
+
+        bits.flag(fields.vui.is_some());
+        if let Some((full_range, color)) = fields.vui {
+            bits.flag(true); // aspect_ratio_info_present_flag
+            bits.bits(255, 8);
+            bits.bits(0x0001_0001, 32);
+            bits.flag(false);
+            bits.flag(true);
+            bits.bits(5, 3);
+            bits.flag(full_range);
+            bits.flag(color.is_some());
+            for code in color.into_iter().flatten() {
+                bits.bits(code as u32, 8);
+            }
+        }
+        bits.nal(0x67)
+    }
+
+    const HIGH: Fields = Fields {
+        profile: 100,
+        chroma_format: 1,
+        bit_depth: 8,
+        scaling_lists: false,
+        pic_order_cnt_type: 0,
+        vui: Some((false, Some([1, 1, 1]))),
+    };
+
+    #[test]
+    fn high_profile() {
+        let sps = Sps::parse(&encode(&HIGH)).unwrap();
+        assert_eq!((sps.chroma_format, sps.bit_depth), (1, 8));
+        assert_eq!(sps.color, Some(ColorTags::BT709_LIMITED));
+
+        let sps = Sps::parse(&encode(&Fields {
+            chroma_format: 3,
+            scaling_lists: true,
+            pic_order_cnt_type: 1,
+            vui: Some((true, Some([9, 16, 9]))),
+            ..HIGH
+        }))
+        .unwrap();
+        assert_eq!((sps.chroma_format, sps.bit_depth), (3, 8));
+        let color = ColorTags {
+            primaries: 9,
+            transfer: 16,
+            matrix: 9,
+            full_range: true,
+        };
+        assert_eq!(sps.color, Some(color));
+    }
+
+    #[test]
+    fn high_10_without_color_description() {
+        let sps = Sps::parse(&encode(&Fields {
+            profile: 110,
+            bit_depth: 10,
+            pic_order_cnt_type: 2,
+            vui: Some((true, None)),
+            ..HIGH
+        }))
+        .unwrap();
+        assert_eq!((sps.chroma_format, sps.bit_depth), (1, 10));
+        let unspecified = ColorTags {
+            primaries: 2,
+            transfer: 2,
+            matrix: 2,
+            full_range: true,
+        };
+        assert_eq!(sps.color, Some(unspecified));
+    }
+
+    #[test]
+    fn main_profile_without_vui() {
+        let sps = Sps::parse(&encode(&Fields {
+            profile: 77,
+            vui: None,
+            ..HIGH
+        }))
+        .unwrap();
+        assert_eq!((sps.chroma_format, sps.bit_depth), (1, 8));
+        assert_eq!(sps.color, None);
+    }
+
+    #[test]
+    fn invalid() {
+        let mut pps = encode(&HIGH);
+        pps[0] = 0x68;
+        assert!(Sps::parse(&pps).is_err());
+        assert!(Sps::parse(&[]).is_err());
+        assert!(Sps::parse(&encode(&HIGH)[..8]).is_err());
+    }
+
+    #[test]
+    fn emulation_prevention() {
+        assert_eq!(
+            unescape(&[0, 0, 3, 1, 0, 0, 3, 0, 0, 3]),
+            [0, 0, 1, 0, 0, 0, 0]
+        );
+        assert_eq!(unescape(&[0, 3, 0, 3]), [0, 3, 0, 3]);
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::{Context, Result};
+use compositor_pipeline::Pipeline;
+use compositor_render::image::{ImageSource, ImageSpec, ImageType};
+use compositor_render::scene::*;
+use compositor_render::shader::ShaderSpec;
+use compositor_render::{RendererId, RendererSpec, Resolution};
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+use crate::config::{ColorCorrectionConfig, InputConfig};
+
+/// Renderer ID of the shader shared by every corrected input.
+const SHADER_ID: &str = "color_correction";
+
+/// Registers the correction shader, and the LUT of every input using one.
+///
+/// Must be called before any corrected component is rendered.
+pub fn register(pipeline: &Arc<Mutex<Pipeline>>, inputs: &[InputConfig]) -> Result<()> {
+    let corrected: Vec<_> = inputs
+        .iter()
+        .filter_map(|input| Some((input.id(), input.correction()?)))
+        .collect();
+    if corrected.is_empty() {
+        return Ok(());
+    }
+
+    Pipeline::register_renderer(
+        pipeline,
+        RendererId(Arc::from(SHADER_ID)),
+        RendererSpec::Shader(ShaderSpec {
+            source: include_str!("color_correction.wgsl").into(),
+        }),
+    )?;
+
+    for (id, correction) in corrected {
+        register_lut(pipeline, id, correction)?;
+    }
+
+    Ok(())
+}
+
+/// Registers the LUT of an input, if it uses one, once the shader is registered.
+pub fn register_lut(
+    pipeline: &Arc<Mutex<Pipeline>>,
+    id: &str,
+    correction: &ColorCorrectionConfig,
+) -> Result<()> {
+    let Some(lut) = &correction.lut else {
+        return Ok(());
+    };
+    Pipeline::register_renderer(
+        pipeline,
+        lut_id(id),
+        RendererSpec::Image(ImageSpec {
+            src: ImageSource::LocalPath {
+                path: lut.to_string_lossy().to_string(),
+            },
+            image_type: ImageType::Png,
+        }),
+    )?;
+    info!("Registered correction LUT {} for {id}", lut.display());
+
+    Ok(())
+}
+
+/// Wraps the component of an input in the correction shader.
+///
+/// The shader renders at a fixed size, so corrected inputs are stretched to `resolution`.
+pub fn wrap(
+    id: &str,
+    correction: &ColorCorrectionConfig,
+    content: Component,
+    resolution: Resolution,
+) -> Result<Component> {
+    let mut children = vec![content];
+    let mut lut_size = 0.0;
+    if let Some(lut) = &correction.lut {
+        let (_, height) = image::image_dimensions(lut)
+            .with_context(|| format!("Cannot read LUT {}", lut.display()))?;
+        lut_size = height as f32;
+        children.push(Component::Image(ImageComponent {
+            id: None,
+            image_id: lut_id(id),
+            width: None,
+            height: None,
+        }));
+    }
+
+    let [r, g, b] = correction.matrix;
+    let offset = correction.offset;
+    let param = ShaderParam::Struct(vec![
+        field("row_r", [r[0], r[1], r[2], offset[0]]),
+        field("row_g", [g[0], g[1], g[2], offset[1]]),
+        field("row_b", [b[0], b[1], b[2], offset[2]]),
+        field("lut", [lut_size, correction.lut_strength, 0.0, 0.0]),
+    ]);
+
+    Ok(Component::Shader(ShaderComponent {
+        id: None,
+        children,
+        shader_id: RendererId(Arc::from(SHADER_ID)),
+        shader_param: Some(param),
+        size: Size {
+            width: resolution.width as f32,
+            height: resolution.height as f32,
+        },
+    }))
+}
+
+/// Renderer ID of the LUT image of an input.
+pub fn lut_id(input_id: &str) -> RendererId {
+    RendererId(Arc::from(format!("{input_id}_lut")))
+}
+
+fn field(name: &str, value: [f32; 4]) -> ShaderParamStructField {
+    ShaderParamStructField {
+        field_name: name.to_string(),
+        value: ShaderParam::List(value.into_iter().map(ShaderParam::F32).collect()),
+    }
+}
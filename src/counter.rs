@@ -0,0 +1,106 @@
+use anyhow::Result;
+use compositor_pipeline::pipeline::GraphicsContext;
+use compositor_pipeline::queue::QueueInputOptions;
+use compositor_pipeline::Pipeline;
+use image::{Rgba, RgbaImage};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::info;
+
+use crate::config::ResolutionConfig;
+use crate::raw_input;
+
+/// Bits of the burned-in frame index, enough for 6 days at 30fps.
+pub const BITS: u32 = 24;
+
+const BACKGROUND: Rgba<u8> = Rgba([128, 128, 128, 255]);
+/// Cells further than this from black or white, in `[0, 1]`, are blends of several frames.
+const MAX_CELL_ERROR: f32 = 0.15;
+
+/// Registers a raw data input whose frames carry their index, one every `interval`.
+///
+/// The index is drawn as a row of black and white cells along the top of the frame,
+/// see [`decode`].
+pub fn register(
+    pipeline: &Arc<Mutex<Pipeline>>,
+    graphics_context: &GraphicsContext,
+    id: &str,
+    resolution: ResolutionConfig,
+    interval: Duration,
+    queue_options: QueueInputOptions,
+) -> Result<()> {
+    let video = raw_input::register(pipeline, id, queue_options)?;
+
+    let graphics_context = graphics_context.clone();
+    let input_id = id.to_string();
+    std::thread::Builder::new()
+        .name(format!("frame counter {id}"))
+        .spawn(move || {
+            let frames = (0..).map(|index: u32| {
+                let image = draw(index, resolution.width as u32, resolution.height as u32);
+                Ok((image, interval * index))
+            });
+            raw_input::play(video, &graphics_context, &input_id, frames);
+        })?;
+
+    info!(
+        "Registered frame counter {id} at {:.3}fps",
+        1.0 / interval.as_secs_f64()
+    );
+    Ok(())
+}
+
+/// Gray frame with `index` as [`BITS`] cells along the top, most significant bit first.
+pub fn draw(index: u32, width: u32, height: u32) -> RgbaImage {
+    let mut image = RgbaImage::from_pixel(width, height, BACKGROUND);
+    for bit in 0..BITS {
+        let value = (index >> (BITS - 1 - bit)) & 1;
+        let color = Rgba([value as u8 * 255, value as u8 * 255, value as u8 * 255, 255]);
+        let (x0, x1, y1) = cell(bit, width, height);
+        for y in 0..y1 {
+            for x in x0..x1 {
+                image.put_pixel(x, y, color);
+            }
+        }
+    }
+    image
+}
+
+/// Reads the index drawn by [`draw`], `None` if a cell is neither black nor white, which
+/// happens when frames are blended.
+///
+/// The center of each cell is averaged, so scaling and encoding artifacts are tolerated.
+pub fn decode(image: &RgbaImage) -> Option<u32> {
+    let (width, height) = image.dimensions();
+    let mut index = 0;
+    for bit in 0..BITS {
+        let (x0, x1, y1) = cell(bit, width, height);
+        let (margin_x, margin_y) = ((x1 - x0) / 4, y1 / 4);
+
+        let mut sum = 0.0;
+        let mut count = 0;
+        for y in margin_y..y1 - margin_y {
+            for x in x0 + margin_x..x1 - margin_x {
+                let [r, g, b, _] = image.get_pixel(x, y).0;
+                sum += (r as f32 + g as f32 + b as f32) / (3.0 * 255.0);
+                count += 1;
+            }
+        }
+        let value = sum / count.max(1) as f32;
+
+        index <<= 1;
+        if value > 1.0 - MAX_CELL_ERROR {
+            index |= 1;
+        } else if value >= MAX_CELL_ERROR {
+            return None;
+        }
+    }
+    Some(index)
+}
+
+/// Horizontal range and bottom of the cell of `bit`, cells span the top eighth of the frame.
+fn cell(bit: u32, width: u32, height: u32) -> (u32, u32, u32) {
+    let cell_width = width / BITS;
+    let x0 = bit * cell_width;
+    (x0, x0 + cell_width, (height / 8).max(1))
+}
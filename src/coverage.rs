@@ -0,0 +1,213 @@
+//! Scene features exercised by the scenes a run shows, so gaps in a test plan are visible.
+
+use compositor_render::scene::*;
+use std::collections::{BTreeSet, HashMap};
+use tracing::{info, warn};
+
+/// Component types and properties tracked, in the order they are reported.
+pub const FEATURES: &[&str] = &[
+    "view",
+    "rescaler",
+    "tiles",
+    "image",
+    "input_stream",
+    "shader",
+    "web_view",
+    "text",
+    "row",
+    "column",
+    "absolute_position",
+    "rotation",
+    "padding",
+    "border",
+    "border_radius",
+    "box_shadow",
+    "translucent_background",
+    "transition",
+    "overflow_visible",
+    "overflow_hidden",
+    "overflow_fit",
+    "rescale_fit",
+    "rescale_fill",
+];
+
+/// Number of shown scenes using each feature.
+#[derive(Debug, Default)]
+pub struct SceneCoverage {
+    scenes: usize,
+    counts: HashMap<&'static str, usize>,
+}
+
+impl SceneCoverage {
+    /// Counts the features of a scene an output switched to.
+    pub fn record(&mut self, component: &Component) {
+        let mut features = BTreeSet::new();
+        collect(component, &mut features);
+        self.scenes += 1;
+        for feature in features {
+            *self.counts.entry(feature).or_default() += 1;
+        }
+    }
+
+    /// Scenes recorded so far.
+    pub fn scenes(&self) -> usize {
+        self.scenes
+    }
+
+    /// Features used by at least one scene, with the number of scenes using them.
+    pub fn exercised(&self) -> Vec<(&'static str, usize)> {
+        FEATURES
+            .iter()
+            .filter_map(|feature| Some((*feature, *self.counts.get(feature)?)))
+            .collect()
+    }
+
+    /// Features no scene used.
+    pub fn missing(&self) -> Vec<&'static str> {
+        FEATURES
+            .iter()
+            .copied()
+            .filter(|feature| !self.counts.contains_key(feature))
+            .collect()
+    }
+
+    pub fn log_summary(&self) {
+        if self.scenes == 0 {
+            return;
+        }
+        let exercised: Vec<String> = self
+            .exercised()
+            .iter()
+            .map(|(feature, scenes)| format!("{feature} ({scenes})"))
+            .collect();
+        info!(
+            "Scene coverage over {} scenes: {}",
+            self.scenes,
+            exercised.join(", ")
+        );
+        let missing = self.missing();
+        if !missing.is_empty() {
+            warn!("Scene features never shown: {}", missing.join(", "));
+        }
+    }
+}
+
+fn collect(component: &Component, features: &mut BTreeSet<&'static str>) {
+    let children: Vec<&Component> = match component {
+        Component::InputStream(_) => {
+            features.insert("input_stream");
+            vec![]
+        }
+        Component::Image(_) => {
+            features.insert("image");
+            vec![]
+        }
+        Component::Text(_) => {
+            features.insert("text");
+            vec![]
+        }
+        Component::Shader(shader) => {
+            features.insert("shader");
+            shader.children.iter().collect()
+        }
+        Component::Web(web) => {
+            features.insert("web_view");
+            web.children.iter().collect()
+        }
+        Component::Tiles(tiles) => {
+            features.insert("tiles");
+            if tiles.transition.is_some() {
+                features.insert("transition");
+            }
+            tiles.children.iter().collect()
+        }
+        Component::View(view) => {
+            features.insert("view");
+            features.insert(match view.direction {
+                ViewChildrenDirection::Row => "row",
+                ViewChildrenDirection::Column => "column",
+            });
+            features.insert(match view.overflow {
+                Overflow::Visible => "overflow_visible",
+                Overflow::Hidden => "overflow_hidden",
+                Overflow::Fit => "overflow_fit",
+            });
+            let padding = &view.padding;
+            if [padding.top, padding.right, padding.bottom, padding.left]
+                .iter()
+                .any(|&side| side != 0.0)
+            {
+                features.insert("padding");
+            }
+            if !matches!(view.background_color.3, 0 | 255) {
+                features.insert("translucent_background");
+            }
+            position(&view.position, features);
+            decoration(
+                view.border_width,
+                &view.border_radius,
+                !view.box_shadow.is_empty(),
+                features,
+            );
+            if view.transition.is_some() {
+                features.insert("transition");
+            }
+            view.children.iter().collect()
+        }
+        Component::Rescaler(rescaler) => {
+            features.insert("rescaler");
+            features.insert(match rescaler.mode {
+                RescaleMode::Fit => "rescale_fit",
+                RescaleMode::Fill => "rescale_fill",
+            });
+            position(&rescaler.position, features);
+            decoration(
+                rescaler.border_width,
+                &rescaler.border_radius,
+                !rescaler.box_shadow.is_empty(),
+                features,
+            );
+            if rescaler.transition.is_some() {
+                features.insert("transition");
+            }
+            vec![&*rescaler.child]
+        }
+    };
+    for child in children {
+        collect(child, features);
+    }
+}
+
+fn position(position: &Position, features: &mut BTreeSet<&'static str>) {
+    if let Position::Absolute(absolute) = position {
+        features.insert("absolute_position");
+        if absolute.rotation_degrees != 0.0 {
+            features.insert("rotation");
+        }
+    }
+}
+
+fn decoration(
+    border_width: f32,
+    radius: &BorderRadius,
+    shadowed: bool,
+    features: &mut BTreeSet<&'static str>,
+) {
+    if border_width > 0.0 {
+        features.insert("border");
+    }
+    if [
+        radius.top_left,
+        radius.top_right,
+        radius.bottom_right,
+        radius.bottom_left,
+    ]
+    .iter()
+    .any(|&radius| radius != 0.0)
+    {
+        features.insert("border_radius");
+    }
+    if shadowed {
+        features.insert("box_shadow");
+    }
+}
@@ -0,0 +1,308 @@
+use anyhow::Result;
+use image::RgbaImage;
+use ratatui::buffer::Buffer;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, Paragraph, Widget};
+use ratatui::DefaultTerminal;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::monitor::ChannelLevel;
+use crate::terminal::{self, HALF_BLOCK};
+
+/// Interval between two redraws, which also bounds how often frames are read back.
+pub const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Number of events kept in the event log panel.
+const MAX_EVENTS: usize = 200;
+
+/// Cells of an audio meter, spanning [`METER_FLOOR_DB`] to 0dBFS.
+const METER_WIDTH: usize = 20;
+const METER_FLOOR_DB: f32 = -60.0;
+
+/// Interactive terminal dashboard: metrics, recent events, scene controls and a
+/// preview of the latest frame.
+///
+/// The terminal is restored when the dashboard is dropped. Logs should be redirected
+/// away from the terminal while it runs.
+pub struct Dashboard {
+    terminal: DefaultTerminal,
+    events: VecDeque<String>,
+}
+
+/// What the dashboard shows besides events.
+#[derive(Debug, Clone, Default)]
+pub struct DashboardState {
+    pub elapsed: Duration,
+    pub frames: usize,
+    /// Frames per second over the last refresh of the dashboard.
+    pub fps: f64,
+    pub skipped_frames: usize,
+    /// Frames missing from the output, see [`crate::analysis::pts_gaps::PtsGapCheck`].
+    pub queue_drops: usize,
+    pub harness_drops: usize,
+    pub scenes: Vec<String>,
+    pub current_scene: usize,
+    /// Whether scenes are switched automatically.
+    pub cycling: bool,
+    /// Levels of the mixed audio per channel, empty without audio.
+    pub audio_levels: Vec<ChannelLevel>,
+    /// Profile the preview is corrected for, see [`crate::icc`].
+    pub color_management: Option<String>,
+    /// Display the preview settings are saved for, see [`crate::display`].
+    pub display: Option<String>,
+    /// Magnification of the preview.
+    pub zoom: f32,
+    /// Share of the width the preview takes, in percent, from 20 to 90.
+    pub preview_width: u16,
+}
+
+/// Action requested with a key press, also the steps of [`crate::macros`] scripts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Command {
+    NextScene,
+    PreviousScene,
+    SelectScene(usize),
+    ToggleCycling,
+    ZoomIn,
+    ZoomOut,
+    WiderPreview,
+    NarrowerPreview,
+    ToggleDisplayCorrection,
+    /// Saves the next frame to a PNG file.
+    Snapshot,
+    /// Saves the preview settings for the display.
+    SaveSettings,
+    Quit,
+}
+
+impl Dashboard {
+    pub fn start() -> Result<Self> {
+        Ok(Self {
+            terminal: ratatui::try_init()?,
+            events: VecDeque::new(),
+        })
+    }
+
+    /// Adds an event to the event log, the oldest ones are dropped.
+    pub fn log(&mut self, event: impl Into<String>) {
+        if self.events.len() == MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event.into());
+    }
+
+    pub fn draw(&mut self, state: &DashboardState, preview: Option<&RgbaImage>) -> Result<()> {
+        let events = &self.events;
+        self.terminal.draw(|frame| {
+            let [main, help] =
+                Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+            let width = state.preview_width.clamp(20, 90);
+            let [preview_area, side] = Layout::horizontal([
+                Constraint::Percentage(width),
+                Constraint::Percentage(100 - width),
+            ])
+            .areas(main);
+            let meters = match state.audio_levels.len() {
+                0 => 0,
+                channels => channels as u16 + 2,
+            };
+            let [metrics_area, audio_area, scenes_area, events_area] = Layout::vertical([
+                Constraint::Length(8),
+                Constraint::Length(meters),
+                Constraint::Length(state.scenes.len().min(9) as u16 + 2),
+                Constraint::Min(3),
+            ])
+            .areas(side);
+
+            let mut title = " Preview ".to_string();
+            if state.zoom > 1.0 {
+                title.push_str(&format!("· {:.1}x ", state.zoom));
+            }
+            if let Some(profile) = &state.color_management {
+                title.push_str(&format!("· ICC: {profile} "));
+            }
+            if let Some(display) = &state.display {
+                title.push_str(&format!("· {display} "));
+            }
+            let block = Block::bordered().title(title);
+            let inner = block.inner(preview_area);
+            frame.render_widget(block, preview_area);
+            if let Some(image) = preview {
+                frame.render_widget(Preview(image), inner);
+            }
+
+            let metrics = vec![
+                Line::from(format!("Elapsed   {:.1}s", state.elapsed.as_secs_f64())),
+                Line::from(format!("Frames    {}", state.frames)),
+                Line::from(format!("FPS       {:.1}", state.fps)),
+                Line::from(format!("Skipped   {}", state.skipped_frames)),
+                Line::from(format!(
+                    "Dropped   {} queue, {} harness",
+                    state.queue_drops, state.harness_drops
+                )),
+                Line::from(format!(
+                    "Cycling   {}",
+                    if state.cycling { "on" } else { "off" }
+                )),
+            ];
+            frame.render_widget(
+                Paragraph::new(metrics).block(Block::bordered().title(" Metrics ")),
+                metrics_area,
+            );
+
+            if !state.audio_levels.is_empty() {
+                let names: &[&str] = match state.audio_levels.len() {
+                    1 => &["M"],
+                    _ => &["L", "R"],
+                };
+                let lines: Vec<Line> = state
+                    .audio_levels
+                    .iter()
+                    .zip(names)
+                    .map(|(level, name)| meter(name, level))
+                    .collect();
+                frame.render_widget(
+                    Paragraph::new(lines).block(Block::bordered().title(" Audio ")),
+                    audio_area,
+                );
+            }
+
+            let scenes: Vec<ListItem> = state
+                .scenes
+                .iter()
+                .enumerate()
+                .map(|(i, scene)| {
+                    let item = ListItem::new(format!("{} {scene}", i + 1));
+                    if i == state.current_scene {
+                        item.style(Style::new().reversed())
+                    } else {
+                        item
+                    }
+                })
+                .collect();
+            frame.render_widget(
+                List::new(scenes).block(Block::bordered().title(" Scenes ")),
+                scenes_area,
+            );
+
+            // Latest events at the bottom
+            let visible = events_area.height.saturating_sub(2) as usize;
+            let recent: Vec<ListItem> = events
+                .iter()
+                .skip(events.len().saturating_sub(visible))
+                .map(|event| ListItem::new(event.as_str()))
+                .collect();
+            frame.render_widget(
+                List::new(recent).block(Block::bordered().title(" Events ")),
+                events_area,
+            );
+
+            frame.render_widget(
+                Line::from(
+                    " q quit  \u{2190}/\u{2192} previous/next scene  1-9 select scene  space toggle cycling  +/- zoom  [ ] width  c ICC  s save  f snapshot",
+                )
+                .dim(),
+                help,
+            );
+        })?;
+
+        Ok(())
+    }
+
+    /// Waits up to `timeout` for a key press, returns the command it maps to.
+    pub fn poll_command(&self, timeout: Duration) -> Result<Option<Command>> {
+        if !event::poll(timeout)? {
+            return Ok(None);
+        }
+        let Event::Key(key) = event::read()? else {
+            return Ok(None);
+        };
+        if key.kind != KeyEventKind::Press {
+            return Ok(None);
+        }
+
+        let command = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => Command::Quit,
+            KeyCode::Right | KeyCode::Char('n') => Command::NextScene,
+            KeyCode::Left | KeyCode::Char('p') => Command::PreviousScene,
+            KeyCode::Char(' ') => Command::ToggleCycling,
+            KeyCode::Char('+') | KeyCode::Char('=') => Command::ZoomIn,
+            KeyCode::Char('-') => Command::ZoomOut,
+            KeyCode::Char(']') => Command::WiderPreview,
+            KeyCode::Char('[') => Command::NarrowerPreview,
+            KeyCode::Char('c') => Command::ToggleDisplayCorrection,
+            KeyCode::Char('s') => Command::SaveSettings,
+            KeyCode::Char('f') => Command::Snapshot,
+            KeyCode::Char(c @ '1'..='9') => Command::SelectScene(c as usize - '1' as usize),
+            _ => return Ok(None),
+        };
+        Ok(Some(command))
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
+
+/// Bar of the RMS level of a channel in dBFS, with its peak and clipped samples.
+fn meter(name: &str, level: &ChannelLevel) -> Line<'static> {
+    let (rms, peak) = (dbfs(level.rms), dbfs(level.peak));
+    let filled = ((rms - METER_FLOOR_DB) / -METER_FLOOR_DB * METER_WIDTH as f32).round() as usize;
+    let color = match peak {
+        peak if peak >= 0.0 => Color::Red,
+        peak if peak >= -6.0 => Color::Yellow,
+        _ => Color::Green,
+    };
+
+    let mut spans = vec![
+        Span::raw(format!("{name} ")),
+        Span::styled("\u{2588}".repeat(filled), Style::new().fg(color)),
+        Span::raw("\u{00B7}".repeat(METER_WIDTH - filled)).dim(),
+        Span::raw(format!(" {rms:>5.1} dB, peak {peak:>5.1}")),
+    ];
+    if level.clipped > 0 {
+        spans.push(Span::raw(format!(" CLIP {}", level.clipped)).red().bold());
+    }
+    Line::from(spans)
+}
+
+/// Level in decibels relative to full scale, down to [`METER_FLOOR_DB`].
+fn dbfs(level: f32) -> f32 {
+    (20.0 * level.log10()).clamp(METER_FLOOR_DB, 0.0)
+}
+
+/// Frame drawn with half blocks, fitted to the area.
+struct Preview<'a>(&'a RgbaImage);
+
+impl Widget for Preview<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.is_empty() {
+            return;
+        }
+
+        // Fit the height too, two pixel rows per cell
+        let aspect = self.0.height() as f64 / self.0.width().max(1) as f64;
+        let columns = (area.width as f64).min(area.height as f64 * 2.0 / aspect) as u32;
+        let small = terminal::downscale(self.0, columns);
+
+        for y in 0..(small.height() / 2).min(area.height as u32) {
+            for x in 0..small.width().min(area.width as u32) {
+                let top = small.get_pixel(x, y * 2);
+                let bottom = small.get_pixel(x, y * 2 + 1);
+                if let Some(cell) = buf.cell_mut((area.x + x as u16, area.y + y as u16)) {
+                    cell.set_char(HALF_BLOCK)
+                        .set_fg(Color::Rgb(top[0], top[1], top[2]))
+                        .set_bg(Color::Rgb(bottom[0], bottom[1], bottom[2]));
+                }
+            }
+        }
+    }
+}
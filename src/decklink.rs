@@ -0,0 +1,74 @@
+use anyhow::Result;
+use compositor_pipeline::queue::QueueInputOptions;
+use compositor_pipeline::Pipeline;
+use std::sync::{Arc, Mutex};
+
+/// Which capture card input to open, the first one when nothing is set.
+#[derive(Debug, Clone, Default)]
+pub struct DecklinkSelector {
+    pub subdevice_index: Option<u32>,
+    pub display_name: Option<String>,
+    pub persistent_id: Option<u32>,
+}
+
+/// Registers a Blackmagic DeckLink capture card input, e.g. an SDI source.
+///
+/// The card detects the video mode of the signal. Only available in builds with the
+/// `decklink` feature.
+pub fn register(
+    pipeline: &Arc<Mutex<Pipeline>>,
+    id: &str,
+    selector: DecklinkSelector,
+    queue_options: QueueInputOptions,
+) -> Result<()> {
+    #[cfg(feature = "decklink")]
+    return capture::register(pipeline, id, selector, queue_options);
+
+    #[cfg(not(feature = "decklink"))]
+    {
+        let _ = (pipeline, selector, queue_options);
+        anyhow::bail!("DeckLink input {id:?} requires a build with the decklink feature");
+    }
+}
+
+#[cfg(feature = "decklink")]
+mod capture {
+    use anyhow::Result;
+    use compositor_pipeline::pipeline::input::decklink::DeckLinkOptions;
+    use compositor_pipeline::pipeline::input::InputOptions;
+    use compositor_pipeline::pipeline::{InputId, RegisterInputOptions};
+    use compositor_pipeline::queue::QueueInputOptions;
+    use compositor_pipeline::Pipeline;
+    use std::sync::{Arc, Mutex};
+    use tracing::info;
+
+    use super::DecklinkSelector;
+
+    pub fn register(
+        pipeline: &Arc<Mutex<Pipeline>>,
+        id: &str,
+        selector: DecklinkSelector,
+        queue_options: QueueInputOptions,
+    ) -> Result<()> {
+        let description = format!("{selector:?}");
+        let input_options = InputOptions::DeckLink(DeckLinkOptions {
+            subdevice_index: selector.subdevice_index,
+            display_name: selector.display_name,
+            persistent_id: selector.persistent_id,
+            enable_audio: false,
+            // Let the card pick the format of the signal
+            pixel_format: None,
+        });
+        Pipeline::register_input(
+            pipeline,
+            InputId(Arc::from(id)),
+            RegisterInputOptions {
+                input_options,
+                queue_options,
+            },
+        )?;
+        info!("Registered DeckLink input {id} ({description})");
+
+        Ok(())
+    }
+}
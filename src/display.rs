@@ -0,0 +1,220 @@
+//! Identity of the attached display, read from its EDID, and the preview settings saved for
+//! each display, so moving the harness between a desk monitor and the reference display
+//! brings back the settings of each.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::config::PreviewConfig;
+
+/// Connectors of the GPUs, each with its `status` and `edid`.
+const DRM_DIR: &str = "/sys/class/drm";
+/// Header every EDID starts with.
+const EDID_HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+
+/// Display told apart by the manufacturer, product code and serial number of its EDID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayIdentity {
+    /// `{manufacturer}-{product}-{serial}`, e.g. `DEL-A0B1-4C32414B`.
+    pub id: String,
+    /// Name of the display in its EDID, e.g. `DELL U2720Q`.
+    pub name: Option<String>,
+}
+
+impl DisplayIdentity {
+    pub fn parse_edid(edid: &[u8]) -> Result<Self> {
+        if edid.len() < 128 || edid[..8] != EDID_HEADER {
+            bail!("Not an EDID");
+        }
+        // Three letters of 5 bits each, from 'A' = 1
+        let code = u16::from_be_bytes([edid[8], edid[9]]);
+        let manufacturer: String = [10, 5, 0]
+            .iter()
+            .map(|shift| (b'A' - 1 + ((code >> shift) & 0x1F) as u8) as char)
+            .collect();
+        let product = u16::from_le_bytes([edid[10], edid[11]]);
+        let serial = u32::from_le_bytes([edid[12], edid[13], edid[14], edid[15]]);
+
+        // Display descriptors of 18 bytes, the name one tagged 0xFC
+        let name = (0..4)
+            .map(|i| &edid[54 + i * 18..72 + i * 18])
+            .find(|descriptor| descriptor[..3] == [0, 0, 0] && descriptor[3] == 0xFC)
+            .map(|descriptor| {
+                let text = &descriptor[5..];
+                let end = text.iter().position(|&byte| byte == b'\n');
+                String::from_utf8_lossy(&text[..end.unwrap_or(text.len())])
+                    .trim()
+                    .to_string()
+            })
+            .filter(|name| !name.is_empty());
+
+        Ok(Self {
+            id: format!("{manufacturer}-{product:04X}-{serial:08X}"),
+            name,
+        })
+    }
+}
+
+impl fmt::Display for DisplayIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{name} ({})", self.id),
+            None => write!(f, "{}", self.id),
+        }
+    }
+}
+
+/// Displays connected to the GPUs, in connector order. Only Linux exposes them.
+pub fn connected_displays() -> Result<Vec<DisplayIdentity>> {
+    let mut connectors: Vec<PathBuf> = std::fs::read_dir(DRM_DIR)
+        .with_context(|| format!("Cannot list the display connectors of {DRM_DIR}"))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.join("edid").exists())
+        .collect();
+    connectors.sort();
+
+    let mut displays = vec![];
+    for connector in connectors {
+        let status = std::fs::read_to_string(connector.join("status")).unwrap_or_default();
+        if status.trim() != "connected" {
+            continue;
+        }
+        let edid = std::fs::read(connector.join("edid"))?;
+        if let Ok(display) = DisplayIdentity::parse_edid(&edid) {
+            displays.push(display);
+        }
+    }
+    Ok(displays)
+}
+
+/// The display `monitor` names, by ID or by the name in its EDID, or the first connected
+/// one.
+pub fn attached_display(monitor: Option<&str>) -> Result<DisplayIdentity> {
+    let displays = connected_displays()?;
+    match monitor {
+        Some(monitor) => displays
+            .into_iter()
+            .find(|display| display.id == monitor || display.name.as_deref() == Some(monitor))
+            .with_context(|| format!("Display {monitor:?} is not connected")),
+        None => displays.into_iter().next().context("No display connected"),
+    }
+}
+
+/// Preview settings saved for a display, see [`PreviewConfig`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PreviewSettings {
+    pub correct_display: bool,
+    pub icc_profile: Option<PathBuf>,
+    pub zoom: f32,
+    pub preview_width: u16,
+}
+
+impl PreviewSettings {
+    pub fn of(config: &PreviewConfig) -> Self {
+        Self {
+            correct_display: config.correct_display,
+            icc_profile: config.icc_profile.clone(),
+            zoom: config.zoom,
+            preview_width: config.preview_width,
+        }
+    }
+
+    pub fn apply(&self, config: &mut PreviewConfig) {
+        config.correct_display = self.correct_display;
+        config.icc_profile = self.icc_profile.clone();
+        config.zoom = self.zoom;
+        config.preview_width = self.preview_width;
+        config.clamp();
+    }
+}
+
+/// Preview settings by display ID, in a TOML file.
+pub struct DisplayProfiles {
+    path: PathBuf,
+    profiles: BTreeMap<String, PreviewSettings>,
+}
+
+impl DisplayProfiles {
+    /// Reads the profiles saved to `path`, none if it does not exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        let profiles = match std::fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text)
+                .with_context(|| format!("Invalid display profiles {}", path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err).context(format!("Cannot read {}", path.display())),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            profiles,
+        })
+    }
+
+    pub fn get(&self, display: &DisplayIdentity) -> Option<&PreviewSettings> {
+        self.profiles.get(&display.id)
+    }
+
+    /// Saves `settings` for `display`, replacing those saved before.
+    pub fn save(&mut self, display: &DisplayIdentity, settings: PreviewSettings) -> Result<()> {
+        self.profiles.insert(display.id.clone(), settings);
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, toml::to_string(&self.profiles)?)
+            .with_context(|| format!("Cannot write {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// EDID of a Dell display, with a detailed timing first and `name` in the second
+    /// descriptor when set.
+    fn edid(name: Option<&[u8]>) -> Vec<u8> {
+        let mut edid = vec![0; 128];
+        edid[..8].copy_from_slice(&EDID_HEADER);
+        edid[8..10].copy_from_slice(&0x10ACu16.to_be_bytes());
+        edid[10..12].copy_from_slice(&0xA0B1u16.to_le_bytes());
+        edid[12..16].copy_from_slice(&0x4C32414Bu32.to_le_bytes());
+        edid[54..56].copy_from_slice(&[0x4D, 0xD0]);
+        if let Some(name) = name {
+            edid[72..77].copy_from_slice(&[0, 0, 0, 0xFC, 0]);
+            edid[77..90].fill(b' ');
+            edid[77..77 + name.len()].copy_from_slice(name);
+        }
+        edid
+    }
+
+    #[test]
+    fn identity() {
+        let display = DisplayIdentity::parse_edid(&edid(Some(b"DELL U2720Q\n"))).unwrap();
+        assert_eq!(display.id, "DEL-A0B1-4C32414B");
+        assert_eq!(display.name.as_deref(), Some("DELL U2720Q"));
+        assert_eq!(display.to_string(), "DELL U2720Q (DEL-A0B1-4C32414B)");
+
+        // Names of the full 13 characters have no line feed
+        let display = DisplayIdentity::parse_edid(&edid(Some(b"REFERENCE 31\""))).unwrap();
+        assert_eq!(display.name.as_deref(), Some("REFERENCE 31\""));
+    }
+
+    #[test]
+    fn unnamed() {
+        for name in [None, Some(&b"\n"[..])] {
+            let display = DisplayIdentity::parse_edid(&edid(name)).unwrap();
+            assert_eq!(display.name, None);
+            assert_eq!(display.to_string(), "DEL-A0B1-4C32414B");
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(DisplayIdentity::parse_edid(&edid(None)[..127]).is_err());
+        let mut edid = edid(None);
+        edid[0] = 0xFF;
+        assert!(DisplayIdentity::parse_edid(&edid).is_err());
+    }
+}
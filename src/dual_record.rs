@@ -0,0 +1,115 @@
+use anyhow::{Context, Result};
+use compositor_render::Resolution;
+use crossbeam_channel::Sender;
+use image::RgbaImage;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tracing::info;
+
+use crate::config::{EncoderConfig, FramerateConfig};
+use crate::raw_video::{self, RawVideoWriter};
+
+/// Writes the same frames to a lossless PNG sequence and an H264 MP4 file.
+///
+/// Frame `i` of the MP4 file is `frame_{i}.png`, and `frames.csv` maps each index to the
+/// pipeline PTS, so both recordings correspond 1:1 without matching them afterwards.
+/// The MP4 file is encoded by `ffmpeg` with the settings of the recordings of the pipeline:
+/// libx264 with the [`EncoderConfig`] settings, converted by [`raw_video::bt709_limited`]
+/// to YUV 4:2:0. Frames `ffmpeg` drops falling behind are left out of both recordings.
+pub struct DualRecorder {
+    frames_dir: PathBuf,
+    writer: RawVideoWriter,
+    png_sender: Sender<(usize, RgbaImage)>,
+    png_writers: Vec<JoinHandle<Result<()>>>,
+    pts: Vec<Duration>,
+}
+
+impl DualRecorder {
+    pub fn start(
+        mp4_path: &Path,
+        frames_dir: &Path,
+        resolution: Resolution,
+        framerate: FramerateConfig,
+        encoder: &EncoderConfig,
+    ) -> Result<Self> {
+        std::fs::create_dir_all(frames_dir)
+            .with_context(|| format!("Cannot create {}", frames_dir.display()))?;
+
+        let mut command = raw_video::command("rgba", resolution, framerate, false);
+        command
+            .args(["-c:v", "libx264", "-preset", encoder.preset.name()])
+            .args(
+                encoder
+                    .raw_options()
+                    .into_iter()
+                    .flat_map(|(key, value)| [format!("-{key}"), value]),
+            )
+            .args(raw_video::bt709_limited("yuv420p"))
+            .arg(mp4_path);
+        let writer = RawVideoWriter::start(command, "the MP4 recording")?;
+
+        // PNG encoding is the slow part, spread it over the cores
+        let (png_sender, png_receiver) = crossbeam_channel::bounded::<(usize, RgbaImage)>(8);
+        let workers = std::thread::available_parallelism().map_or(4, |n| n.get().min(8));
+        let png_writers = (0..workers)
+            .map(|worker| {
+                let receiver = png_receiver.clone();
+                let frames_dir = frames_dir.to_path_buf();
+                std::thread::Builder::new()
+                    .name(format!("png writer {worker}"))
+                    .spawn(move || -> Result<()> {
+                        for (index, image) in receiver {
+                            image.save(frames_dir.join(format!("frame_{index:06}.png")))?;
+                        }
+                        Ok(())
+                    })
+                    .context("Cannot spawn PNG writer")
+            })
+            .collect::<Result<_>>()?;
+
+        info!(
+            "Recording to {} and {}",
+            mp4_path.display(),
+            frames_dir.display()
+        );
+        Ok(Self {
+            frames_dir: frames_dir.to_path_buf(),
+            writer,
+            png_sender,
+            png_writers,
+            pts: vec![],
+        })
+    }
+
+    pub fn push(&mut self, image: RgbaImage, pts: Duration) -> Result<()> {
+        let index = self.pts.len();
+        if !self.writer.push(image.as_raw().clone())? {
+            return Ok(());
+        }
+        self.png_sender
+            .send((index, image))
+            .context("PNG writers stopped")?;
+        self.pts.push(pts);
+
+        Ok(())
+    }
+
+    /// Waits for both recordings to be written, returns the number of frames.
+    pub fn finish(self) -> Result<usize> {
+        drop(self.png_sender);
+        for writer in self.png_writers {
+            writer.join().expect("PNG writer panicked")?;
+        }
+        self.writer.finish()?;
+
+        let mut csv = String::from("index,pts_us\n");
+        for (index, pts) in self.pts.iter().enumerate() {
+            csv += &format!("{index},{}\n", pts.as_micros());
+        }
+        std::fs::write(self.frames_dir.join("frames.csv"), csv)?;
+
+        info!("Recorded {} frames", self.pts.len());
+        Ok(self.pts.len())
+    }
+}
@@ -0,0 +1,165 @@
+//! Machine-readable log of a run, one JSON object per line.
+//!
+//! Every line has the milliseconds elapsed since the compositor started in `time_ms`, and
+//! the kind of event in `event`. The other fields depend on the kind, see [`LogEvent`] and
+//! the README. Fields are only added within a [`SCHEMA_VERSION`], never removed or changed.
+
+use anyhow::{Context, Result};
+use compositor_pipeline::event::Event;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::analysis::Finding;
+
+/// Version of the schema, written in the `started` event.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LogEvent<'a> {
+    /// The compositor is ready.
+    Started {
+        schema_version: u32,
+        width: usize,
+        height: usize,
+        /// Output framerate, as a fraction.
+        framerate: [u32; 2],
+        /// Seed of the noise over the inputs, `None` without noise.
+        noise_seed: Option<u32>,
+        /// Name of the GPU adapter.
+        adapter: &'a str,
+        /// Driver name and version.
+        driver: String,
+        /// Names of the GPU driver workarounds enabled, see [`crate::workarounds`].
+        workarounds: Vec<&'static str>,
+    },
+    /// An output of the config starts, `output` is its type.
+    OutputStarted { output: &'a str },
+    /// An output switched to an input or scene.
+    SceneApplied {
+        /// `mp4` for recordings, `raw` for captures.
+        output: &'a str,
+        scene: &'a str,
+    },
+    /// A frame was read back from the raw output.
+    FrameCaptured {
+        /// Index in the current capture, from 0.
+        index: usize,
+        pts_ms: f64,
+    },
+    /// A check or analyzer reported a problem.
+    Finding {
+        analyzer: &'a str,
+        pts_ms: Option<f64>,
+        message: &'a str,
+    },
+    /// An event of the Smelter pipeline.
+    Pipeline {
+        /// `video_input_delivered`, `video_input_eos` or `other`.
+        kind: &'a str,
+        input: Option<&'a str>,
+        /// Debug representation, not stable.
+        detail: String,
+    },
+    /// The encoder of the recording to `path` could not start, its frames are saved to
+    /// `frames_dir` instead.
+    EncoderFallback {
+        path: String,
+        frames_dir: String,
+        reason: String,
+    },
+    /// Scene features the outputs showed, see [`crate::coverage::FEATURES`].
+    Coverage {
+        scenes: usize,
+        exercised: Vec<&'static str>,
+        missing: Vec<&'static str>,
+    },
+    /// Every output ran, `error` is set if one failed.
+    Finished { error: Option<String> },
+}
+
+impl<'a> LogEvent<'a> {
+    pub fn finding(finding: &'a Finding) -> Self {
+        LogEvent::Finding {
+            analyzer: &finding.analyzer,
+            pts_ms: finding.pts.map(millis),
+            message: &finding.message,
+        }
+    }
+
+    pub fn pipeline(event: &'a Event) -> Self {
+        let (kind, input) = match event {
+            Event::VideoInputStreamDelivered(input_id) => {
+                ("video_input_delivered", Some(&*input_id.0))
+            }
+            Event::VideoInputStreamEos(input_id) => ("video_input_eos", Some(&*input_id.0)),
+            _ => ("other", None),
+        };
+        LogEvent::Pipeline {
+            kind,
+            input,
+            detail: format!("{event:?}"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    time_ms: f64,
+    #[serde(flatten)]
+    event: LogEvent<'a>,
+}
+
+/// Writes events to a JSON lines file, or nowhere when disabled.
+///
+/// Lines are flushed as they are written, so the file can be followed during a run.
+pub struct EventLog {
+    writer: Option<BufWriter<File>>,
+    start: Instant,
+}
+
+impl EventLog {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Cannot create event log {}", path.display()))?;
+        Ok(Self {
+            writer: Some(BufWriter::new(file)),
+            start: Instant::now(),
+        })
+    }
+
+    pub fn disabled() -> Self {
+        Self {
+            writer: None,
+            start: Instant::now(),
+        }
+    }
+
+    /// Writes an event, logging failures rather than interrupting the run.
+    pub fn write(&mut self, event: LogEvent) {
+        let Some(writer) = &mut self.writer else {
+            return;
+        };
+
+        let line = Line {
+            time_ms: millis(self.start.elapsed()),
+            event,
+        };
+        let result = serde_json::to_writer(&mut *writer, &line)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| Ok(writeln!(writer)?))
+            .and_then(|()| Ok(writer.flush()?));
+        if let Err(err) = result {
+            warn!("Disabling the event log: {err:#}");
+            self.writer = None;
+        }
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,628 @@
+//! Expected frames of simple scenes, drawn on the CPU.
+//!
+//! Only scenes whose pixels do not depend on filtering or blending are supported: opaque or
+//! fully transparent views and borders on whole pixels, and images drawn at their own size.
+//! Their frames are known exactly, so renders can be checked without stored goldens.
+
+use anyhow::{bail, Context, Result};
+use compositor_render::scene::*;
+use compositor_render::{RendererId, Resolution};
+use image::{Rgba, RgbaImage};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::annotate;
+
+/// Draws the expected frame of a component tree.
+#[derive(Debug, Clone, Default)]
+pub struct ExpectedRenderer {
+    /// Files of the images scenes can show, by renderer ID.
+    images: HashMap<RendererId, PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    x: i64,
+    y: i64,
+    width: i64,
+    height: i64,
+}
+
+impl ExpectedRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes the image `id` available to the scenes, decoded from `path` when drawn.
+    pub fn with_image(mut self, id: RendererId, path: impl Into<PathBuf>) -> Self {
+        self.images.insert(id, path.into());
+        self
+    }
+
+    /// Draws `component` at `resolution`, the way the pipeline renders it.
+    ///
+    /// Fails on anything that cannot be drawn exactly: other components, rounded
+    /// corners, shadows, rotations, fractional positions, translucent colors or scaling.
+    pub fn render(&self, component: &Component, resolution: Resolution) -> Result<RgbaImage> {
+        let mut canvas = RgbaImage::new(resolution.width as u32, resolution.height as u32);
+        let output = Rect {
+            x: 0,
+            y: 0,
+            width: resolution.width as i64,
+            height: resolution.height as i64,
+        };
+        let (width, height) = self.size(component)?;
+        let rect = Rect {
+            width: width.unwrap_or(output.width),
+            height: height.unwrap_or(output.height),
+            ..output
+        };
+        self.draw(component, rect, output, &mut canvas)?;
+        Ok(canvas)
+    }
+
+    fn draw(
+        &self,
+        component: &Component,
+        rect: Rect,
+        clip: Rect,
+        canvas: &mut RgbaImage,
+    ) -> Result<()> {
+        match component {
+            Component::View(view) => self.draw_view(view, rect, clip, canvas),
+            Component::Rescaler(rescaler) => {
+                if rescaler.border_width != 0.0
+                    || !rescaler.box_shadow.is_empty()
+                    || !is_square(&rescaler.border_radius)
+                {
+                    bail!("Rescalers with borders or shadows cannot be drawn exactly");
+                }
+                // Only children already at the size of the rescaler are drawn, as is
+                match self.size(&rescaler.child)? {
+                    (Some(width), Some(height)) if (width, height) == (rect.width, rect.height) => {
+                        self.draw(&rescaler.child, rect, intersect(clip, rect), canvas)
+                    }
+                    _ => bail!("Scaled content cannot be drawn exactly"),
+                }
+            }
+            Component::Image(component) => {
+                let image = self.image(&component.image_id)?;
+                for (x, y, pixel) in image.enumerate_pixels() {
+                    fill(
+                        canvas,
+                        Rect {
+                            x: rect.x + x as i64,
+                            y: rect.y + y as i64,
+                            width: 1,
+                            height: 1,
+                        },
+                        clip,
+                        *pixel,
+                    );
+                }
+                Ok(())
+            }
+            _ => bail!("Only views, rescalers and images can be drawn on the CPU"),
+        }
+    }
+
+    fn draw_view(
+        &self,
+        view: &ViewComponent,
+        rect: Rect,
+        clip: Rect,
+        canvas: &mut RgbaImage,
+    ) -> Result<()> {
+        if !view.box_shadow.is_empty() || !is_square(&view.border_radius) {
+            bail!("Rounded or shadowed views cannot be drawn exactly");
+        }
+        fill(canvas, rect, clip, color(view.background_color)?);
+
+        let border = whole(view.border_width)?;
+        if border > 0 {
+            let border_color = color(view.border_color)?;
+            let Rect {
+                x,
+                y,
+                width,
+                height,
+            } = rect;
+            for side in [
+                Rect {
+                    height: border,
+                    ..rect
+                },
+                Rect {
+                    y: y + height - border,
+                    height: border,
+                    ..rect
+                },
+                Rect {
+                    width: border,
+                    ..rect
+                },
+                Rect {
+                    x: x + width - border,
+                    width: border,
+                    ..rect
+                },
+            ] {
+                fill(canvas, side, clip, border_color);
+            }
+        }
+
+        // Children are laid out inside the border and the padding
+        let padding = &view.padding;
+        let (top, right, bottom, left) = (
+            whole(padding.top)? + border,
+            whole(padding.right)? + border,
+            whole(padding.bottom)? + border,
+            whole(padding.left)? + border,
+        );
+        let content = Rect {
+            x: rect.x + left,
+            y: rect.y + top,
+            width: rect.width - left - right,
+            height: rect.height - top - bottom,
+        };
+        let clip = match view.overflow {
+            Overflow::Visible => clip,
+            Overflow::Hidden => intersect(clip, rect),
+            Overflow::Fit => bail!("Views fitting their children cannot be drawn exactly"),
+        };
+
+        let row = matches!(view.direction, ViewChildrenDirection::Row);
+        let main = |(width, height): (i64, i64)| if row { width } else { height };
+        let requested = |&(width, height): &(Option<i64>, Option<i64>)| match row {
+            true => width,
+            false => height,
+        };
+
+        // Static children without a size share the space the others leave
+        let mut sizes = vec![];
+        for child in &view.children {
+            sizes.push(match position(child) {
+                Some(Position::Absolute(_)) => None,
+                _ => Some(self.size(child)?),
+            });
+        }
+        let fixed: i64 = sizes.iter().flatten().filter_map(requested).sum();
+        let flexible = sizes
+            .iter()
+            .flatten()
+            .filter(|size| requested(size).is_none())
+            .count() as i64;
+        let remaining = main((content.width, content.height)) - fixed;
+        if flexible > 0 && remaining % flexible != 0 {
+            bail!("Children of a view do not share its size in whole pixels");
+        }
+
+        let mut offset = 0;
+        for (child, size) in view.children.iter().zip(sizes) {
+            let rect = match (position(child), size) {
+                (Some(Position::Absolute(absolute)), _) => absolute_rect(absolute, content)?,
+                (_, Some((width, height))) => {
+                    let share = (flexible > 0).then(|| remaining / flexible).unwrap_or(0);
+                    let rect = if row {
+                        Rect {
+                            x: content.x + offset,
+                            y: content.y,
+                            width: width.unwrap_or(share),
+                            height: height.unwrap_or(content.height),
+                        }
+                    } else {
+                        Rect {
+                            x: content.x,
+                            y: content.y + offset,
+                            width: width.unwrap_or(content.width),
+                            height: height.unwrap_or(share),
+                        }
+                    };
+                    offset += main((rect.width, rect.height));
+                    rect
+                }
+                (_, None) => unreachable!("Static children have a size"),
+            };
+            self.draw(child, rect, clip, canvas)?;
+        }
+
+        Ok(())
+    }
+
+    /// Size a component asks for, `None` on the sides its parent decides.
+    fn size(&self, component: &Component) -> Result<(Option<i64>, Option<i64>)> {
+        let size = match component {
+            Component::Image(component) => {
+                let image = self.image(&component.image_id)?;
+                let (width, height) = (image.width() as i64, image.height() as i64);
+                for (requested, actual) in [(component.width, width), (component.height, height)] {
+                    if requested.is_some_and(|requested| whole(requested).ok() != Some(actual)) {
+                        bail!("Scaled images cannot be drawn exactly");
+                    }
+                }
+                (Some(width), Some(height))
+            }
+            _ => match position(component) {
+                Some(Position::Static { width, height }) => (
+                    width.map(whole).transpose()?,
+                    height.map(whole).transpose()?,
+                ),
+                Some(Position::Absolute(absolute)) => (
+                    absolute.width.map(whole).transpose()?,
+                    absolute.height.map(whole).transpose()?,
+                ),
+                None => bail!("Only views, rescalers and images can be drawn on the CPU"),
+            },
+        };
+        Ok(size)
+    }
+
+    fn image(&self, id: &RendererId) -> Result<RgbaImage> {
+        let path = self
+            .images
+            .get(id)
+            .with_context(|| format!("Unknown image {:?}", id.0))?;
+        let image = image::open(path)
+            .with_context(|| format!("Cannot decode {}", path.display()))?
+            .to_rgba8();
+        if image.pixels().any(|pixel| !matches!(pixel[3], 0 | 255)) {
+            bail!(
+                "Translucent image {} cannot be drawn exactly",
+                path.display()
+            );
+        }
+        Ok(image)
+    }
+}
+
+/// Pixels of a rendered frame differing from its expected frame.
+#[derive(Debug, Clone, Copy)]
+pub struct Mismatch {
+    pub count: usize,
+    /// First differing pixel, in reading order.
+    pub first: (u32, u32),
+    pub expected: [u8; 3],
+    pub actual: [u8; 3],
+    /// Smallest area holding every differing pixel.
+    pub bounds: annotate::Rect,
+}
+
+/// Compares a rendered frame with its expected frame, describing the mismatch if any pixel
+/// differs by more than `tolerance` on a color channel.
+pub fn compare(expected: &RgbaImage, actual: &RgbaImage, tolerance: u8) -> Option<String> {
+    if expected.dimensions() != actual.dimensions() {
+        return Some(format!(
+            "frame is {}x{}, expected {}x{}",
+            actual.width(),
+            actual.height(),
+            expected.width(),
+            expected.height()
+        ));
+    }
+
+    let mismatch = mismatch(expected, actual, tolerance)?;
+    let (x, y) = mismatch.first;
+    Some(format!(
+        "{} pixels differ, the first at {x},{y} is {:?} instead of {:?}",
+        mismatch.count, mismatch.actual, mismatch.expected
+    ))
+}
+
+/// Pixels differing by more than `tolerance` on a color channel, frames having the same size.
+pub fn mismatch(expected: &RgbaImage, actual: &RgbaImage, tolerance: u8) -> Option<Mismatch> {
+    let mut mismatches =
+        expected
+            .enumerate_pixels()
+            .zip(actual.pixels())
+            .filter(|((_, _, expected), actual)| {
+                (0..3).any(|c| expected[c].abs_diff(actual[c]) > tolerance)
+            });
+    let ((x, y, expected), actual) = mismatches.next()?;
+    let (mut count, mut min, mut max) = (1, (x, y), (x, y));
+    for ((x, y, _), _) in mismatches {
+        count += 1;
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
+    }
+    Some(Mismatch {
+        count,
+        first: (x, y),
+        expected: [expected[0], expected[1], expected[2]],
+        actual: [actual[0], actual[1], actual[2]],
+        bounds: annotate::Rect {
+            x: min.0,
+            y: min.1,
+            width: max.0 - min.0 + 1,
+            height: max.1 - min.1 + 1,
+        },
+    })
+}
+
+fn position(component: &Component) -> Option<&Position> {
+    match component {
+        Component::View(view) => Some(&view.position),
+        Component::Rescaler(rescaler) => Some(&rescaler.position),
+        _ => None,
+    }
+}
+
+fn absolute_rect(absolute: &AbsolutePosition, parent: Rect) -> Result<Rect> {
+    if absolute.rotation_degrees != 0.0 {
+        bail!("Rotated components cannot be drawn exactly");
+    }
+    let width = absolute
+        .width
+        .map(whole)
+        .transpose()?
+        .unwrap_or(parent.width);
+    let height = absolute
+        .height
+        .map(whole)
+        .transpose()?
+        .unwrap_or(parent.height);
+    let x = match absolute.position_horizontal {
+        HorizontalPosition::LeftOffset(left) => parent.x + whole(left)?,
+        HorizontalPosition::RightOffset(right) => parent.x + parent.width - whole(right)? - width,
+    };
+    let y = match absolute.position_vertical {
+        VerticalPosition::TopOffset(top) => parent.y + whole(top)?,
+        VerticalPosition::BottomOffset(bottom) => {
+            parent.y + parent.height - whole(bottom)? - height
+        }
+    };
+    Ok(Rect {
+        x,
+        y,
+        width,
+        height,
+    })
+}
+
+/// Fills the part of `rect` inside `clip`, transparent colors leave the canvas untouched.
+fn fill(canvas: &mut RgbaImage, rect: Rect, clip: Rect, color: Rgba<u8>) {
+    if color[3] == 0 {
+        return;
+    }
+    let Rect {
+        x,
+        y,
+        width,
+        height,
+    } = intersect(
+        intersect(rect, clip),
+        Rect {
+            x: 0,
+            y: 0,
+            width: canvas.width() as i64,
+            height: canvas.height() as i64,
+        },
+    );
+    for y in y..y + height {
+        for x in x..x + width {
+            canvas.put_pixel(x as u32, y as u32, color);
+        }
+    }
+}
+
+fn intersect(a: Rect, b: Rect) -> Rect {
+    let (x, y) = (a.x.max(b.x), a.y.max(b.y));
+    Rect {
+        x,
+        y,
+        width: ((a.x + a.width).min(b.x + b.width) - x).max(0),
+        height: ((a.y + a.height).min(b.y + b.height) - y).max(0),
+    }
+}
+
+fn color(color: RGBAColor) -> Result<Rgba<u8>> {
+    let RGBAColor(r, g, b, a) = color;
+    if !matches!(a, 0 | 255) {
+        bail!("Translucent color #{r:02X}{g:02X}{b:02X}{a:02X} cannot be drawn exactly");
+    }
+    Ok(Rgba([r, g, b, a]))
+}
+
+/// A length in whole pixels.
+fn whole(length: f32) -> Result<i64> {
+    if length.fract() != 0.0 {
+        bail!("{length}px is not a whole number of pixels");
+    }
+    Ok(length as i64)
+}
+
+fn is_square(radius: &BorderRadius) -> bool {
+    [
+        radius.top_left,
+        radius.top_right,
+        radius.bottom_right,
+        radius.bottom_left,
+    ]
+    .iter()
+    .all(|&radius| radius == 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene;
+
+    fn render(json: &str, width: usize, height: usize) -> Result<RgbaImage> {
+        ExpectedRenderer::new().render(&scene::parse(json)?, Resolution { width, height })
+    }
+
+    /// Rows of the frame, a letter per pixel: `R`ed, `G`reen, `B`lue, `W`hite, `.` for
+    /// transparent ones.
+    fn rows(image: &RgbaImage) -> Vec<String> {
+        image
+            .rows()
+            .map(|row| {
+                row.map(|pixel| match pixel.0 {
+                    [255, 0, 0, 255] => 'R',
+                    [0, 255, 0, 255] => 'G',
+                    [0, 0, 255, 255] => 'B',
+                    [255, 255, 255, 255] => 'W',
+                    [_, _, _, 0] => '.',
+                    _ => '?',
+                })
+                .collect()
+            })
+            .collect()
+    }
+
+    fn error(json: &str) -> String {
+        render(json, 4, 4)
+            .err()
+            .expect("scene should not be drawn")
+            .to_string()
+    }
+
+    #[test]
+    fn solid_view() {
+        let image = render(r##"{"type": "view", "background_color": "#ff0000"}"##, 4, 2).unwrap();
+        assert_eq!(rows(&image), ["RRRR", "RRRR"]);
+    }
+
+    #[test]
+    fn bordered_view() {
+        let scene = r##"{
+            "type": "view",
+            "background_color": "#00ff00",
+            "border_width": 1,
+            "border_color": "#ffffff"
+        }"##;
+        assert_eq!(
+            rows(&render(scene, 6, 4).unwrap()),
+            ["WWWWWW", "WGGGGW", "WGGGGW", "WWWWWW"]
+        );
+    }
+
+    #[test]
+    fn row_children() {
+        // The views without a width share the 4 pixels left
+        let scene = r##"{
+            "type": "view",
+            "children": [
+                {"type": "view", "width": 2, "background_color": "#ff0000"},
+                {"type": "view", "background_color": "#00ff00"},
+                {"type": "view", "background_color": "#0000ff"}
+            ]
+        }"##;
+        assert_eq!(rows(&render(scene, 6, 2).unwrap()), ["RRGGBB", "RRGGBB"]);
+        // Unless they cannot share them evenly
+        let error = render(scene, 5, 2)
+            .err()
+            .expect("scene should not be drawn");
+        assert_eq!(
+            error.to_string(),
+            "Children of a view do not share its size in whole pixels"
+        );
+    }
+
+    #[test]
+    fn column_children() {
+        let scene = r##"{
+            "type": "view",
+            "direction": "column",
+            "padding": 1,
+            "children": [
+                {"type": "view", "height": 1, "background_color": "#ff0000"},
+                {"type": "view", "background_color": "#0000ff"}
+            ]
+        }"##;
+        assert_eq!(
+            rows(&render(scene, 4, 5).unwrap()),
+            ["....", ".RR.", ".BB.", ".BB.", "...."]
+        );
+    }
+
+    #[test]
+    fn absolute_children() {
+        let scene = r##"{
+            "type": "view",
+            "children": [
+                {
+                    "type": "view",
+                    "width": 2,
+                    "height": 1,
+                    "right": 1,
+                    "bottom": 1,
+                    "background_color": "#ff0000"
+                },
+                {
+                    "type": "view",
+                    "width": 1,
+                    "height": 1,
+                    "left": 0,
+                    "top": 0,
+                    "background_color": "#0000ff"
+                }
+            ]
+        }"##;
+        assert_eq!(
+            rows(&render(scene, 6, 4).unwrap()),
+            ["B.....", "......", "...RR.", "......"]
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        let scene = |overflow: &str| {
+            format!(
+                r##"{{
+                    "type": "view",
+                    "width": 4,
+                    "overflow": "{overflow}",
+                    "children": [
+                        {{"type": "view", "width": 4, "left": 2, "background_color": "#ff0000"}}
+                    ]
+                }}"##
+            )
+        };
+        assert_eq!(rows(&render(&scene("hidden"), 6, 1).unwrap()), ["..RR.."]);
+        assert_eq!(rows(&render(&scene("visible"), 6, 1).unwrap()), ["..RRRR"]);
+    }
+
+    #[test]
+    fn inexact_scenes() {
+        assert_eq!(
+            error(r#"{"type": "view", "width": 2.5}"#),
+            "2.5px is not a whole number of pixels"
+        );
+        assert_eq!(
+            error(r##"{"type": "view", "background_color": "#ff000080"}"##),
+            "Translucent color #FF000080 cannot be drawn exactly"
+        );
+        assert_eq!(
+            error(r#"{"type": "view", "border_radius": 2}"#),
+            "Rounded or shadowed views cannot be drawn exactly"
+        );
+        assert_eq!(
+            error(r#"{"type": "view", "children": [{"type": "view", "left": 0, "rotation": 45}]}"#),
+            "Rotated components cannot be drawn exactly"
+        );
+    }
+
+    #[test]
+    fn mismatches() {
+        let expected = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+        let mut actual = expected.clone();
+        assert!(mismatch(&expected, &actual, 0).is_none());
+
+        actual.put_pixel(1, 2, Rgba([102, 100, 100, 255]));
+        actual.put_pixel(3, 0, Rgba([100, 90, 100, 255]));
+        assert!(mismatch(&expected, &actual, 10).is_none());
+        let mismatch = mismatch(&expected, &actual, 1).unwrap();
+        assert_eq!((mismatch.count, mismatch.first), (2, (3, 0)));
+        assert_eq!(mismatch.actual, [100, 90, 100]);
+        let bounds = mismatch.bounds;
+        assert_eq!(
+            (bounds.x, bounds.y, bounds.width, bounds.height),
+            (1, 0, 3, 3)
+        );
+
+        assert_eq!(
+            compare(&expected, &RgbaImage::new(2, 2), 0).unwrap(),
+            "frame is 2x2, expected 4x4"
+        );
+    }
+}
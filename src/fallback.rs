@@ -0,0 +1,92 @@
+//! PNG sequences recorded in place of a recording whose encoder cannot start, e.g. without
+//! `ffmpeg` installed, so the rest of a verification run still goes on.
+
+use anyhow::{bail, Context, Result};
+use compositor_pipeline::pipeline::output::RawDataReceiver;
+use compositor_pipeline::pipeline::GraphicsContext;
+use compositor_pipeline::queue::PipelineEvent;
+use compositor_render::FrameData;
+use image::RgbaImage;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+use tracing::info;
+
+use crate::wgpu::to_image;
+
+/// Directory of the frames recorded in place of `path`, `{stem}_frames` next to it.
+pub fn frames_dir(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{stem}_frames"))
+}
+
+/// Saves the frames of a raw output to `frame_{index:06}.png` files of a directory, until it
+/// is unregistered, with their PTS in `frames.csv` like [`crate::dual_record::DualRecorder`].
+pub struct PngSequenceSink {
+    thread: JoinHandle<Result<usize>>,
+}
+
+impl PngSequenceSink {
+    pub fn start(dir: &Path, receiver: RawDataReceiver, context: GraphicsContext) -> Result<Self> {
+        let video = receiver.video.context("No video channel")?;
+        std::fs::create_dir_all(dir).with_context(|| format!("Cannot create {}", dir.display()))?;
+
+        // PNG encoding is the slow part, spread it over the cores
+        let (png_sender, png_receiver) = crossbeam_channel::bounded::<(usize, RgbaImage)>(8);
+        let workers = std::thread::available_parallelism().map_or(4, |n| n.get().min(8));
+        let png_writers = (0..workers)
+            .map(|worker| {
+                let receiver = png_receiver.clone();
+                let dir = dir.to_path_buf();
+                std::thread::Builder::new()
+                    .name(format!("fallback png writer {worker}"))
+                    .spawn(move || -> Result<()> {
+                        for (index, image) in receiver {
+                            image.save(dir.join(format!("frame_{index:06}.png")))?;
+                        }
+                        Ok(())
+                    })
+                    .context("Cannot spawn PNG writer")
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let frames_csv = dir.join("frames.csv");
+        let thread = std::thread::Builder::new()
+            .name("fallback recorder".to_string())
+            .spawn(move || -> Result<usize> {
+                let mut csv = String::from("index,pts_us\n");
+                let mut frames = 0;
+                // The channel closes once the output is unregistered
+                for event in video {
+                    let PipelineEvent::Data(frame) = event else {
+                        break;
+                    };
+                    let FrameData::Rgba8UnormWgpuTexture(texture) = &frame.data else {
+                        bail!("Expected Rgba8UnormWgpuTexture");
+                    };
+                    let image = to_image(&context, texture, None)?;
+                    png_sender
+                        .send((frames, image))
+                        .context("PNG writers stopped")?;
+                    csv += &format!("{frames},{}\n", frame.pts.as_micros());
+                    frames += 1;
+                }
+
+                drop(png_sender);
+                for writer in png_writers {
+                    writer.join().expect("PNG writer panicked")?;
+                }
+                std::fs::write(frames_csv, csv)?;
+                Ok(frames)
+            })
+            .context("Cannot spawn fallback recorder")?;
+
+        info!("Recording PNG frames to {}", dir.display());
+        Ok(Self { thread })
+    }
+
+    /// Waits for the frames to be written, once the output is unregistered. Returns the
+    /// number of frames.
+    pub fn finish(self) -> Result<usize> {
+        self.thread.join().expect("Fallback recorder panicked")
+    }
+}
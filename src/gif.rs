@@ -0,0 +1,61 @@
+//! Animated GIF captures of the output, small enough to embed demos in bug reports.
+
+use anyhow::Result;
+use compositor_render::Resolution;
+use image::RgbaImage;
+use std::path::Path;
+use tracing::info;
+
+use crate::config::FramerateConfig;
+use crate::raw_video::{self, RawVideoWriter};
+
+/// Encodes frames to an animated GIF with `ffmpeg`.
+///
+/// Frames are dropped down to `fps`, then a palette of up to `max_colors` is made from all
+/// of them, weighting the pixels that change between frames, and frames are stored as the
+/// rectangles that changed: a cycle through test patterns stays a small file.
+pub struct GifWriter {
+    writer: RawVideoWriter,
+}
+
+impl GifWriter {
+    pub fn start(
+        path: &Path,
+        resolution: Resolution,
+        framerate: FramerateConfig,
+        fps: u32,
+        max_colors: u16,
+    ) -> Result<Self> {
+        let filter = format!(
+            "fps={fps},split[frames][stats];\
+             [stats]palettegen=max_colors={max_colors}:stats_mode=diff[palette];\
+             [frames][palette]paletteuse=dither=bayer:bayer_scale=3:diff_mode=rectangle"
+        );
+        let mut command = raw_video::command("rgba", resolution, framerate, false);
+        command
+            .arg("-filter_complex")
+            .arg(filter)
+            .args(["-loop", "0", "-f", "gif"])
+            .arg(path);
+        let writer = RawVideoWriter::start(command, "the GIF")?;
+
+        info!(
+            "Capturing a GIF at {fps} fps, {max_colors} colors, to {}",
+            path.display()
+        );
+        Ok(Self { writer })
+    }
+
+    pub fn push(&mut self, image: RgbaImage) -> Result<()> {
+        self.writer.push(image.into_raw())?;
+        Ok(())
+    }
+
+    /// Waits for the palette and the file to be written, returns the number of frames
+    /// captured, before dropping them down to the GIF framerate.
+    pub fn finish(self) -> Result<usize> {
+        let frames = self.writer.finish()?;
+        info!("Captured {frames} frames to the GIF");
+        Ok(frames)
+    }
+}
@@ -0,0 +1,158 @@
+//! Stored frame sequences a render is compared with, such as the frames of a transition
+//! animation following a scene switch.
+//!
+//! A sequence is a directory of `frame_0000.png`, `frame_0001.png`, ... and `frames.csv`,
+//! listing the index of every frame with its PTS from the first one.
+
+use anyhow::{bail, Context, Result};
+use image::RgbaImage;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::info;
+
+use crate::config::{FrameToleranceConfig, GoldenMatchingConfig};
+
+const INDEX_FILE: &str = "frames.csv";
+
+/// A frame of a sequence.
+pub struct GoldenFrame {
+    /// From the first frame of the sequence.
+    pub pts: Duration,
+    pub image: RgbaImage,
+}
+
+fn frame_path(dir: &Path, index: usize) -> PathBuf {
+    dir.join(format!("frame_{index:04}.png"))
+}
+
+/// Whether `dir` holds a sequence.
+pub fn exists(dir: &Path) -> bool {
+    dir.join(INDEX_FILE).is_file()
+}
+
+pub fn load(dir: &Path) -> Result<Vec<GoldenFrame>> {
+    let index_path = dir.join(INDEX_FILE);
+    let index = std::fs::read_to_string(&index_path)
+        .with_context(|| format!("Cannot read {}", index_path.display()))?;
+    let mut frames = vec![];
+    for (line, row) in index.lines().enumerate().skip(1) {
+        let Some((frame, pts_ms)) = row.split_once(',') else {
+            bail!(
+                "{}:{}: expected index,pts_ms",
+                index_path.display(),
+                line + 1
+            );
+        };
+        let frame: usize = frame
+            .trim()
+            .parse()
+            .with_context(|| format!("{}:{}: invalid index", index_path.display(), line + 1))?;
+        let pts_ms: f64 = pts_ms
+            .trim()
+            .parse()
+            .with_context(|| format!("{}:{}: invalid PTS", index_path.display(), line + 1))?;
+        let path = frame_path(dir, frame);
+        let image = image::open(&path)
+            .with_context(|| format!("Cannot open {}", path.display()))?
+            .to_rgba8();
+        frames.push(GoldenFrame {
+            pts: Duration::from_secs_f64(pts_ms / 1000.0),
+            image,
+        });
+    }
+    Ok(frames)
+}
+
+/// Replaces the sequence stored in `dir` with `frames`.
+pub fn save(dir: &Path, frames: &[GoldenFrame]) -> Result<()> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Cannot create {}", dir.display()))?;
+    let mut index = "index,pts_ms\n".to_string();
+    for (i, frame) in frames.iter().enumerate() {
+        let path = frame_path(dir, i);
+        frame
+            .image
+            .save(&path)
+            .with_context(|| format!("Cannot save {}", path.display()))?;
+        writeln!(index, "{i},{:.3}", frame.pts.as_secs_f64() * 1000.0)?;
+    }
+    std::fs::write(dir.join(INDEX_FILE), index)?;
+    info!("Saved {} golden frames to {}", frames.len(), dir.display());
+    Ok(())
+}
+
+/// How rendered frames are compared with a sequence.
+#[derive(Debug, Clone)]
+pub struct SequenceCheck<'a> {
+    /// Frames rendered, when matched by index or saving the sequence.
+    pub frames: usize,
+    pub tolerance: u8,
+    pub frame_tolerances: &'a [FrameToleranceConfig],
+    pub matching: GoldenMatchingConfig,
+    /// Rendered frames right after the switch left out of the comparison.
+    pub skip_after_switch: usize,
+    /// Whether to save the sequence rendered, even if there is one.
+    pub update: bool,
+}
+
+impl SequenceCheck<'_> {
+    /// Tolerance of the `index`th golden frame: the last of the frame tolerances covering
+    /// it, or the default one.
+    pub fn tolerance(&self, index: usize) -> u8 {
+        self.frame_tolerances
+            .iter()
+            .rev()
+            .find(|config| (config.from..=config.to).contains(&index))
+            .map_or(self.tolerance, |config| config.tolerance)
+    }
+}
+
+/// The frame of `goldens` closest to `pts`, with its index, the first one on ties.
+///
+/// Sequences saved at another framerate than the run are compared this way, each rendered
+/// frame with the golden one shown at about the same time.
+pub fn nearest(goldens: &[GoldenFrame], pts: Duration) -> Option<(usize, &GoldenFrame)> {
+    goldens
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, golden)| golden.pts.abs_diff(pts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn goldens(pts_ms: &[u64]) -> Vec<GoldenFrame> {
+        pts_ms
+            .iter()
+            .map(|&pts| GoldenFrame {
+                pts: Duration::from_millis(pts),
+                image: RgbaImage::new(1, 1),
+            })
+            .collect()
+    }
+
+    fn nearest_index(goldens: &[GoldenFrame], pts_ms: u64) -> Option<usize> {
+        nearest(goldens, Duration::from_millis(pts_ms)).map(|(index, _)| index)
+    }
+
+    #[test]
+    fn across_framerates() {
+        // 25fps golden frames for a 30fps render
+        let goldens = goldens(&[0, 40, 80, 120]);
+        let matched: Vec<_> = [0, 33, 67, 100, 133]
+            .into_iter()
+            .map(|pts| nearest_index(&goldens, pts))
+            .collect();
+        assert_eq!(matched, [Some(0), Some(1), Some(2), Some(2), Some(3)]);
+    }
+
+    #[test]
+    fn ties_and_bounds() {
+        let goldens = goldens(&[0, 40, 80]);
+        assert_eq!(nearest_index(&goldens, 20), Some(0));
+        assert_eq!(nearest_index(&goldens, 60), Some(1));
+        assert_eq!(nearest_index(&goldens, 1000), Some(2));
+        assert_eq!(nearest_index(&[], 0), None);
+    }
+}
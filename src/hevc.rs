@@ -0,0 +1,92 @@
+//! H265 recordings, encoded by `ffmpeg` from the frames of a raw output, as the pipeline
+//! only encodes H264.
+
+use anyhow::{bail, Context, Result};
+use compositor_pipeline::pipeline::output::RawDataReceiver;
+use compositor_pipeline::pipeline::GraphicsContext;
+use compositor_pipeline::queue::PipelineEvent;
+use compositor_render::{FrameData, Resolution};
+use std::path::Path;
+use std::thread::JoinHandle;
+use tracing::info;
+
+use crate::config::{EncoderConfig, FramerateConfig};
+use crate::raw_video::{self, RawVideoWriter};
+use crate::wgpu::{to_image, to_image_u16};
+
+/// Encodes the frames of a raw output to an H265 MP4 file, until it is unregistered.
+///
+/// Frames are converted like the recordings of the pipeline, see
+/// [`raw_video::bt709_limited`], in YUV 4:2:0. With 10 bits, they are read back as 16-bit sRGB and encoded in the Main10
+/// profile, so gradients keep the precision of the rendering. Recordings have no audio.
+pub struct HevcEncoder {
+    thread: JoinHandle<Result<usize>>,
+}
+
+impl HevcEncoder {
+    pub fn start(
+        path: &Path,
+        receiver: RawDataReceiver,
+        context: GraphicsContext,
+        resolution: Resolution,
+        framerate: FramerateConfig,
+        encoder: &EncoderConfig,
+    ) -> Result<Self> {
+        let video = receiver.video.context("No video channel")?;
+        let ten_bit = encoder.bit_depth == 10;
+        let (input_format, output_format, profile) = match ten_bit {
+            true => ("rgba64le", "yuv420p10le", "main10"),
+            false => ("rgba", "yuv420p", "main"),
+        };
+
+        let mut command = raw_video::command(input_format, resolution, framerate, false);
+        command
+            .args(["-c:v", "libx265", "-preset", encoder.preset.name()])
+            .args(["-profile:v", profile])
+            .args(
+                encoder
+                    .raw_options()
+                    .into_iter()
+                    .flat_map(|(key, value)| [format!("-{key}"), value]),
+            )
+            .args(raw_video::bt709_limited(output_format))
+            // Tagged so that QuickTime and browsers play it
+            .args(["-tag:v", "hvc1"])
+            .arg(path);
+        let mut writer = RawVideoWriter::start(command, "the H265 recording")?;
+
+        let thread = std::thread::Builder::new()
+            .name("h265 encoder".to_string())
+            .spawn(move || -> Result<usize> {
+                // The channel closes once the output is unregistered
+                for event in video {
+                    let PipelineEvent::Data(frame) = event else {
+                        break;
+                    };
+                    let FrameData::Rgba8UnormWgpuTexture(texture) = &frame.data else {
+                        bail!("Expected Rgba8UnormWgpuTexture");
+                    };
+                    let bytes: Vec<u8> = match ten_bit {
+                        true => to_image_u16(&context, texture, None)?
+                            .as_raw()
+                            .iter()
+                            .flat_map(|value| value.to_le_bytes())
+                            .collect(),
+                        false => to_image(&context, texture, None)?.into_raw(),
+                    };
+                    writer.push(bytes)?;
+                }
+                writer.finish()
+            })
+            .context("Cannot spawn H265 encoder")?;
+
+        info!("Recording H265 {profile} to {}", path.display());
+        Ok(Self { thread })
+    }
+
+    /// Waits for the file to be written, once the output is unregistered. Returns the number
+    /// of frames.
+    pub fn finish(self) -> Result<usize> {
+        self.thread.join().expect("H265 encoder panicked")
+    }
+}
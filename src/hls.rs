@@ -0,0 +1,92 @@
+//! HLS streams of the output, a playlist and its segments written to a directory, to review
+//! the output from a browser on another machine once the directory is served over HTTP.
+
+use anyhow::{Context, Result};
+use compositor_render::Resolution;
+use image::RgbaImage;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::config::{EncoderConfig, FramerateConfig, HlsSegmentConfig};
+use crate::raw_video::{self, RawVideoWriter};
+
+/// Name of the playlist in the directory.
+pub const PLAYLIST: &str = "index.m3u8";
+
+/// Settings of an HLS stream.
+#[derive(Debug, Clone, Copy)]
+pub struct HlsOptions {
+    pub segment_type: HlsSegmentConfig,
+    pub segment_secs: u64,
+    /// Segments listed in the playlist, older ones are deleted. All of them when unset.
+    pub playlist_size: Option<usize>,
+}
+
+/// Encodes frames to an HLS stream with `ffmpeg`, H264 with the `encoder` settings.
+///
+/// Frames come from the raw output and are converted like the recordings of the pipeline,
+/// see [`raw_video::bt709_limited`], in YUV 4:2:0. A keyframe starts every segment, so they
+/// all last `segment_secs`.
+pub struct HlsWriter {
+    writer: RawVideoWriter,
+    playlist: PathBuf,
+}
+
+impl HlsWriter {
+    pub fn start(
+        dir: &Path,
+        options: HlsOptions,
+        resolution: Resolution,
+        framerate: FramerateConfig,
+        encoder: &EncoderConfig,
+    ) -> Result<Self> {
+        std::fs::create_dir_all(dir).with_context(|| format!("Cannot create {}", dir.display()))?;
+        let playlist = dir.join(PLAYLIST);
+        let (segment_type, extension) = match options.segment_type {
+            HlsSegmentConfig::Ts => ("mpegts", "ts"),
+            HlsSegmentConfig::Fmp4 => ("fmp4", "m4s"),
+        };
+        let flags = match options.playlist_size {
+            Some(_) => "delete_segments+independent_segments",
+            None => "independent_segments",
+        };
+
+        let mut command = raw_video::command("rgba", resolution, framerate, false);
+        command
+            .args(["-c:v", "libx264", "-preset", encoder.preset.name()])
+            .args(
+                encoder
+                    .raw_options()
+                    .into_iter()
+                    .flat_map(|(key, value)| [format!("-{key}"), value]),
+            )
+            .arg("-force_key_frames")
+            .arg(format!("expr:gte(t,n_forced*{})", options.segment_secs))
+            .args(raw_video::bt709_limited("yuv420p"))
+            .args(["-f", "hls", "-hls_time"])
+            .arg(options.segment_secs.to_string())
+            .arg("-hls_list_size")
+            .arg(options.playlist_size.unwrap_or(0).to_string())
+            .args(["-hls_flags", flags, "-hls_segment_type", segment_type])
+            .arg("-hls_segment_filename")
+            .arg(dir.join(format!("segment_%05d.{extension}")))
+            .arg(&playlist);
+        let writer = RawVideoWriter::start(command, "the HLS stream")?;
+
+        info!("Writing HLS stream to {}", playlist.display());
+        Ok(Self { writer, playlist })
+    }
+
+    pub fn push(&mut self, image: RgbaImage) -> Result<()> {
+        self.writer.push(image.into_raw())?;
+        Ok(())
+    }
+
+    /// Waits for the last segment and the playlist to be written, returns the number of
+    /// frames.
+    pub fn finish(self) -> Result<usize> {
+        let frames = self.writer.finish()?;
+        info!("Wrote {frames} frames to {}", self.playlist.display());
+        Ok(frames)
+    }
+}
@@ -0,0 +1,466 @@
+//! Correction of the terminal and dashboard previews for the attached display, from its
+//! ICC profile, so uncalibrated monitors show about the colors the frames hold.
+//!
+//! Only matrix/TRC display profiles are supported, which is what calibration tools and
+//! EDID-derived profiles produce. Frames are assumed sRGB, converted to XYZ and then to the
+//! display primaries and tone curves: a preview pixel then shows the color its buffer value
+//! encodes, not the one the display would show for it uncorrected.
+
+use anyhow::{bail, Context, Result};
+use image::RgbaImage;
+use std::path::Path;
+use std::process::Command;
+
+/// Linear sRGB to XYZ, adapted to the D50 white point of ICC profiles with Bradford.
+const SRGB_TO_XYZ_D50: [[f32; 3]; 3] = [
+    [0.436_074_7, 0.385_064_9, 0.143_080_4],
+    [0.222_504_5, 0.716_878_6, 0.060_616_9],
+    [0.013_932_2, 0.097_104_5, 0.714_173_3],
+];
+/// Entries of the tables inverting the tone curves.
+const INVERSE_SIZE: usize = 4096;
+
+/// Tone curve of a display channel, from device values to linear light, both in `[0, 1]`.
+#[derive(Debug, Clone)]
+enum Curve {
+    Gamma(f32),
+    /// Samples spread evenly over the device values.
+    Table(Vec<f32>),
+    /// ICC parametric function of the given type, with its parameters `g a b c d e f`.
+    Parametric(u16, [f32; 7]),
+}
+
+impl Curve {
+    fn eval(&self, x: f32) -> f32 {
+        match self {
+            Curve::Gamma(gamma) => x.powf(*gamma),
+            Curve::Table(table) => {
+                let position = x.clamp(0.0, 1.0) * (table.len() - 1) as f32;
+                let index = (position as usize).min(table.len() - 2);
+                let fraction = position - index as f32;
+                table[index] * (1.0 - fraction) + table[index + 1] * fraction
+            }
+            Curve::Parametric(function, [g, a, b, c, d, e, f]) => match *function {
+                0 => x.powf(*g),
+                1 if x >= -b / a => (a * x + b).powf(*g),
+                1 => 0.0,
+                2 if x >= -b / a => (a * x + b).powf(*g) + c,
+                2 => *c,
+                3 if x >= *d => (a * x + b).powf(*g),
+                3 => c * x,
+                _ if x >= *d => (a * x + b).powf(*g) + e,
+                _ => c * x + f,
+            },
+        }
+    }
+
+    /// Device values of evenly spread linear values, found by bisection as curves are
+    /// increasing.
+    fn inverse(&self) -> Vec<u8> {
+        (0..INVERSE_SIZE)
+            .map(|i| {
+                let target = i as f32 / (INVERSE_SIZE - 1) as f32;
+                let (mut low, mut high) = (0.0f32, 1.0f32);
+                for _ in 0..20 {
+                    let middle = (low + high) / 2.0;
+                    match self.eval(middle) < target {
+                        true => low = middle,
+                        false => high = middle,
+                    }
+                }
+                ((low + high) / 2.0 * 255.0).round() as u8
+            })
+            .collect()
+    }
+}
+
+/// Conversion of sRGB preview pixels to the values the display needs to show them.
+pub struct DisplayCorrection {
+    /// From the description of the profile, for the preview HUD.
+    description: String,
+    decode: [f32; 256],
+    /// Linear sRGB to linear display RGB.
+    matrix: [[f32; 3]; 3],
+    encode: [Vec<u8>; 3],
+}
+
+impl DisplayCorrection {
+    /// Loads the profile at `path`, or the one of the attached display when unset.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let profile = match path {
+            Some(path) => {
+                std::fs::read(path).with_context(|| format!("Cannot read {}", path.display()))?
+            }
+            None => attached_display_profile()?,
+        };
+        Self::parse(&profile)
+    }
+
+    pub fn parse(profile: &[u8]) -> Result<Self> {
+        let tags = Tags::read(profile)?;
+        if !matches!(tags.profile.get(12..16), Some(b"mntr")) {
+            bail!("ICC profile is not a display profile");
+        }
+        let colorant = |signature: &[u8; 4]| tags.xyz(signature);
+        let primaries = [colorant(b"rXYZ")?, colorant(b"gXYZ")?, colorant(b"bXYZ")?];
+        // Display RGB to XYZ has the colorants as columns
+        let display_to_xyz = [0, 1, 2].map(|row| [0, 1, 2].map(|column| primaries[column][row]));
+        let matrix = multiply(&invert(&display_to_xyz)?, &SRGB_TO_XYZ_D50);
+        let curves = [
+            tags.curve(b"rTRC")?,
+            tags.curve(b"gTRC")?,
+            tags.curve(b"bTRC")?,
+        ];
+
+        let decode = std::array::from_fn(|value| srgb_to_linear(value as f32 / 255.0));
+        Ok(Self {
+            description: tags.description().unwrap_or_else(|| "display".to_string()),
+            decode,
+            matrix,
+            encode: curves.map(|curve| curve.inverse()),
+        })
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Converts the pixels of a preview in place, alpha is kept.
+    pub fn apply(&self, image: &mut RgbaImage) {
+        for pixel in image.pixels_mut() {
+            let linear = [0, 1, 2].map(|c| self.decode[pixel[c] as usize]);
+            for (c, row) in self.matrix.iter().enumerate() {
+                let value = row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2];
+                let index = (value.clamp(0.0, 1.0) * (INVERSE_SIZE - 1) as f32).round();
+                pixel[c] = self.encode[c][index as usize];
+            }
+        }
+    }
+}
+
+/// ICC profile of the attached display, as exposed by the OS.
+///
+/// Only X11 exposes it through a standard property, `_ICC_PROFILE` of the root window, set by
+/// color managers such as colord or the desktop settings. Elsewhere the profile is to be
+/// set in the config.
+fn attached_display_profile() -> Result<Vec<u8>> {
+    if std::env::consts::OS != "linux" || std::env::var_os("DISPLAY").is_none() {
+        bail!("The display profile is only read on X11, set preview.icc_profile instead");
+    }
+    let output = Command::new("xprop")
+        .args(["-root", "-notype", "_ICC_PROFILE"])
+        .output()
+        .context("Cannot run xprop, which reads the display profile")?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    // _ICC_PROFILE = 0, 0, 12, 72, ...
+    let Some((_, bytes)) = text.split_once('=') else {
+        bail!("The display has no ICC profile set");
+    };
+    bytes
+        .split(',')
+        .map(|byte| byte.trim().parse::<u8>())
+        .collect::<std::result::Result<_, _>>()
+        .context("Cannot parse the display profile")
+}
+
+/// Tag table of an ICC profile.
+struct Tags<'a> {
+    profile: &'a [u8],
+    /// Signature, offset and size of every tag.
+    entries: Vec<([u8; 4], usize, usize)>,
+}
+
+impl<'a> Tags<'a> {
+    fn read(profile: &'a [u8]) -> Result<Self> {
+        if profile.len() < 132 || !matches!(profile.get(36..40), Some(b"acsp")) {
+            bail!("Not an ICC profile");
+        }
+        let count = be_u32(profile, 128)? as usize;
+        let entries = (0..count)
+            .map(|i| {
+                let at = 132 + i * 12;
+                let signature: [u8; 4] = profile
+                    .get(at..at + 4)
+                    .context("Truncated ICC tag table")?
+                    .try_into()?;
+                Ok((
+                    signature,
+                    be_u32(profile, at + 4)? as usize,
+                    be_u32(profile, at + 8)? as usize,
+                ))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { profile, entries })
+    }
+
+    fn tag(&self, signature: &[u8; 4]) -> Result<&'a [u8]> {
+        let (_, offset, size) = self
+            .entries
+            .iter()
+            .find(|(tag, _, _)| tag == signature)
+            .with_context(|| {
+                format!(
+                    "ICC profile has no {} tag, only matrix/TRC profiles are supported",
+                    String::from_utf8_lossy(signature)
+                )
+            })?;
+        self.profile
+            .get(*offset..offset + size)
+            .context("Truncated ICC tag")
+    }
+
+    fn xyz(&self, signature: &[u8; 4]) -> Result<[f32; 3]> {
+        let tag = self.tag(signature)?;
+        if !matches!(tag.get(0..4), Some(b"XYZ ")) {
+            bail!("{} is not an XYZ tag", String::from_utf8_lossy(signature));
+        }
+        Ok([
+            s15_fixed16(tag, 8)?,
+            s15_fixed16(tag, 12)?,
+            s15_fixed16(tag, 16)?,
+        ])
+    }
+
+    fn curve(&self, signature: &[u8; 4]) -> Result<Curve> {
+        let tag = self.tag(signature)?;
+        match tag.get(0..4) {
+            Some(b"curv") => {
+                let count = be_u32(tag, 8)? as usize;
+                let entry = |i: usize| be_u16(tag, 12 + i * 2);
+                match count {
+                    0 => Ok(Curve::Gamma(1.0)),
+                    1 => Ok(Curve::Gamma(entry(0)? as f32 / 256.0)),
+                    count => Ok(Curve::Table(
+                        (0..count)
+                            .map(|i| Ok(entry(i)? as f32 / 65535.0))
+                            .collect::<Result<_>>()?,
+                    )),
+                }
+            }
+            Some(b"para") => {
+                let function = be_u16(tag, 8)?;
+                let count = match function {
+                    0 => 1,
+                    1 => 3,
+                    2 => 4,
+                    3 => 5,
+                    4 => 7,
+                    other => bail!("Unknown ICC parametric curve type {other}"),
+                };
+                let mut parameters = [0.0; 7];
+                for (i, parameter) in parameters.iter_mut().take(count).enumerate() {
+                    *parameter = s15_fixed16(tag, 12 + i * 4)?;
+                }
+                Ok(Curve::Parametric(function, parameters))
+            }
+            _ => bail!("{} is not a curve", String::from_utf8_lossy(signature)),
+        }
+    }
+
+    /// Name of the profile, from its `desc` tag in ICC v2 or v4 form.
+    fn description(&self) -> Option<String> {
+        let tag = self.tag(b"desc").ok()?;
+        match tag.get(0..4)? {
+            b"desc" => {
+                let length = be_u32(tag, 8).ok()? as usize;
+                let text = tag.get(12..12 + length)?;
+                Some(
+                    String::from_utf8_lossy(text)
+                        .trim_end_matches('\0')
+                        .to_string(),
+                )
+            }
+            b"mluc" => {
+                // First record, UTF-16BE
+                let length = be_u32(tag, 20).ok()? as usize;
+                let offset = be_u32(tag, 24).ok()? as usize;
+                let units: Vec<u16> = tag
+                    .get(offset..offset + length)?
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                    .collect();
+                Some(String::from_utf16_lossy(&units))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn be_u16(data: &[u8], at: usize) -> Result<u16> {
+    let bytes = data.get(at..at + 2).context("Truncated ICC profile")?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn be_u32(data: &[u8], at: usize) -> Result<u32> {
+    let bytes = data.get(at..at + 4).context("Truncated ICC profile")?;
+    Ok(u32::from_be_bytes(bytes.try_into()?))
+}
+
+fn s15_fixed16(data: &[u8], at: usize) -> Result<f32> {
+    Ok(be_u32(data, at)? as i32 as f32 / 65536.0)
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    match value <= 0.04045 {
+        true => value / 12.92,
+        false => ((value + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+fn multiply(a: &[[f32; 3]; 3], b: &[[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    std::array::from_fn(|row| {
+        std::array::from_fn(|column| (0..3).map(|k| a[row][k] * b[k][column]).sum())
+    })
+}
+
+fn invert(m: &[[f32; 3]; 3]) -> Result<[[f32; 3]; 3]> {
+    let cofactor = |row: usize, column: usize| {
+        let (r0, r1) = ((row + 1) % 3, (row + 2) % 3);
+        let (c0, c1) = ((column + 1) % 3, (column + 2) % 3);
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+    let determinant: f32 = (0..3)
+        .map(|column| m[0][column] * cofactor(0, column))
+        .sum();
+    if determinant.abs() < 1e-6 {
+        bail!("ICC profile primaries are degenerate");
+    }
+    // Inverse is the transposed cofactor matrix over the determinant
+    Ok(std::array::from_fn(|row| {
+        std::array::from_fn(|column| cofactor(column, row) / determinant)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// sRGB parametric curve, type 3.
+    const SRGB_CURVE: [f32; 5] = [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045];
+
+    fn s15(value: f32) -> [u8; 4] {
+        ((value * 65536.0).round() as i32).to_be_bytes()
+    }
+
+    /// Display profile with the sRGB primaries and `trc` as the curve tag of every channel.
+    fn profile(class: &[u8; 4], trc: Vec<u8>) -> Vec<u8> {
+        let xyz = |column: usize| {
+            let mut tag = b"XYZ \0\0\0\0".to_vec();
+            for row in SRGB_TO_XYZ_D50 {
+                tag.extend(s15(row[column]));
+            }
+            tag
+        };
+        let mut desc = b"desc\0\0\0\0".to_vec();
+        desc.extend(13u32.to_be_bytes());
+        desc.extend(b"Test display\0");
+        let tags: [(&[u8; 4], Vec<u8>); 7] = [
+            (b"rXYZ", xyz(0)),
+            (b"gXYZ", xyz(1)),
+            (b"bXYZ", xyz(2)),
+            (b"rTRC", trc.clone()),
+            (b"gTRC", trc.clone()),
+            (b"bTRC", trc),
+            (b"desc", desc),
+        ];
+
+        let mut header = vec![0; 128];
+        header[12..16].copy_from_slice(class);
+        header[36..40].copy_from_slice(b"acsp");
+        let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+        let mut data = vec![];
+        let mut offset = 128 + 4 + tags.len() * 12;
+        for (signature, tag) in tags {
+            table.extend(signature);
+            table.extend((offset as u32).to_be_bytes());
+            table.extend((tag.len() as u32).to_be_bytes());
+            offset += tag.len();
+            data.extend(tag);
+        }
+        [header, table, data].concat()
+    }
+
+    fn parametric(function: u16, parameters: &[f32]) -> Vec<u8> {
+        let mut tag = b"para\0\0\0\0".to_vec();
+        tag.extend(function.to_be_bytes());
+        tag.extend([0, 0]);
+        for &parameter in parameters {
+            tag.extend(s15(parameter));
+        }
+        tag
+    }
+
+    fn linear() -> Vec<u8> {
+        b"curv\0\0\0\0\0\0\0\0".to_vec()
+    }
+
+    fn corrected(correction: &DisplayCorrection, rgb: [u8; 3]) -> [u8; 3] {
+        let mut image = RgbaImage::from_pixel(1, 1, image::Rgba([rgb[0], rgb[1], rgb[2], 255]));
+        correction.apply(&mut image);
+        let pixel = image.get_pixel(0, 0);
+        assert_eq!(pixel[3], 255);
+        [pixel[0], pixel[1], pixel[2]]
+    }
+
+    #[test]
+    fn srgb_display_keeps_colors() {
+        let correction =
+            DisplayCorrection::parse(&profile(b"mntr", parametric(3, &SRGB_CURVE))).unwrap();
+        assert_eq!(correction.description(), "Test display");
+        for rgb in [[0, 0, 0], [255, 255, 255], [128, 64, 200], [10, 250, 3]] {
+            let corrected = corrected(&correction, rgb);
+            for c in 0..3 {
+                assert!(
+                    corrected[c].abs_diff(rgb[c]) <= 1,
+                    "{rgb:?} -> {corrected:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn linear_display() {
+        let correction = DisplayCorrection::parse(&profile(b"mntr", linear())).unwrap();
+        // sRGB 128 is 21.6% linear light
+        assert_eq!(
+            corrected(&correction, [128, 128, 128]).map(|c| c.abs_diff(55) <= 1),
+            [true; 3]
+        );
+    }
+
+    #[test]
+    fn invalid_profiles() {
+        assert!(DisplayCorrection::parse(&profile(b"scnr", linear())).is_err());
+        assert!(DisplayCorrection::parse(&[0; 64]).is_err());
+
+        let mut truncated = profile(b"mntr", linear());
+        truncated.truncate(200);
+        assert!(DisplayCorrection::parse(&truncated).is_err());
+
+        let unknown = DisplayCorrection::parse(&profile(b"mntr", parametric(7, &[1.0])));
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn inverse_curves() {
+        let inverse = Curve::Table(vec![0.0, 1.0]).inverse();
+        assert_eq!(inverse.len(), INVERSE_SIZE);
+        assert_eq!(
+            (inverse[0], inverse[2048], inverse[INVERSE_SIZE - 1]),
+            (0, 128, 255)
+        );
+
+        // Half the light is about 73% of a gamma 2.2 signal
+        let inverse = Curve::Gamma(2.2).inverse();
+        assert!(inverse.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!((inverse[0], inverse[INVERSE_SIZE - 1]), (0, 255));
+        assert_eq!(inverse[INVERSE_SIZE / 2], 186);
+
+        let [g, a, b, c, d] = SRGB_CURVE;
+        let inverse = Curve::Parametric(3, [g, a, b, c, d, 0.0, 0.0]).inverse();
+        for (value, expected) in [(0.0, 0), (0.2158605, 128), (1.0, 255)] {
+            let index = (value * (INVERSE_SIZE - 1) as f32).round() as usize;
+            assert_eq!(inverse[index], expected);
+        }
+    }
+}
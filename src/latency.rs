@@ -0,0 +1,151 @@
+use image::RgbaImage;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info};
+
+use crate::analysis::FrameStats;
+
+/// Markers further than this from the expected color, in `[0, 1]`, do not show it yet.
+const MAX_MARKER_ERROR: f32 = 0.1;
+
+/// When frames became available on a channel, from their PTS.
+///
+/// A frame waiting in the channel while the previous one is read back is received late, by
+/// the readback time. Frames received right away, on a channel that was empty, are the
+/// earliest relative to their PTS: that offset maps PTS to the time they were sent.
+#[derive(Debug, Default)]
+pub struct ArrivalClock {
+    origin: Option<Instant>,
+}
+
+impl ArrivalClock {
+    /// Time the frame with `pts`, `received` now, became available.
+    pub fn arrival(&mut self, received: Instant, pts: Duration) -> Instant {
+        let Some(origin) = received.checked_sub(pts) else {
+            return received;
+        };
+        let origin = self.origin.map_or(origin, |earliest| earliest.min(origin));
+        self.origin = Some(origin);
+        origin + pts
+    }
+}
+
+/// Distribution of latencies measured over several samples.
+#[derive(Debug, Clone)]
+pub struct LatencyStats {
+    pub samples: usize,
+    pub min: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl LatencyStats {
+    pub fn new(samples: &[Duration]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        let percentile = |p: f64| sorted[((sorted.len() - 1) as f64 * p).round() as usize];
+        let mean = sorted.iter().map(Duration::as_secs_f64).sum::<f64>() / sorted.len() as f64;
+
+        Some(Self {
+            samples: sorted.len(),
+            min: sorted[0],
+            mean: Duration::from_secs_f64(mean),
+            p50: percentile(0.5),
+            p95: percentile(0.95),
+            p99: percentile(0.99),
+            max: *sorted.last().unwrap(),
+        })
+    }
+
+    pub fn log(&self, what: &str) {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        info!(
+            "{what} over {} samples: min {:.2}ms, mean {:.2}ms, p50 {:.2}ms, p95 {:.2}ms, p99 {:.2}ms, max {:.2}ms",
+            self.samples,
+            ms(self.min),
+            ms(self.mean),
+            ms(self.p50),
+            ms(self.p95),
+            ms(self.p99),
+            ms(self.max),
+        );
+    }
+}
+
+/// Region of a `width`x`height` frame the latency marker is drawn on: x, y and size of a
+/// square in the top-left corner, a sixteenth of the frame height wide.
+pub fn marker_region(width: u32, height: u32) -> (u32, u32, u32) {
+    let size = (height / 16).max(4).min(width);
+    (0, 0, size)
+}
+
+/// Whether the marker region of `image` shows `color`.
+///
+/// The center of the region is averaged, so scaling and filtering at its edges are tolerated.
+pub fn shows_marker(image: &RgbaImage, color: [u8; 3]) -> bool {
+    let (x0, y0, size) = marker_region(image.width(), image.height());
+    let margin = size / 4;
+
+    let mut sum = [0.0f32; 3];
+    let mut count = 0;
+    for y in y0 + margin..y0 + size - margin {
+        for x in x0 + margin..x0 + size - margin {
+            let pixel = image.get_pixel(x, y);
+            for c in 0..3 {
+                sum[c] += pixel[c] as f32 / 255.0;
+            }
+            count += 1;
+        }
+    }
+    (0..3)
+        .all(|c| (sum[c] / count.max(1) as f32 - color[c] as f32 / 255.0).abs() <= MAX_MARKER_ERROR)
+}
+
+/// Detects a live input flashing at the start of every `interval` of the system clock, e.g.
+/// `assets/flash.html` shown on a screen filmed by a camera, and measures how long after
+/// each flash the output shows it.
+///
+/// The source and this machine must share the same clock, and latencies must be shorter
+/// than `interval`.
+pub struct FlashDetector {
+    interval: Duration,
+    /// Luma of the whole frame, in `[0, 1]`, above which the flash is shown.
+    threshold: f32,
+    lit: bool,
+}
+
+impl FlashDetector {
+    pub fn new(interval: Duration, threshold: f32) -> Self {
+        Self {
+            interval,
+            threshold,
+            lit: false,
+        }
+    }
+
+    /// Returns the latency of the flash if `image`, received at `arrival`, is the first
+    /// frame showing it.
+    pub fn process(&mut self, image: &RgbaImage, arrival: SystemTime) -> Option<Duration> {
+        let lit = FrameStats::of(image).luma > self.threshold;
+        let rising = lit && !self.lit;
+        self.lit = lit;
+        if !rising {
+            return None;
+        }
+
+        let since_epoch = arrival.duration_since(UNIX_EPOCH).ok()?;
+        let latency =
+            Duration::from_nanos((since_epoch.as_nanos() % self.interval.as_nanos().max(1)) as u64);
+        debug!(
+            "Flash shown {:.1}ms after it started",
+            latency.as_secs_f64() * 1000.0
+        );
+        Some(latency)
+    }
+}
@@ -0,0 +1,76 @@
+//! Minimal Smelter pipeline comparing the colors of image and video inputs.
+//!
+//! The binary runs it from a [`Config`], but the [`Compositor`] and the texture
+//! helpers in [`wgpu`] can also be embedded, e.g. in integration tests.
+
+pub mod alerts;
+pub mod analysis;
+pub mod animation;
+pub mod annotate;
+pub mod audio;
+pub mod batch;
+pub mod builder;
+pub mod cache;
+pub mod camera;
+pub mod canary;
+pub mod compositor;
+pub mod config;
+pub mod container;
+pub mod correction;
+pub mod counter;
+pub mod coverage;
+pub mod dashboard;
+pub mod decklink;
+pub mod display;
+pub mod dual_record;
+pub mod event_log;
+pub mod expected;
+pub mod fallback;
+pub mod gif;
+pub mod golden;
+pub mod hevc;
+pub mod hls;
+pub mod icc;
+pub mod latency;
+pub mod lossless;
+pub mod macros;
+pub mod markers;
+pub mod media;
+pub mod monitor;
+pub mod naming;
+pub mod ndi;
+pub mod noise;
+pub mod outputs;
+pub mod pacing;
+pub mod patterns;
+pub mod playlist;
+pub mod raw_input;
+pub mod raw_video;
+pub mod recording;
+pub mod rtp_sender;
+pub mod scene;
+pub mod screen;
+pub mod sequence;
+pub mod session;
+pub mod srt;
+pub mod startup;
+pub mod stdin_input;
+pub mod svg;
+pub mod sweep;
+pub mod telemetry;
+pub mod terminal;
+pub mod threads;
+pub mod utilization;
+pub mod watch;
+pub mod webm;
+pub mod wgpu;
+pub mod workarounds;
+pub mod y4m;
+
+pub use builder::CompositorBuilder;
+pub use compositor::Compositor;
+pub use config::Config;
+
+// Re-exported so that users can build scenes without pinning the same revision
+pub use compositor_pipeline;
+pub use compositor_render;
@@ -0,0 +1,117 @@
+//! Archival recordings in ProRes 4444 or FFV1, encoded by `ffmpeg` from the frames of a raw
+//! output, for color tests whose chroma detail H264 in 4:2:0 would throw away.
+
+use anyhow::{bail, Context, Result};
+use compositor_pipeline::pipeline::output::RawDataReceiver;
+use compositor_pipeline::pipeline::GraphicsContext;
+use compositor_pipeline::queue::PipelineEvent;
+use compositor_render::{FrameData, Resolution};
+use std::path::Path;
+use std::thread::JoinHandle;
+use tracing::info;
+
+use crate::config::{EncoderConfig, FramerateConfig, RecordCodecConfig};
+use crate::raw_video::{self, RawVideoWriter};
+use crate::wgpu::{to_image, to_image_u16};
+
+/// Encodes the frames of a raw output to a ProRes MOV or FFV1 Matroska file, until it is
+/// unregistered.
+///
+/// ProRes 4444 is visually lossless: frames are converted to YUV 4:4:4 in 10 bits, see
+/// [`raw_video::bt709_limited`], so no chroma is subsampled. FFV1 is mathematically lossless: frames are
+/// stored as the RGB read back, tagged sRGB. With 10 bits, frames are read back as 16-bit
+/// sRGB. Recordings have no audio.
+pub struct LosslessEncoder {
+    thread: JoinHandle<Result<usize>>,
+}
+
+impl LosslessEncoder {
+    pub fn start(
+        path: &Path,
+        receiver: RawDataReceiver,
+        context: GraphicsContext,
+        resolution: Resolution,
+        framerate: FramerateConfig,
+        encoder: &EncoderConfig,
+    ) -> Result<Self> {
+        let video = receiver.video.context("No video channel")?;
+        let sixteen_bit = encoder.bit_depth == 10;
+        let input_format = match sixteen_bit {
+            true => "rgba64le",
+            false => "rgba",
+        };
+        let codec_args = match encoder.codec {
+            RecordCodecConfig::Prores => {
+                ["-c:v", "prores_ks", "-profile:v", "4444", "-vendor", "apl0"]
+                    .into_iter()
+                    .chain(raw_video::bt709_limited("yuv444p10le"))
+                    .chain(["-f", "mov"])
+                    .collect::<Vec<_>>()
+            }
+            RecordCodecConfig::Ffv1 => vec![
+                "-c:v",
+                "ffv1",
+                "-level",
+                "3",
+                // Every frame a keyframe, each slice checksummed, as archives are
+                "-g",
+                "1",
+                "-slices",
+                "4",
+                "-slicecrc",
+                "1",
+                "-pix_fmt",
+                if sixteen_bit { "gbrp16le" } else { "gbrp" },
+                "-colorspace",
+                "rgb",
+                "-color_primaries",
+                "bt709",
+                "-color_trc",
+                "iec61966-2-1",
+                "-color_range",
+                "pc",
+                "-f",
+                "matroska",
+            ],
+            codec => bail!("{codec:?} recordings are not lossless"),
+        };
+
+        let mut command = raw_video::command(input_format, resolution, framerate, false);
+        command.args(codec_args).arg(path);
+        let mut writer = RawVideoWriter::start(command, "the lossless recording")?;
+
+        let thread = std::thread::Builder::new()
+            .name("lossless encoder".to_string())
+            .spawn(move || -> Result<usize> {
+                // The channel closes once the output is unregistered
+                for event in video {
+                    let PipelineEvent::Data(frame) = event else {
+                        break;
+                    };
+                    let FrameData::Rgba8UnormWgpuTexture(texture) = &frame.data else {
+                        bail!("Expected Rgba8UnormWgpuTexture");
+                    };
+                    let bytes: Vec<u8> = match sixteen_bit {
+                        true => to_image_u16(&context, texture, None)?
+                            .as_raw()
+                            .iter()
+                            .flat_map(|value| value.to_le_bytes())
+                            .collect(),
+                        false => to_image(&context, texture, None)?.into_raw(),
+                    };
+                    writer.push(bytes)?;
+                }
+                writer.finish()
+            })
+            .context("Cannot spawn lossless encoder")?;
+
+        info!("Recording {:?} to {}", encoder.codec, path.display());
+        Ok(Self { thread })
+    }
+
+    /// Waits for the file to be written, once the output is unregistered. Returns the number
+    /// of frames.
+    pub fn finish(self) -> Result<usize> {
+        self.thread.join().expect("Lossless encoder panicked")
+    }
+}
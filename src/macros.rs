@@ -0,0 +1,76 @@
+//! Commands issued in the dashboard, recorded with their time to a script that replays them
+//! headless, turning a manual exploration into a reproducible test plan.
+//!
+//! Scripts are JSON lines, one [`MacroStep`] per line, so they can be written or edited by
+//! hand, e.g. `{"at_ms":2500,"command":{"select_scene":1}}`.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use crate::dashboard::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MacroStep {
+    /// Time of the command since the session started.
+    pub at_ms: u64,
+    pub command: Command,
+}
+
+/// Writes the commands of a session to a script as they are issued.
+pub struct MacroRecorder {
+    file: BufWriter<File>,
+    start: Instant,
+    steps: usize,
+}
+
+impl MacroRecorder {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("Cannot create {}", path.display()))?;
+        Ok(Self {
+            file: BufWriter::new(file),
+            start: Instant::now(),
+            steps: 0,
+        })
+    }
+
+    pub fn record(&mut self, command: Command) -> Result<()> {
+        let step = MacroStep {
+            at_ms: self.start.elapsed().as_millis() as u64,
+            command,
+        };
+        serde_json::to_writer(&mut self.file, &step)?;
+        writeln!(self.file)?;
+        // Kept when the session ends abruptly
+        self.file.flush()?;
+        self.steps += 1;
+        Ok(())
+    }
+
+    /// Number of commands recorded.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+}
+
+/// Reads the steps of a script, in time order.
+pub fn load(path: &Path) -> Result<Vec<MacroStep>> {
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    let mut steps = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("Invalid step on line {} of {}", i + 1, path.display()))
+        })
+        .collect::<Result<Vec<MacroStep>>>()?;
+    steps.sort_by_key(|step| step.at_ms);
+    Ok(steps)
+}
@@ -1,19 +1,301 @@
-mod compositor;
-mod wgpu;
-
-use anyhow::Result;
-use compositor::Compositor;
+use anyhow::{bail, Context, Result};
+use smelter_colors::config::{
+    FramerateConfig, InputConfig, PixelFormatConfig, RecordCodecConfig, ResolutionConfig,
+    RtpCodecConfig, RtpTransportConfig,
+};
+use smelter_colors::{alerts, batch, camera, ndi, Compositor, Config};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+/// CSV file batch runs write their findings to, in the directory of the batch unless
+/// `--batch-report` is given.
+const BATCH_REPORT: &str = "batch_report.csv";
+
+/// Directory of a batch run, its report, and whether new files dropped into it are captured
+/// too.
+struct Batch {
+    dir: PathBuf,
+    report: PathBuf,
+    watch: bool,
+}
+
+const USAGE: &str = "Usage: smelter-colors [CONFIG] [--rtp-port PORT] \
+                     [--rtp-transport udp|tcp_server] [--rtp-codec h264|vp8] \
+                     [--stdin-input WIDTHxHEIGHT@FPS] [--stdin-format rgba|yuv420p] \
+                     [--web-url URL] [--ndi-source NAME] [--mute] [--fresh-session] \
+                     [--record-codec h264|h265|prores|ffv1] [--record-10bit] \
+                     [--disable-wgpu-feature NAME]... [--headless]\n       \
+                     smelter-colors [CONFIG] --batch DIR [--batch-report PATH] [--watch]\n       \
+                     smelter-colors --list-cameras\n       \
+                     smelter-colors --list-ndi-sources";
+
 fn main() -> Result<()> {
     tracing_subscriber::fmt()
         .with_max_level(tracing::Level::DEBUG)
         .with_env_filter("smelter_colors=debug,compositor_pipeline=error,compositor_render=error")
+        .with_writer(std::io::stderr)
         .init();
 
-    let mut compositor = Compositor::new()?;
-    compositor.save_images()?;
-    compositor.record_for(Duration::from_secs(5))?;
+    if std::env::args().nth(1).as_deref() == Some("--list-cameras") {
+        for camera in camera::list()? {
+            println!(
+                "{}\t{}\t{}",
+                camera.index,
+                camera.path.display(),
+                camera.name
+            );
+        }
+        return Ok(());
+    }
+    if std::env::args().nth(1).as_deref() == Some("--list-ndi-sources") {
+        for source in ndi::discover(Duration::from_secs(3))? {
+            println!("{source}");
+        }
+        return Ok(());
+    }
+
+    let (config, batch) = parse_args().context(USAGE)?;
 
+    let result = match batch {
+        Some(Batch {
+            dir,
+            report,
+            watch: true,
+        }) => batch::watch(&config, &dir, &report),
+        Some(Batch {
+            dir,
+            report,
+            watch: false,
+        }) => run_batch(&config, &dir, &report),
+        None => run(&config),
+    };
+    if let Err(err) = &result {
+        alerts::failure(&config.alerts, err);
+    }
+    result
+}
+
+fn run(config: &Config) -> Result<()> {
+    let mut compositor = Compositor::new(config)?;
+    compositor.startup_timings().check(&config.startup_budget)?;
+    compositor.run()
+}
+
+/// Fails if any file of the batch failed, once all of them were captured.
+fn run_batch(config: &Config, dir: &Path, report: &Path) -> Result<()> {
+    let entries = batch::run(config, dir, report)?;
+    let failed = entries
+        .iter()
+        .filter(|entry| entry.error.is_some() || !entry.findings.is_empty())
+        .count();
+    if failed > 0 {
+        bail!(
+            "{failed} of {} files failed, see {}",
+            entries.len(),
+            report.display()
+        );
+    }
     Ok(())
 }
+
+/// Optional config file, TOML or JSON, then options adding an RTP input with the ID `rtp`,
+/// a stdin input with the ID `stdin`, a web input with the ID `web` and an NDI input with
+/// the ID `ndi`, or a batch run.
+fn parse_args() -> Result<(Config, Option<Batch>)> {
+    let mut args = std::env::args().skip(1);
+    let mut config_path = None;
+    let mut rtp_port = None;
+    let mut transport = RtpTransportConfig::default();
+    let mut codec = RtpCodecConfig::default();
+    let mut stdin_geometry = None;
+    let mut pixel_format = PixelFormatConfig::default();
+    let mut web_url = None;
+    let mut ndi_source = None;
+    let mut mute = false;
+    let mut fresh_session = false;
+    let mut headless = false;
+    let mut record_codec = None;
+    let mut record_10bit = false;
+    let mut disabled_features = vec![];
+    let mut batch_dir = None;
+    let mut batch_report = None;
+    let mut watch = false;
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("Missing value for {arg}"))
+        };
+        match arg.as_str() {
+            "--rtp-port" => rtp_port = Some(value()?.parse().context("Invalid RTP port")?),
+            "--rtp-transport" => {
+                transport = match value()?.as_str() {
+                    "udp" => RtpTransportConfig::Udp,
+                    "tcp_server" => RtpTransportConfig::TcpServer,
+                    other => bail!("Unknown RTP transport {other:?}"),
+                }
+            }
+            "--rtp-codec" => {
+                codec = match value()?.as_str() {
+                    "h264" => RtpCodecConfig::H264,
+                    "vp8" => RtpCodecConfig::Vp8,
+                    other => bail!("Unknown RTP codec {other:?}"),
+                }
+            }
+            "--stdin-input" => stdin_geometry = Some(parse_geometry(&value()?)?),
+            "--stdin-format" => {
+                pixel_format = match value()?.as_str() {
+                    "rgba" => PixelFormatConfig::Rgba,
+                    "yuv420p" => PixelFormatConfig::Yuv420p,
+                    other => bail!("Unknown stdin pixel format {other:?}"),
+                }
+            }
+            "--web-url" => web_url = Some(value()?),
+            "--ndi-source" => ndi_source = Some(value()?),
+            "--mute" => mute = true,
+            "--fresh-session" => fresh_session = true,
+            "--headless" => headless = true,
+            "--record-codec" => {
+                record_codec = Some(match value()?.as_str() {
+                    "h264" => RecordCodecConfig::H264,
+                    "h265" => RecordCodecConfig::H265,
+                    "prores" => RecordCodecConfig::Prores,
+                    "ffv1" => RecordCodecConfig::Ffv1,
+                    other => bail!("Unknown recording codec {other:?}"),
+                })
+            }
+            "--record-10bit" => record_10bit = true,
+            "--disable-wgpu-feature" => disabled_features.push(value()?),
+            "--batch" => batch_dir = Some(PathBuf::from(value()?)),
+            "--batch-report" => batch_report = Some(PathBuf::from(value()?)),
+            "--watch" => watch = true,
+            _ if arg.starts_with("--") => bail!("Unknown option {arg}"),
+            _ if config_path.is_none() => config_path = Some(PathBuf::from(arg)),
+            _ => bail!("Unexpected argument {arg}"),
+        }
+    }
+
+    let mut config = match config_path {
+        Some(path) => Config::load(&path)?,
+        None => Config::default(),
+    };
+    config.audio.mute |= mute;
+    config.session.restore &= !fresh_session;
+    config.headless |= headless;
+    if let Some(codec) = record_codec {
+        config.encoder.codec = codec;
+    }
+    if record_10bit {
+        config.encoder.bit_depth = 10;
+    }
+    config.disabled_wgpu_features.extend(disabled_features);
+    if let Some(port) = rtp_port {
+        config.inputs.push(InputConfig::Rtp {
+            id: "rtp".to_string(),
+            port,
+            transport,
+            codec,
+            decoder: None,
+            correction: None,
+        });
+    }
+    if let Some((resolution, framerate)) = stdin_geometry {
+        config.inputs.push(InputConfig::Stdin {
+            id: "stdin".to_string(),
+            resolution,
+            framerate,
+            pixel_format,
+            correction: None,
+        });
+    }
+
+    if let Some(url) = web_url {
+        config.web_renderer.enabled = true;
+        config.inputs.push(InputConfig::Web {
+            id: "web".to_string(),
+            url,
+            resolution: None,
+            correction: None,
+        });
+    }
+
+    if let Some(source) = ndi_source {
+        config.inputs.push(InputConfig::Ndi {
+            id: "ndi".to_string(),
+            source,
+            correction: None,
+        });
+    }
+
+    if watch && batch_dir.is_none() {
+        bail!("--watch needs --batch");
+    }
+    if batch_report.is_some() && batch_dir.is_none() {
+        bail!("--batch-report needs --batch");
+    }
+    let batch = batch_dir.map(|dir| Batch {
+        report: batch_report.unwrap_or_else(|| dir.join(BATCH_REPORT)),
+        dir,
+        watch,
+    });
+
+    // Checked again with the inputs and settings of the command line
+    config.validate()?;
+    Ok((config, batch))
+}
+
+/// Parses `WIDTHxHEIGHT@FPS`, where the framerate can be a fraction such as `30000/1001`.
+fn parse_geometry(geometry: &str) -> Result<(ResolutionConfig, FramerateConfig)> {
+    let parse = || {
+        let (resolution, framerate) = geometry.split_once('@')?;
+        let (width, height) = resolution.split_once('x')?;
+        let (num, den) = framerate.split_once('/').unwrap_or((framerate, "1"));
+        Some((
+            ResolutionConfig {
+                width: width.parse().ok()?,
+                height: height.parse().ok()?,
+            },
+            FramerateConfig {
+                num: num.parse().ok()?,
+                den: den.parse().ok()?,
+            },
+        ))
+    };
+
+    parse()
+        .filter(|(resolution, framerate)| {
+            resolution.width > 0 && resolution.height > 0 && framerate.num > 0 && framerate.den > 0
+        })
+        .with_context(|| format!("Invalid stdin geometry {geometry:?}, expected e.g. 1920x1080@30"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geometry() {
+        let (resolution, framerate) = parse_geometry("1920x1080@30").unwrap();
+        assert_eq!((resolution.width, resolution.height), (1920, 1080));
+        assert_eq!((framerate.num, framerate.den), (30, 1));
+
+        let (_, framerate) = parse_geometry("1280x720@30000/1001").unwrap();
+        assert_eq!((framerate.num, framerate.den), (30000, 1001));
+    }
+
+    #[test]
+    fn invalid_geometry() {
+        for geometry in [
+            "1920x1080",
+            "1920@30",
+            "1920x@30",
+            "0x1080@30",
+            "1920x1080@0",
+            "1920x1080@30/0",
+            "1920x1080@30/",
+            "-1x1080@30",
+        ] {
+            assert!(parse_geometry(geometry).is_err(), "{geometry}");
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Colored squares flashed in a corner of recordings when a pipeline event occurs, so
+//! artifacts seen in a recording can be matched with the event log, by eye or by tools.
+
+use compositor_pipeline::event::Event;
+use compositor_render::scene::*;
+use compositor_render::Resolution;
+use std::collections::HashSet;
+
+use crate::annotate::Rect;
+use crate::config::{MarkerCornerConfig, MarkersConfig};
+
+/// Pipeline event a marker is shown for, each with its own color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Marker {
+    /// The recording switched to another input or scene.
+    SceneSwitch,
+    /// An input delivered a stream again, after a previous one.
+    InputReconnected,
+}
+
+impl Marker {
+    pub const ALL: [Marker; 2] = [Marker::SceneSwitch, Marker::InputReconnected];
+
+    /// Name of the event, as in the event log.
+    pub fn name(self) -> &'static str {
+        match self {
+            Marker::SceneSwitch => "scene_applied",
+            Marker::InputReconnected => "input_reconnected",
+        }
+    }
+
+    /// Fully saturated primaries, far apart once encoded to 4:2:0.
+    pub fn color(self) -> [u8; 3] {
+        match self {
+            Marker::SceneSwitch => [255, 0, 0],
+            Marker::InputReconnected => [0, 0, 255],
+        }
+    }
+}
+
+/// Tells reconnections apart from the first stream of each input.
+#[derive(Debug, Default)]
+pub struct Reconnections {
+    delivered: HashSet<String>,
+}
+
+impl Reconnections {
+    /// Whether `event` is a stream delivered by an input that already delivered one.
+    pub fn is_reconnection(&mut self, event: &Event) -> bool {
+        match event {
+            Event::VideoInputStreamDelivered(input_id) => {
+                !self.delivered.insert(input_id.0.to_string())
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Draws the square of `marker` over `scene`, `inset` pixels away from the edges of the
+/// corner, e.g. to keep clear of the canary border.
+pub fn wrap(
+    scene: Component,
+    marker: Marker,
+    markers: &MarkersConfig,
+    inset: u32,
+    resolution: Resolution,
+) -> Component {
+    let [r, g, b] = marker.color();
+    let (size, inset) = (markers.size as f32, inset as f32);
+    let (position_horizontal, position_vertical) = match markers.corner {
+        MarkerCornerConfig::TopLeft => (
+            HorizontalPosition::LeftOffset(inset),
+            VerticalPosition::TopOffset(inset),
+        ),
+        MarkerCornerConfig::TopRight => (
+            HorizontalPosition::RightOffset(inset),
+            VerticalPosition::TopOffset(inset),
+        ),
+        MarkerCornerConfig::BottomLeft => (
+            HorizontalPosition::LeftOffset(inset),
+            VerticalPosition::BottomOffset(inset),
+        ),
+        MarkerCornerConfig::BottomRight => (
+            HorizontalPosition::RightOffset(inset),
+            VerticalPosition::BottomOffset(inset),
+        ),
+    };
+    let square = view(
+        vec![],
+        Position::Absolute(AbsolutePosition {
+            width: Some(size),
+            height: Some(size),
+            position_horizontal,
+            position_vertical,
+            rotation_degrees: 0.0,
+        }),
+        RGBAColor(r, g, b, 255),
+    );
+
+    view(
+        vec![scene, square],
+        Position::Static {
+            width: Some(resolution.width as f32),
+            height: Some(resolution.height as f32),
+        },
+        RGBAColor(0, 0, 0, 0),
+    )
+}
+
+/// Where [`wrap`] draws the square in the output.
+pub fn square(markers: &MarkersConfig, inset: u32, resolution: Resolution) -> Rect {
+    let (width, height) = (resolution.width as u32, resolution.height as u32);
+    let far = |length: u32| length.saturating_sub(inset + markers.size);
+    let (x, y) = match markers.corner {
+        MarkerCornerConfig::TopLeft => (inset, inset),
+        MarkerCornerConfig::TopRight => (far(width), inset),
+        MarkerCornerConfig::BottomLeft => (inset, far(height)),
+        MarkerCornerConfig::BottomRight => (far(width), far(height)),
+    };
+    Rect {
+        x,
+        y,
+        width: markers.size,
+        height: markers.size,
+    }
+}
+
+fn view(children: Vec<Component>, position: Position, background_color: RGBAColor) -> Component {
+    Component::View(ViewComponent {
+        id: None,
+        children,
+        direction: ViewChildrenDirection::Row,
+        position,
+        transition: None,
+        overflow: Overflow::Hidden,
+        background_color,
+        border_radius: BorderRadius::ZERO,
+        border_width: 0.,
+        border_color: RGBAColor(0, 0, 0, 0),
+        box_shadow: vec![],
+        padding: Padding {
+            top: 0.,
+            right: 0.,
+            bottom: 0.,
+            left: 0.,
+        },
+    })
+}
@@ -0,0 +1,319 @@
+use anyhow::{bail, Context, Result};
+use image::RgbaImage;
+use mp4::{AvcConfig, MediaConfig, MediaType, Mp4Config, TrackConfig, TrackType};
+use std::fs::File;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tracing::{debug, info};
+
+use crate::cache::ShaderCache;
+use crate::config::{TrackSelectionConfig, TrimConfig};
+
+/// Duration of an MP4 file, read from its header.
+pub fn mp4_duration(path: &Path) -> Result<Duration> {
+    let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+    let size = file.metadata()?.len();
+    let reader = mp4::Mp4Reader::read_header(BufReader::new(file), size)
+        .with_context(|| format!("Invalid MP4 {}", path.display()))?;
+
+    Ok(reader.duration())
+}
+
+/// Width and height of the video track of an MP4 file.
+pub fn mp4_resolution(path: &Path) -> Result<(u32, u32)> {
+    let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+    let size = file.metadata()?.len();
+    let reader = mp4::Mp4Reader::read_header(BufReader::new(file), size)
+        .with_context(|| format!("Invalid MP4 {}", path.display()))?;
+    let track = reader
+        .tracks()
+        .values()
+        .find(|track| track.track_type().ok() == Some(TrackType::Video))
+        .with_context(|| format!("{} has no video track", path.display()))?;
+
+    Ok((track.width() as u32, track.height() as u32))
+}
+
+/// First frame of an MP4 file, decoded by `ffmpeg` at its own resolution with the color
+/// matrix and range of its metadata.
+pub fn mp4_first_frame(path: &Path) -> Result<RgbaImage> {
+    let (width, height) = mp4_resolution(path)?;
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-i"])
+        .arg(path)
+        .args([
+            "-an",
+            "-frames:v",
+            "1",
+            "-pix_fmt",
+            "rgba",
+            "-f",
+            "rawvideo",
+            "-",
+        ])
+        .stdin(Stdio::null())
+        .output()
+        .context("Cannot start ffmpeg, which decodes reference frames")?;
+    if !output.status.success() {
+        bail!(
+            "Cannot decode {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    RgbaImage::from_raw(width, height, output.stdout)
+        .with_context(|| format!("Truncated first frame of {}", path.display()))
+}
+
+/// Joins MP4 files encoded with the same settings into `path`, one after the other.
+///
+/// Samples are copied as they are by `ffmpeg`, timestamps following on from one file to
+/// the next.
+pub fn concat_mp4(segments: &[PathBuf], path: &Path) -> Result<()> {
+    let list_path = path.with_extension("segments.txt");
+    let list: String = segments
+        .iter()
+        .map(|segment| -> Result<String> {
+            let segment = segment.canonicalize()?;
+            let segment = segment.display().to_string().replace('\'', "'\\''");
+            Ok(format!("file '{segment}'\n"))
+        })
+        .collect::<Result<_>>()?;
+    std::fs::write(&list_path, list)
+        .with_context(|| format!("Cannot write {}", list_path.display()))?;
+
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-y"])
+        .args(["-f", "concat", "-safe", "0", "-i"])
+        .arg(&list_path)
+        .args(["-c", "copy"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .context("Cannot start ffmpeg, which joins recording segments")?;
+    std::fs::remove_file(&list_path)?;
+    if !output.status.success() {
+        bail!(
+            "Cannot join segments into {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
+
+/// Whether an MP4 file has an audio track.
+pub fn mp4_has_audio(path: &Path) -> Result<bool> {
+    let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+    let size = file.metadata()?.len();
+    let reader = mp4::Mp4Reader::read_header(BufReader::new(file), size)
+        .with_context(|| format!("Invalid MP4 {}", path.display()))?;
+
+    Ok(reader
+        .tracks()
+        .values()
+        .any(|track| track.track_type().ok() == Some(TrackType::Audio)))
+}
+
+/// Tracks of an MP4 file.
+#[derive(Debug, Clone, Default)]
+pub struct Mp4Tracks {
+    pub video: usize,
+    /// Language of each audio track, e.g. `eng`, in file order.
+    pub audio: Vec<String>,
+}
+
+/// Video and audio tracks of an MP4 file, read from its header.
+pub fn mp4_tracks(path: &Path) -> Result<Mp4Tracks> {
+    let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+    let size = file.metadata()?.len();
+    let reader = mp4::Mp4Reader::read_header(BufReader::new(file), size)
+        .with_context(|| format!("Invalid MP4 {}", path.display()))?;
+
+    let mut tracks: Vec<_> = reader.tracks().values().collect();
+    tracks.sort_by_key(|track| track.track_id());
+    let mut found = Mp4Tracks::default();
+    for track in tracks {
+        match track.track_type().ok() {
+            Some(TrackType::Video) => found.video += 1,
+            Some(TrackType::Audio) => found.audio.push(track.language().to_string()),
+            _ => {}
+        }
+    }
+    Ok(found)
+}
+
+/// Copies a video track of an MP4 file, and an audio track when selected, to their own
+/// file in the cache directory, and returns its path: the pipeline only plays the first
+/// video and audio tracks of a file.
+///
+/// Samples are copied as they are by `ffmpeg`. The file is reused until its source changes.
+pub fn select_mp4_tracks(path: &Path, tracks: &TrackSelectionConfig) -> Result<PathBuf> {
+    let stem = cache_stem(path);
+    let audio = tracks
+        .audio
+        .map_or("none".to_string(), |audio| audio.to_string());
+    let dir = ShaderCache::default_dir().join("tracks");
+    let selected = dir.join(format!("{stem}_video{}_audio{audio}.mp4", tracks.video));
+    if is_up_to_date(&selected, path) {
+        debug!("Reusing {}", selected.display());
+        return Ok(selected);
+    }
+
+    std::fs::create_dir_all(&dir).with_context(|| format!("Cannot create {}", dir.display()))?;
+    let mut ffmpeg = Command::new("ffmpeg");
+    ffmpeg
+        .args(["-hide_banner", "-loglevel", "error", "-y", "-i"])
+        .arg(path)
+        .args(["-map", &format!("0:v:{}", tracks.video)]);
+    if let Some(audio) = tracks.audio {
+        ffmpeg.args(["-map", &format!("0:a:{audio}")]);
+    }
+    let output = ffmpeg
+        .args(["-c", "copy"])
+        .arg(&selected)
+        .stdin(Stdio::null())
+        .output()
+        .context("Cannot start ffmpeg, which copies the tracks of MP4 inputs")?;
+    if !output.status.success() {
+        bail!(
+            "Cannot copy the tracks of {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    info!(
+        "Copied video track {} and audio track {audio} of {} to {}",
+        tracks.video,
+        path.display(),
+        selected.display()
+    );
+
+    Ok(selected)
+}
+
+/// Stem of the files made from `source` in the cache directory: its own stem, followed by
+/// a hash of its canonical path, so that files of the same name in different directories
+/// do not share them.
+fn cache_stem(source: &Path) -> String {
+    let canonical = source
+        .canonicalize()
+        .unwrap_or_else(|_| source.to_path_buf());
+    let mut hasher = DefaultHasher::new();
+    canonical.hash(&mut hasher);
+    let stem = source.file_stem().unwrap_or_default().to_string_lossy();
+    format!("{stem}_{:016x}", hasher.finish())
+}
+
+/// Whether a file made from `source` exists, and was made after the last change to it.
+fn is_up_to_date(derived: &Path, source: &Path) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|meta| meta.modified());
+    match (modified(source), modified(derived)) {
+        (Ok(source), Ok(derived)) => derived >= source,
+        _ => false,
+    }
+}
+
+/// Copies a segment of the H264 video track of an MP4 file to its own file, in the
+/// cache directory, and returns its path.
+///
+/// Samples are copied as they are, so colors are untouched, but the segment has to start
+/// on a keyframe: it starts at the last one at or before `trim.start_ms`. Other tracks are
+/// dropped. A segment is reused until its source changes.
+pub fn trim_mp4(path: &Path, trim: &TrimConfig) -> Result<PathBuf> {
+    let stem = cache_stem(path);
+    let duration = trim
+        .duration_ms
+        .map_or("end".to_string(), |duration| duration.to_string());
+    let dir = ShaderCache::default_dir().join("trimmed");
+    let trimmed = dir.join(format!("{stem}_{}_{duration}.mp4", trim.start_ms));
+
+    if is_up_to_date(&trimmed, path) {
+        debug!("Reusing {}", trimmed.display());
+        return Ok(trimmed);
+    }
+
+    let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+    let size = file.metadata()?.len();
+    let mut reader = mp4::Mp4Reader::read_header(BufReader::new(file), size)
+        .with_context(|| format!("Invalid MP4 {}", path.display()))?;
+    let Some(track) = reader.tracks().values().find(|track| {
+        track.track_type().ok() == Some(TrackType::Video)
+            && track.media_type().ok() == Some(MediaType::H264)
+    }) else {
+        bail!("{} has no H264 video track", path.display());
+    };
+    let track_id = track.track_id();
+    let timescale = track.timescale();
+    let sample_count = track.sample_count();
+    let config = TrackConfig {
+        track_type: TrackType::Video,
+        timescale,
+        language: "und".to_string(),
+        media_conf: MediaConfig::AvcConfig(AvcConfig {
+            width: track.width(),
+            height: track.height(),
+            seq_param_set: track.sequence_parameter_set()?.to_vec(),
+            pic_param_set: track.picture_parameter_set()?.to_vec(),
+        }),
+    };
+
+    let to_units = |ms: u64| ms * timescale as u64 / 1000;
+    let start = to_units(trim.start_ms);
+    let end = trim.duration_ms.map(|duration| start + to_units(duration));
+
+    let mut samples = vec![];
+    for sample_id in 1..=sample_count {
+        let Some(sample) = reader.read_sample(track_id, sample_id)? else {
+            continue;
+        };
+        if end.is_some_and(|end| sample.start_time >= end) {
+            break;
+        }
+        // Restart from every keyframe until the start is passed
+        if sample.is_sync && sample.start_time <= start {
+            samples.clear();
+        }
+        samples.push(sample);
+    }
+    let Some(first) = samples.first().map(|sample| sample.start_time) else {
+        bail!("{} has no frame after {}ms", path.display(), trim.start_ms);
+    };
+    if !samples[0].is_sync {
+        bail!("{} does not start with a keyframe", path.display());
+    }
+
+    std::fs::create_dir_all(&dir).with_context(|| format!("Cannot create {}", dir.display()))?;
+    let file =
+        File::create(&trimmed).with_context(|| format!("Cannot create {}", trimmed.display()))?;
+    let mut writer = mp4::Mp4Writer::write_start(
+        BufWriter::new(file),
+        &Mp4Config {
+            major_brand: "isom".parse()?,
+            minor_version: 512,
+            compatible_brands: vec!["isom".parse()?, "avc1".parse()?, "mp41".parse()?],
+            timescale: 1000,
+        },
+    )?;
+    writer.add_track(&config)?;
+    for mut sample in samples {
+        sample.start_time -= first;
+        writer.write_sample(1, &sample)?;
+    }
+    writer.write_end()?;
+
+    let ms = |units: u64| units * 1000 / timescale.max(1) as u64;
+    info!(
+        "Trimmed {} from {}ms (keyframe at {}ms) to {}",
+        path.display(),
+        trim.start_ms,
+        ms(first),
+        trimmed.display()
+    );
+    Ok(trimmed)
+}
@@ -0,0 +1,168 @@
+//! Playback and levels of the mixed audio, while monitoring.
+
+use anyhow::{bail, Context, Result};
+use compositor_pipeline::audio_mixer::{AudioChannels, AudioSamples, OutputSamples};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info};
+
+use crate::audio::SAMPLE_RATE;
+
+/// Most audio buffered ahead of the device, older samples are dropped past it so the sound
+/// stays in sync with the preview.
+const MAX_BUFFERED_MS: usize = 200;
+
+/// Plays the samples of a raw audio output as they are received.
+///
+/// The stream stops when the monitor is dropped. It must stay on the thread that started it.
+pub struct AudioMonitor {
+    _stream: cpal::Stream,
+    buffer: Arc<Mutex<VecDeque<f32>>>,
+    channels: usize,
+}
+
+impl AudioMonitor {
+    pub fn start(channels: AudioChannels) -> Result<Self> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .context("No audio output device")?;
+        let channels = match channels {
+            AudioChannels::Mono => 1,
+            AudioChannels::Stereo => 2,
+        };
+        let config = cpal::StreamConfig {
+            channels: channels as u16,
+            sample_rate: cpal::SampleRate(SAMPLE_RATE),
+            buffer_size: cpal::BufferSize::Default,
+        };
+
+        let buffer = Arc::new(Mutex::new(VecDeque::new()));
+        let source = buffer.clone();
+        let stream = device
+            .build_output_stream(
+                &config,
+                move |data: &mut [f32], _| {
+                    let mut source = source.lock().unwrap();
+                    for sample in data {
+                        // Silence while the pipeline has not caught up
+                        *sample = source.pop_front().unwrap_or(0.0);
+                    }
+                },
+                |err| debug!("Audio monitor stream error: {err}"),
+                None,
+            )
+            .context("Cannot open an audio output stream")?;
+        if let Err(err) = stream.play() {
+            bail!("Cannot start audio playback: {err}");
+        }
+        info!(
+            "Playing the mixed audio on {}",
+            device
+                .name()
+                .unwrap_or_else(|_| "the default device".to_string())
+        );
+
+        Ok(Self {
+            _stream: stream,
+            buffer,
+            channels,
+        })
+    }
+
+    /// Queues samples for playback.
+    pub fn push(&self, samples: &OutputSamples) {
+        let mut buffer = self.buffer.lock().unwrap();
+        match &samples.samples {
+            AudioSamples::Mono(samples) => {
+                buffer.extend(samples.iter().map(|&sample| sample as f32))
+            }
+            AudioSamples::Stereo(samples) => buffer.extend(
+                samples
+                    .iter()
+                    .flat_map(|&(left, right)| [left as f32, right as f32]),
+            ),
+        }
+
+        let max = SAMPLE_RATE as usize * MAX_BUFFERED_MS / 1000 * self.channels;
+        if buffer.len() > max {
+            let excess = buffer.len() - max;
+            buffer.drain(..excess);
+        }
+    }
+}
+
+/// Level of a channel over the samples of a dashboard refresh.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChannelLevel {
+    /// Root mean square of the samples, 1 being full scale.
+    pub rms: f32,
+    pub peak: f32,
+    /// Samples at or past full scale since monitoring started.
+    pub clipped: usize,
+}
+
+/// RMS and peak levels of each channel of the mixed audio.
+#[derive(Debug, Clone)]
+pub struct LevelMeter {
+    /// Sum of the squared samples, peak and count since the last read, per channel.
+    windows: Vec<(f64, f32, usize)>,
+    clipped: Vec<usize>,
+}
+
+impl LevelMeter {
+    pub fn new(channels: AudioChannels) -> Self {
+        let channels = match channels {
+            AudioChannels::Mono => 1,
+            AudioChannels::Stereo => 2,
+        };
+        Self {
+            windows: vec![(0.0, 0.0, 0); channels],
+            clipped: vec![0; channels],
+        }
+    }
+
+    pub fn push(&mut self, samples: &OutputSamples) {
+        match &samples.samples {
+            AudioSamples::Mono(samples) => {
+                for &sample in samples {
+                    self.add(0, sample as f32);
+                }
+            }
+            AudioSamples::Stereo(samples) => {
+                for &(left, right) in samples {
+                    self.add(0, left as f32);
+                    self.add(1, right as f32);
+                }
+            }
+        }
+    }
+
+    /// Levels since the last call, an empty window reading as silence.
+    pub fn read(&mut self) -> Vec<ChannelLevel> {
+        self.windows
+            .iter_mut()
+            .zip(&self.clipped)
+            .map(|(window, &clipped)| {
+                let (sum, peak, count) = std::mem::take(window);
+                ChannelLevel {
+                    rms: (sum / count.max(1) as f64).sqrt() as f32,
+                    peak,
+                    clipped,
+                }
+            })
+            .collect()
+    }
+
+    fn add(&mut self, channel: usize, sample: f32) {
+        let Some((sum, peak, count)) = self.windows.get_mut(channel) else {
+            return;
+        };
+        *sum += sample as f64 * sample as f64;
+        *peak = peak.max(sample.abs());
+        *count += 1;
+        if sample.abs() >= 1.0 {
+            self.clipped[channel] += 1;
+        }
+    }
+}
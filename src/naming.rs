@@ -0,0 +1,143 @@
+//! Names of recording files, from templates with the time the recording starts, e.g.
+//! `capture_%Y%m%d_%H%M%S.mp4`, never names of existing files: repeated runs add files
+//! next to the earlier ones instead of deleting them.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Replaces the fields of `template` with the UTC date and time of `time`: `%Y` the year,
+/// `%m` the month, `%d` the day, `%H` the hour, `%M` the minute, `%S` the second, and `%%` a
+/// `%`. Other characters are kept, so fixed names are used as-is.
+pub fn expand(template: &Path, time: SystemTime) -> PathBuf {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let (hour, minute, second) = (secs / 3600 % 24, secs / 60 % 60, secs % 60);
+
+    let template = template.to_string_lossy();
+    let mut name = String::with_capacity(template.len());
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            name.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => name.push_str(&format!("{year:04}")),
+            Some('m') => name.push_str(&format!("{month:02}")),
+            Some('d') => name.push_str(&format!("{day:02}")),
+            Some('H') => name.push_str(&format!("{hour:02}")),
+            Some('M') => name.push_str(&format!("{minute:02}")),
+            Some('S') => name.push_str(&format!("{second:02}")),
+            Some('%') => name.push('%'),
+            Some(other) => {
+                name.push('%');
+                name.push(other);
+            }
+            None => name.push('%'),
+        }
+    }
+    PathBuf::from(name)
+}
+
+/// `path`, or `{stem}-2.{extension}`, `{stem}-3.{extension}`, ... the first one that is not
+/// `taken`.
+pub fn available(path: &Path, taken: impl Fn(&Path) -> bool) -> PathBuf {
+    if !taken(path) {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    (2..)
+        .map(|index| path.with_file_name(format!("{stem}-{index}{extension}")))
+        .find(|candidate| !taken(candidate))
+        .expect("Ran out of file names")
+}
+
+/// Path to write to from `template`, expanded with the current time and not `taken`, see
+/// [`expand`] and [`available`], its directory created.
+pub fn resolve(template: &Path, taken: impl Fn(&Path) -> bool) -> Result<PathBuf> {
+    let path = available(&expand(template, SystemTime::now()), taken);
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).with_context(|| format!("Cannot create {}", dir.display()))?;
+    }
+    Ok(path)
+}
+
+/// [`resolve`] for a single file, taken when it exists.
+pub fn resolve_file(template: &Path) -> Result<PathBuf> {
+    resolve(template, Path::exists)
+}
+
+/// Year, month and day of the `days`th day since 1970-01-01, in the proleptic Gregorian
+/// calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // Eras of 400 years starting on March 1st, so that leap days end the year
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// 2024-02-29 13:05:09 UTC.
+    fn time() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_709_211_909)
+    }
+
+    #[test]
+    fn fields() {
+        let expand = |template: &str| expand(Path::new(template), time());
+        assert_eq!(
+            expand("recordings/capture_%Y%m%d_%H%M%S.mp4"),
+            Path::new("recordings/capture_20240229_130509.mp4")
+        );
+        assert_eq!(expand("output.mp4"), Path::new("output.mp4"));
+        assert_eq!(expand("100%%_%d.mp4"), Path::new("100%_29.mp4"));
+        assert_eq!(expand("%q%Y%"), Path::new("%q2024%"));
+    }
+
+    #[test]
+    fn dates() {
+        for (days, date) in [
+            (0, (1970, 1, 1)),
+            (-1, (1969, 12, 31)),
+            (11_016, (2000, 2, 29)),
+            (11_017, (2000, 3, 1)),
+            (-25_508, (1900, 3, 1)),
+            (20_088, (2024, 12, 31)),
+        ] {
+            assert_eq!(civil_from_days(days), date, "{days}");
+        }
+    }
+
+    #[test]
+    fn available_names() {
+        let taken = ["out/capture.mp4", "out/capture-2.mp4", "out/log"];
+        let taken = |path: &Path| taken.iter().any(|taken| path == Path::new(taken));
+        let available = |path: &str| available(Path::new(path), taken);
+        assert_eq!(available("out/other.mp4"), Path::new("out/other.mp4"));
+        assert_eq!(available("out/capture.mp4"), Path::new("out/capture-3.mp4"));
+        assert_eq!(available("out/log"), Path::new("out/log-2"));
+    }
+}
@@ -0,0 +1,465 @@
+//! NDI sources, received or sent with the NDI runtime loaded when first needed.
+//!
+//! The runtime is not redistributed with this crate, it is looked up in the directory of
+//! `NDI_RUNTIME_DIR_V6` or `NDI_RUNTIME_DIR_V5` when set, then in the library search path.
+//! Sending is only available in builds with the `ndi` feature.
+
+use anyhow::{anyhow, bail, Context, Result};
+use compositor_pipeline::pipeline::GraphicsContext;
+use compositor_pipeline::queue::{PipelineEvent, QueueInputOptions};
+use compositor_pipeline::Pipeline;
+use image::RgbaImage;
+use libloading::Library;
+use std::ffi::{c_void, CStr, CString};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::config::FramerateConfig;
+use crate::raw_input;
+
+/// How long a capture waits for a frame, so unregistered inputs are noticed.
+const CAPTURE_TIMEOUT_MS: u32 = 500;
+
+/// Lists the names of the NDI sources announced on the network within `timeout`.
+pub fn discover(timeout: Duration) -> Result<Vec<String>> {
+    let runtime = runtime()?;
+    let settings = sys::FindCreate {
+        show_local_sources: true,
+        groups: std::ptr::null(),
+        extra_ips: std::ptr::null(),
+    };
+    let finder = unsafe { (runtime.find_create)(&settings) };
+    if finder.is_null() {
+        bail!("Cannot create an NDI finder");
+    }
+
+    // Sources keep being announced, wait until none is added for the rest of the timeout
+    let start = Instant::now();
+    while let Some(rest) = timeout.checked_sub(start.elapsed()) {
+        if !unsafe { (runtime.find_wait_for_sources)(finder, rest.as_millis() as u32) } {
+            break;
+        }
+    }
+
+    let mut count = 0;
+    let sources = unsafe { (runtime.find_get_current_sources)(finder, &mut count) };
+    let names = (0..count as usize)
+        .filter_map(|index| {
+            let source = unsafe { &*sources.add(index) };
+            (!source.name.is_null()).then(|| {
+                unsafe { CStr::from_ptr(source.name) }
+                    .to_string_lossy()
+                    .into_owned()
+            })
+        })
+        .collect();
+    unsafe { (runtime.find_destroy)(finder) };
+
+    Ok(names)
+}
+
+/// Registers a raw data input fed with the video of the NDI source named `source`, e.g.
+/// `"STUDIO (Preview Monitor)"`.
+///
+/// The source does not need to be announced yet, the receiver connects once it is. Frames
+/// are received as RGBA at the resolution of the source, on a thread of their own.
+pub fn register(
+    pipeline: &Arc<Mutex<Pipeline>>,
+    graphics_context: &GraphicsContext,
+    id: &str,
+    source: &str,
+    queue_options: QueueInputOptions,
+) -> Result<()> {
+    let runtime = runtime()?;
+    let source_name = CString::new(source)?;
+    let receiver_name = CString::new(format!("smelter-colors {id}"))?;
+    let settings = sys::RecvCreate {
+        source: sys::Source {
+            name: source_name.as_ptr(),
+            url_address: std::ptr::null(),
+        },
+        color_format: sys::RECV_COLOR_FORMAT_RGBX_RGBA,
+        bandwidth: sys::RECV_BANDWIDTH_HIGHEST,
+        allow_video_fields: false,
+        name: receiver_name.as_ptr(),
+    };
+    let receiver = Receiver(unsafe { (runtime.recv_create)(&settings) });
+    if receiver.0.is_null() {
+        bail!("Cannot create an NDI receiver for {source:?}");
+    }
+    let video = raw_input::register(pipeline, id, queue_options)?;
+
+    let graphics_context = graphics_context.clone();
+    let input_id = id.to_string();
+    let source = source.to_string();
+    std::thread::Builder::new()
+        .name(format!("ndi {id}"))
+        .spawn(move || {
+            // Moved as a whole, so it is destroyed when the thread stops
+            let receiver = receiver;
+            let start = Instant::now();
+            let mut connected = false;
+            loop {
+                let mut frame = sys::VideoFrame::default();
+                let frame_type = unsafe {
+                    (runtime.recv_capture)(
+                        receiver.0,
+                        &mut frame,
+                        std::ptr::null_mut(),
+                        std::ptr::null_mut(),
+                        CAPTURE_TIMEOUT_MS,
+                    )
+                };
+                let image = match frame_type {
+                    sys::FRAME_TYPE_VIDEO => {
+                        let image = to_image(&frame);
+                        unsafe { (runtime.recv_free_video)(receiver.0, &frame) };
+                        image
+                    }
+                    sys::FRAME_TYPE_ERROR => {
+                        warn!("NDI source {source:?} of {input_id} disconnected");
+                        return;
+                    }
+                    // Timeouts, audio and metadata
+                    _ => None,
+                };
+
+                let event = match image {
+                    Some(image) => {
+                        if !connected {
+                            info!(
+                                "Receiving {source:?} at {}x{} as {input_id}",
+                                image.width(),
+                                image.height()
+                            );
+                            connected = true;
+                        }
+                        PipelineEvent::Data(raw_input::frame(
+                            &graphics_context,
+                            &image,
+                            start.elapsed(),
+                        ))
+                    }
+                    None => continue,
+                };
+                if video.send(event).is_err() {
+                    debug!("Input {input_id} unregistered, stopping NDI receiver");
+                    return;
+                }
+            }
+        })?;
+
+    info!("Waiting for NDI source {source:?} as {id}");
+    Ok(())
+}
+
+/// Copies an RGBA or RGBX frame, `None` for other formats.
+fn to_image(frame: &sys::VideoFrame) -> Option<RgbaImage> {
+    let opaque = match frame.fourcc {
+        sys::FOURCC_RGBA => false,
+        sys::FOURCC_RGBX => true,
+        other => {
+            warn!("Unsupported NDI frame format {:?}", other.to_le_bytes());
+            return None;
+        }
+    };
+    let (width, height) = (frame.xres.max(0) as u32, frame.yres.max(0) as u32);
+    let stride = (frame.line_stride_in_bytes.max(0) as usize).max(width as usize * 4);
+    if frame.data.is_null() || width == 0 || height == 0 {
+        return None;
+    }
+
+    let data = unsafe { std::slice::from_raw_parts(frame.data, stride * height as usize) };
+    let mut image = RgbaImage::new(width, height);
+    for (row, pixels) in data
+        .chunks_exact(stride)
+        .zip(image.chunks_exact_mut(width as usize * 4))
+    {
+        pixels.copy_from_slice(&row[..width as usize * 4]);
+        if opaque {
+            pixels.chunks_exact_mut(4).for_each(|pixel| pixel[3] = 255);
+        }
+    }
+    Some(image)
+}
+
+/// Publishes frames as an NDI source, announced on the network under the name of the
+/// machine and `name`, e.g. `"STUDIO (smelter-colors)"`.
+///
+/// Frames are sent as RGBA, progressive, tagged with the output framerate. They are paced
+/// by the output, not by the NDI runtime.
+pub struct NdiSender {
+    #[cfg(feature = "ndi")]
+    sender: Sender,
+    #[cfg(feature = "ndi")]
+    framerate: FramerateConfig,
+    name: String,
+    frames: usize,
+}
+
+impl NdiSender {
+    pub fn create(name: &str, framerate: FramerateConfig) -> Result<Self> {
+        #[cfg(not(feature = "ndi"))]
+        {
+            let _ = framerate;
+            bail!("NDI output {name:?} requires a build with the ndi feature");
+        }
+
+        #[cfg(feature = "ndi")]
+        {
+            let runtime = runtime()?;
+            let source_name = CString::new(name)?;
+            let settings = sys::SendCreate {
+                name: source_name.as_ptr(),
+                groups: std::ptr::null(),
+                clock_video: false,
+                clock_audio: false,
+            };
+            let sender = Sender(unsafe { (runtime.send_create)(&settings) });
+            if sender.0.is_null() {
+                bail!("Cannot create the NDI source {name:?}");
+            }
+            info!("Publishing NDI source {name:?}");
+            Ok(Self {
+                sender,
+                framerate,
+                name: name.to_string(),
+                frames: 0,
+            })
+        }
+    }
+
+    pub fn send(&mut self, image: &RgbaImage) -> Result<()> {
+        #[cfg(feature = "ndi")]
+        {
+            let runtime = runtime()?;
+            let (width, height) = image.dimensions();
+            let frame = sys::VideoFrame {
+                xres: width as i32,
+                yres: height as i32,
+                fourcc: sys::FOURCC_RGBA,
+                frame_rate_n: self.framerate.num as i32,
+                frame_rate_d: self.framerate.den as i32,
+                picture_aspect_ratio: width as f32 / height.max(1) as f32,
+                frame_format_type: sys::FRAME_FORMAT_PROGRESSIVE,
+                timecode: sys::SEND_TIMECODE_SYNTHESIZE,
+                // Only read, the frame is copied before the call returns
+                data: image.as_ptr() as *mut u8,
+                line_stride_in_bytes: width as i32 * 4,
+                ..Default::default()
+            };
+            unsafe { (runtime.send_send_video)(self.sender.0, &frame) };
+        }
+        #[cfg(not(feature = "ndi"))]
+        let _ = image;
+
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Stops publishing, returns the number of frames sent.
+    pub fn finish(self) -> usize {
+        info!("Sent {} frames as NDI source {:?}", self.frames, self.name);
+        self.frames
+    }
+}
+
+/// Destroyed when the [`NdiSender`] is dropped.
+#[cfg(feature = "ndi")]
+struct Sender(*mut c_void);
+
+#[cfg(feature = "ndi")]
+impl Drop for Sender {
+    fn drop(&mut self) {
+        if let Ok(runtime) = runtime() {
+            unsafe { (runtime.send_destroy)(self.0) };
+        }
+    }
+}
+
+/// Destroyed when the capture thread stops.
+struct Receiver(*mut c_void);
+
+// NDI receivers can be used from any thread, one at a time
+unsafe impl Send for Receiver {}
+
+impl Drop for Receiver {
+    fn drop(&mut self) {
+        if let Ok(runtime) = runtime() {
+            unsafe { (runtime.recv_destroy)(self.0) };
+        }
+    }
+}
+
+struct Runtime {
+    find_create: unsafe extern "C" fn(*const sys::FindCreate) -> *mut c_void,
+    find_destroy: unsafe extern "C" fn(*mut c_void),
+    find_wait_for_sources: unsafe extern "C" fn(*mut c_void, u32) -> bool,
+    find_get_current_sources: unsafe extern "C" fn(*mut c_void, *mut u32) -> *const sys::Source,
+    recv_create: unsafe extern "C" fn(*const sys::RecvCreate) -> *mut c_void,
+    recv_destroy: unsafe extern "C" fn(*mut c_void),
+    recv_capture: unsafe extern "C" fn(
+        *mut c_void,
+        *mut sys::VideoFrame,
+        *mut c_void,
+        *mut c_void,
+        u32,
+    ) -> i32,
+    recv_free_video: unsafe extern "C" fn(*mut c_void, *const sys::VideoFrame),
+    #[cfg(feature = "ndi")]
+    send_create: unsafe extern "C" fn(*const sys::SendCreate) -> *mut c_void,
+    #[cfg(feature = "ndi")]
+    send_destroy: unsafe extern "C" fn(*mut c_void),
+    #[cfg(feature = "ndi")]
+    send_send_video: unsafe extern "C" fn(*mut c_void, *const sys::VideoFrame),
+    // Kept loaded for the whole run, the functions above live in it
+    _library: Library,
+}
+
+/// The NDI runtime, loaded and initialized on first use.
+fn runtime() -> Result<&'static Runtime> {
+    static RUNTIME: OnceLock<std::result::Result<Runtime, String>> = OnceLock::new();
+    RUNTIME
+        .get_or_init(|| Runtime::load().map_err(|err| format!("{err:#}")))
+        .as_ref()
+        .map_err(|err| anyhow!("{err}"))
+}
+
+impl Runtime {
+    fn load() -> Result<Self> {
+        let library = library_candidates()
+            .into_iter()
+            // Safety: loading runs the library initializers, the NDI runtime is trusted
+            .find_map(|path| unsafe { Library::new(&path) }.ok())
+            .context("NDI runtime not found, install it or set NDI_RUNTIME_DIR_V6")?;
+
+        unsafe {
+            let initialize =
+                *library.get::<unsafe extern "C" fn() -> bool>(b"NDIlib_initialize\0")?;
+            if !initialize() {
+                bail!("NDI runtime does not support this CPU");
+            }
+            let runtime = Self {
+                find_create: *library.get(b"NDIlib_find_create_v2\0")?,
+                find_destroy: *library.get(b"NDIlib_find_destroy\0")?,
+                find_wait_for_sources: *library.get(b"NDIlib_find_wait_for_sources\0")?,
+                find_get_current_sources: *library.get(b"NDIlib_find_get_current_sources\0")?,
+                recv_create: *library.get(b"NDIlib_recv_create_v3\0")?,
+                recv_destroy: *library.get(b"NDIlib_recv_destroy\0")?,
+                recv_capture: *library.get(b"NDIlib_recv_capture_v2\0")?,
+                recv_free_video: *library.get(b"NDIlib_recv_free_video_v2\0")?,
+                #[cfg(feature = "ndi")]
+                send_create: *library.get(b"NDIlib_send_create\0")?,
+                #[cfg(feature = "ndi")]
+                send_destroy: *library.get(b"NDIlib_send_destroy\0")?,
+                #[cfg(feature = "ndi")]
+                send_send_video: *library.get(b"NDIlib_send_send_video_v2\0")?,
+                _library: library,
+            };
+            info!("Loaded the NDI runtime");
+            Ok(runtime)
+        }
+    }
+}
+
+fn library_candidates() -> Vec<PathBuf> {
+    let names: &[&str] = match std::env::consts::OS {
+        "windows" => &["Processing.NDI.Lib.x64.dll"],
+        "macos" => &["libndi.dylib"],
+        _ => &["libndi.so.6", "libndi.so.5", "libndi.so"],
+    };
+
+    let mut candidates = vec![];
+    for variable in ["NDI_RUNTIME_DIR_V6", "NDI_RUNTIME_DIR_V5"] {
+        if let Some(dir) = std::env::var_os(variable) {
+            candidates.extend(names.iter().map(|name| PathBuf::from(&dir).join(name)));
+        }
+    }
+    candidates.extend(names.iter().map(PathBuf::from));
+    candidates
+}
+
+/// Types and constants of the NDI SDK headers used here.
+mod sys {
+    use std::ffi::c_char;
+
+    pub const FRAME_TYPE_VIDEO: i32 = 1;
+    pub const FRAME_TYPE_ERROR: i32 = 4;
+    pub const RECV_COLOR_FORMAT_RGBX_RGBA: i32 = 2;
+    pub const RECV_BANDWIDTH_HIGHEST: i32 = 100;
+    pub const FOURCC_RGBA: u32 = u32::from_le_bytes(*b"RGBA");
+    pub const FOURCC_RGBX: u32 = u32::from_le_bytes(*b"RGBX");
+    #[cfg(feature = "ndi")]
+    pub const FRAME_FORMAT_PROGRESSIVE: i32 = 1;
+    /// Lets the runtime timestamp sent frames.
+    #[cfg(feature = "ndi")]
+    pub const SEND_TIMECODE_SYNTHESIZE: i64 = i64::MAX;
+
+    #[repr(C)]
+    pub struct Source {
+        pub name: *const c_char,
+        pub url_address: *const c_char,
+    }
+
+    #[repr(C)]
+    pub struct FindCreate {
+        pub show_local_sources: bool,
+        pub groups: *const c_char,
+        pub extra_ips: *const c_char,
+    }
+
+    #[repr(C)]
+    pub struct RecvCreate {
+        pub source: Source,
+        pub color_format: i32,
+        pub bandwidth: i32,
+        pub allow_video_fields: bool,
+        pub name: *const c_char,
+    }
+
+    #[cfg(feature = "ndi")]
+    #[repr(C)]
+    pub struct SendCreate {
+        pub name: *const c_char,
+        pub groups: *const c_char,
+        pub clock_video: bool,
+        pub clock_audio: bool,
+    }
+
+    #[repr(C)]
+    pub struct VideoFrame {
+        pub xres: i32,
+        pub yres: i32,
+        pub fourcc: u32,
+        pub frame_rate_n: i32,
+        pub frame_rate_d: i32,
+        pub picture_aspect_ratio: f32,
+        pub frame_format_type: i32,
+        pub timecode: i64,
+        pub data: *mut u8,
+        pub line_stride_in_bytes: i32,
+        pub metadata: *const c_char,
+        pub timestamp: i64,
+    }
+
+    impl Default for VideoFrame {
+        fn default() -> Self {
+            Self {
+                xres: 0,
+                yres: 0,
+                fourcc: 0,
+                frame_rate_n: 0,
+                frame_rate_d: 0,
+                picture_aspect_ratio: 0.0,
+                frame_format_type: 0,
+                timecode: 0,
+                data: std::ptr::null_mut(),
+                line_stride_in_bytes: 0,
+                metadata: std::ptr::null(),
+                timestamp: 0,
+            }
+        }
+    }
+}
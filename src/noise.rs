@@ -0,0 +1,90 @@
+//! Gaussian noise added over inputs, to test encoder settings and the robustness of the
+//! analyzers on noisy sources.
+
+use anyhow::Result;
+use compositor_pipeline::Pipeline;
+use compositor_render::scene::*;
+use compositor_render::shader::ShaderSpec;
+use compositor_render::{RendererId, RendererSpec};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use crate::config::NoiseConfig;
+
+/// Renderer ID of the noise shader.
+const SHADER_ID: &str = "noise";
+
+/// Draws the seed of the noise when it is not configured, and logs it so the run can be
+/// reproduced.
+pub fn resolve_seed(noise: &mut NoiseConfig) {
+    if !noise.enabled {
+        return;
+    }
+    let seed = *noise.seed.get_or_insert_with(|| {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .subsec_nanos();
+        // Spread the nanoseconds over all the bits
+        nanos.wrapping_mul(0x9E37_79B9)
+    });
+    info!("Noise seed {seed}, set `noise.seed = {seed}` to reproduce this run");
+}
+
+/// Registers the noise shader when the noise is enabled.
+///
+/// Must be called before any noisy component is rendered.
+pub fn register(pipeline: &Arc<Mutex<Pipeline>>, noise: &NoiseConfig) -> Result<()> {
+    if !noise.enabled {
+        return Ok(());
+    }
+
+    Pipeline::register_renderer(
+        pipeline,
+        RendererId(Arc::from(SHADER_ID)),
+        RendererSpec::Shader(ShaderSpec {
+            source: include_str!("noise.wgsl").into(),
+        }),
+    )?;
+    info!("Registered noise shader, sigma {:?}", noise.sigma);
+
+    Ok(())
+}
+
+/// Whether the noise is added over the input `id`.
+pub fn applies(noise: &NoiseConfig, id: &str) -> bool {
+    noise.enabled && (noise.inputs.is_empty() || noise.inputs.iter().any(|input| input == id))
+}
+
+/// Adds the noise over the component of an input, rendering at `size`.
+///
+/// The shader renders at a fixed size, which must be that of `content` to keep its
+/// geometry. The seed must have been resolved, see [`resolve_seed`].
+pub fn wrap(noise: &NoiseConfig, content: Component, size: Size) -> Component {
+    let seed = noise.seed.unwrap_or_default();
+    let [r, g, b] = noise.sigma.map(|sigma| sigma / 255.0);
+    // Shader parameters are floats, exact up to 24 bits, so the seed is split in halves
+    let param = ShaderParam::Struct(vec![
+        field("sigma", [r, g, b, noise.temporal as u32 as f32]),
+        field(
+            "seed",
+            [(seed & 0xFFFF) as f32, (seed >> 16) as f32, 0.0, 0.0],
+        ),
+    ]);
+
+    Component::Shader(ShaderComponent {
+        id: None,
+        children: vec![content],
+        shader_id: RendererId(Arc::from(SHADER_ID)),
+        shader_param: Some(param),
+        size,
+    })
+}
+
+fn field(name: &str, value: [f32; 4]) -> ShaderParamStructField {
+    ShaderParamStructField {
+        field_name: name.to_string(),
+        value: ShaderParam::List(value.into_iter().map(ShaderParam::F32).collect()),
+    }
+}
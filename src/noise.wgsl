@@ -0,0 +1,83 @@
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+}
+
+struct BaseShaderParameters {
+    plane_id: i32,
+    time: f32,
+    output_resolution: vec2<u32>,
+    texture_count: u32,
+}
+
+struct NoiseParams {
+    // Standard deviation of each channel, in [0, 1], and whether the noise changes every
+    // frame in `w`
+    sigma: vec4<f32>,
+    // Low and high 16 bits of the seed
+    seed: vec4<f32>,
+}
+
+@group(0) @binding(0) var textures: binding_array<texture_2d<f32>, 16>;
+@group(1) @binding(0) var<uniform> params: NoiseParams;
+@group(2) @binding(0) var sampler_: sampler;
+
+var<push_constant> base_params: BaseShaderParameters;
+
+const PI: f32 = 3.14159265;
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    output.position = vec4<f32>(input.position, 1.0);
+    output.tex_coords = input.tex_coords;
+    return output;
+}
+
+// PCG hash, uniform over the 32 bits
+fn pcg(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// Uniform in (0, 1]
+fn uniform(value: u32) -> f32 {
+    return (f32(pcg(value) >> 8u) + 1.0) / 16777216.0;
+}
+
+// Standard normal sample of a pixel, channel and frame, by the Box-Muller transform
+fn gaussian(pixel: vec2<u32>, channel: u32, frame: u32) -> f32 {
+    let seed = u32(params.seed.x) | (u32(params.seed.y) << 16u);
+    let key = pcg(pcg(pcg(pcg(seed ^ pixel.x) ^ pixel.y) ^ frame) ^ channel);
+    let u1 = uniform(key);
+    let u2 = uniform(key ^ 0x5bd1e995u);
+    return sqrt(-2.0 * log(u1)) * cos(2.0 * PI * u2);
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    if (base_params.texture_count == 0u) {
+        return vec4<f32>(0.0);
+    }
+
+    let color = textureSample(textures[0], sampler_, input.tex_coords);
+    let pixel = vec2<u32>(input.position.xy);
+    // Frames are told apart by their PTS, so the noise is the same from run to run
+    var frame = 0u;
+    if (params.sigma.w > 0.5) {
+        frame = u32(base_params.time * 1000.0);
+    }
+    let noise = vec3<f32>(
+        gaussian(pixel, 0u, frame),
+        gaussian(pixel, 1u, frame),
+        gaussian(pixel, 2u, frame),
+    ) * params.sigma.xyz;
+
+    return vec4<f32>(clamp(color.rgb + noise, vec3<f32>(0.0), vec3<f32>(1.0)), color.a);
+}
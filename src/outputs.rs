@@ -0,0 +1,138 @@
+//! Outputs registered by name, next to the recording and the raw output, so several run at
+//! once: a preview, recordings and streams, each cycling through its own inputs at its own
+//! resolution.
+
+use anyhow::{bail, Context, Result};
+use compositor_pipeline::pipeline::output::RawDataReceiver;
+use compositor_pipeline::pipeline::OutputId;
+use compositor_pipeline::queue::PipelineEvent;
+use compositor_render::scene::{Component, RescaleMode};
+use compositor_render::{Frame, Resolution};
+use std::path::PathBuf;
+
+/// Where the frames of a registered output go.
+pub enum Sink {
+    /// Read back by the harness, which keeps the latest frame, saved to `path` when the
+    /// output is unregistered.
+    Preview {
+        receiver: RawDataReceiver,
+        path: Option<PathBuf>,
+        latest: Option<Frame>,
+        frames: usize,
+    },
+    /// Encoded by the pipeline, to a file or a stream.
+    Encoded,
+}
+
+pub struct RegisteredOutput {
+    pub id: OutputId,
+    pub resolution: Resolution,
+    /// How scenes are scaled to the resolution of the output.
+    pub scaling: RescaleMode,
+    /// Inputs the output cycles through, by input ID.
+    pub components: Vec<(String, Component)>,
+    /// Whether the output switches input along with the others.
+    pub alternate: bool,
+    pub sink: Sink,
+}
+
+impl RegisteredOutput {
+    /// Component shown after `switches` switches.
+    pub fn component(&self, switches: usize) -> Option<&(String, Component)> {
+        match self.components.len() {
+            0 => None,
+            len => self.components.get(switches % len),
+        }
+    }
+
+    /// Keeps the latest frame a preview received, without waiting for one.
+    pub fn drain(&mut self) {
+        let Sink::Preview {
+            receiver,
+            latest,
+            frames,
+            ..
+        } = &mut self.sink
+        else {
+            return;
+        };
+        let Some(video) = receiver.video.as_ref() else {
+            return;
+        };
+        for event in video.try_iter() {
+            if let PipelineEvent::Data(frame) = event {
+                *latest = Some(frame);
+                *frames += 1;
+            }
+        }
+    }
+}
+
+/// Outputs registered by name, in registration order.
+#[derive(Default)]
+pub struct OutputRegistry {
+    outputs: Vec<(String, RegisteredOutput)>,
+}
+
+impl OutputRegistry {
+    pub fn insert(&mut self, name: &str, output: RegisteredOutput) -> Result<()> {
+        if self.outputs.iter().any(|(existing, _)| existing == name) {
+            bail!("Output {name:?} already registered");
+        }
+        self.outputs.push((name.to_string(), output));
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Result<&RegisteredOutput> {
+        self.outputs
+            .iter()
+            .find(|(existing, _)| existing == name)
+            .map(|(_, output)| output)
+            .with_context(|| format!("Unknown output {name:?}"))
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<RegisteredOutput> {
+        let index = self
+            .outputs
+            .iter()
+            .position(|(existing, _)| existing == name)
+            .with_context(|| format!("Unknown output {name:?}"))?;
+        Ok(self.outputs.remove(index).1)
+    }
+
+    /// Name of the output registered as `id`.
+    pub fn name_of(&self, id: &OutputId) -> Option<&str> {
+        self.outputs
+            .iter()
+            .find(|(_, output)| output.id == *id)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Names of the outputs, in registration order.
+    pub fn names(&self) -> Vec<String> {
+        self.outputs.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// Names of the outputs that switch input along with the others.
+    pub fn alternating(&self) -> Vec<String> {
+        self.outputs
+            .iter()
+            .filter(|(_, output)| output.alternate)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Leaves an input out of the inputs every output cycles through.
+    pub fn remove_input(&mut self, id: &str) {
+        for (_, output) in &mut self.outputs {
+            output.components.retain(|(existing, _)| existing != id);
+        }
+    }
+
+    /// Keeps the latest frame of every preview.
+    pub fn drain(&mut self) {
+        for (_, output) in &mut self.outputs {
+            output.drain();
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// Collects frame arrival times to quantify pacing against the output framerate.
+pub struct FramePacing {
+    target: Duration,
+    last: Option<Instant>,
+    intervals: Vec<Duration>,
+}
+
+/// Distribution of the intervals between consecutive frames.
+#[derive(Debug, Clone)]
+pub struct PacingStats {
+    pub target: Duration,
+    pub frames: usize,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+    /// Standard deviation of the intervals.
+    pub jitter: Duration,
+    /// Intervals longer than 1.5 times the target, i.e. at least one missed frame slot.
+    pub late: usize,
+}
+
+impl FramePacing {
+    pub fn new(target: Duration) -> Self {
+        Self {
+            target,
+            last: None,
+            intervals: vec![],
+        }
+    }
+
+    pub fn record(&mut self, arrival: Instant) {
+        if let Some(last) = self.last {
+            self.intervals.push(arrival.saturating_duration_since(last));
+        }
+        self.last = Some(arrival);
+    }
+
+    pub fn stats(&self) -> Option<PacingStats> {
+        if self.intervals.is_empty() {
+            return None;
+        }
+
+        let mut sorted = self.intervals.clone();
+        sorted.sort_unstable();
+        let percentile = |p: f64| sorted[((sorted.len() - 1) as f64 * p).round() as usize];
+
+        let secs: Vec<f64> = sorted.iter().map(Duration::as_secs_f64).collect();
+        let mean = secs.iter().sum::<f64>() / secs.len() as f64;
+        let variance = secs.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / secs.len() as f64;
+        let late_threshold = self.target.mul_f64(1.5);
+
+        Some(PacingStats {
+            target: self.target,
+            frames: self.intervals.len() + 1,
+            mean: Duration::from_secs_f64(mean),
+            p50: percentile(0.5),
+            p95: percentile(0.95),
+            p99: percentile(0.99),
+            max: *sorted.last().unwrap(),
+            jitter: Duration::from_secs_f64(variance.sqrt()),
+            late: sorted.iter().filter(|i| **i > late_threshold).count(),
+        })
+    }
+}
+
+impl PacingStats {
+    pub fn log(&self) {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        info!(
+            "Frame pacing over {} frames (target {:.2}ms): mean {:.2}ms, p50 {:.2}ms, p95 {:.2}ms, p99 {:.2}ms, max {:.2}ms, jitter {:.2}ms, {} late",
+            self.frames,
+            ms(self.target),
+            ms(self.mean),
+            ms(self.p50),
+            ms(self.p95),
+            ms(self.p99),
+            ms(self.max),
+            ms(self.jitter),
+            self.late,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: Duration = Duration::from_millis(20);
+
+    fn stats(intervals: &[Duration]) -> Option<PacingStats> {
+        let mut pacing = FramePacing::new(TARGET);
+        let mut arrival = Instant::now();
+        pacing.record(arrival);
+        for interval in intervals {
+            arrival += *interval;
+            pacing.record(arrival);
+        }
+        pacing.stats()
+    }
+
+    fn assert_close(actual: Duration, expected: Duration) {
+        let difference = actual.max(expected) - actual.min(expected);
+        assert!(
+            difference < Duration::from_micros(1),
+            "{actual:?} instead of {expected:?}"
+        );
+    }
+
+    #[test]
+    fn no_intervals() {
+        assert!(FramePacing::new(TARGET).stats().is_none());
+        assert!(stats(&[]).is_none());
+    }
+
+    #[test]
+    fn single_interval() {
+        let stats = stats(&[Duration::from_millis(25)]).unwrap();
+        assert_eq!(stats.frames, 2);
+        assert_close(stats.mean, Duration::from_millis(25));
+        assert_eq!(stats.p50, Duration::from_millis(25));
+        assert_eq!(stats.p95, Duration::from_millis(25));
+        assert_eq!(stats.p99, Duration::from_millis(25));
+        assert_eq!(stats.max, Duration::from_millis(25));
+        assert_eq!(stats.jitter, Duration::ZERO);
+        assert_eq!(stats.late, 0);
+    }
+
+    #[test]
+    fn distribution() {
+        let intervals = [40, 20, 30, 20, 20].map(Duration::from_millis);
+        let stats = stats(&intervals).unwrap();
+        assert_eq!(stats.target, TARGET);
+        assert_eq!(stats.frames, 6);
+        assert_close(stats.mean, Duration::from_millis(26));
+        assert_eq!(stats.p50, Duration::from_millis(20));
+        assert_eq!(stats.p95, Duration::from_millis(40));
+        assert_eq!(stats.max, Duration::from_millis(40));
+        assert_close(stats.jitter, Duration::from_millis(8));
+        // 30ms is 1.5 times the target, still within the frame slot
+        assert_eq!(stats.late, 1);
+    }
+
+    #[test]
+    fn late_boundary() {
+        let boundary = TARGET.mul_f64(1.5);
+        let stats = stats(&[boundary, boundary + Duration::from_nanos(1)]).unwrap();
+        assert_eq!(stats.late, 1);
+    }
+}
@@ -0,0 +1,68 @@
+use anyhow::Result;
+use compositor_pipeline::Pipeline;
+use compositor_render::scene::*;
+use compositor_render::shader::ShaderSpec;
+use compositor_render::{RendererId, RendererSpec, Resolution};
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+use crate::config::{InputConfig, PatternConfig};
+
+/// Renderer ID of the shader shared by every pattern input.
+const SHADER_ID: &str = "test_pattern";
+
+/// Registers the pattern shader if an input uses it.
+///
+/// Must be called before any pattern is rendered.
+pub fn register(pipeline: &Arc<Mutex<Pipeline>>, inputs: &[InputConfig]) -> Result<()> {
+    if !inputs
+        .iter()
+        .any(|input| matches!(input, InputConfig::Pattern { .. }))
+    {
+        return Ok(());
+    }
+
+    Pipeline::register_renderer(
+        pipeline,
+        RendererId(Arc::from(SHADER_ID)),
+        RendererSpec::Shader(ShaderSpec {
+            source: include_str!("patterns.wgsl").into(),
+        }),
+    )?;
+    info!("Registered test pattern shader");
+
+    Ok(())
+}
+
+/// Component rendering a pattern over the whole output, synthesized on the GPU.
+///
+/// `square_size` is the size in pixels of the checkerboard squares.
+pub fn component(pattern: PatternConfig, square_size: u32, resolution: Resolution) -> Component {
+    let kind = match pattern {
+        PatternConfig::SmpteBars => 0,
+        PatternConfig::Ramp => 1,
+        PatternConfig::ZonePlate => 2,
+        PatternConfig::Checkerboard => 3,
+        PatternConfig::SlantedEdge => 4,
+    };
+    let param = ShaderParam::Struct(vec![ShaderParamStructField {
+        field_name: "pattern".to_string(),
+        value: ShaderParam::List(
+            [kind as f32, square_size as f32, 0.0, 0.0]
+                .into_iter()
+                .map(ShaderParam::F32)
+                .collect(),
+        ),
+    }]);
+
+    Component::Shader(ShaderComponent {
+        id: None,
+        children: vec![],
+        shader_id: RendererId(Arc::from(SHADER_ID)),
+        shader_param: Some(param),
+        size: Size {
+            width: resolution.width as f32,
+            height: resolution.height as f32,
+        },
+    })
+}
@@ -0,0 +1,132 @@
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+}
+
+struct BaseShaderParameters {
+    plane_id: i32,
+    time: f32,
+    output_resolution: vec2<u32>,
+    texture_count: u32,
+}
+
+struct PatternParams {
+    // x: pattern (0 bars, 1 ramp, 2 zone plate, 3 checkerboard, 4 slanted edge),
+    // y: size in pixels
+    pattern: vec4<f32>,
+}
+
+@group(0) @binding(0) var textures: binding_array<texture_2d<f32>, 16>;
+@group(1) @binding(0) var<uniform> params: PatternParams;
+@group(2) @binding(0) var sampler_: sampler;
+
+var<push_constant> base_params: BaseShaderParameters;
+
+const PI: f32 = 3.14159265;
+// Angle of the slanted edge from vertical, 5 degrees
+const SLANT: f32 = 0.0872665;
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    output.position = vec4<f32>(input.position, 1.0);
+    output.tex_coords = input.tex_coords;
+    return output;
+}
+
+// SMPTE EG 1 color bars: 75% bars, the reverse blue bars, then -I, white, +Q and PLUGE
+fn smpte_bars(uv: vec2<f32>) -> vec3<f32> {
+    let column = u32(clamp(uv.x * 7.0, 0.0, 6.0));
+    if (uv.y < 0.67) {
+        let bars = array<vec3<f32>, 7>(
+            vec3<f32>(0.75, 0.75, 0.75),
+            vec3<f32>(0.75, 0.75, 0.0),
+            vec3<f32>(0.0, 0.75, 0.75),
+            vec3<f32>(0.0, 0.75, 0.0),
+            vec3<f32>(0.75, 0.0, 0.75),
+            vec3<f32>(0.75, 0.0, 0.0),
+            vec3<f32>(0.0, 0.0, 0.75),
+        );
+        return bars[column];
+    }
+    if (uv.y < 0.75) {
+        let bars = array<vec3<f32>, 7>(
+            vec3<f32>(0.0, 0.0, 0.75),
+            vec3<f32>(0.075, 0.075, 0.075),
+            vec3<f32>(0.75, 0.0, 0.75),
+            vec3<f32>(0.075, 0.075, 0.075),
+            vec3<f32>(0.0, 0.75, 0.75),
+            vec3<f32>(0.075, 0.075, 0.075),
+            vec3<f32>(0.75, 0.75, 0.75),
+        );
+        return bars[column];
+    }
+
+    // Bottom row: -I, white, +Q over the first 5 bar widths, then PLUGE
+    let x = uv.x * 7.0;
+    if (x < 1.25) {
+        return vec3<f32>(0.0, 0.129, 0.298);
+    }
+    if (x < 2.5) {
+        return vec3<f32>(1.0, 1.0, 1.0);
+    }
+    if (x < 3.75) {
+        return vec3<f32>(0.196, 0.0, 0.416);
+    }
+    if (x < 5.0) {
+        return vec3<f32>(0.075, 0.075, 0.075);
+    }
+    if (x < 5.33) {
+        return vec3<f32>(0.035, 0.035, 0.035);
+    }
+    if (x < 5.67) {
+        return vec3<f32>(0.075, 0.075, 0.075);
+    }
+    if (x < 6.0) {
+        return vec3<f32>(0.114, 0.114, 0.114);
+    }
+    return vec3<f32>(0.075, 0.075, 0.075);
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = input.tex_coords;
+    let resolution = vec2<f32>(base_params.output_resolution);
+    let pixel = uv * resolution;
+    let size = max(params.pattern.y, 1.0);
+
+    switch (u32(params.pattern.x)) {
+        case 0u: {
+            return vec4<f32>(smpte_bars(uv), 1.0);
+        }
+        // Full-range gray ramp, black on the left
+        case 1u: {
+            return vec4<f32>(vec3<f32>(uv.x), 1.0);
+        }
+        // Circular zone plate, the frequency reaching Nyquist at the edges
+        case 2u: {
+            let centered = pixel - resolution / 2.0;
+            let radius = length(resolution) / 2.0;
+            let phase = PI * dot(centered, centered) / (2.0 * radius);
+            return vec4<f32>(vec3<f32>(0.5 + 0.5 * cos(phase)), 1.0);
+        }
+        // Dark to light edge through the center, slanted from vertical, antialiased by the
+        // share of each pixel on the light side
+        case 4u: {
+            let centered = pixel - resolution / 2.0;
+            let distance = (centered.x - tan(SLANT) * centered.y) * cos(SLANT);
+            let coverage = clamp(distance + 0.5, 0.0, 1.0);
+            return vec4<f32>(vec3<f32>(mix(0.2, 0.8, coverage)), 1.0);
+        }
+        default: {
+            let square = vec2<u32>(pixel / size);
+            let value = f32((square.x + square.y) % 2u);
+            return vec4<f32>(vec3<f32>(value), 1.0);
+        }
+    }
+}
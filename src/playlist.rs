@@ -0,0 +1,254 @@
+use anyhow::{anyhow, Context, Result};
+use compositor_pipeline::pipeline::GraphicsContext;
+use compositor_pipeline::queue::QueueInputOptions;
+use compositor_pipeline::Pipeline;
+use image::RgbaImage;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, info};
+
+use crate::config::{FramerateConfig, PlaylistItemConfig};
+use crate::{media, raw_input};
+
+/// How long images without a duration are shown.
+pub const DEFAULT_IMAGE_DURATION: Duration = Duration::from_secs(5);
+
+/// Registers a raw data input playing `items` one after the other, at `framerate`.
+///
+/// Images are shown for their duration, MP4 files until their end or their duration.
+/// MP4 files are decoded by an `ffmpeg` process to RGBA, with the color matrix and range
+/// they are tagged with, BT.601 limited range when untagged. The next item is opened while
+/// the current one plays, so switching does not stall the input.
+pub fn register(
+    pipeline: &Arc<Mutex<Pipeline>>,
+    graphics_context: &GraphicsContext,
+    id: &str,
+    items: &[PlaylistItemConfig],
+    framerate: FramerateConfig,
+    should_loop: bool,
+    queue_options: QueueInputOptions,
+) -> Result<()> {
+    // Fail on the first item now, rather than on the input thread
+    let first = Item::open(&items[0], framerate)?;
+    let video = raw_input::register(pipeline, id, queue_options)?;
+    info!("Registered playlist of {} items as {id}", items.len());
+
+    let mut player = Player {
+        id: id.to_string(),
+        items: items.to_vec(),
+        framerate,
+        should_loop,
+        position: 0,
+        current: Some(first),
+        next: None,
+        index: 0,
+        empty_items: 0,
+        item_frames: 0,
+    };
+    let graphics_context = graphics_context.clone();
+    let input_id = id.to_string();
+    std::thread::Builder::new()
+        .name(format!("playlist {id}"))
+        .spawn(move || {
+            player.log_item();
+            raw_input::play(video, &graphics_context, &input_id, player);
+        })?;
+
+    Ok(())
+}
+
+/// Frames of the whole playlist, with continuous PTS.
+struct Player {
+    id: String,
+    items: Vec<PlaylistItemConfig>,
+    framerate: FramerateConfig,
+    should_loop: bool,
+    /// Item currently played.
+    position: usize,
+    current: Option<Item>,
+    /// Item after the current one, opened ahead of time.
+    next: Option<Result<Item>>,
+    /// Frames played so far.
+    index: usize,
+    /// Items in a row that ended without a frame, a looping playlist of only those would
+    /// never yield one.
+    empty_items: usize,
+    /// Frames played of the current item.
+    item_frames: usize,
+}
+
+impl Player {
+    fn next_position(&self) -> Option<usize> {
+        match self.position + 1 {
+            next if next < self.items.len() => Some(next),
+            _ if self.should_loop => Some(0),
+            _ => None,
+        }
+    }
+
+    fn log_item(&self) {
+        info!(
+            "Playing {} ({}/{}) on {}",
+            self.items[self.position].path.display(),
+            self.position + 1,
+            self.items.len(),
+            self.id
+        );
+    }
+}
+
+impl Iterator for Player {
+    type Item = Result<(Arc<RgbaImage>, Duration)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.next.is_none() {
+                self.next = self
+                    .next_position()
+                    .map(|next| Item::open(&self.items[next], self.framerate));
+            }
+
+            if let Some(current) = &mut self.current {
+                match current.next_frame() {
+                    Ok(Some(image)) => {
+                        let pts = self.framerate.interval() * self.index as u32;
+                        self.index += 1;
+                        self.item_frames += 1;
+                        return Some(Ok((image, pts)));
+                    }
+                    Ok(None) => {}
+                    Err(err) => return Some(Err(err)),
+                }
+            }
+
+            // The current item is over, switch to the next one
+            match self.item_frames {
+                0 => self.empty_items += 1,
+                _ => self.empty_items = 0,
+            }
+            self.item_frames = 0;
+            if self.empty_items >= self.items.len() {
+                return Some(Err(anyhow!("No item of playlist {} has frames", self.id)));
+            }
+            self.position = self.next_position()?;
+            match self.next.take()? {
+                Ok(item) => self.current = Some(item),
+                Err(err) => return Some(Err(err)),
+            }
+            self.log_item();
+        }
+    }
+}
+
+/// An item being played.
+struct Item {
+    source: Source,
+    /// Frames left to play, until the end of the source when unset.
+    frames_left: Option<usize>,
+}
+
+enum Source {
+    Image(Arc<RgbaImage>),
+    Video {
+        ffmpeg: Child,
+        stream: ChildStdout,
+        width: u32,
+        height: u32,
+    },
+}
+
+impl Item {
+    fn open(item: &PlaylistItemConfig, framerate: FramerateConfig) -> Result<Self> {
+        let path = &item.path;
+        let (source, default_duration) = if is_mp4(path) {
+            (open_video(path, framerate)?, None)
+        } else {
+            let image = image::open(path)
+                .with_context(|| format!("Cannot decode {}", path.display()))?
+                .to_rgba8();
+            (Source::Image(Arc::new(image)), Some(DEFAULT_IMAGE_DURATION))
+        };
+        let duration = item
+            .duration_ms
+            .map(Duration::from_millis)
+            .or(default_duration);
+
+        Ok(Self {
+            source,
+            frames_left: duration.map(|duration| {
+                (duration.as_secs_f64() / framerate.interval().as_secs_f64()).round() as usize
+            }),
+        })
+    }
+
+    /// `None` once the item is over.
+    fn next_frame(&mut self) -> Result<Option<Arc<RgbaImage>>> {
+        if let Some(frames_left) = &mut self.frames_left {
+            if *frames_left == 0 {
+                return Ok(None);
+            }
+            *frames_left -= 1;
+        }
+
+        match &mut self.source {
+            Source::Image(image) => Ok(Some(image.clone())),
+            Source::Video {
+                stream,
+                width,
+                height,
+                ..
+            } => {
+                let mut buffer = vec![0; *width as usize * *height as usize * 4];
+                if let Err(err) = stream.read_exact(&mut buffer) {
+                    debug!("Playlist video ended: {err}");
+                    return Ok(None);
+                }
+                let image = RgbaImage::from_raw(*width, *height, buffer)
+                    .expect("Buffer has the size of a frame");
+                Ok(Some(Arc::new(image)))
+            }
+        }
+    }
+}
+
+impl Drop for Item {
+    fn drop(&mut self) {
+        if let Source::Video { ffmpeg, .. } = &mut self.source {
+            let _ = ffmpeg.kill();
+            let _ = ffmpeg.wait();
+        }
+    }
+}
+
+/// Starts decoding an MP4 file at its own resolution, resampled to `framerate`.
+fn open_video(path: &Path, framerate: FramerateConfig) -> Result<Source> {
+    let (width, height) = media::mp4_resolution(path)?;
+    let mut ffmpeg = Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-i"])
+        .arg(path)
+        .args(["-an", "-vf"])
+        .arg(format!("scale={width}:{height}"))
+        .arg("-r")
+        .arg(format!("{}/{}", framerate.num, framerate.den))
+        .args(["-pix_fmt", "rgba", "-f", "rawvideo", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Cannot start ffmpeg, which decodes playlist videos")?;
+    let stream = ffmpeg.stdout.take().context("No ffmpeg output")?;
+
+    Ok(Source::Video {
+        ffmpeg,
+        stream,
+        width,
+        height,
+    })
+}
+
+pub fn is_mp4(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("mp4"))
+}
@@ -0,0 +1,91 @@
+use anyhow::{Context, Result};
+use compositor_pipeline::pipeline::input::RawDataInputOptions;
+use compositor_pipeline::pipeline::GraphicsContext;
+use compositor_pipeline::queue::{PipelineEvent, QueueInputOptions};
+use compositor_pipeline::Pipeline;
+use compositor_render::{Frame, FrameData, InputId, Resolution};
+use crossbeam_channel::Sender;
+use image::RgbaImage;
+use std::borrow::Borrow;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::wgpu::from_image;
+
+/// Sends the video frames of a raw data input.
+///
+/// Sending fails once the input is unregistered, which producers use to stop.
+pub type RawFrameSender = Sender<PipelineEvent<Frame>>;
+
+/// Registers a video-only raw data input, returns the sender its frames are pushed to.
+pub fn register(
+    pipeline: &Arc<Mutex<Pipeline>>,
+    id: &str,
+    queue_options: QueueInputOptions,
+) -> Result<RawFrameSender> {
+    let sender = Pipeline::register_raw_data_input(
+        pipeline,
+        InputId(Arc::from(id)),
+        RawDataInputOptions {
+            video: true,
+            audio: false,
+        },
+        queue_options,
+    )?;
+
+    sender.video.context("No video channel")
+}
+
+/// Uploads a CPU image as a raw input frame.
+pub fn frame(graphics_context: &GraphicsContext, image: &RgbaImage, pts: Duration) -> Frame {
+    Frame {
+        data: FrameData::Rgba8UnormWgpuTexture(Arc::new(from_image(graphics_context, image))),
+        resolution: Resolution {
+            width: image.width() as usize,
+            height: image.height() as usize,
+        },
+        pts,
+    }
+}
+
+/// Sends each frame to a raw input once its PTS is due, then the end of the stream.
+///
+/// Meant to run on a thread of its own, it stops early when a frame cannot be produced
+/// or the input is unregistered.
+pub fn play<I: Borrow<RgbaImage>>(
+    video: RawFrameSender,
+    graphics_context: &GraphicsContext,
+    id: &str,
+    frames: impl Iterator<Item = Result<(I, Duration)>>,
+) {
+    let start = Instant::now();
+    let mut late_frames = 0;
+    for next in frames {
+        let (image, pts) = match next {
+            Ok(next) => next,
+            Err(err) => {
+                warn!("Stopping input {id}: {err:#}");
+                return;
+            }
+        };
+
+        // Frames are sent when due, the queue buffers them from there
+        match pts.checked_sub(start.elapsed()) {
+            Some(wait) => std::thread::sleep(wait),
+            None => {
+                late_frames += 1;
+                if late_frames == 10 {
+                    warn!("Input {id} produces frames slower than its framerate");
+                }
+            }
+        }
+        let frame = frame(graphics_context, image.borrow(), pts);
+        if video.send(PipelineEvent::Data(frame)).is_err() {
+            debug!("Input {id} unregistered, stopping");
+            return;
+        }
+    }
+
+    let _ = video.send(PipelineEvent::EOS);
+}
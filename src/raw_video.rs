@@ -0,0 +1,170 @@
+//! Frames of the raw output piped to `ffmpeg`, which encodes the recordings and streams the
+//! pipeline has no encoder for.
+
+use anyhow::{bail, Context, Result};
+use compositor_render::Resolution;
+use crossbeam_channel::{Sender, TrySendError};
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::thread::JoinHandle;
+use tracing::warn;
+
+use crate::config::FramerateConfig;
+
+/// Frames waiting for `ffmpeg`, those pushed while it is this far behind are dropped.
+const BUFFERED_FRAMES: usize = 8;
+
+/// Output arguments converting frames like the recordings of the pipeline.
+///
+/// `ffmpeg` converts the RGB read back to YUV with the BT.709 matrix, in limited range, and
+/// tags the stream with the BT.709 primaries and transfer, so that players convert it back
+/// the same way. `pixel_format` is the YUV layout, e.g. `yuv420p`.
+pub fn bt709_limited(pixel_format: &str) -> [&str; 12] {
+    [
+        "-vf",
+        "scale=out_color_matrix=bt709:out_range=tv",
+        "-pix_fmt",
+        pixel_format,
+        "-colorspace",
+        "bt709",
+        "-color_primaries",
+        "bt709",
+        "-color_trc",
+        "bt709",
+        "-color_range",
+        "tv",
+    ]
+}
+
+/// `ffmpeg` reading frames of `pixel_format`, `rgba` or `rgba64le`, from its standard input,
+/// to which the output arguments are added. Existing files are only replaced when
+/// `overwrite` is set.
+pub fn command(
+    pixel_format: &str,
+    resolution: Resolution,
+    framerate: FramerateConfig,
+    overwrite: bool,
+) -> Command {
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-hide_banner", "-loglevel", "error"])
+        .arg(if overwrite { "-y" } else { "-n" })
+        .args(["-f", "rawvideo", "-pix_fmt", pixel_format, "-s"])
+        .arg(format!("{}x{}", resolution.width, resolution.height))
+        .arg("-framerate")
+        .arg(format!("{}/{}", framerate.num, framerate.den))
+        .args(["-i", "-"]);
+    command
+}
+
+/// Frames written to a running `ffmpeg`.
+///
+/// Frames are written from a thread of their own, so that a slow encode does not hold up the
+/// frames read back from the pipeline: up to [`BUFFERED_FRAMES`] wait for `ffmpeg`, further
+/// ones are dropped, logged and counted. `ffmpeg` is killed when the writer is dropped
+/// without being finished, e.g. on errors.
+pub struct RawVideoWriter {
+    ffmpeg: Child,
+    sender: Option<Sender<Vec<u8>>>,
+    thread: Option<JoinHandle<std::io::Result<()>>>,
+    /// What `ffmpeg` encodes, e.g. `the WebM recording`.
+    name: &'static str,
+    frames: usize,
+    dropped: usize,
+    finished: bool,
+}
+
+impl RawVideoWriter {
+    /// Starts `command`, see [`command`].
+    pub fn start(mut command: Command, name: &'static str) -> Result<Self> {
+        let ffmpeg = command
+            .stdin(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Cannot start ffmpeg, which encodes {name}"))?;
+        let mut writer = Self {
+            ffmpeg,
+            sender: None,
+            thread: None,
+            name,
+            frames: 0,
+            dropped: 0,
+            finished: false,
+        };
+
+        let mut input = writer.ffmpeg.stdin.take().context("No ffmpeg input")?;
+        let (sender, receiver) = crossbeam_channel::bounded::<Vec<u8>>(BUFFERED_FRAMES);
+        let thread = std::thread::Builder::new()
+            .name("ffmpeg writer".to_string())
+            .spawn(move || -> std::io::Result<()> {
+                for frame in receiver {
+                    input.write_all(&frame)?;
+                }
+                Ok(())
+            })
+            .context("Cannot spawn ffmpeg writer")?;
+        writer.sender = Some(sender);
+        writer.thread = Some(thread);
+        Ok(writer)
+    }
+
+    /// Queues the bytes of a frame, returns whether it was queued: `false` when it was
+    /// dropped, `ffmpeg` falling behind.
+    pub fn push(&mut self, frame: Vec<u8>) -> Result<bool> {
+        let Some(sender) = &self.sender else {
+            bail!("ffmpeg writer of {} is finished", self.name);
+        };
+        match sender.try_send(frame) {
+            Ok(()) => {
+                self.frames += 1;
+                Ok(true)
+            }
+            Err(TrySendError::Full(_)) => {
+                if self.dropped == 0 {
+                    warn!("ffmpeg falls behind on {}, dropping frames", self.name);
+                }
+                self.dropped += 1;
+                Ok(false)
+            }
+            Err(TrySendError::Disconnected(_)) => bail!("ffmpeg stopped encoding {}", self.name),
+        }
+    }
+
+    /// Waits for the frames queued to be encoded and `ffmpeg` to exit, returns the number of
+    /// frames encoded.
+    pub fn finish(mut self) -> Result<usize> {
+        drop(self.sender.take());
+        let written = self.thread.take().map_or(Ok(()), |thread| {
+            thread.join().expect("ffmpeg writer panicked")
+        });
+        let status = self.ffmpeg.wait()?;
+        self.finished = true;
+        if !status.success() {
+            bail!("ffmpeg failed to encode {}: {status}", self.name);
+        }
+        written.with_context(|| format!("ffmpeg stopped encoding {}", self.name))?;
+        if self.dropped > 0 {
+            warn!(
+                "Dropped {} frames of {}, ffmpeg fell behind",
+                self.dropped, self.name
+            );
+        }
+        Ok(self.frames)
+    }
+}
+
+impl Drop for RawVideoWriter {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        drop(self.sender.take());
+        // Killed before joining the thread, which may be blocked writing to it
+        if let Err(err) = self.ffmpeg.kill() {
+            warn!("Cannot kill ffmpeg encoding {}: {err}", self.name);
+        }
+        let _ = self.ffmpeg.wait();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
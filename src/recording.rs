@@ -0,0 +1,167 @@
+//! Files of an MP4 recording: the segments of a paused recording, joined when it stops, or
+//! numbered files rolled over by duration or size.
+
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+use crate::media;
+
+/// Longest wait for the segments of a paused recording to be finalized.
+const SEGMENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// When a recording moves on to its next file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rollover {
+    pub duration: Option<Duration>,
+    /// Size of the file, in bytes.
+    pub size: Option<u64>,
+}
+
+/// Decides which file each part of the recording goes to.
+///
+/// Without a [`Rollover`], the recording goes to its path until paused; each resume starts a
+/// segment next to it, and the segments are joined into the path when the recording stops.
+/// With one, the recording goes to `{stem}_0001.mp4`, `{stem}_0002.mp4`, ... starting a new
+/// file after each rollover or resume, and the files are kept as they are. Numbered files and
+/// segments keep the extension of the path, e.g. `.mov` for ProRes.
+pub struct RecordingController {
+    path: PathBuf,
+    rollover: Option<Rollover>,
+    /// Files recorded to, the last one being recorded to unless paused.
+    files: Vec<PathBuf>,
+    /// When the last file started.
+    started: Instant,
+    paused: bool,
+    kept_apart: bool,
+}
+
+impl RecordingController {
+    pub fn new(path: PathBuf, rollover: Option<Rollover>) -> Self {
+        Self {
+            path,
+            rollover,
+            files: vec![],
+            started: Instant::now(),
+            paused: false,
+            kept_apart: false,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Keeps the files apart when the recording stops, as the PNG frames recorded in place
+    /// of a file cannot be joined, see [`crate::fallback`].
+    pub fn keep_files_apart(&mut self) {
+        self.kept_apart = true;
+    }
+
+    /// Path of the first file recorded to.
+    pub fn first_file(&self) -> PathBuf {
+        self.file(0)
+    }
+
+    /// Moves on to the next file, and returns its path.
+    pub fn next_file(&mut self) -> PathBuf {
+        let file = self.file(self.files.len());
+        self.files.push(file.clone());
+        self.started = Instant::now();
+        file
+    }
+
+    fn file(&self, index: usize) -> PathBuf {
+        match self.rollover {
+            Some(_) => numbered_path(&self.path, index + 1),
+            None if index == 0 => self.path.clone(),
+            None => segment_path(&self.path, index),
+        }
+    }
+
+    /// Number of files started so far.
+    pub fn files(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Whether the file being recorded to reached the rollover duration or size.
+    pub fn should_roll_over(&self) -> bool {
+        let (Some(rollover), Some(file)) = (&self.rollover, self.files.last()) else {
+            return false;
+        };
+        if self.paused {
+            return false;
+        }
+        let size = || file.metadata().map_or(0, |metadata| metadata.len());
+        rollover
+            .duration
+            .is_some_and(|duration| self.started.elapsed() >= duration)
+            || rollover.size.is_some_and(|max| size() >= max)
+    }
+
+    /// Once the output is unregistered, joins the segments of a paused recording, and
+    /// returns the files of the recording.
+    pub fn finish(self) -> Result<Vec<PathBuf>> {
+        if self.rollover.is_some() || self.kept_apart || self.files.len() < 2 {
+            return Ok(self.files);
+        }
+        join_segments(&self.path, &self.files)?;
+        Ok(vec![self.path])
+    }
+}
+
+/// Path of the `index`th file of a rolled over recording, from 1.
+fn numbered_path(path: &Path, index: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{stem}_{index:04}.{}", extension(path)))
+}
+
+/// Path of the `index`th segment of a paused recording, next to it.
+fn segment_path(path: &Path, index: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{stem}.part{index}.{}", extension(path)))
+}
+
+/// Extension of the recording, kept by its numbered files and segments.
+fn extension(path: &Path) -> std::borrow::Cow<'_, str> {
+    path.extension().unwrap_or("mp4".as_ref()).to_string_lossy()
+}
+
+/// Waits for the segments of a recording to be finalized, then joins them into its file.
+fn join_segments(path: &Path, segments: &[PathBuf]) -> Result<()> {
+    let start = Instant::now();
+    // Segments encoded by ffmpeg are written by the time they are unregistered
+    for segment in segments
+        .iter()
+        .filter(|segment| extension(segment) == "mp4")
+    {
+        // The header is written once the segment is finalized
+        while media::mp4_duration(segment).is_err() {
+            if start.elapsed() > SEGMENT_TIMEOUT {
+                bail!("Recording segment {} not finalized", segment.display());
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    // Joined next to the recording, which only the joined file replaces: segments are kept
+    // when joining fails
+    let joined = path.with_extension(format!("joining.{}", extension(path)));
+    media::concat_mp4(segments, &joined)?;
+    std::fs::rename(&joined, path).with_context(|| format!("Cannot replace {}", path.display()))?;
+    for segment in segments.iter().filter(|segment| *segment != path) {
+        std::fs::remove_file(segment)?;
+    }
+    info!("Joined {} segments into {}", segments.len(), path.display());
+
+    Ok(())
+}
@@ -0,0 +1,97 @@
+//! RTP streams of the output sent to a remote host, e.g. to another compositor's `rtp` input
+//! or to `ffplay`, for network loopback tests of the color pipeline.
+
+use anyhow::Result;
+use compositor_render::Resolution;
+use image::RgbaImage;
+use std::path::Path;
+use tracing::info;
+
+use crate::config::{EncoderConfig, FramerateConfig, RtpCodecConfig};
+use crate::raw_video::{self, RawVideoWriter};
+
+/// Settings of an RTP stream.
+#[derive(Debug, Clone)]
+pub struct RtpSenderOptions<'a> {
+    /// Host the packets are sent to.
+    pub address: &'a str,
+    pub port: u16,
+    pub payload_type: u8,
+    pub codec: RtpCodecConfig,
+    /// Where the SDP description of the stream is written, which `ffplay` needs to play it.
+    pub sdp: Option<&'a Path>,
+}
+
+/// Encodes frames and sends them over RTP with `ffmpeg`, video only.
+///
+/// Frames come from the raw output and are converted like the recordings of the pipeline,
+/// see [`raw_video::bt709_limited`], in YUV 4:2:0. H264 is encoded with the `encoder`
+/// settings, VP8 in real time.
+pub struct RtpSender {
+    writer: RawVideoWriter,
+    url: String,
+}
+
+impl RtpSender {
+    pub fn start(
+        options: &RtpSenderOptions,
+        resolution: Resolution,
+        framerate: FramerateConfig,
+        encoder: &EncoderConfig,
+    ) -> Result<Self> {
+        let url = format!("rtp://{}:{}", options.address, options.port);
+        let codec_args: Vec<String> = match options.codec {
+            RtpCodecConfig::H264 => ["-c:v", "libx264", "-preset", encoder.preset.name()]
+                .into_iter()
+                .map(String::from)
+                .chain(
+                    encoder
+                        .raw_options()
+                        .into_iter()
+                        .flat_map(|(key, value)| [format!("-{key}"), value]),
+                )
+                .collect(),
+            RtpCodecConfig::Vp8 => ["-c:v", "libvpx", "-deadline", "realtime", "-b:v", "4M"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        };
+
+        // The SDP file is rewritten for every stream
+        let mut command = raw_video::command("rgba", resolution, framerate, true);
+        command
+            .args(codec_args)
+            .args(raw_video::bt709_limited("yuv420p"))
+            .args(["-f", "rtp", "-payload_type"])
+            .arg(options.payload_type.to_string());
+        if let Some(sdp) = options.sdp {
+            command.arg("-sdp_file").arg(sdp);
+        }
+        command.arg(&url);
+        let writer = RawVideoWriter::start(command, "the RTP stream")?;
+
+        info!(
+            "Sending {:?} over RTP to {url}, payload type {}",
+            options.codec, options.payload_type
+        );
+        if let Some(sdp) = options.sdp {
+            info!(
+                "Play it with: ffplay -protocol_whitelist file,udp,rtp {}",
+                sdp.display()
+            );
+        }
+        Ok(Self { writer, url })
+    }
+
+    pub fn push(&mut self, image: RgbaImage) -> Result<()> {
+        self.writer.push(image.into_raw())?;
+        Ok(())
+    }
+
+    /// Waits for the last packets to be sent, returns the number of frames.
+    pub fn finish(self) -> Result<usize> {
+        let frames = self.writer.finish()?;
+        info!("Sent {frames} frames to {}", self.url);
+        Ok(frames)
+    }
+}
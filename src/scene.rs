@@ -0,0 +1,510 @@
+use anyhow::{bail, Context, Result};
+use compositor_render::scene::*;
+use compositor_render::{InputId, RendererId};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// JSON description of a component tree.
+///
+/// A subset of the Smelter scene API: views, rescalers, images and input streams.
+/// Positions are static unless one of `top`, `left`, `bottom` or `right` is set,
+/// colors are `#RRGGBB` or `#RRGGBBAA` strings. Unknown fields are rejected.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SceneComponent {
+    View {
+        id: Option<String>,
+        #[serde(default)]
+        children: Vec<SceneComponent>,
+        #[serde(default)]
+        direction: DirectionSpec,
+        #[serde(default)]
+        overflow: OverflowSpec,
+        #[serde(default)]
+        padding: f32,
+        background_color: Option<String>,
+        #[serde(flatten)]
+        position: PositionSpec,
+        #[serde(flatten)]
+        border: BorderSpec,
+        #[serde(flatten)]
+        unknown: UnknownFields,
+    },
+    Rescaler {
+        id: Option<String>,
+        child: Box<SceneComponent>,
+        #[serde(default)]
+        mode: RescaleModeSpec,
+        #[serde(default)]
+        horizontal_align: HorizontalAlignSpec,
+        #[serde(default)]
+        vertical_align: VerticalAlignSpec,
+        #[serde(flatten)]
+        position: PositionSpec,
+        #[serde(flatten)]
+        border: BorderSpec,
+        #[serde(flatten)]
+        unknown: UnknownFields,
+    },
+    Image {
+        id: Option<String>,
+        image_id: String,
+        width: Option<f32>,
+        height: Option<f32>,
+        #[serde(flatten)]
+        unknown: UnknownFields,
+    },
+    InputStream {
+        id: Option<String>,
+        input_id: String,
+        #[serde(flatten)]
+        unknown: UnknownFields,
+    },
+}
+
+/// Fields no other field of a component takes. `deny_unknown_fields` does not work with
+/// the flattened position and border, so they are collected and rejected instead.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UnknownFields(BTreeMap<String, serde_json::Value>);
+
+impl UnknownFields {
+    fn reject(&self) -> Result<()> {
+        match self.0.keys().next() {
+            Some(field) => bail!("Unknown field {field:?}"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PositionSpec {
+    pub width: Option<f32>,
+    pub height: Option<f32>,
+    pub top: Option<f32>,
+    pub left: Option<f32>,
+    pub bottom: Option<f32>,
+    pub right: Option<f32>,
+    pub rotation: Option<f32>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BorderSpec {
+    #[serde(default)]
+    pub border_radius: f32,
+    #[serde(default)]
+    pub border_width: f32,
+    pub border_color: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirectionSpec {
+    #[default]
+    Row,
+    Column,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowSpec {
+    #[default]
+    Hidden,
+    Visible,
+    Fit,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RescaleModeSpec {
+    #[default]
+    Fit,
+    Fill,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HorizontalAlignSpec {
+    Left,
+    Right,
+    Justified,
+    #[default]
+    Center,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerticalAlignSpec {
+    Top,
+    Bottom,
+    Justified,
+    #[default]
+    Center,
+}
+
+/// Loads a JSON scene file and converts it to a component tree.
+pub fn load(path: &Path) -> Result<Component> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Cannot read scene {}", path.display()))?;
+
+    parse(&content).with_context(|| format!("Invalid scene {}", path.display()))
+}
+
+/// Converts a JSON scene to a component tree.
+pub fn parse(json: &str) -> Result<Component> {
+    let scene: SceneComponent = serde_json::from_str(json)?;
+    scene.try_into()
+}
+
+impl TryFrom<SceneComponent> for Component {
+    type Error = anyhow::Error;
+
+    fn try_from(scene: SceneComponent) -> Result<Self> {
+        match &scene {
+            SceneComponent::View { unknown, .. }
+            | SceneComponent::Rescaler { unknown, .. }
+            | SceneComponent::Image { unknown, .. }
+            | SceneComponent::InputStream { unknown, .. } => unknown.reject()?,
+        }
+
+        let component = match scene {
+            SceneComponent::View {
+                id,
+                children,
+                direction,
+                overflow,
+                padding,
+                background_color,
+                position,
+                border,
+                ..
+            } => Component::View(ViewComponent {
+                id: component_id(id),
+                children: children
+                    .into_iter()
+                    .map(Component::try_from)
+                    .collect::<Result<_>>()?,
+                direction: match direction {
+                    DirectionSpec::Row => ViewChildrenDirection::Row,
+                    DirectionSpec::Column => ViewChildrenDirection::Column,
+                },
+                position: position.try_into()?,
+                transition: None,
+                overflow: match overflow {
+                    OverflowSpec::Hidden => Overflow::Hidden,
+                    OverflowSpec::Visible => Overflow::Visible,
+                    OverflowSpec::Fit => Overflow::Fit,
+                },
+                background_color: parse_color(background_color.as_deref())?,
+                border_radius: border_radius(border.border_radius),
+                border_width: border.border_width,
+                border_color: parse_color(border.border_color.as_deref())?,
+                box_shadow: vec![],
+                padding: Padding {
+                    top: padding,
+                    right: padding,
+                    bottom: padding,
+                    left: padding,
+                },
+            }),
+            SceneComponent::Rescaler {
+                id,
+                child,
+                mode,
+                horizontal_align,
+                vertical_align,
+                position,
+                border,
+                ..
+            } => Component::Rescaler(RescalerComponent {
+                id: component_id(id),
+                child: Box::new((*child).try_into()?),
+                position: position.try_into()?,
+                transition: None,
+                mode: match mode {
+                    RescaleModeSpec::Fit => RescaleMode::Fit,
+                    RescaleModeSpec::Fill => RescaleMode::Fill,
+                },
+                horizontal_align: match horizontal_align {
+                    HorizontalAlignSpec::Left => HorizontalAlign::Left,
+                    HorizontalAlignSpec::Right => HorizontalAlign::Right,
+                    HorizontalAlignSpec::Justified => HorizontalAlign::Justified,
+                    HorizontalAlignSpec::Center => HorizontalAlign::Center,
+                },
+                vertical_align: match vertical_align {
+                    VerticalAlignSpec::Top => VerticalAlign::Top,
+                    VerticalAlignSpec::Bottom => VerticalAlign::Bottom,
+                    VerticalAlignSpec::Justified => VerticalAlign::Justified,
+                    VerticalAlignSpec::Center => VerticalAlign::Center,
+                },
+                border_radius: border_radius(border.border_radius),
+                border_width: border.border_width,
+                border_color: parse_color(border.border_color.as_deref())?,
+                box_shadow: vec![],
+            }),
+            SceneComponent::Image {
+                id,
+                image_id,
+                width,
+                height,
+                ..
+            } => Component::Image(ImageComponent {
+                id: component_id(id),
+                image_id: RendererId(Arc::from(image_id)),
+                width,
+                height,
+            }),
+            SceneComponent::InputStream { id, input_id, .. } => {
+                Component::InputStream(InputStreamComponent {
+                    id: component_id(id),
+                    input_id: InputId(Arc::from(input_id)),
+                })
+            }
+        };
+
+        Ok(component)
+    }
+}
+
+impl TryFrom<PositionSpec> for Position {
+    type Error = anyhow::Error;
+
+    fn try_from(spec: PositionSpec) -> Result<Self> {
+        let position_horizontal = match (spec.left, spec.right) {
+            (Some(_), Some(_)) => bail!("Only one of \"left\" and \"right\" can be set"),
+            (Some(left), None) => Some(HorizontalPosition::LeftOffset(left)),
+            (None, Some(right)) => Some(HorizontalPosition::RightOffset(right)),
+            (None, None) => None,
+        };
+        let position_vertical = match (spec.top, spec.bottom) {
+            (Some(_), Some(_)) => bail!("Only one of \"top\" and \"bottom\" can be set"),
+            (Some(top), None) => Some(VerticalPosition::TopOffset(top)),
+            (None, Some(bottom)) => Some(VerticalPosition::BottomOffset(bottom)),
+            (None, None) => None,
+        };
+
+        if position_horizontal.is_none() && position_vertical.is_none() {
+            if spec.rotation.is_some() {
+                bail!("\"rotation\" requires an absolute position");
+            }
+            return Ok(Position::Static {
+                width: spec.width,
+                height: spec.height,
+            });
+        }
+
+        Ok(Position::Absolute(AbsolutePosition {
+            width: spec.width,
+            height: spec.height,
+            position_horizontal: position_horizontal.unwrap_or(HorizontalPosition::LeftOffset(0.0)),
+            position_vertical: position_vertical.unwrap_or(VerticalPosition::TopOffset(0.0)),
+            rotation_degrees: spec.rotation.unwrap_or(0.0),
+        }))
+    }
+}
+
+fn border_radius(radius: f32) -> BorderRadius {
+    BorderRadius {
+        top_left: radius,
+        top_right: radius,
+        bottom_right: radius,
+        bottom_left: radius,
+    }
+}
+
+fn component_id(id: Option<String>) -> Option<ComponentId> {
+    id.map(|id| ComponentId(Arc::from(id)))
+}
+
+/// Parses `#RRGGBB` or `#RRGGBBAA`, a missing color is transparent.
+pub fn parse_color(color: Option<&str>) -> Result<RGBAColor> {
+    let Some(color) = color else {
+        return Ok(RGBAColor(0, 0, 0, 0));
+    };
+
+    let hex = color
+        .strip_prefix('#')
+        .with_context(|| format!("Color {color:?} must start with '#'"))?;
+    // from_str_radix would take a sign too
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        bail!("Invalid color {color:?}");
+    }
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|c| u8::from_str_radix(c, 16).ok())
+            .with_context(|| format!("Invalid color {color:?}"))
+    };
+
+    match hex.len() {
+        6 => Ok(RGBAColor(channel(0)?, channel(2)?, channel(4)?, 255)),
+        8 => Ok(RGBAColor(
+            channel(0)?,
+            channel(2)?,
+            channel(4)?,
+            channel(6)?,
+        )),
+        _ => bail!("Invalid color {color:?}, expected #RRGGBB or #RRGGBBAA"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color(color: &str) -> Result<(u8, u8, u8, u8)> {
+        parse_color(Some(color)).map(|RGBAColor(r, g, b, a)| (r, g, b, a))
+    }
+
+    /// Position of a view with `fields`.
+    fn position(fields: &str) -> Result<Position> {
+        let Component::View(view) = parse(&format!(r#"{{"type": "view", {fields}}}"#))? else {
+            panic!("expected a view");
+        };
+        Ok(view.position)
+    }
+
+    fn error(json: &str) -> String {
+        parse(json)
+            .err()
+            .expect("scene should be invalid")
+            .to_string()
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(color("#ff8000").unwrap(), (255, 128, 0, 255));
+        assert_eq!(color("#FF800080").unwrap(), (255, 128, 0, 128));
+        let RGBAColor(r, g, b, a) = parse_color(None).unwrap();
+        assert_eq!((r, g, b, a), (0, 0, 0, 0));
+
+        for invalid in [
+            "ff8000",
+            "#fff",
+            "#ff80001",
+            "#ff8000ff00",
+            "#gg0000",
+            "#+f+f+f",
+            "# f f f",
+        ] {
+            assert!(color(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn static_positions() {
+        let Position::Static { width, height } = position(r#""width": 100"#).unwrap() else {
+            panic!("expected a static position");
+        };
+        assert_eq!((width, height), (Some(100.0), None));
+    }
+
+    #[test]
+    fn absolute_positions() {
+        let Position::Absolute(absolute) =
+            position(r#""width": 100, "height": 50, "left": 10, "top": 20"#).unwrap()
+        else {
+            panic!("expected an absolute position");
+        };
+        assert_eq!((absolute.width, absolute.height), (Some(100.0), Some(50.0)));
+        assert!(matches!(
+            absolute.position_horizontal,
+            HorizontalPosition::LeftOffset(left) if left == 10.0
+        ));
+        assert!(matches!(
+            absolute.position_vertical,
+            VerticalPosition::TopOffset(top) if top == 20.0
+        ));
+        assert_eq!(absolute.rotation_degrees, 0.0);
+
+        // The other side defaults to the top or left edge
+        let Position::Absolute(absolute) = position(r#""right": 30, "rotation": 90"#).unwrap()
+        else {
+            panic!("expected an absolute position");
+        };
+        assert!(matches!(
+            absolute.position_horizontal,
+            HorizontalPosition::RightOffset(right) if right == 30.0
+        ));
+        assert!(matches!(
+            absolute.position_vertical,
+            VerticalPosition::TopOffset(top) if top == 0.0
+        ));
+        assert_eq!(absolute.rotation_degrees, 90.0);
+
+        let Position::Absolute(absolute) = position(r#""bottom": 5"#).unwrap() else {
+            panic!("expected an absolute position");
+        };
+        assert!(matches!(
+            absolute.position_vertical,
+            VerticalPosition::BottomOffset(bottom) if bottom == 5.0
+        ));
+    }
+
+    #[test]
+    fn conflicting_positions() {
+        assert_eq!(
+            error(r#"{"type": "view", "left": 0, "right": 0}"#),
+            r#"Only one of "left" and "right" can be set"#
+        );
+        assert_eq!(
+            error(r#"{"type": "view", "top": 0, "bottom": 0}"#),
+            r#"Only one of "top" and "bottom" can be set"#
+        );
+        assert_eq!(
+            error(r#"{"type": "view", "width": 10, "rotation": 45}"#),
+            r#""rotation" requires an absolute position"#
+        );
+    }
+
+    #[test]
+    fn unknown_fields() {
+        assert_eq!(
+            error(r##"{"type": "view", "backgroud_color": "#ffffff"}"##),
+            r#"Unknown field "backgroud_color""#
+        );
+        let nested = r#"{
+            "type": "rescaler",
+            "child": {"type": "input_stream", "input_id": "camera", "mode": "fill"}
+        }"#;
+        assert_eq!(error(nested), r#"Unknown field "mode""#);
+        assert_eq!(
+            error(r#"{"type": "image", "image_id": "logo", "top": 0}"#),
+            r#"Unknown field "top""#
+        );
+    }
+
+    #[test]
+    fn tree() {
+        let scene = r##"{
+            "type": "view",
+            "direction": "column",
+            "background_color": "#000000",
+            "border_width": 2,
+            "border_color": "#ffffff",
+            "children": [
+                {
+                    "type": "rescaler",
+                    "mode": "fill",
+                    "child": {"type": "input_stream", "input_id": "camera"}
+                },
+                {"type": "image", "id": "logo", "image_id": "logo", "width": 64}
+            ]
+        }"##;
+        let Component::View(view) = parse(scene).unwrap() else {
+            panic!("expected a view");
+        };
+        assert_eq!(view.children.len(), 2);
+        assert_eq!(view.border_width, 2.0);
+        assert!(matches!(view.direction, ViewChildrenDirection::Column));
+        let Component::Rescaler(rescaler) = &view.children[0] else {
+            panic!("expected a rescaler");
+        };
+        assert!(matches!(rescaler.mode, RescaleMode::Fill));
+        let Component::InputStream(input) = &*rescaler.child else {
+            panic!("expected an input stream");
+        };
+        assert_eq!(&*input.input_id.0, "camera");
+        assert!(matches!(&view.children[1], Component::Image(image) if image.width == Some(64.0)));
+    }
+}
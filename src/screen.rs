@@ -0,0 +1,91 @@
+use anyhow::{Context, Result};
+use compositor_pipeline::pipeline::GraphicsContext;
+use compositor_pipeline::queue::{PipelineEvent, QueueInputOptions};
+use compositor_pipeline::Pipeline;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+use xcap::Monitor;
+
+use crate::raw_input;
+
+/// Registers a raw data input fed with captures of a monitor, one every `interval`.
+///
+/// The capture runs on its own thread, which stops once the input is unregistered.
+pub fn register(
+    pipeline: &Arc<Mutex<Pipeline>>,
+    graphics_context: &GraphicsContext,
+    id: &str,
+    monitor: Option<&str>,
+    interval: Duration,
+    queue_options: QueueInputOptions,
+) -> Result<()> {
+    let video = raw_input::register(pipeline, id, queue_options)?;
+
+    // Monitors are not always `Send`, they are looked up on the capture thread
+    let (init_sender, init) = mpsc::channel();
+    let graphics_context = graphics_context.clone();
+    let monitor_name = monitor.map(str::to_string);
+    let input_id = id.to_string();
+    std::thread::Builder::new()
+        .name(format!("screen capture {id}"))
+        .spawn(move || {
+            let monitor = match find_monitor(monitor_name.as_deref()) {
+                Ok(monitor) => {
+                    let _ = init_sender.send(Ok(monitor.name().unwrap_or_default()));
+                    monitor
+                }
+                Err(err) => {
+                    let _ = init_sender.send(Err(err));
+                    return;
+                }
+            };
+
+            let start = Instant::now();
+            loop {
+                let capture_start = Instant::now();
+                match monitor.capture_image() {
+                    Ok(image) => {
+                        let frame = raw_input::frame(&graphics_context, &image, start.elapsed());
+                        if video.send(PipelineEvent::Data(frame)).is_err() {
+                            debug!("Input {input_id} unregistered, stopping screen capture");
+                            return;
+                        }
+                    }
+                    Err(err) => warn!("Screen capture for {input_id} failed: {err}"),
+                }
+
+                if let Some(rest) = interval.checked_sub(capture_start.elapsed()) {
+                    std::thread::sleep(rest);
+                }
+            }
+        })?;
+
+    let name = init.recv().context("Screen capture thread stopped")??;
+    info!("Capturing monitor {name:?} as {id}");
+
+    Ok(())
+}
+
+/// Monitor named `name`, or the primary one.
+fn find_monitor(name: Option<&str>) -> Result<Monitor> {
+    let monitors = Monitor::all().context("Cannot list monitors")?;
+    let monitor = match name {
+        Some(name) => monitors
+            .into_iter()
+            .find(|monitor| monitor.name().is_ok_and(|n| n == name))
+            .with_context(|| format!("No monitor named {name:?}"))?,
+        None => {
+            let primary = monitors
+                .iter()
+                .position(|monitor| monitor.is_primary().unwrap_or(false))
+                .unwrap_or(0);
+            monitors
+                .into_iter()
+                .nth(primary)
+                .context("No monitor found")?
+        }
+    };
+
+    Ok(monitor)
+}
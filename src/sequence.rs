@@ -0,0 +1,111 @@
+use anyhow::{bail, Context, Result};
+use compositor_pipeline::pipeline::GraphicsContext;
+use compositor_pipeline::queue::QueueInputOptions;
+use compositor_pipeline::Pipeline;
+use image::{Rgba, Rgba32FImage, RgbaImage};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::info;
+
+use crate::raw_input;
+
+const EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "exr"];
+
+/// Registers a raw data input playing the numbered images of `dir`, one every `interval`.
+///
+/// Frames are decoded on the fly by a thread of their own. EXR frames are assumed to be
+/// scene-linear and are encoded to sRGB.
+pub fn register(
+    pipeline: &Arc<Mutex<Pipeline>>,
+    graphics_context: &GraphicsContext,
+    id: &str,
+    dir: &Path,
+    interval: Duration,
+    should_loop: bool,
+    queue_options: QueueInputOptions,
+) -> Result<()> {
+    let frames = list_frames(dir)?;
+    let video = raw_input::register(pipeline, id, queue_options)?;
+    info!(
+        "Registered {} ({} frames) as {id}",
+        dir.display(),
+        frames.len()
+    );
+
+    let graphics_context = graphics_context.clone();
+    let input_id = id.to_string();
+    std::thread::Builder::new()
+        .name(format!("image sequence {id}"))
+        .spawn(move || {
+            let paths: Box<dyn Iterator<Item = &PathBuf>> = match should_loop {
+                true => Box::new(frames.iter().cycle()),
+                false => Box::new(frames.iter()),
+            };
+            let frames = paths
+                .enumerate()
+                .map(|(index, path)| Ok((load_frame(path)?, interval * index as u32)));
+            raw_input::play(video, &graphics_context, &input_id, frames);
+        })?;
+
+    Ok(())
+}
+
+/// Images of `dir`, ordered by the number ending their name.
+fn list_frames(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut frames: Vec<_> = std::fs::read_dir(dir)
+        .with_context(|| format!("Cannot read image sequence {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        })
+        .collect();
+    if frames.is_empty() {
+        bail!("No images in {}", dir.display());
+    }
+
+    frames.sort_by_cached_key(|path| (frame_number(path), path.clone()));
+    Ok(frames)
+}
+
+fn frame_number(path: &Path) -> Option<u64> {
+    let stem = path.file_stem()?.to_str()?;
+    let digits = stem.len() - stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    stem[stem.len() - digits..].parse().ok()
+}
+
+fn load_frame(path: &Path) -> Result<RgbaImage> {
+    let image =
+        image::open(path).with_context(|| format!("Cannot decode frame {}", path.display()))?;
+
+    let is_exr = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("exr"));
+    match is_exr {
+        true => Ok(linear_to_srgb(&image.to_rgba32f())),
+        false => Ok(image.to_rgba8()),
+    }
+}
+
+fn linear_to_srgb(image: &Rgba32FImage) -> RgbaImage {
+    let encode = |linear: f32| {
+        let linear = linear.clamp(0.0, 1.0);
+        let encoded = match linear <= 0.0031308 {
+            true => linear * 12.92,
+            false => 1.055 * linear.powf(1.0 / 2.4) - 0.055,
+        };
+        (encoded * 255.0).round() as u8
+    };
+
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        Rgba([
+            encode(r),
+            encode(g),
+            encode(b),
+            (a.clamp(0.0, 1.0) * 255.0).round() as u8,
+        ])
+    })
+}
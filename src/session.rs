@@ -0,0 +1,64 @@
+//! Interactive state of the dashboard, saved when it stops and restored when it starts
+//! again, so repeated debugging sessions pick up where the last one left off.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::config::PreviewConfig;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionState {
+    /// ID of the input or scene shown.
+    pub scene: Option<String>,
+    /// Whether scenes were switched automatically.
+    pub cycling: bool,
+    /// Magnification and width of the preview, see [`PreviewConfig`].
+    pub zoom: Option<f32>,
+    pub preview_width: Option<u16>,
+}
+
+impl Default for SessionState {
+    fn default() -> Self {
+        Self {
+            scene: None,
+            cycling: true,
+            zoom: None,
+            preview_width: None,
+        }
+    }
+}
+
+impl SessionState {
+    /// Reads the state saved to `path`, `None` if no session was saved yet.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text)
+                .map(Some)
+                .with_context(|| format!("Invalid session {}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).context(format!("Cannot read {}", path.display())),
+        }
+    }
+
+    /// Restores the preview settings of the session, clamped to those the dashboard allows,
+    /// as the file may have been edited.
+    pub fn apply_preview(&self, config: &mut PreviewConfig) {
+        if let Some(zoom) = self.zoom {
+            config.zoom = zoom;
+        }
+        if let Some(width) = self.preview_width {
+            config.preview_width = width;
+        }
+        config.clamp();
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, toml::to_string(self)?)
+            .with_context(|| format!("Cannot write {}", path.display()))
+    }
+}
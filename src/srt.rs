@@ -0,0 +1,146 @@
+use anyhow::{Context, Result};
+use compositor_pipeline::pipeline::GraphicsContext;
+use compositor_pipeline::queue::{PipelineEvent, QueueInputOptions};
+use compositor_pipeline::Pipeline;
+use compositor_render::{InputId, Resolution};
+use image::RgbaImage;
+use std::io::{Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::config::SrtModeConfig;
+use crate::raw_input::{self, RawFrameSender};
+
+/// Settings of an SRT connection.
+#[derive(Debug, Clone)]
+pub struct SrtOptions<'a> {
+    pub mode: SrtModeConfig,
+    /// `host:port` to connect to as a caller, or to bind as a listener.
+    pub address: &'a str,
+    pub passphrase: Option<&'a str>,
+    pub latency: Option<Duration>,
+}
+
+/// Registers a raw data input fed with an SRT stream, scaled to `resolution`.
+///
+/// The stream is received and decoded by an `ffmpeg` process, whose frames are forwarded
+/// by a thread of their own until the stream ends or the input is unregistered. The
+/// passphrase is given to `ffmpeg` on its standard input, not to be listed with the
+/// processes, which needs FFmpeg 7 or later.
+pub fn register(
+    pipeline: &Arc<Mutex<Pipeline>>,
+    graphics_context: &GraphicsContext,
+    id: &str,
+    options: &SrtOptions,
+    resolution: Resolution,
+    queue_options: QueueInputOptions,
+) -> Result<()> {
+    // Registered first, for the stream not to start before the input can take it
+    let video = raw_input::register(pipeline, id, queue_options)?;
+    if let Err(err) = receive(video, graphics_context, id, options, resolution) {
+        let unregistered =
+            Pipeline::unregister_input(&mut *pipeline.lock().unwrap(), &InputId(Arc::from(id)));
+        if let Err(unregister_err) = unregistered {
+            warn!("Cannot unregister input {id}: {unregister_err:#}");
+        }
+        return Err(err);
+    }
+
+    match options.mode {
+        SrtModeConfig::Caller => info!("Connecting to SRT stream {} as {id}", options.address),
+        SrtModeConfig::Listener => info!("Listening for SRT stream {id} on {}", options.address),
+    }
+
+    Ok(())
+}
+
+/// `ffmpeg` receiving the stream, killed once dropped.
+struct Receiver(Child);
+
+impl Drop for Receiver {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Starts `ffmpeg` and the thread forwarding its frames to `video`.
+fn receive(
+    video: RawFrameSender,
+    graphics_context: &GraphicsContext,
+    id: &str,
+    options: &SrtOptions,
+    resolution: Resolution,
+) -> Result<()> {
+    let mut command = Command::new("ffmpeg");
+    command.args(["-hide_banner", "-loglevel", "error", "-nostdin"]);
+    if options.passphrase.is_some() {
+        // `-/` options read their value from a file, here the standard input
+        command.args(["-/passphrase", "/dev/stdin"]);
+    }
+    let ffmpeg = command
+        .args(["-i", &url(options)])
+        .args(["-an", "-vf"])
+        .arg(format!("scale={}:{}", resolution.width, resolution.height))
+        .args(["-pix_fmt", "rgba", "-f", "rawvideo", "-"])
+        .stdin(match options.passphrase {
+            Some(_) => Stdio::piped(),
+            None => Stdio::null(),
+        })
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Cannot start ffmpeg, which receives SRT streams")?;
+    let mut receiver = Receiver(ffmpeg);
+    if let (Some(passphrase), Some(mut input)) = (options.passphrase, receiver.0.stdin.take()) {
+        // Closed right after, the end of the file
+        input
+            .write_all(passphrase.as_bytes())
+            .context("Cannot give ffmpeg the SRT passphrase")?;
+    }
+    let mut stream = receiver.0.stdout.take().context("No ffmpeg output")?;
+
+    let graphics_context = graphics_context.clone();
+    let input_id = id.to_string();
+    let (width, height) = (resolution.width as u32, resolution.height as u32);
+    std::thread::Builder::new()
+        .name(format!("srt {id}"))
+        .spawn(move || {
+            // Live stream, timestamps start with the first frame received
+            let mut start = None;
+            loop {
+                let mut buffer = vec![0; width as usize * height as usize * 4];
+                if let Err(err) = stream.read_exact(&mut buffer) {
+                    debug!("SRT stream {input_id} ended: {err}");
+                    let _ = video.send(PipelineEvent::EOS);
+                    break;
+                }
+
+                let start = *start.get_or_insert_with(Instant::now);
+                let image = RgbaImage::from_raw(width, height, buffer)
+                    .expect("Buffer has the size of a frame");
+                let frame = raw_input::frame(&graphics_context, &image, start.elapsed());
+                if video.send(PipelineEvent::Data(frame)).is_err() {
+                    debug!("Input {input_id} unregistered, stopping SRT receiver");
+                    break;
+                }
+            }
+            drop(receiver);
+        })?;
+
+    Ok(())
+}
+
+fn url(options: &SrtOptions) -> String {
+    let mode = match options.mode {
+        SrtModeConfig::Caller => "caller",
+        SrtModeConfig::Listener => "listener",
+    };
+    let mut url = format!("srt://{}?mode={mode}", options.address);
+    if let Some(latency) = options.latency {
+        // Microseconds for FFmpeg
+        url += &format!("&latency={}", latency.as_micros());
+    }
+    url
+}
@@ -0,0 +1,90 @@
+use anyhow::{bail, Result};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+use crate::config::StartupBudgetConfig;
+
+/// Durations of each startup stage, up to the first output frame.
+#[derive(Debug, Clone, Default)]
+pub struct StartupTimings {
+    pub graphics_init: Duration,
+    pub pipeline_create: Duration,
+    pub input_register: Duration,
+    /// From the output registration to the first frame received from it.
+    pub first_frame: Duration,
+}
+
+/// Measures consecutive stages, each one starting when the previous one ends.
+pub struct StageTimer {
+    last: Instant,
+}
+
+impl StageTimer {
+    pub fn start() -> Self {
+        Self {
+            last: Instant::now(),
+        }
+    }
+
+    /// Returns the time elapsed since the previous call, or since `start`.
+    pub fn lap(&mut self) -> Duration {
+        let now = Instant::now();
+        let elapsed = now - self.last;
+        self.last = now;
+        elapsed
+    }
+}
+
+impl StartupTimings {
+    pub fn total(&self) -> Duration {
+        self.graphics_init + self.pipeline_create + self.input_register + self.first_frame
+    }
+
+    fn stages(&self) -> [(&'static str, Duration); 5] {
+        [
+            ("graphics init", self.graphics_init),
+            ("pipeline create", self.pipeline_create),
+            ("input register", self.input_register),
+            ("first frame", self.first_frame),
+            ("total", self.total()),
+        ]
+    }
+
+    pub fn log(&self) {
+        for (stage, duration) in self.stages() {
+            info!("Startup {stage}: {:.1}ms", duration.as_secs_f64() * 1000.0);
+        }
+    }
+
+    /// Fails if any stage took longer than its budget.
+    pub fn check(&self, budget: &StartupBudgetConfig) -> Result<()> {
+        let budgets = [
+            budget.graphics_init_ms,
+            budget.pipeline_create_ms,
+            budget.input_register_ms,
+            budget.first_frame_ms,
+            budget.total_ms,
+        ];
+
+        let exceeded: Vec<String> = self
+            .stages()
+            .into_iter()
+            .zip(budgets)
+            .filter_map(|((stage, duration), budget_ms)| {
+                let budget = Duration::from_millis(budget_ms?);
+                (duration > budget).then(|| {
+                    format!(
+                        "{stage} took {:.1}ms (budget {}ms)",
+                        duration.as_secs_f64() * 1000.0,
+                        budget.as_millis()
+                    )
+                })
+            })
+            .collect();
+
+        if !exceeded.is_empty() {
+            bail!("Startup budget exceeded: {}", exceeded.join(", "));
+        }
+        Ok(())
+    }
+}
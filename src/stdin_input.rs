@@ -0,0 +1,82 @@
+use anyhow::Result;
+use compositor_pipeline::pipeline::GraphicsContext;
+use compositor_pipeline::queue::QueueInputOptions;
+use compositor_pipeline::Pipeline;
+use image::{Rgba, RgbaImage};
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, info};
+
+use crate::config::{PixelFormatConfig, ResolutionConfig};
+use crate::raw_input;
+
+/// Registers a raw data input playing the raw frames written to stdin, one every `interval`.
+///
+/// Frames are read by a thread of their own until stdin is closed, e.g. by
+/// `ffmpeg -i <source> -f rawvideo -pix_fmt rgba -`.
+pub fn register(
+    pipeline: &Arc<Mutex<Pipeline>>,
+    graphics_context: &GraphicsContext,
+    id: &str,
+    resolution: ResolutionConfig,
+    interval: Duration,
+    pixel_format: PixelFormatConfig,
+    queue_options: QueueInputOptions,
+) -> Result<()> {
+    let video = raw_input::register(pipeline, id, queue_options)?;
+
+    let graphics_context = graphics_context.clone();
+    let input_id = id.to_string();
+    let (width, height) = (resolution.width as u32, resolution.height as u32);
+    std::thread::Builder::new()
+        .name(format!("stdin {id}"))
+        .spawn(move || {
+            let mut stdin = std::io::stdin().lock();
+            let mut index = 0;
+            let frames = std::iter::from_fn(|| {
+                let mut buffer = vec![0; pixel_format.frame_size(width, height)];
+                if let Err(err) = stdin.read_exact(&mut buffer) {
+                    debug!("End of stdin after {index} frames: {err}");
+                    return None;
+                }
+
+                let image = match pixel_format {
+                    PixelFormatConfig::Rgba => RgbaImage::from_raw(width, height, buffer)
+                        .expect("Buffer has the size of a frame"),
+                    PixelFormatConfig::Yuv420p => yuv420p_to_rgba(&buffer, width, height),
+                };
+                let pts = interval * index;
+                index += 1;
+                Some(Ok((image, pts)))
+            });
+            raw_input::play(video, &graphics_context, &input_id, frames);
+        })?;
+
+    info!("Reading {width}x{height} {pixel_format:?} frames from stdin as {id}");
+    Ok(())
+}
+
+/// Converts planar 4:2:0 YUV, BT.709 limited range as encoded by default for HD.
+fn yuv420p_to_rgba(buffer: &[u8], width: u32, height: u32) -> RgbaImage {
+    let luma_size = (width * height) as usize;
+    let chroma_width = width.div_ceil(2);
+    let chroma_size = (chroma_width * height.div_ceil(2)) as usize;
+    let (luma, chroma) = buffer.split_at(luma_size);
+    let (u_plane, v_plane) = chroma.split_at(chroma_size);
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let chroma_index = ((y / 2) * chroma_width + x / 2) as usize;
+        let y = (luma[(y * width + x) as usize] as f32 - 16.0) * (255.0 / 219.0);
+        let u = (u_plane[chroma_index] as f32 - 128.0) * (255.0 / 224.0);
+        let v = (v_plane[chroma_index] as f32 - 128.0) * (255.0 / 224.0);
+
+        let clamp = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+        Rgba([
+            clamp(y + 1.5748 * v),
+            clamp(y - 0.1873 * u - 0.4681 * v),
+            clamp(y + 1.8556 * u),
+            255,
+        ])
+    })
+}
@@ -0,0 +1,171 @@
+//! Sizes of SVG image inputs, which are rasterized at the size they fit the output at
+//! rather than scaled afterwards.
+
+use anyhow::{Context, Result};
+use compositor_render::scene::Size;
+use compositor_render::Resolution;
+use std::path::Path;
+use tracing::warn;
+
+pub fn is_svg(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+}
+
+/// Size of an SVG fitted to `resolution`, keeping the aspect of its `viewBox`, or of its
+/// `width` and `height`. SVGs declaring neither fill the output.
+pub fn size(path: &Path, resolution: Resolution) -> Result<Size> {
+    let svg =
+        std::fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    let Some(dimensions) = dimensions(&svg) else {
+        warn!(
+            "{} declares no viewBox or size, it fills the output",
+            path.display()
+        );
+        return Ok(Size {
+            width: resolution.width as f32,
+            height: resolution.height as f32,
+        });
+    };
+    Ok(fit(dimensions, resolution))
+}
+
+/// Largest size of the aspect of `svg_width` and `svg_height` within `resolution`.
+fn fit((svg_width, svg_height): (f32, f32), resolution: Resolution) -> Size {
+    let (width, height) = (resolution.width as f32, resolution.height as f32);
+    let scale = (width / svg_width).min(height / svg_height);
+    Size {
+        width: (svg_width * scale).min(width),
+        height: (svg_height * scale).min(height),
+    }
+}
+
+/// Width and height of the `viewBox` of the root element of an SVG, or its `width` and
+/// `height` attributes when in pixels.
+fn dimensions(svg: &str) -> Option<(f32, f32)> {
+    let start = svg.find("<svg")?;
+    let tag = &svg[start..start + svg[start..].find('>')?];
+    let attribute = |name: &str| {
+        ['"', '\''].into_iter().find_map(|quote| {
+            let key = format!("{name}={quote}");
+            // Not the end of another attribute name, e.g. `stroke-width`
+            let (index, _) = tag
+                .match_indices(&key)
+                .find(|(index, _)| tag[..*index].ends_with(char::is_whitespace))?;
+            let value = &tag[index + key.len()..];
+            Some(&value[..value.find(quote)?])
+        })
+    };
+    let positive = |value: f32| (value.is_finite() && value > 0.0).then_some(value);
+
+    if let Some(view_box) = attribute("viewBox") {
+        let values: Vec<f32> = view_box
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|value| !value.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .ok()?;
+        if let [_, _, width, height] = values[..] {
+            return Some((positive(width)?, positive(height)?));
+        }
+    }
+    let pixels = |name: &str| {
+        let value = attribute(name)?;
+        positive(
+            value
+                .strip_suffix("px")
+                .unwrap_or(value)
+                .trim()
+                .parse()
+                .ok()?,
+        )
+    };
+    Some((pixels("width")?, pixels("height")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_box() {
+        let svg = r#"<?xml version="1.0"?>
+            <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 200 100"><rect/></svg>"#;
+        assert_eq!(dimensions(svg), Some((200.0, 100.0)));
+        assert_eq!(
+            dimensions(r#"<svg viewBox="-10,-10, 40.5,20">"#),
+            Some((40.5, 20.0))
+        );
+        // Preferred over the size, which only scales the drawing
+        assert_eq!(
+            dimensions(r#"<svg width="400" height="400" viewBox="0 0 200 100">"#),
+            Some((200.0, 100.0))
+        );
+        assert_eq!(dimensions(r#"<svg viewBox="0 0 0 100">"#), None);
+    }
+
+    #[test]
+    fn width_and_height() {
+        assert_eq!(
+            dimensions(r#"<svg width="300px" height="150">"#),
+            Some((300.0, 150.0))
+        );
+        // Malformed view boxes fall back to the size
+        assert_eq!(
+            dimensions(r#"<svg viewBox="0 0 200" width="30" height="15">"#),
+            Some((30.0, 15.0))
+        );
+        // Other units cannot be converted to pixels
+        assert_eq!(dimensions(r#"<svg width="10cm" height="5cm">"#), None);
+        assert_eq!(dimensions(r#"<svg width="100%" height="100%">"#), None);
+        assert_eq!(dimensions(r#"<svg width="300">"#), None);
+    }
+
+    #[test]
+    fn quotes() {
+        assert_eq!(
+            dimensions("<svg width='64' height='32'>"),
+            Some((64.0, 32.0))
+        );
+        assert_eq!(
+            dimensions("<svg\n  viewBox='0 0 16 9'\n>"),
+            Some((16.0, 9.0))
+        );
+        assert_eq!(dimensions(r#"<svg width='64" height="32'>"#), None);
+    }
+
+    #[test]
+    fn other_attributes() {
+        assert_eq!(
+            dimensions(r#"<svg stroke-width="5" width="40" height="20">"#),
+            Some((40.0, 20.0))
+        );
+        assert_eq!(dimensions(r#"<svg stroke-width="5" height="20">"#), None);
+        // Only the root element is read
+        assert_eq!(
+            dimensions(r#"<svg><rect width="10" height="10"/></svg>"#),
+            None
+        );
+        assert_eq!(dimensions("<html></html>"), None);
+    }
+
+    #[test]
+    fn fitted_sizes() {
+        let resolution = Resolution {
+            width: 1920,
+            height: 1080,
+        };
+        for (svg, fitted) in [
+            ((200.0, 100.0), (1920.0, 960.0)),
+            ((100.0, 200.0), (540.0, 1080.0)),
+        ] {
+            let size = fit(svg, resolution);
+            assert!(
+                (size.width - fitted.0).abs() < 0.01 && (size.height - fitted.1).abs() < 0.01,
+                "{svg:?} fitted to {}x{}",
+                size.width,
+                size.height
+            );
+        }
+    }
+}
@@ -0,0 +1,524 @@
+//! Encoder settings sweeps: the same sequence encoded with each preset and CRF of a matrix,
+//! each encode measured against it, to pick the production settings of color-critical
+//! content from its rate-distortion curves.
+//!
+//! The sequence is captured once to a lossless reference, `reference.mkv` (FFV1 in RGB), and
+//! every encode starts from it, so all of them see exactly the same frames. Encodes use
+//! libx264 like the recordings, converted to YUV 4:2:0, BT.709 limited range, with the other
+//! `encoder` settings.
+//!
+//! x264 logs the QP and size of each frame, kept with the color error of the frame and the
+//! scene it shows, see [`crate::telemetry`].
+
+use anyhow::{bail, Context, Result};
+use compositor_render::Resolution;
+use image::RgbaImage;
+use std::fmt::Write as _;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use tracing::{info, warn};
+
+use crate::config::{EncoderConfig, EncoderPresetConfig, FramerateConfig};
+use crate::raw_video::{self, RawVideoWriter};
+use crate::telemetry::{self, EncodedFrame, FrameTelemetry};
+
+/// ΔE is measured on one pixel of each block of this size, it costs far more than PSNR.
+const DELTA_E_STRIDE: usize = 4;
+
+/// Settings of a sweep.
+#[derive(Debug, Clone)]
+pub struct SweepSettings {
+    pub presets: Vec<EncoderPresetConfig>,
+    pub crfs: Vec<u8>,
+    /// Whether to measure VMAF, with the `libvmaf` filter of `ffmpeg`.
+    pub vmaf: bool,
+}
+
+/// One encode of the sweep and its distortion from the reference.
+#[derive(Debug, Clone)]
+pub struct RateDistortion {
+    pub preset: EncoderPresetConfig,
+    pub crf: u8,
+    pub path: PathBuf,
+    pub size: u64,
+    pub bitrate_kbps: f64,
+    /// PSNR over the RGB channels of every frame, infinite for identical frames.
+    pub psnr_db: f64,
+    /// Mean and largest CIEDE2000 difference.
+    pub delta_e_mean: f64,
+    pub delta_e_max: f64,
+    /// `None` when not measured or `ffmpeg` has no `libvmaf`.
+    pub vmaf: Option<f64>,
+    /// Mean QP of the frames, `None` when x264 logged none.
+    pub qp_mean: Option<f64>,
+    /// Every frame of the encode, in display order.
+    pub frames: Vec<FrameTelemetry>,
+    /// Whether no other encode is both smaller and closer in color.
+    pub pareto: bool,
+}
+
+/// Writes the frames of the reference to an FFV1 Matroska file with `ffmpeg`.
+pub struct ReferenceWriter {
+    writer: RawVideoWriter,
+}
+
+impl ReferenceWriter {
+    pub fn start(path: &Path, resolution: Resolution, framerate: FramerateConfig) -> Result<Self> {
+        // The reference of the previous sweep in the directory is replaced
+        let mut command = raw_video::command("rgba", resolution, framerate, true);
+        command
+            .args(["-c:v", "ffv1", "-level", "3", "-pix_fmt", "gbrp"])
+            .args(["-colorspace", "rgb", "-color_primaries", "bt709"])
+            .args(["-color_trc", "iec61966-2-1", "-f", "matroska"])
+            .arg(path);
+        let writer = RawVideoWriter::start(command, "the sweep reference")?;
+        Ok(Self { writer })
+    }
+
+    /// Returns whether the frame is part of the reference, see [`RawVideoWriter::push`].
+    pub fn push(&mut self, image: RgbaImage) -> Result<bool> {
+        self.writer.push(image.into_raw())
+    }
+
+    /// Waits for the file to be written, returns the number of frames.
+    pub fn finish(self) -> Result<usize> {
+        self.writer.finish()
+    }
+}
+
+/// Encodes `reference` with each preset and CRF of `settings` to `dir`, measuring each
+/// encode. `scenes` are the inputs shown in each frame of the reference, at `framerate`.
+pub fn run(
+    reference: &Path,
+    dir: &Path,
+    settings: &SweepSettings,
+    encoder: &EncoderConfig,
+    resolution: Resolution,
+    framerate: FramerateConfig,
+    scenes: &[String],
+) -> Result<Vec<RateDistortion>> {
+    let duration_secs = scenes.len() as f64 * framerate.den as f64 / framerate.num as f64;
+    let mut points = vec![];
+    for &preset in &settings.presets {
+        for &crf in &settings.crfs {
+            let path = dir.join(format!("{}_crf{crf}.mp4", preset.name()));
+            let encoded = encode(reference, &path, preset, crf, encoder)?;
+            let size = std::fs::metadata(&path)?.len();
+            let distortion = measure(reference, &path, resolution)?;
+            let vmaf = match settings.vmaf.then(|| vmaf(reference, &path)) {
+                Some(Ok(score)) => Some(score),
+                Some(Err(err)) => {
+                    warn!("Cannot measure the VMAF of {}: {err:#}", path.display());
+                    None
+                }
+                None => None,
+            };
+            let frames: Vec<FrameTelemetry> = distortion
+                .frames
+                .iter()
+                .enumerate()
+                .map(|(index, frame)| FrameTelemetry {
+                    scene: scenes.get(index).cloned().unwrap_or_default(),
+                    encoded: encoded.get(index).copied().flatten(),
+                    psnr_db: frame.psnr_db,
+                    delta_e_mean: frame.delta_e_mean,
+                    delta_e_max: frame.delta_e_max,
+                })
+                .collect();
+            if encoded.is_empty() {
+                warn!("x264 logged no frame statistics for {}", path.display());
+            }
+            let point = RateDistortion {
+                preset,
+                crf,
+                size,
+                bitrate_kbps: size as f64 * 8.0 / duration_secs.max(f64::EPSILON) / 1000.0,
+                psnr_db: distortion.psnr_db,
+                delta_e_mean: distortion.delta_e_mean,
+                delta_e_max: distortion.delta_e_max,
+                vmaf,
+                qp_mean: telemetry::mean_qp(&frames),
+                frames,
+                pareto: false,
+                path,
+            };
+            info!(
+                "{} crf {crf}: {:.0} kbps, PSNR {:.2} dB, mean ΔE {:.3}",
+                preset.name(),
+                point.bitrate_kbps,
+                point.psnr_db,
+                point.delta_e_mean
+            );
+            points.push(point);
+        }
+    }
+    mark_pareto(&mut points);
+    Ok(points)
+}
+
+/// Encodes `reference` to `path` like the recordings, with `preset` and `crf` in place of
+/// those of `encoder`. Returns what x264 logged about each frame.
+fn encode(
+    reference: &Path,
+    path: &Path,
+    preset: EncoderPresetConfig,
+    crf: u8,
+    encoder: &EncoderConfig,
+) -> Result<Vec<Option<EncodedFrame>>> {
+    let options = encoder
+        .raw_options()
+        .into_iter()
+        .filter(|(key, _)| key != "crf" && key != "b")
+        .flat_map(|(key, value)| [format!("-{key}"), value]);
+    let output = Command::new("ffmpeg")
+        // x264 logs its frame statistics at the debug level
+        .args(["-hide_banner", "-loglevel", "level+debug", "-y", "-i"])
+        .arg(reference)
+        .args(["-c:v", "libx264", "-preset", preset.name(), "-crf"])
+        .arg(crf.to_string())
+        .args(options)
+        .args(raw_video::bt709_limited("yuv420p"))
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .context("Cannot start ffmpeg, which encodes the sweep")?;
+    let log = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        let errors: Vec<&str> = log
+            .lines()
+            .filter(|line| line.contains("[error]") || line.contains("[fatal]"))
+            .collect();
+        bail!("Cannot encode {}: {}", path.display(), errors.join("\n"));
+    }
+    Ok(telemetry::parse_x264_log(&log))
+}
+
+struct Distortion {
+    psnr_db: f64,
+    delta_e_mean: f64,
+    delta_e_max: f64,
+    frames: Vec<FrameDistortion>,
+}
+
+struct FrameDistortion {
+    psnr_db: f64,
+    delta_e_mean: f64,
+    delta_e_max: f64,
+}
+
+/// Decodes both files frame by frame, with the color tags of each, and compares them.
+fn measure(reference: &Path, encoded: &Path, resolution: Resolution) -> Result<Distortion> {
+    let frame_size = resolution.width * resolution.height * 4;
+    let mut reference = Decoder::start(reference)?;
+    let mut encoded = Decoder::start(encoded)?;
+    let (mut expected, mut actual) = (vec![0; frame_size], vec![0; frame_size]);
+
+    let lab = LabTable::new();
+    let (mut squared_error, mut samples) = (0.0, 0u64);
+    let (mut delta_e_sum, mut delta_e_max, mut delta_e_samples) = (0.0, 0.0f64, 0u64);
+    let mut frames = vec![];
+    while reference.read_frame(&mut expected)? {
+        if !encoded.read_frame(&mut actual)? {
+            bail!("The encode has fewer frames than the reference");
+        }
+        let mut frame_squared_error = 0.0;
+        for (expected, actual) in expected.chunks_exact(4).zip(actual.chunks_exact(4)) {
+            for channel in 0..3 {
+                let error = expected[channel] as f64 - actual[channel] as f64;
+                frame_squared_error += error * error;
+            }
+        }
+        squared_error += frame_squared_error;
+        samples += (frame_size / 4 * 3) as u64;
+
+        let (mut frame_delta_e_sum, mut frame_delta_e_max, mut frame_samples) = (0.0, 0.0f64, 0);
+        for y in (0..resolution.height).step_by(DELTA_E_STRIDE) {
+            for x in (0..resolution.width).step_by(DELTA_E_STRIDE) {
+                let i = (y * resolution.width + x) * 4;
+                let delta_e =
+                    delta_e2000(lab.get(&expected[i..i + 3]), lab.get(&actual[i..i + 3])) as f64;
+                frame_delta_e_sum += delta_e;
+                frame_delta_e_max = frame_delta_e_max.max(delta_e);
+                frame_samples += 1;
+            }
+        }
+        delta_e_sum += frame_delta_e_sum;
+        delta_e_max = delta_e_max.max(frame_delta_e_max);
+        delta_e_samples += frame_samples;
+
+        frames.push(FrameDistortion {
+            psnr_db: psnr(frame_squared_error / (frame_size / 4 * 3) as f64),
+            delta_e_mean: frame_delta_e_sum / frame_samples as f64,
+            delta_e_max: frame_delta_e_max,
+        });
+    }
+    reference.finish()?;
+    encoded.finish()?;
+    if samples == 0 {
+        bail!("The sweep reference has no frames");
+    }
+
+    Ok(Distortion {
+        psnr_db: psnr(squared_error / samples as f64),
+        delta_e_mean: delta_e_sum / delta_e_samples as f64,
+        delta_e_max,
+        frames,
+    })
+}
+
+/// PSNR of 8-bit values with a mean squared error of `mse`.
+fn psnr(mse: f64) -> f64 {
+    10.0 * (255.0 * 255.0 / mse).log10()
+}
+
+/// Mean VMAF of `encoded` against `reference`, with the `libvmaf` filter of `ffmpeg`.
+fn vmaf(reference: &Path, encoded: &Path) -> Result<f64> {
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-i"])
+        .arg(encoded)
+        .arg("-i")
+        .arg(reference)
+        // Converted like the encodes, which libvmaf compares in YUV
+        .arg("-lavfi")
+        .arg(
+            "[1:v]scale=out_color_matrix=bt709:out_range=tv,format=yuv420p[reference];\
+             [0:v][reference]libvmaf",
+        )
+        .args(["-f", "null", "-"])
+        .stdin(Stdio::null())
+        .output()
+        .context("Cannot start ffmpeg, which measures VMAF")?;
+    let log = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        bail!("{}", log.lines().last().unwrap_or_default());
+    }
+    log.lines()
+        .find_map(|line| line.split_once("VMAF score:"))
+        .and_then(|(_, score)| score.trim().parse().ok())
+        .context("No VMAF score in the ffmpeg output")
+}
+
+/// Marks the encodes no other one beats on both size and mean ΔE.
+fn mark_pareto(points: &mut [RateDistortion]) {
+    for i in 0..points.len() {
+        let dominated = points.iter().enumerate().any(|(j, other)| {
+            j != i
+                && other.size <= points[i].size
+                && other.delta_e_mean <= points[i].delta_e_mean
+                && (other.size < points[i].size || other.delta_e_mean < points[i].delta_e_mean)
+        });
+        points[i].pareto = !dominated;
+    }
+}
+
+/// Writes the encodes to a CSV file, ordered by preset then CRF, one curve per preset, and
+/// the telemetry of each encode next to it, `{stem}_telemetry.csv` and a plot of it,
+/// `{stem}_telemetry.svg`.
+pub fn write_report(points: &[RateDistortion], path: &Path) -> Result<()> {
+    let mut csv = String::from(
+        "preset,crf,size_bytes,bitrate_kbps,psnr_db,delta_e_mean,delta_e_max,vmaf,qp_mean,pareto,file\n",
+    );
+    let format = |value: Option<f64>| value.map(|v| format!("{v:.3}")).unwrap_or_default();
+    for point in points {
+        writeln!(
+            csv,
+            "{},{},{},{:.1},{:.3},{:.4},{:.4},{},{},{},{}",
+            point.preset.name(),
+            point.crf,
+            point.size,
+            point.bitrate_kbps,
+            point.psnr_db,
+            point.delta_e_mean,
+            point.delta_e_max,
+            format(point.vmaf),
+            format(point.qp_mean),
+            point.pareto,
+            point.path.display()
+        )?;
+        telemetry::write_csv(&point.frames, &telemetry::path(&point.path, "csv"))?;
+        telemetry::write_plot(
+            &point.frames,
+            &format!("{} crf {}", point.preset.name(), point.crf),
+            &telemetry::path(&point.path, "svg"),
+        )?;
+    }
+    std::fs::write(path, csv).with_context(|| format!("Cannot write {}", path.display()))?;
+    info!("Rate-distortion report saved to {}", path.display());
+    Ok(())
+}
+
+/// Decodes a file to RGBA frames with `ffmpeg`.
+struct Decoder {
+    ffmpeg: Child,
+    output: ChildStdout,
+}
+
+impl Decoder {
+    fn start(path: &Path) -> Result<Self> {
+        let mut ffmpeg = Command::new("ffmpeg")
+            .args(["-hide_banner", "-loglevel", "error", "-i"])
+            .arg(path)
+            .args(["-an", "-pix_fmt", "rgba", "-f", "rawvideo", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()
+            .context("Cannot start ffmpeg, which decodes the sweep")?;
+        let output = ffmpeg.stdout.take().context("No ffmpeg output")?;
+        Ok(Self { ffmpeg, output })
+    }
+
+    /// Reads the next frame, `false` at the end of the file.
+    fn read_frame(&mut self, frame: &mut [u8]) -> Result<bool> {
+        match self.output.read_exact(frame) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn finish(mut self) -> Result<()> {
+        drop(self.output);
+        self.ffmpeg.wait()?;
+        Ok(())
+    }
+}
+
+/// CIELAB of sRGB colors, under D65.
+struct LabTable {
+    linear: [f32; 256],
+}
+
+impl LabTable {
+    fn new() -> Self {
+        let mut linear = [0.0; 256];
+        for (value, linear) in linear.iter_mut().enumerate() {
+            let encoded = value as f32 / 255.0;
+            *linear = match encoded <= 0.04045 {
+                true => encoded / 12.92,
+                false => ((encoded + 0.055) / 1.055).powf(2.4),
+            };
+        }
+        Self { linear }
+    }
+
+    fn get(&self, rgb: &[u8]) -> [f32; 3] {
+        let [r, g, b] = [0, 1, 2].map(|channel| self.linear[rgb[channel] as usize]);
+        let x = (0.4124564 * r + 0.3575761 * g + 0.1804375 * b) / 0.95047;
+        let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+        let z = (0.0193339 * r + 0.1191920 * g + 0.9503041 * b) / 1.08883;
+        let f = |t: f32| match t > 216.0 / 24389.0 {
+            true => t.cbrt(),
+            false => (24389.0 / 27.0 * t + 16.0) / 116.0,
+        };
+        let (fx, fy, fz) = (f(x), f(y), f(z));
+        [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+    }
+}
+
+/// CIEDE2000 difference of two CIELAB colors.
+fn delta_e2000([l1, a1, b1]: [f32; 3], [l2, a2, b2]: [f32; 3]) -> f32 {
+    let pow7 = |value: f32| value.powi(7);
+    let c_bar = ((a1 * a1 + b1 * b1).sqrt() + (a2 * a2 + b2 * b2).sqrt()) / 2.0;
+    let g = 0.5 * (1.0 - (pow7(c_bar) / (pow7(c_bar) + pow7(25.0))).sqrt());
+    let (a1, a2) = ((1.0 + g) * a1, (1.0 + g) * a2);
+    let (c1, c2) = ((a1 * a1 + b1 * b1).sqrt(), (a2 * a2 + b2 * b2).sqrt());
+    let hue = |b: f32, a: f32| b.atan2(a).to_degrees().rem_euclid(360.0);
+    let (h1, h2) = (hue(b1, a1), hue(b2, a2));
+
+    let delta_l = l2 - l1;
+    let delta_c = c2 - c1;
+    let delta_h = match (c1 * c2 == 0.0, h2 - h1) {
+        (true, _) => 0.0,
+        (false, diff) if diff > 180.0 => diff - 360.0,
+        (false, diff) if diff < -180.0 => diff + 360.0,
+        (false, diff) => diff,
+    };
+    let delta_h = 2.0 * (c1 * c2).sqrt() * (delta_h / 2.0).to_radians().sin();
+
+    let l_bar = (l1 + l2) / 2.0;
+    let c_bar = (c1 + c2) / 2.0;
+    let h_bar = match (c1 * c2 == 0.0, (h1 - h2).abs() > 180.0) {
+        (true, _) => h1 + h2,
+        (false, true) if h1 + h2 < 360.0 => (h1 + h2 + 360.0) / 2.0,
+        (false, true) => (h1 + h2 - 360.0) / 2.0,
+        (false, false) => (h1 + h2) / 2.0,
+    };
+    let cos = |degrees: f32| degrees.to_radians().cos();
+    let t =
+        1.0 - 0.17 * cos(h_bar - 30.0) + 0.24 * cos(2.0 * h_bar) + 0.32 * cos(3.0 * h_bar + 6.0)
+            - 0.20 * cos(4.0 * h_bar - 63.0);
+    let delta_theta = 30.0 * (-((h_bar - 275.0) / 25.0).powi(2)).exp();
+    let r_c = 2.0 * (pow7(c_bar) / (pow7(c_bar) + pow7(25.0))).sqrt();
+    let s_l = 1.0 + 0.015 * (l_bar - 50.0).powi(2) / (20.0 + (l_bar - 50.0).powi(2)).sqrt();
+    let s_c = 1.0 + 0.045 * c_bar;
+    let s_h = 1.0 + 0.015 * c_bar * t;
+    let r_t = -(2.0 * delta_theta).to_radians().sin() * r_c;
+
+    let (l, c, h) = (delta_l / s_l, delta_c / s_c, delta_h / s_h);
+    (l * l + c * c + h * h + r_t * c * h).max(0.0).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test data of Sharma, Wu and Dalal, "The CIEDE2000 color-difference formula:
+    /// implementation notes, supplementary test data, and mathematical observations" (2005).
+    #[rustfmt::skip]
+    const SHARMA_PAIRS: [([f32; 3], [f32; 3], f32); 34] = [
+        ([50.0000, 2.6772, -79.7751], [50.0000, 0.0000, -82.7485], 2.0425),
+        ([50.0000, 3.1571, -77.2803], [50.0000, 0.0000, -82.7485], 2.8615),
+        ([50.0000, 2.8361, -74.0200], [50.0000, 0.0000, -82.7485], 3.4412),
+        ([50.0000, -1.3802, -84.2814], [50.0000, 0.0000, -82.7485], 1.0000),
+        ([50.0000, -1.1848, -84.8006], [50.0000, 0.0000, -82.7485], 1.0000),
+        ([50.0000, -0.9009, -85.5211], [50.0000, 0.0000, -82.7485], 1.0000),
+        ([50.0000, 0.0000, 0.0000], [50.0000, -1.0000, 2.0000], 2.3669),
+        ([50.0000, -1.0000, 2.0000], [50.0000, 0.0000, 0.0000], 2.3669),
+        ([50.0000, 2.4900, -0.0010], [50.0000, -2.4900, 0.0009], 7.1792),
+        ([50.0000, 2.4900, -0.0010], [50.0000, -2.4900, 0.0010], 7.1792),
+        ([50.0000, 2.4900, -0.0010], [50.0000, -2.4900, 0.0011], 7.2195),
+        ([50.0000, 2.4900, -0.0010], [50.0000, -2.4900, 0.0012], 7.2195),
+        ([50.0000, -0.0010, 2.4900], [50.0000, 0.0009, -2.4900], 4.8045),
+        ([50.0000, -0.0010, 2.4900], [50.0000, 0.0010, -2.4900], 4.8045),
+        ([50.0000, -0.0010, 2.4900], [50.0000, 0.0011, -2.4900], 4.7461),
+        ([50.0000, 2.5000, 0.0000], [50.0000, 0.0000, -2.5000], 4.3065),
+        ([50.0000, 2.5000, 0.0000], [73.0000, 25.0000, -18.0000], 27.1492),
+        ([50.0000, 2.5000, 0.0000], [61.0000, -5.0000, 29.0000], 22.8977),
+        ([50.0000, 2.5000, 0.0000], [56.0000, -27.0000, -3.0000], 31.9030),
+        ([50.0000, 2.5000, 0.0000], [58.0000, 24.0000, 15.0000], 19.4535),
+        ([50.0000, 2.5000, 0.0000], [50.0000, 3.1736, 0.5854], 1.0000),
+        ([50.0000, 2.5000, 0.0000], [50.0000, 3.2972, 0.0000], 1.0000),
+        ([50.0000, 2.5000, 0.0000], [50.0000, 1.8634, 0.5757], 1.0000),
+        ([50.0000, 2.5000, 0.0000], [50.0000, 3.2592, 0.3350], 1.0000),
+        ([60.2574, -34.0099, 36.2677], [60.4626, -34.1751, 39.4387], 1.2644),
+        ([63.0109, -31.0961, -5.8663], [62.8187, -29.7946, -4.0864], 1.2630),
+        ([61.2901, 3.7196, -5.3901], [61.4292, 2.2480, -4.9620], 1.8731),
+        ([35.0831, -44.1164, 3.7933], [35.0232, -40.0716, 1.5901], 1.8645),
+        ([22.7233, 20.0904, -46.6940], [23.0331, 14.9730, -42.5619], 2.0373),
+        ([36.4612, 47.8580, 18.3852], [36.2715, 50.5065, 21.2231], 1.4146),
+        ([90.8027, -2.0831, 1.4410], [91.1528, -1.6435, 0.0447], 1.4441),
+        ([90.9257, -0.5406, -0.9208], [88.6381, -0.8985, -0.7239], 1.5381),
+        ([6.7747, -0.2908, -2.4247], [5.8714, -0.0985, -2.2286], 0.6377),
+        ([2.0776, 0.0795, -1.1350], [0.9033, -0.0636, -0.5514], 0.9082),
+    ];
+
+    #[test]
+    fn ciede2000() {
+        for (pair, (lab1, lab2, expected)) in SHARMA_PAIRS.into_iter().enumerate() {
+            let delta_e = delta_e2000(lab1, lab2);
+            assert!(
+                (delta_e - expected).abs() < 1e-4,
+                "pair {}: {delta_e}, expected {expected}",
+                pair + 1
+            );
+            // Symmetric
+            assert!(
+                (delta_e2000(lab2, lab1) - delta_e).abs() < 1e-4,
+                "pair {}",
+                pair + 1
+            );
+        }
+        assert_eq!(delta_e2000([50.0, 2.5, 0.0], [50.0, 2.5, 0.0]), 0.0);
+    }
+}
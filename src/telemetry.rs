@@ -0,0 +1,282 @@
+//! Per-frame telemetry of libx264 encodes: the QP, slice type and size x264 logs for each
+//! frame, next to the color error of the frame and the scene it shows, to tell which scenes
+//! or transitions the quality dips of an encode come from.
+
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Size of the plot area of each chart, in SVG user units.
+const CHART_WIDTH: f64 = 1000.0;
+const CHART_HEIGHT: f64 = 140.0;
+/// Space left of the charts for the axis labels, and between two charts.
+const MARGIN: f64 = 60.0;
+
+/// What x264 logged about one frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EncodedFrame {
+    /// Average QP of the macroblocks.
+    pub qp: f64,
+    /// `I`, `P` or `B`.
+    pub slice: char,
+    pub size: u64,
+}
+
+/// Telemetry of one frame of an encode.
+#[derive(Debug, Clone)]
+pub struct FrameTelemetry {
+    /// Input shown in the frame, frames next to a switch may be those of the previous one.
+    pub scene: String,
+    /// `None` when x264 logged nothing for the frame.
+    pub encoded: Option<EncodedFrame>,
+    /// PSNR over the RGB channels, infinite for identical frames.
+    pub psnr_db: f64,
+    pub delta_e_mean: f64,
+    pub delta_e_max: f64,
+}
+
+/// Frames of the debug log of libx264, as printed by `ffmpeg -loglevel level+debug`, in
+/// display order. Frames missing from the log are `None`.
+pub fn parse_x264_log(log: &str) -> Vec<Option<EncodedFrame>> {
+    let mut frames = vec![];
+    let (mut coded, mut gop_start) = (0, 0);
+    for line in log.lines() {
+        let Some((poc, frame)) = parse_frame_line(line) else {
+            continue;
+        };
+        // Lines are in coding order. POCs count up by 2 from each IDR frame, which closed
+        // GOPs code after all the frames shown before it.
+        if poc == 0 {
+            gop_start = coded;
+        }
+        let index = gop_start + poc / 2;
+        if frames.len() <= index {
+            frames.resize(index + 1, None);
+        }
+        frames[index] = Some(frame);
+        coded += 1;
+    }
+    frames
+}
+
+/// Parses e.g. `[libx264 @ 0x...] [debug] frame=   0 QP=21.40 NAL=3 Slice:I Poc:0 ...
+/// size=188231 bytes`, returning the POC and the frame.
+fn parse_frame_line(line: &str) -> Option<(usize, EncodedFrame)> {
+    if !line.contains("[libx264 @") {
+        return None;
+    }
+    let (_, fields) = line.split_once("frame=")?;
+    let field = |name: &str| {
+        fields
+            .split_whitespace()
+            .find_map(|token| token.strip_prefix(name))
+    };
+    Some((
+        field("Poc:")?.parse().ok()?,
+        EncodedFrame {
+            qp: field("QP=")?.parse().ok()?,
+            slice: field("Slice:")?.chars().next()?,
+            size: field("size=")?.parse().ok()?,
+        },
+    ))
+}
+
+/// Mean QP of the frames x264 logged.
+pub fn mean_qp(frames: &[FrameTelemetry]) -> Option<f64> {
+    let qps: Vec<f64> = frames
+        .iter()
+        .filter_map(|frame| frame.encoded.map(|encoded| encoded.qp))
+        .collect();
+    (!qps.is_empty()).then(|| qps.iter().sum::<f64>() / qps.len() as f64)
+}
+
+/// `{stem}_telemetry.{extension}` next to `encode`.
+pub fn path(encode: &Path, extension: &str) -> PathBuf {
+    let stem = encode.file_stem().unwrap_or_default().to_string_lossy();
+    encode.with_file_name(format!("{stem}_telemetry.{extension}"))
+}
+
+/// Writes the frames as CSV, one line per frame in display order.
+pub fn write_csv(frames: &[FrameTelemetry], path: &Path) -> Result<()> {
+    let mut csv =
+        String::from("frame,scene,slice,qp,size_bytes,psnr_db,delta_e_mean,delta_e_max\n");
+    for (index, frame) in frames.iter().enumerate() {
+        let (slice, qp, size) = match frame.encoded {
+            Some(encoded) => (
+                encoded.slice.to_string(),
+                format!("{:.2}", encoded.qp),
+                encoded.size.to_string(),
+            ),
+            None => Default::default(),
+        };
+        writeln!(
+            csv,
+            "{index},{},{slice},{qp},{size},{:.3},{:.4},{:.4}",
+            frame.scene, frame.psnr_db, frame.delta_e_mean, frame.delta_e_max
+        )?;
+    }
+    std::fs::write(path, csv).with_context(|| format!("Cannot write {}", path.display()))
+}
+
+/// Plots the QP, size and mean ΔE of the frames to an SVG file, one chart above the other
+/// on the same frame axis, with the scene switches across all of them and the I-frames
+/// marked on the sizes.
+pub fn write_plot(frames: &[FrameTelemetry], title: &str, path: &Path) -> Result<()> {
+    let charts: [(&str, fn(&FrameTelemetry) -> Option<f64>); 3] = [
+        ("QP", |frame| frame.encoded.map(|encoded| encoded.qp)),
+        ("Size (kbit)", |frame| {
+            frame
+                .encoded
+                .map(|encoded| encoded.size as f64 * 8.0 / 1000.0)
+        }),
+        ("Mean ΔE", |frame| Some(frame.delta_e_mean)),
+    ];
+    let x = |index: usize| MARGIN + index as f64 * CHART_WIDTH / frames.len().max(2) as f64;
+    let height = MARGIN + charts.len() as f64 * (CHART_HEIGHT + MARGIN);
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{height}" font-family="sans-serif" font-size="12">"#,
+        CHART_WIDTH + 2.0 * MARGIN
+    )?;
+    writeln!(
+        svg,
+        r#"<rect width="100%" height="100%" fill="white"/><text x="{MARGIN}" y="24" font-size="16">{}</text>"#,
+        escape(title)
+    )?;
+
+    // Scene switches, named above the first chart
+    let bottom = height - MARGIN;
+    for (index, frame) in frames.iter().enumerate() {
+        if index > 0 && frames[index - 1].scene == frame.scene {
+            continue;
+        }
+        writeln!(
+            svg,
+            r##"<line x1="{0:.1}" y1="{MARGIN}" x2="{0:.1}" y2="{bottom}" stroke="#999" stroke-dasharray="4 4"/><text x="{1:.1}" y="{2}">{3}</text>"##,
+            x(index),
+            x(index) + 3.0,
+            MARGIN - 6.0,
+            escape(&frame.scene)
+        )?;
+    }
+
+    for (chart, (name, value)) in charts.iter().enumerate() {
+        let top = MARGIN + chart as f64 * (CHART_HEIGHT + MARGIN);
+        let values: Vec<Option<f64>> = frames
+            .iter()
+            .map(|frame| value(frame).filter(|value| value.is_finite()))
+            .collect();
+        let max = values
+            .iter()
+            .flatten()
+            .copied()
+            .fold(0.0, f64::max)
+            .max(1e-3);
+        let y = |value: f64| top + CHART_HEIGHT * (1.0 - value / max);
+        writeln!(
+            svg,
+            r##"<rect x="{MARGIN}" y="{top}" width="{CHART_WIDTH}" height="{CHART_HEIGHT}" fill="none" stroke="#333"/><text x="{MARGIN}" y="{0}">{name}</text><text x="{1}" y="{2}" text-anchor="end">{max:.2}</text><text x="{1}" y="{3}" text-anchor="end">0</text>"##,
+            top + CHART_HEIGHT + 16.0,
+            MARGIN - 6.0,
+            top + 12.0,
+            top + CHART_HEIGHT
+        )?;
+        let points = values
+            .iter()
+            .enumerate()
+            .filter_map(|(index, value)| {
+                value.map(|value| format!("{:.1},{:.1}", x(index), y(value)))
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(
+            svg,
+            r##"<polyline points="{points}" fill="none" stroke="#1f77b4"/>"##
+        )?;
+        if chart == 1 {
+            for (index, frame) in frames.iter().enumerate() {
+                let (Some(value), Some(EncodedFrame { slice: 'I', .. })) =
+                    (values[index], frame.encoded)
+                else {
+                    continue;
+                };
+                writeln!(
+                    svg,
+                    r##"<circle cx="{:.1}" cy="{:.1}" r="3" fill="#d62728"/>"##,
+                    x(index),
+                    y(value)
+                )?;
+            }
+        }
+    }
+    svg += "</svg>\n";
+    std::fs::write(path, svg).with_context(|| format!("Cannot write {}", path.display()))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(frame: usize, qp: f64, slice: char, poc: usize, size: u64) -> String {
+        format!(
+            "[libx264 @ 0x55d0c8a4e2c0] [debug] frame={frame:4} QP={qp:.2} NAL=2 Slice:{slice} \
+             Poc:{poc:<3} I:0    P:1200 SKIP:1500 size={size} bytes"
+        )
+    }
+
+    #[test]
+    fn b_frames_in_display_order() {
+        // Two closed GOPs of I/P frames and pairs of B-frames, in coding order
+        let coded = [
+            ('I', 0, 9000),
+            ('P', 6, 3000),
+            ('B', 2, 1000),
+            ('B', 4, 1100),
+            ('P', 12, 3100),
+            ('B', 8, 1200),
+            ('B', 10, 1300),
+            ('I', 0, 9100),
+            ('P', 2, 3200),
+        ];
+        let mut log = vec![
+            "[libx264 @ 0x55d0c8a4e2c0] [info] profile High, level 4.0".to_string(),
+            "frame=   12 fps=0.0 q=28.0 size=       0kB time=00:00:00.40".to_string(),
+        ];
+        for (frame, &(slice, poc, size)) in coded.iter().enumerate() {
+            log.push(line(frame, 20.0 + frame as f64, slice, poc, size));
+        }
+
+        let frames = parse_x264_log(&log.join("\n"));
+        let sizes: Vec<_> = frames.iter().map(|frame| frame.unwrap().size).collect();
+        assert_eq!(
+            sizes,
+            [9000, 1000, 1100, 3000, 1200, 1300, 3100, 9100, 3200]
+        );
+        let slices: String = frames.iter().map(|frame| frame.unwrap().slice).collect();
+        assert_eq!(slices, "IBBPBBPIP");
+        assert_eq!(frames[3].unwrap().qp, 21.0);
+    }
+
+    #[test]
+    fn missing_frames() {
+        let log = [line(0, 20.0, 'I', 0, 9000), line(1, 22.0, 'P', 4, 3000)].join("\n");
+        let frames = parse_x264_log(&log);
+        assert_eq!(frames.len(), 3);
+        assert!(frames[0].is_some() && frames[2].is_some());
+        assert_eq!(frames[1], None);
+
+        assert!(parse_x264_log("").is_empty());
+        // Truncated lines are left out
+        assert!(
+            parse_x264_log("[libx264 @ 0x55d0c8a4e2c0] [debug] frame=   0 QP=21.40").is_empty()
+        );
+    }
+}
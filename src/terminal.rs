@@ -0,0 +1,55 @@
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use std::fmt::Write;
+
+/// Upper half block, its foreground is the top pixel and its background the bottom one.
+pub const HALF_BLOCK: char = '\u{2580}';
+
+/// Resizes a frame to `columns` pixels wide, and an even height fitting half blocks.
+///
+/// Each character covers two pixel rows, which roughly keeps the aspect ratio
+/// of the frame with the usual 1:2 terminal cells.
+pub fn downscale(image: &RgbaImage, columns: u32) -> RgbaImage {
+    let columns = columns.clamp(1, image.width().max(1));
+    let rows = ((image.height() as u64 * columns as u64 / image.width().max(1) as u64) as u32)
+        .div_ceil(2)
+        .max(1);
+    imageops::resize(image, columns, rows * 2, FilterType::Triangle)
+}
+
+/// Crops a frame to its center, `zoom` times smaller, to magnify it in a preview.
+pub fn zoom(image: &RgbaImage, zoom: f32) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let crop = |length: u32| ((length as f32 / zoom.max(1.0)).round() as u32).clamp(1, length);
+    let (cropped_width, cropped_height) = (crop(width), crop(height));
+    imageops::crop_imm(
+        image,
+        (width - cropped_width) / 2,
+        (height - cropped_height) / 2,
+        cropped_width,
+        cropped_height,
+    )
+    .to_image()
+}
+
+/// Renders a frame as ANSI truecolor text, `columns` characters wide.
+pub fn render_ansi(image: &RgbaImage, columns: u32) -> String {
+    let small = downscale(image, columns);
+    let (columns, rows) = (small.width(), small.height() / 2);
+
+    let mut text = String::with_capacity((columns * rows * 40) as usize);
+    for row in 0..rows {
+        for x in 0..columns {
+            let top = small.get_pixel(x, row * 2);
+            let bottom = small.get_pixel(x, row * 2 + 1);
+            let _ = write!(
+                text,
+                "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m{HALF_BLOCK}",
+                top[0], top[1], top[2], bottom[0], bottom[1], bottom[2]
+            );
+        }
+        text.push_str("\x1b[0m\n");
+    }
+
+    text
+}
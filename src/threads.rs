@@ -0,0 +1,74 @@
+use anyhow::{anyhow, Result};
+use thread_priority::{ThreadPriority, ThreadPriorityValue};
+use tracing::{debug, warn};
+
+use crate::config::ThreadOptions;
+
+impl ThreadOptions {
+    fn is_default(&self) -> bool {
+        self.priority.is_none() && self.cores.is_empty()
+    }
+
+    /// Applies the options to the calling thread.
+    pub fn apply(&self, group: &str) -> Result<()> {
+        if let Some(priority) = self.priority {
+            let value = ThreadPriorityValue::try_from(priority)
+                .map_err(|err| anyhow!("Invalid {group} thread priority {priority}: {err}"))?;
+            match thread_priority::set_current_thread_priority(ThreadPriority::Crossplatform(value))
+            {
+                Ok(()) => debug!("Set {group} thread priority to {priority}"),
+                // Raising priorities is usually not allowed to unprivileged users
+                Err(err) => warn!("Cannot set {group} thread priority to {priority}: {err:?}"),
+            }
+        }
+
+        if !self.cores.is_empty() {
+            pin_to_cores(&self.cores, group)?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs `f` with the options applied to the calling thread, then restores the previous ones.
+    pub fn scoped<T>(&self, group: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        if self.is_default() {
+            return f();
+        }
+
+        let previous_priority = thread_priority::get_current_thread_priority().ok();
+        let previous_cores = affinity::get_thread_affinity().ok();
+        self.apply(group)?;
+
+        let result = f();
+
+        if let (Some(_), Some(previous)) = (self.priority, previous_priority) {
+            if let Err(err) = thread_priority::set_current_thread_priority(previous) {
+                warn!("Cannot restore thread priority after {group}: {err:?}");
+            }
+        }
+        if let (false, Some(previous)) = (self.cores.is_empty(), previous_cores) {
+            if let Err(err) = affinity::set_thread_affinity(previous) {
+                warn!("Cannot restore thread affinity after {group}: {err}");
+            }
+        }
+
+        result
+    }
+}
+
+/// Restricts the calling thread to the given cores.
+fn pin_to_cores(cores: &[usize], group: &str) -> Result<()> {
+    let available = affinity::get_core_num();
+    if let Some(core) = cores.iter().find(|core| **core >= available) {
+        return Err(anyhow!(
+            "Invalid {group} core {core}, only {available} available"
+        ));
+    }
+
+    match affinity::set_thread_affinity(cores) {
+        Ok(()) => debug!("Pinned {group} thread to cores {cores:?}"),
+        Err(err) => warn!("Cannot pin {group} thread to cores {cores:?}: {err}"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+/// Size of the plot area of the chart, in SVG user units.
+const CHART_WIDTH: f64 = 1000.0;
+const CHART_HEIGHT: f64 = 240.0;
+/// Space around the chart for the axis labels and the legend.
+const MARGIN: f64 = 60.0;
+
+/// One utilization measurement, percentages are `None` when not available on this system.
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub elapsed: Duration,
+    pub cpu_percent: Option<f32>,
+    pub gpu_percent: Option<f32>,
+}
+
+/// Samples CPU and GPU utilization on a background thread while a run is in progress.
+///
+/// CPU usage is read from `/proc/stat` (Linux only), GPU usage from `nvidia-smi`
+/// when it is installed. Other platforms produce samples without values.
+pub struct UtilizationSampler {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<Vec<Sample>>,
+}
+
+impl UtilizationSampler {
+    pub fn start(interval: Duration) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = std::thread::Builder::new()
+            .name("utilization sampler".to_string())
+            .spawn({
+                let stop = stop.clone();
+                move || sample_until(&stop, interval)
+            })
+            .expect("Cannot spawn utilization sampler thread");
+
+        Self { stop, handle }
+    }
+
+    /// Stops sampling and returns every sample taken.
+    pub fn stop(self) -> Vec<Sample> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.join().unwrap_or_default()
+    }
+}
+
+fn sample_until(stop: &AtomicBool, interval: Duration) -> Vec<Sample> {
+    let start = Instant::now();
+    let mut samples = vec![];
+    let mut last_cpu = read_cpu_times();
+    let has_nvidia_smi = read_gpu_percent().is_some();
+    if !has_nvidia_smi {
+        debug!("nvidia-smi not available, GPU utilization will not be sampled");
+    }
+
+    while !stop.load(Ordering::Relaxed) {
+        std::thread::sleep(interval);
+
+        let cpu = read_cpu_times();
+        let cpu_percent = match (last_cpu, cpu) {
+            (Some((last_busy, last_total)), Some((busy, total))) if total > last_total => {
+                Some((busy - last_busy) as f32 / (total - last_total) as f32 * 100.0)
+            }
+            _ => None,
+        };
+        last_cpu = cpu;
+
+        samples.push(Sample {
+            elapsed: start.elapsed(),
+            cpu_percent,
+            gpu_percent: has_nvidia_smi.then(read_gpu_percent).flatten(),
+        });
+    }
+
+    samples
+}
+
+/// Busy and total CPU time since boot, from the first line of `/proc/stat`.
+fn read_cpu_times() -> Option<(u64, u64)> {
+    let stat = std::fs::read_to_string("/proc/stat").ok()?;
+    let times: Vec<u64> = stat
+        .lines()
+        .next()?
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|value| value.parse().ok())
+        .collect();
+
+    // user nice system idle iowait irq softirq steal
+    let idle = times.get(3)? + times.get(4).unwrap_or(&0);
+    let total: u64 = times.iter().take(8).sum();
+    Some((total - idle, total))
+}
+
+fn read_gpu_percent() -> Option<f32> {
+    let output = Command::new("nvidia-smi")
+        .args([
+            "--query-gpu=utilization.gpu",
+            "--format=csv,noheader,nounits",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // One line per GPU, report the busiest one
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().parse::<f32>().ok())
+        .reduce(f32::max)
+}
+
+/// Writes the samples as CSV, ready to be plotted.
+pub fn write_csv(samples: &[Sample], path: &Path) -> Result<()> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    writeln!(file, "elapsed_ms,cpu_percent,gpu_percent")?;
+    for sample in samples {
+        let format = |value: Option<f32>| value.map(|v| format!("{v:.1}")).unwrap_or_default();
+        writeln!(
+            file,
+            "{},{},{}",
+            sample.elapsed.as_millis(),
+            format(sample.cpu_percent),
+            format(sample.gpu_percent)
+        )?;
+    }
+    file.flush()?;
+
+    Ok(())
+}
+
+/// Plots the samples to an SVG file, CPU and GPU utilization over the elapsed time of the
+/// run, from 0 to 100%.
+pub fn write_plot(samples: &[Sample], path: &Path) -> Result<()> {
+    let series: [(&str, &str, fn(&Sample) -> Option<f32>); 2] = [
+        ("CPU", "#1f77b4", |sample| sample.cpu_percent),
+        ("GPU", "#d62728", |sample| sample.gpu_percent),
+    ];
+    let duration = samples
+        .last()
+        .map_or(1.0, |sample| sample.elapsed.as_secs_f64())
+        .max(1e-3);
+    let x = |elapsed: Duration| MARGIN + elapsed.as_secs_f64() / duration * CHART_WIDTH;
+    let y = |percent: f32| MARGIN + CHART_HEIGHT * (1.0 - f64::from(percent) / 100.0);
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="sans-serif" font-size="12">"#,
+        CHART_WIDTH + 2.0 * MARGIN,
+        CHART_HEIGHT + 2.0 * MARGIN
+    )?;
+    writeln!(
+        svg,
+        r##"<rect width="100%" height="100%" fill="white"/><rect x="{MARGIN}" y="{MARGIN}" width="{CHART_WIDTH}" height="{CHART_HEIGHT}" fill="none" stroke="#333"/>"##
+    )?;
+    writeln!(
+        svg,
+        r#"<text x="{0}" y="{1}" text-anchor="end">100%</text><text x="{0}" y="{2}" text-anchor="end">0%</text><text x="{3}" y="{4}" text-anchor="end">{duration:.0} s</text>"#,
+        MARGIN - 6.0,
+        MARGIN + 12.0,
+        MARGIN + CHART_HEIGHT,
+        MARGIN + CHART_WIDTH,
+        MARGIN + CHART_HEIGHT + 16.0
+    )?;
+    for (index, (name, color, value)) in series.iter().enumerate() {
+        let points = samples
+            .iter()
+            .filter_map(|sample| {
+                let percent = value(sample)?;
+                Some(format!("{:.1},{:.1}", x(sample.elapsed), y(percent)))
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(
+            svg,
+            r#"<polyline points="{points}" fill="none" stroke="{color}"/><text x="{}" y="{}" fill="{color}">{name}</text>"#,
+            MARGIN + index as f64 * 60.0,
+            MARGIN - 12.0
+        )?;
+    }
+    svg += "</svg>\n";
+    std::fs::write(path, svg).with_context(|| format!("Cannot write {}", path.display()))
+}
+
+/// Logs the mean and maximum of each measured value.
+pub fn log_summary(samples: &[Sample]) {
+    let summarize = |name: &str, values: Vec<f32>| {
+        if values.is_empty() {
+            return;
+        }
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        let max = values.iter().cloned().fold(0.0, f32::max);
+        info!("{name} utilization: mean {mean:.1}%, max {max:.1}%");
+    };
+
+    summarize(
+        "CPU",
+        samples.iter().filter_map(|s| s.cpu_percent).collect(),
+    );
+    summarize(
+        "GPU",
+        samples.iter().filter_map(|s| s.gpu_percent).collect(),
+    );
+}
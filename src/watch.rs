@@ -0,0 +1,102 @@
+use anyhow::{Context, Result};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+/// Delay letting editors finish writing a file before it is reloaded.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Notifies when a file is modified.
+///
+/// The parent directory is watched rather than the file itself, since many editors save
+/// by replacing the file, which would silently stop a watch on the original one.
+pub struct FileWatcher {
+    path: PathBuf,
+    events: Receiver<notify::Result<Event>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl FileWatcher {
+    pub fn new(path: &Path) -> Result<Self> {
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("Cannot watch {}", path.display()))?;
+        let dir = path
+            .parent()
+            .context("Watched file has no parent directory")?;
+
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            path,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// Waits up to `timeout` for the file to change, coalescing bursts of events.
+    pub fn wait_for_change(&self, timeout: Duration) -> Result<bool> {
+        match self.events.recv_timeout(timeout) {
+            Ok(event) if self.concerns_file(event?) => {}
+            Ok(_) | Err(RecvTimeoutError::Timeout) => return Ok(false),
+            Err(RecvTimeoutError::Disconnected) => anyhow::bail!("File watcher stopped"),
+        }
+
+        std::thread::sleep(DEBOUNCE);
+        while self.events.try_recv().is_ok() {}
+
+        Ok(true)
+    }
+
+    fn concerns_file(&self, event: Event) -> bool {
+        (event.kind.is_modify() || event.kind.is_create())
+            && event.paths.iter().any(|path| *path == self.path)
+    }
+}
+
+/// Notifies when files are created or modified in a directory.
+pub struct DirWatcher {
+    events: Receiver<notify::Result<Event>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl DirWatcher {
+    pub fn new(dir: &Path) -> Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Cannot watch {}", dir.display()))?;
+
+        Ok(Self {
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// Waits up to `timeout` for files to change, returns the changed ones, which may still
+    /// be being written.
+    pub fn wait_for_changes(&self, timeout: Duration) -> Result<Vec<PathBuf>> {
+        let mut paths = vec![];
+        let mut add = |event: Event| {
+            if event.kind.is_modify() || event.kind.is_create() {
+                paths.extend(event.paths);
+            }
+        };
+        match self.events.recv_timeout(timeout) {
+            Ok(event) => add(event?),
+            Err(RecvTimeoutError::Timeout) => return Ok(vec![]),
+            Err(RecvTimeoutError::Disconnected) => anyhow::bail!("Directory watcher stopped"),
+        }
+        for event in self.events.try_iter() {
+            add(event?);
+        }
+
+        paths.sort();
+        paths.dedup();
+        Ok(paths)
+    }
+}
@@ -0,0 +1,52 @@
+//! WebM recordings encoded in VP9 or AV1, to check the colors survive other encoders than
+//! the H264 one of the pipeline.
+
+use anyhow::Result;
+use compositor_render::Resolution;
+use image::RgbaImage;
+use std::path::Path;
+use tracing::info;
+
+use crate::config::{FramerateConfig, WebmCodecConfig};
+use crate::raw_video::{self, RawVideoWriter};
+
+/// Encodes frames to a WebM file with `ffmpeg`.
+///
+/// The pipeline only encodes H264 to MP4 files, so frames come from the raw output and are
+/// converted by `ffmpeg` like the recordings of the pipeline, see
+/// [`raw_video::bt709_limited`], in YUV 4:2:0.
+pub struct WebmRecorder {
+    writer: RawVideoWriter,
+}
+
+impl WebmRecorder {
+    pub fn start(
+        path: &Path,
+        codec: WebmCodecConfig,
+        resolution: Resolution,
+        framerate: FramerateConfig,
+    ) -> Result<Self> {
+        let mut command = raw_video::command("rgba", resolution, framerate, false);
+        command
+            .args(codec.ffmpeg_args())
+            .args(raw_video::bt709_limited("yuv420p"))
+            .args(["-f", "webm"])
+            .arg(path);
+        let writer = RawVideoWriter::start(command, "the WebM recording")?;
+
+        info!("Recording {codec:?} to {}", path.display());
+        Ok(Self { writer })
+    }
+
+    pub fn push(&mut self, image: RgbaImage) -> Result<()> {
+        self.writer.push(image.into_raw())?;
+        Ok(())
+    }
+
+    /// Waits for the file to be written, returns the number of frames.
+    pub fn finish(self) -> Result<usize> {
+        let frames = self.writer.finish()?;
+        info!("Recorded {frames} frames");
+        Ok(frames)
+    }
+}
@@ -1,9 +1,23 @@
 use anyhow::{anyhow, ensure, Result};
 use compositor_pipeline::pipeline::GraphicsContext;
-use image::{ImageBuffer, RgbaImage};
+use image::{ImageBuffer, Rgba, Rgba32FImage, RgbaImage};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
 use wgpu::*;
 
 const U8_MEM_SIZE: usize = std::mem::size_of::<u8>();
+const U16_MEM_SIZE: usize = std::mem::size_of::<u16>();
+
+/// Whether [`capture`] leaves `Rgba8Unorm` textures to be encoded on the CPU, see
+/// [`set_cpu_srgb_encoding`].
+static CPU_SRGB_ENCODING: AtomicBool = AtomicBool::new(false);
+
+/// Makes [`to_image`] and [`capture`] read `Rgba8Unorm` textures back as they are and encode them to sRGB
+/// on the CPU, instead of converting them with a render pass first. The results are the
+/// same, for adapters where that pass is slow or broken, see [`crate::workarounds`].
+pub fn set_cpu_srgb_encoding(enabled: bool) {
+    CPU_SRGB_ENCODING.store(enabled, Ordering::Relaxed);
+}
 
 /// Converts any texture to a specified format.
 ///
@@ -13,13 +27,14 @@ pub fn convert_to(
     context: &GraphicsContext,
     source: &Texture,
     format: TextureFormat,
+    cache: Option<&PipelineCache>,
 ) -> Result<Texture> {
     let src_view = source.create_view(&TextureViewDescriptor::default());
     let src_size = source.size();
 
     // Create destination texture
     let dst_texture = context.device.create_texture(&TextureDescriptor {
-        label: Some("Converted RGBA8Unorm Texture"),
+        label: Some("Converted Texture"),
         size: src_size,
         mip_level_count: 1,
         sample_count: 1,
@@ -87,7 +102,7 @@ pub fn convert_to(
                 module: &shader,
                 entry_point: Some("fs_main"),
                 targets: &[Some(ColorTargetState {
-                    format: TextureFormat::Rgba8Unorm,
+                    format,
                     blend: Some(BlendState::REPLACE),
                     write_mask: ColorWrites::ALL,
                 })],
@@ -97,7 +112,7 @@ pub fn convert_to(
             depth_stencil: None,
             multisample: MultisampleState::default(),
             multiview: None,
-            cache: None,
+            cache,
         });
 
     // Sampler
@@ -155,17 +170,57 @@ pub fn convert_to(
     Ok(dst_texture)
 }
 
+/// Uploads an image to a new Rgba8Unorm texture, the format of raw input frames.
+pub fn from_image(context: &GraphicsContext, image: &RgbaImage) -> Texture {
+    let size = Extent3d {
+        width: image.width(),
+        height: image.height(),
+        depth_or_array_layers: 1,
+    };
+    let texture = context.device.create_texture(&TextureDescriptor {
+        label: Some("Uploaded RGBA8Unorm Texture"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: TextureFormat::Rgba8Unorm,
+        usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+
+    context.queue.write_texture(
+        TexelCopyTextureInfo {
+            texture: &texture,
+            mip_level: 0,
+            origin: Origin3d::ZERO,
+            aspect: TextureAspect::All,
+        },
+        image.as_raw(),
+        TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(image.width() * 4),
+            rows_per_image: Some(image.height()),
+        },
+        size,
+    );
+
+    texture
+}
+
 /// Compute the number of byter per row for a texture, considering padding for alignment.
 fn padded_bytes_per_row(texture: &Texture) -> Result<u32> {
     let format = texture.format();
     ensure!(
-        format == TextureFormat::Rgba8Unorm || format == TextureFormat::Rgba8UnormSrgb,
-        "Can only compute padding for Rgba8Unorm or Rgba8UnormSrgb textures, got {:?}",
+        matches!(
+            format,
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb | TextureFormat::Rgba16Float
+        ),
+        "Can only compute padding for Rgba8Unorm, Rgba8UnormSrgb or Rgba16Float textures, got {:?}",
         format
     );
 
     let texture_size = texture.size();
-    let unaligned_bytes_per_row = texture_size.width * U8_MEM_SIZE as u32 * 4;
+    let unaligned_bytes_per_row = texture_size.width * bytes_per_pixel(format);
 
     let padded_bytes_per_row = ((unaligned_bytes_per_row + COPY_BYTES_PER_ROW_ALIGNMENT - 1)
         / COPY_BYTES_PER_ROW_ALIGNMENT)
@@ -174,18 +229,142 @@ fn padded_bytes_per_row(texture: &Texture) -> Result<u32> {
     Ok(padded_bytes_per_row)
 }
 
+fn bytes_per_pixel(format: TextureFormat) -> u32 {
+    match format {
+        TextureFormat::Rgba16Float => U16_MEM_SIZE as u32 * 4,
+        _ => U8_MEM_SIZE as u32 * 4,
+    }
+}
+
 /// Converts a Wgpu texture to an image buffer (RgbaImage).
-pub fn to_image(context: &GraphicsContext, texture: &Texture) -> Result<RgbaImage> {
+pub fn to_image(
+    context: &GraphicsContext,
+    texture: &Texture,
+    cache: Option<&PipelineCache>,
+) -> Result<RgbaImage> {
+    capture(context, texture, cache)?.into_image()
+}
+
+/// A texture converted once for reading: GPU-side analyzers use [`Capture::texture`], CPU-side
+/// ones and writers the image, read back on first use and shared from then on.
+pub struct Capture {
+    context: GraphicsContext,
+    texture: Texture,
+    image: OnceLock<RgbaImage>,
+}
+
+/// Converts `texture` to `Rgba8UnormSrgb`, without reading it back yet.
+///
+/// With [`set_cpu_srgb_encoding`], `Rgba8Unorm` textures are kept as they are and encoded
+/// when read back instead, so [`Capture::texture`] is not always in `Rgba8UnormSrgb`.
+pub fn capture(
+    context: &GraphicsContext,
+    texture: &Texture,
+    cache: Option<&PipelineCache>,
+) -> Result<Capture> {
     // The image crate "assumes an sRGB color space of its data".
     // Before copying pixel data, we need to ensure the texture is in sRGB color space.
     let target_format = TextureFormat::Rgba8UnormSrgb;
+    let cpu_encoding =
+        CPU_SRGB_ENCODING.load(Ordering::Relaxed) && texture.format() == TextureFormat::Rgba8Unorm;
     let texture = match texture.format() {
-        format if format == target_format => texture.clone(),
-        _ => convert_to(context, texture, target_format)?,
+        format if format == target_format || cpu_encoding => texture.clone(),
+        _ => convert_to(context, texture, target_format, cache)?,
     };
 
+    Ok(Capture {
+        context: context.clone(),
+        texture,
+        image: OnceLock::new(),
+    })
+}
+
+impl Capture {
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// The texture read back to the CPU, on the first call only.
+    pub fn image(&self) -> Result<&RgbaImage> {
+        if let Some(image) = self.image.get() {
+            return Ok(image);
+        }
+        let image = self.read_image()?;
+        Ok(self.image.get_or_init(|| image))
+    }
+
+    pub fn into_image(mut self) -> Result<RgbaImage> {
+        match self.image.take() {
+            Some(image) => Ok(image),
+            None => self.read_image(),
+        }
+    }
+
+    fn read_image(&self) -> Result<RgbaImage> {
+        let size = self.texture.size();
+        let mut image_data = read_back(&self.context, &self.texture)?;
+        if self.texture.format() == TextureFormat::Rgba8Unorm {
+            let lut: [u8; 256] = std::array::from_fn(|value| {
+                (encode_srgb(value as f32 / 255.0) * 255.0).round() as u8
+            });
+            for pixel in image_data.chunks_exact_mut(4) {
+                for channel in &mut pixel[..3] {
+                    *channel = lut[*channel as usize];
+                }
+            }
+        }
+        ImageBuffer::from_raw(size.width, size.height, image_data)
+            .ok_or(anyhow!("Failed to create image buffer"))
+    }
+}
+
+/// Converts a Wgpu texture to an image of linear floating point values, without the
+/// rounding of the 8-bit sRGB encoding of [`to_image`], e.g. to save it as OpenEXR.
+pub fn to_image_f32(
+    context: &GraphicsContext,
+    texture: &Texture,
+    cache: Option<&PipelineCache>,
+) -> Result<Rgba32FImage> {
+    // Half floats keep the 8-bit linear values exactly, and can be blended into
+    let texture = convert_to(context, texture, TextureFormat::Rgba16Float, cache)?;
+
+    let size = texture.size();
+    let image_data = read_back(context, &texture)?
+        .chunks_exact(U16_MEM_SIZE)
+        .map(|bytes| f16_to_f32(u16::from_le_bytes([bytes[0], bytes[1]])))
+        .collect();
+    ImageBuffer::from_raw(size.width, size.height, image_data)
+        .ok_or(anyhow!("Failed to create image buffer"))
+}
+
+/// Converts a Wgpu texture to a 16-bit sRGB image, encoded from the linear values of
+/// [`to_image_f32`], e.g. to save it as a 16-bit PNG.
+pub fn to_image_u16(
+    context: &GraphicsContext,
+    texture: &Texture,
+    cache: Option<&PipelineCache>,
+) -> Result<ImageBuffer<Rgba<u16>, Vec<u16>>> {
+    let linear = to_image_f32(context, texture, cache)?;
+    let quantize = |value: f32| (value.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16;
+
+    let mut image = ImageBuffer::new(linear.width(), linear.height());
+    for (pixel, linear) in image.pixels_mut().zip(linear.pixels()) {
+        let [r, g, b, a] = linear.0;
+        *pixel = Rgba([
+            quantize(encode_srgb(r)),
+            quantize(encode_srgb(g)),
+            quantize(encode_srgb(b)),
+            quantize(a),
+        ]);
+    }
+    Ok(image)
+}
+
+/// Copies the pixels of a texture to main memory, without the row padding.
+fn read_back(context: &GraphicsContext, texture: &Texture) -> Result<Vec<u8>> {
     let texture_size = texture.size();
-    let padded_bytes_per_row = padded_bytes_per_row(&texture)?;
+    let padded_bytes_per_row = padded_bytes_per_row(texture)?;
+    let bytes_per_row = texture_size.width * bytes_per_pixel(texture.format());
     let buffer_size = padded_bytes_per_row * texture_size.height;
 
     let buffer = context.device.create_buffer(&BufferDescriptor {
@@ -203,7 +382,7 @@ pub fn to_image(context: &GraphicsContext, texture: &Texture) -> Result<RgbaImag
 
     encoder.copy_texture_to_buffer(
         TexelCopyTextureInfo {
-            texture: &texture,
+            texture,
             mip_level: 0,
             origin: Origin3d { x: 0, y: 0, z: 0 },
             aspect: TextureAspect::All,
@@ -227,12 +406,29 @@ pub fn to_image(context: &GraphicsContext, texture: &Texture) -> Result<RgbaImag
     let data = buffer_slice.get_mapped_range();
 
     // Allocate the final image data, copying each row without the extra padding
-    let mut image_data =
-        Vec::with_capacity((texture_size.width * texture_size.height * 4) as usize);
+    let mut image_data = Vec::with_capacity((bytes_per_row * texture_size.height) as usize);
     for chunk in data.chunks(padded_bytes_per_row as usize) {
-        image_data.extend_from_slice(&chunk[..(texture_size.width * 4) as usize]);
+        image_data.extend_from_slice(&chunk[..bytes_per_row as usize]);
     }
 
-    ImageBuffer::from_raw(texture_size.width, texture_size.height, image_data)
-        .ok_or(anyhow!("Failed to create image buffer"))
+    Ok(image_data)
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+fn encode_srgb(linear: f32) -> f32 {
+    match linear <= 0.0031308 {
+        true => linear * 12.92,
+        false => 1.055 * linear.powf(1.0 / 2.4) - 0.055,
+    }
 }
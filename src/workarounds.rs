@@ -0,0 +1,93 @@
+//! Registry of GPU driver workarounds, enabled by adapter and driver version.
+//!
+//! The workarounds enabled on a machine are logged and written to the event log, so the
+//! results of machines running with different code paths are not compared blindly.
+
+use std::collections::BTreeSet;
+use tracing::info;
+use wgpu::{AdapterInfo, DeviceType};
+
+use crate::config::{WorkaroundConfig, WorkaroundRuleConfig, WorkaroundsConfig};
+
+/// Adapters a workaround is known to be needed on.
+struct KnownRule {
+    workaround: WorkaroundConfig,
+    matches: fn(&AdapterInfo) -> bool,
+    reason: &'static str,
+}
+
+const KNOWN: &[KnownRule] = &[KnownRule {
+    workaround: WorkaroundConfig::CpuReadbackEncoding,
+    // llvmpipe, lavapipe, WARP, SwiftShader
+    matches: |info| info.device_type == DeviceType::Cpu,
+    reason: "software rasterizers render the conversion pass slower than the CPU encodes",
+}];
+
+/// Workarounds for the adapter of `info`: the known ones and those of the configured
+/// rules when `auto` is set, and the enabled ones, less the disabled ones.
+pub fn select(info: &AdapterInfo, config: &WorkaroundsConfig) -> Vec<WorkaroundConfig> {
+    let mut selected = BTreeSet::new();
+    let mut enable = |workaround: WorkaroundConfig, reason: &str| {
+        if config.disable.contains(&workaround) {
+            info!("Workaround {} disabled ({reason})", workaround.name());
+        } else if selected.insert(workaround) {
+            info!("Workaround {} enabled: {reason}", workaround.name());
+        }
+    };
+
+    if config.auto {
+        for rule in KNOWN.iter().filter(|rule| (rule.matches)(info)) {
+            enable(rule.workaround, rule.reason);
+        }
+        for rule in config.rules.iter().filter(|rule| matches(rule, info)) {
+            enable(
+                rule.workaround,
+                rule.reason.as_deref().unwrap_or("matching configured rule"),
+            );
+        }
+    }
+    for &workaround in &config.enable {
+        enable(workaround, "configured");
+    }
+
+    selected.into_iter().collect()
+}
+
+/// Whether the adapter of `info` matches every set field of `rule`.
+fn matches(rule: &WorkaroundRuleConfig, info: &AdapterInfo) -> bool {
+    let contains = |haystack: &str, needle: &str| {
+        haystack
+            .to_ascii_lowercase()
+            .contains(&needle.to_ascii_lowercase())
+    };
+    let driver = format!("{} {}", info.driver, info.driver_info);
+
+    rule.vendor.map_or(true, |vendor| vendor == info.vendor)
+        && rule
+            .name
+            .as_ref()
+            .map_or(true, |name| contains(&info.name, name))
+        && rule
+            .driver
+            .as_ref()
+            .map_or(true, |needle| contains(&driver, needle))
+        && rule.driver_below.as_ref().map_or(true, |below| {
+            // Drivers not telling their version are left alone
+            match (version(&info.driver_info), version(below)) {
+                (Some(version), Some(below)) => version < below,
+                _ => false,
+            }
+        })
+}
+
+/// First dotted version number of `text`, e.g. `[24, 1, 3]` in "Mesa 24.1.3-1ubuntu1".
+fn version(text: &str) -> Option<Vec<u32>> {
+    let start = text.find(|c: char| c.is_ascii_digit())?;
+    let version: Vec<u32> = text[start..]
+        .split(|c: char| !c.is_ascii_digit() && c != '.')
+        .next()?
+        .split('.')
+        .map_while(|part| part.parse().ok())
+        .collect();
+    (!version.is_empty()).then_some(version)
+}
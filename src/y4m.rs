@@ -0,0 +1,150 @@
+//! YUV4MPEG2 recordings of raw output frames, for tools like `vmaf` or `ffprobe` to analyze
+//! uncompressed.
+
+use anyhow::{bail, Context, Result};
+use compositor_render::Resolution;
+use image::RgbaImage;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::config::FramerateConfig;
+
+/// Writes frames to a Y4M file as planar YUV 4:2:0, BT.709 limited range.
+///
+/// Raw outputs deliver RGBA frames, so the planes are made with the conversion of the
+/// recordings of the pipeline: the same matrix and range, chroma averaged over each 2x2
+/// block, which Y4M sites at its center (`C420jpeg`).
+pub struct Y4mWriter {
+    file: BufWriter<File>,
+    resolution: Resolution,
+    frames: usize,
+}
+
+impl Y4mWriter {
+    pub fn create(path: &Path, resolution: Resolution, framerate: FramerateConfig) -> Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("Cannot create {}", path.display()))?;
+        let mut file = BufWriter::new(file);
+        writeln!(
+            file,
+            "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C420jpeg XCOLORRANGE=LIMITED",
+            resolution.width, resolution.height, framerate.num, framerate.den
+        )?;
+
+        Ok(Self {
+            file,
+            resolution,
+            frames: 0,
+        })
+    }
+
+    pub fn write(&mut self, image: &RgbaImage) -> Result<()> {
+        let (width, height) = image.dimensions();
+        if (width as usize, height as usize) != (self.resolution.width, self.resolution.height) {
+            bail!(
+                "Frame is {width}x{height}, the Y4M file {}x{}",
+                self.resolution.width,
+                self.resolution.height
+            );
+        }
+
+        let (y_plane, u_plane, v_plane) = to_yuv420p(image);
+        self.file.write_all(b"FRAME\n")?;
+        self.file.write_all(&y_plane)?;
+        self.file.write_all(&u_plane)?;
+        self.file.write_all(&v_plane)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Flushes the file, and returns the number of frames written.
+    pub fn finish(mut self) -> Result<usize> {
+        self.file.flush()?;
+        Ok(self.frames)
+    }
+}
+
+/// Converts to planar 4:2:0 YUV, BT.709 limited range, the reverse of
+/// [`crate::stdin_input`]'s conversion.
+fn to_yuv420p(image: &RgbaImage) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+    let (width, height) = image.dimensions();
+    let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+    let rgb = |x: u32, y: u32| {
+        let pixel = image.get_pixel(x.min(width - 1), y.min(height - 1));
+        [0, 1, 2].map(|c| pixel[c] as f32 / 255.0)
+    };
+    let luma = |[r, g, b]: [f32; 3]| 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let clamp = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+
+    let mut y_plane = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            y_plane.push(clamp(16.0 + 219.0 * luma(rgb(x, y))));
+        }
+    }
+
+    let chroma_size = (chroma_width * chroma_height) as usize;
+    let (mut u_plane, mut v_plane) = (
+        Vec::with_capacity(chroma_size),
+        Vec::with_capacity(chroma_size),
+    );
+    for y in 0..chroma_height {
+        for x in 0..chroma_width {
+            let (mut u, mut v) = (0.0, 0.0);
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let rgb = rgb(x * 2 + dx, y * 2 + dy);
+                let luma = luma(rgb);
+                u += (rgb[2] - luma) / 1.8556 / 4.0;
+                v += (rgb[0] - luma) / 1.5748 / 4.0;
+            }
+            u_plane.push(clamp(128.0 + 224.0 * u));
+            v_plane.push(clamp(128.0 + 224.0 * v));
+        }
+    }
+
+    (y_plane, u_plane, v_plane)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+    #[test]
+    fn limited_range_levels() {
+        for (color, expected) in [
+            (BLACK, (16, 128, 128)),
+            (Rgba([255, 255, 255, 255]), (235, 128, 128)),
+            (RED, (63, 102, 240)),
+        ] {
+            let (y, u, v) = to_yuv420p(&RgbaImage::from_pixel(2, 2, color));
+            assert_eq!(
+                (y, u, v),
+                (vec![expected.0; 4], vec![expected.1], vec![expected.2])
+            );
+        }
+    }
+
+    #[test]
+    fn chroma_averaged_over_blocks() {
+        let image = RgbaImage::from_fn(4, 2, |x, _| if x == 0 { RED } else { BLACK });
+        let (y, u, v) = to_yuv420p(&image);
+        assert_eq!(y, [63, 16, 16, 16, 63, 16, 16, 16]);
+        // Half of the first block is red
+        assert_eq!(v, [184, 128]);
+        assert_eq!(u[1], 128);
+    }
+
+    #[test]
+    fn odd_sizes() {
+        // The last column and row are repeated to fill their blocks
+        let image = RgbaImage::from_fn(3, 3, |x, y| if (x, y) == (2, 2) { RED } else { BLACK });
+        let (y, u, v) = to_yuv420p(&image);
+        assert_eq!((y.len(), u.len(), v.len()), (9, 4, 4));
+        assert_eq!(v, [128, 128, 128, 240]);
+    }
+}